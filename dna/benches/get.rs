@@ -0,0 +1,17 @@
+use criterion::{black_box, criterion_group, criterion_main, Criterion};
+use dna::PackedDna;
+use std::str::FromStr;
+
+fn bench_get(c: &mut Criterion) {
+    let dna = PackedDna::from_str(&"GATTACA".repeat(10_000)).unwrap();
+    c.bench_function("get 70k positions", |b| {
+        b.iter(|| {
+            for idx in 0..70_000 {
+                black_box(dna.get(black_box(idx)).unwrap());
+            }
+        })
+    });
+}
+
+criterion_group!(benches, bench_get);
+criterion_main!(benches);
@@ -0,0 +1,27 @@
+#![no_main]
+
+use dna::{Nuc, PackedDna};
+use libfuzzer_sys::fuzz_target;
+use std::str::FromStr;
+
+fuzz_target!(|s: &str| {
+    let valid = s.chars().all(|c| "ACGTacgt".contains(c));
+    let dna = match PackedDna::from_str(s) {
+        Ok(dna) => dna,
+        Err(_) => {
+            assert!(!valid, "rejected {:?}", s);
+            return;
+        }
+    };
+    assert!(valid, "accepted {:?}", s);
+    assert_eq!(dna.len(), s.len());
+    assert_eq!(dna.to_string(), s.to_ascii_uppercase());
+    let nucs: Vec<Nuc> = dna.iter().collect();
+    assert_eq!(nucs.iter().copied().collect::<PackedDna>(), dna);
+    assert_eq!(dna.reverse_complement().reverse_complement(), dna);
+
+    let half = s.len() / 2;
+    let mut left = dna.to_slice(0..half);
+    left.append(&dna.to_slice(half..s.len()));
+    assert_eq!(left, dna);
+});
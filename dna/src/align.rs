@@ -0,0 +1,1281 @@
+//! Pairwise alignment with affine gap penalties.
+//!
+//! [`Aligner`] scores alignments with Gotoh's algorithm, so one long gap is cheaper than several
+//! short ones, as is typical of real indels. The [`AlignMode`] decides which end gaps are free:
+//! global alignment charges them all, semi-global alignment places a read anywhere in a
+//! reference, overlap alignment joins the ends of two contigs and local alignment finds the
+//! best-matching pair of substrings.
+//!
+//! Alignment takes O(NM) time and one traceback byte per pair of positions, so it suits reads
+//! and contigs rather than whole chromosomes. [`Aligner::band`] cuts both down for
+//! near-identical sequences, [`Aligner::score`] skips the traceback when only the score is
+//! needed, and [`PackedDna::diff`] handles long ones.
+//!
+//! Substitutions score a flat match or mismatch unless the aligner is given a
+//! [`SubstitutionMatrix`], which can also score IUPAC ambiguity codes partially when aligning
+//! [`IupacDna`].
+//!
+//! Coding sequences can be aligned codon by codon with [`Aligner::align_codons`], so every gap
+//! is a whole number of codons and the reading frame is kept.
+
+use crate::{
+    iupac::{Iupac, IupacDna},
+    striped::{self, Substitutions},
+    translate::GeneticCode,
+    wavefront, Nuc, PackedDna,
+};
+use std::{
+    fmt::Write,
+    ops::{Range, RangeInclusive},
+};
+
+/// Stand-in for minus infinity that cannot overflow when penalties are added to it
+const NEG_INF: i32 = i32::MIN / 2;
+
+/// Traceback: where the best score of a cell came from
+const FROM_DIAGONAL: u8 = 0;
+const FROM_INSERTION: u8 = 1;
+const FROM_DELETION: u8 = 2;
+/// Local alignment only: the alignment through a cell starts there
+const FROM_START: u8 = 3;
+/// Traceback: the insertion or deletion ending at a cell extends a gap rather than opening one
+const INSERTION_EXTENDS: u8 = 1 << 2;
+const DELETION_EXTENDS: u8 = 1 << 3;
+
+/// Scores of alignment columns; penalties are negative.
+///
+/// A gap of length `L` scores `gap_open + L * gap_extend`. With `gap_open` zero, gaps are
+/// linear.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Scoring {
+    /// Score of two identical nucleotides
+    pub match_score: i32,
+    /// Score of two different nucleotides
+    pub mismatch: i32,
+    /// Score added once for every gap
+    pub gap_open: i32,
+    /// Score added for every position of a gap
+    pub gap_extend: i32,
+}
+
+/// The scores used by BWA-MEM: match 1, mismatch -4, gap open -6 and gap extension -1
+impl Default for Scoring {
+    fn default() -> Self {
+        Scoring {
+            match_score: 1,
+            mismatch: -4,
+            gap_open: -6,
+            gap_extend: -1,
+        }
+    }
+}
+
+impl Scoring {
+    fn gap(&self, len: usize) -> i32 {
+        if len == 0 {
+            0
+        } else {
+            self.gap_open + len as i32 * self.gap_extend
+        }
+    }
+}
+
+/// Scores of every pair of nucleotides or IUPAC codes, overriding the match and mismatch scores
+/// of a [`Scoring`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct SubstitutionMatrix {
+    /// Indexed by query then target code, in the order of [`Iupac::ALL`]
+    scores: [[i32; 15]; 15],
+}
+
+impl SubstitutionMatrix {
+    /// A matrix scoring identical nucleotides `match_score` and different ones `mismatch`,
+    /// extended to ambiguity codes like [`from_nucs`](Self::from_nucs)
+    pub fn new(match_score: i32, mismatch: i32) -> Self {
+        let mut scores = [[mismatch; 4]; 4];
+        for (idx, row) in scores.iter_mut().enumerate() {
+            row[idx] = match_score;
+        }
+        SubstitutionMatrix::from_nucs(scores)
+    }
+
+    /// A matrix from the scores of every pair of nucleotides, indexed by query then target
+    /// nucleotide in the order A, C, G, T.
+    ///
+    /// A pair involving an ambiguity code scores the mean over the pairs of nucleotides it
+    /// stands for, rounded to the nearest integer, e.g. `N` against `A` scores the mean of the
+    /// `A` column.
+    pub fn from_nucs(scores: [[i32; 4]; 4]) -> Self {
+        let mut iupac = [[0; 15]; 15];
+        for (row, a) in iupac.iter_mut().zip(Iupac::ALL) {
+            for (score, b) in row.iter_mut().zip(Iupac::ALL) {
+                let pairs = a.nucs().len() * b.nucs().len();
+                let total: i32 = a
+                    .nucs()
+                    .iter()
+                    .flat_map(|&x| {
+                        b.nucs()
+                            .iter()
+                            .map(move |&y| scores[x as usize][y as usize])
+                    })
+                    .sum();
+                *score = (f64::from(total) / pairs as f64).round() as i32;
+            }
+        }
+        SubstitutionMatrix { scores: iupac }
+    }
+
+    /// A matrix from the scores of every pair of IUPAC codes, indexed by query then target
+    /// code in the order of [`Iupac::ALL`]
+    pub fn from_iupac(scores: [[i32; 15]; 15]) -> Self {
+        SubstitutionMatrix { scores }
+    }
+
+    /// The score of query code `a` against target code `b`
+    pub fn score(&self, a: Iupac, b: Iupac) -> i32 {
+        self.scores[a as usize][b as usize]
+    }
+}
+
+/// Which end gaps an alignment is charged for
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum AlignMode {
+    /// End to end in both sequences (Needleman–Wunsch)
+    #[default]
+    Global,
+    /// The whole query against any part of the target, e.g. a read against a reference
+    SemiGlobal,
+    /// A suffix or the whole of either sequence against a prefix or the whole of the other,
+    /// e.g. overlapping contigs; no end gap is charged
+    Overlap,
+    /// The best-scoring pair of substrings (Smith–Waterman), e.g. a conserved region shared by
+    /// otherwise unrelated sequences
+    Local,
+}
+
+/// One column of an alignment
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum AlignOp {
+    /// Identical nucleotides
+    Match,
+    /// Different nucleotides
+    Mismatch,
+    /// A query nucleotide missing from the target
+    Insertion,
+    /// A target nucleotide missing from the query
+    Deletion,
+}
+
+impl AlignOp {
+    /// The extended CIGAR letter: `=`, `X`, `I` or `D`
+    pub fn to_char(self) -> char {
+        match self {
+            AlignOp::Match => '=',
+            AlignOp::Mismatch => 'X',
+            AlignOp::Insertion => 'I',
+            AlignOp::Deletion => 'D',
+        }
+    }
+}
+
+/// The best alignment of a query against a target
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Alignment {
+    /// Total score of the alignment
+    pub score: i32,
+    /// 0-based range of the query covered by the alignment
+    pub query_range: Range<usize>,
+    /// 0-based range of the target covered by the alignment
+    pub target_range: Range<usize>,
+    /// The alignment columns from first to last
+    pub ops: Vec<AlignOp>,
+    /// Length of the whole query, including any parts left out of the alignment
+    pub query_len: usize,
+}
+
+/// How the parts of a query outside its alignment are written in a SAM CIGAR
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Clip {
+    /// `S`: the clipped nucleotides stay in the record's sequence, as for primary alignments
+    Soft,
+    /// `H`: the clipped nucleotides are left out of the record's sequence, as supplementary
+    /// alignments usually are
+    Hard,
+}
+
+impl Alignment {
+    /// The alignment as an extended CIGAR string, e.g. `4=1X2I3=`
+    pub fn cigar(&self) -> String {
+        let mut cigar = String::new();
+        let mut ops = self.ops.iter().peekable();
+        while let Some(&op) = ops.next() {
+            let mut run = 1;
+            while ops.next_if_eq(&&op).is_some() {
+                run += 1;
+            }
+            let _ = write!(cigar, "{}{}", run, op.to_char());
+        }
+        cigar
+    }
+
+    /// Number of query nucleotides before and after the alignment
+    pub fn clips(&self) -> (usize, usize) {
+        (
+            self.query_range.start,
+            self.query_len - self.query_range.end,
+        )
+    }
+
+    /// The alignment as a SAM CIGAR string, accounting for the whole query: the unaligned ends
+    /// left out by local and overlap alignment are written as `clip` operations, e.g.
+    /// `3S4=1X2I3=5S`
+    pub fn sam_cigar(&self, clip: Clip) -> String {
+        let letter = match clip {
+            Clip::Soft => 'S',
+            Clip::Hard => 'H',
+        };
+        let (before, after) = self.clips();
+        let mut cigar = String::new();
+        if before > 0 {
+            let _ = write!(cigar, "{}{}", before, letter);
+        }
+        cigar.push_str(&self.cigar());
+        if after > 0 {
+            let _ = write!(cigar, "{}{}", after, letter);
+        }
+        cigar
+    }
+}
+
+/// A query split into several local alignments against one target, as for chimeric reads or
+/// reads spanning a structural variant, see [`Aligner::align_split`]
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct SplitAlignment {
+    /// The best-scoring part, reported as the primary SAM record
+    pub primary: Alignment,
+    /// The other parts, covering disjoint ranges of the query outside the primary one, in
+    /// query order; reported as supplementary SAM records
+    pub supplementary: Vec<Alignment>,
+}
+
+impl SplitAlignment {
+    /// Whether the query aligns in more than one part
+    pub fn is_split(&self) -> bool {
+        !self.supplementary.is_empty()
+    }
+
+    /// All parts in query order
+    pub fn parts(&self) -> Vec<&Alignment> {
+        let mut parts: Vec<&Alignment> = std::iter::once(&self.primary)
+            .chain(&self.supplementary)
+            .collect();
+        parts.sort_by_key(|part| part.query_range.start);
+        parts
+    }
+}
+
+/// The score and end of the best local alignment, see [`Aligner::local_score`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct LocalScore {
+    /// Score of the alignment, 0 if no two nucleotides match
+    pub score: i32,
+    /// 0-based exclusive end of the alignment in the query
+    pub query_end: usize,
+    /// 0-based exclusive end of the alignment in the target
+    pub target_end: usize,
+}
+
+/// The algorithm an [`Aligner`] uses
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum AlignerKind {
+    /// Fill the whole dynamic programming matrix; handles every mode and scoring
+    #[default]
+    DynamicProgramming,
+    /// Wavefront alignment, whose work grows with the differences between the sequences rather
+    /// than their lengths; much faster for long, high-identity sequences such as assembly
+    /// polishing or long reads.
+    ///
+    /// Only global alignment without a [`SubstitutionMatrix`] is computed this way, and only
+    /// when mismatches and gap positions score less than matches; other alignments fall back
+    /// to dynamic programming.
+    Wavefront,
+}
+
+/// Aligns pairs of sequences with fixed scores and mode
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub struct Aligner {
+    scoring: Scoring,
+    mode: AlignMode,
+    kind: AlignerKind,
+    circular: bool,
+    band: Option<usize>,
+    matrix: Option<SubstitutionMatrix>,
+}
+
+impl Aligner {
+    /// Create an aligner for `mode` with the [default scores](Scoring::default)
+    pub fn new(mode: AlignMode) -> Self {
+        Aligner {
+            scoring: Scoring::default(),
+            mode,
+            kind: AlignerKind::default(),
+            circular: false,
+            band: None,
+            matrix: None,
+        }
+    }
+
+    /// Use the algorithm `kind` instead of dynamic programming
+    pub fn kind(mut self, kind: AlignerKind) -> Self {
+        self.kind = kind;
+        self
+    }
+
+    /// Use `scoring` instead of the default scores
+    pub fn scoring(mut self, scoring: Scoring) -> Self {
+        self.scoring = scoring;
+        self
+    }
+
+    /// Score substitutions with `matrix` instead of the match and mismatch scores
+    pub fn matrix(mut self, matrix: SubstitutionMatrix) -> Self {
+        self.matrix = Some(matrix);
+        self
+    }
+
+    /// Treat targets as circular, like plasmids or mitochondrial genomes.
+    ///
+    /// Semi-global alignments may then run past the end of the target and continue at its
+    /// start. The target range of such an alignment starts within the target and ends past its
+    /// length: position `len + i` is position `i` after wrapping. Other modes align the target
+    /// as linear.
+    pub fn circular(mut self, circular: bool) -> Self {
+        self.circular = circular;
+        self
+    }
+
+    /// Only fill the diagonals within `width` of those joining the starts and the ends of the
+    /// two sequences.
+    ///
+    /// Time and memory then grow with the length times the width instead of the product of
+    /// the lengths, but alignments straying further from those diagonals, e.g. with more than
+    /// `width` net inserted or deleted nucleotides, are not found. This suits checking reads
+    /// against the reference region they came from, and other near-identical pairs.
+    pub fn band(mut self, width: usize) -> Self {
+        self.band = Some(width);
+        self
+    }
+
+    /// The alignment mode
+    pub fn mode(&self) -> AlignMode {
+        self.mode
+    }
+
+    /// Consider the cells `cols` of row `i` of `n` that an alignment may end in, given the
+    /// row's best scores `h`
+    fn update_end(
+        &self,
+        end: &mut (usize, usize, i32),
+        (i, n): (usize, usize),
+        cols: RangeInclusive<usize>,
+        h: &[i32],
+    ) {
+        let m = h.len() - 1;
+        let mut consider = |j: usize| {
+            if cols.contains(&j) && h[j] > end.2 {
+                *end = (i, j, h[j]);
+            }
+        };
+        match self.mode {
+            AlignMode::Global if i == n => consider(m),
+            AlignMode::SemiGlobal | AlignMode::Overlap if i == n => (0..=m).for_each(consider),
+            AlignMode::Overlap => consider(m),
+            AlignMode::Local => (0..=m).for_each(consider),
+            AlignMode::Global | AlignMode::SemiGlobal => {}
+        }
+    }
+
+    /// Find the best-scoring alignment of `query` against `target`.
+    ///
+    /// Among equally good alignments, dynamic programming prefers substitutions over gaps when
+    /// tracing back; wavefront alignment may pick another of them.
+    pub fn align(&self, query: &PackedDna, target: &PackedDna) -> Alignment {
+        if self.kind == AlignerKind::Wavefront
+            && self.mode == AlignMode::Global
+            && self.matrix.is_none()
+        {
+            let query: Vec<Nuc> = query.iter().collect();
+            let target: Vec<Nuc> = target.iter().collect();
+            if let Some(alignment) = wavefront::align(&query, &target, &self.scoring) {
+                return alignment;
+            }
+        }
+        self.align_codes(
+            query.iter().map(Iupac::from).collect(),
+            target.iter().map(Iupac::from).collect(),
+        )
+    }
+
+    /// Align every query against `target`, returning the alignments in query order.
+    ///
+    /// With the `rayon` feature the queries are spread over rayon's thread pool; run the call
+    /// inside `rayon::ThreadPool::install` to choose the number of threads.
+    pub fn align_batch(&self, queries: &[PackedDna], target: &PackedDna) -> Vec<Alignment> {
+        #[cfg(feature = "rayon")]
+        {
+            use rayon::prelude::*;
+            queries
+                .par_iter()
+                .map(|query| self.align(query, target))
+                .collect()
+        }
+        #[cfg(not(feature = "rayon"))]
+        queries
+            .iter()
+            .map(|query| self.align(query, target))
+            .collect()
+    }
+
+    /// Align `query` against `target` in as many local parts as score at least `min_score`, or
+    /// `None` if no part does.
+    ///
+    /// The best local alignment becomes the primary part. The query nucleotides on either side
+    /// of it are then aligned locally again, and so on, so parts never overlap in the query but
+    /// may lie anywhere in the target, in any order. The aligner's scores, matrix and band are
+    /// used whatever its mode.
+    pub fn align_split(
+        &self,
+        query: &PackedDna,
+        target: &PackedDna,
+        min_score: i32,
+    ) -> Option<SplitAlignment> {
+        let local = Aligner {
+            mode: AlignMode::Local,
+            ..*self
+        };
+        let align_part = |range: Range<usize>| {
+            let mut part = local.align(&query.to_slice(range.clone()), target);
+            part.query_range =
+                part.query_range.start + range.start..part.query_range.end + range.start;
+            part.query_len = query.len();
+            (part.score >= min_score && !part.ops.is_empty()).then_some(part)
+        };
+        let primary = align_part(0..query.len())?;
+        let mut supplementary = Vec::new();
+        // Unaligned query ranges still to try
+        let mut pending = vec![
+            0..primary.query_range.start,
+            primary.query_range.end..query.len(),
+        ];
+        while let Some(range) = pending.pop() {
+            if range.is_empty() {
+                continue;
+            }
+            if let Some(part) = align_part(range.clone()) {
+                pending.push(range.start..part.query_range.start);
+                pending.push(part.query_range.end..range.end);
+                supplementary.push(part);
+            }
+        }
+        supplementary.sort_by_key(|part| part.query_range.start);
+        Some(SplitAlignment {
+            primary,
+            supplementary,
+        })
+    }
+
+    /// Find the best-scoring alignment of `query` against `target`, which may hold ambiguity
+    /// codes.
+    ///
+    /// Without a [matrix](Self::matrix), a code only matches itself, so e.g. `N` against `A`
+    /// scores as a mismatch. Alignment always uses dynamic programming.
+    pub fn align_iupac(&self, query: &IupacDna, target: &IupacDna) -> Alignment {
+        self.align_codes(query.iter().collect(), target.iter().collect())
+    }
+
+    /// Find the best-scoring alignment of the coding sequences `query` and `target` codon by
+    /// codon, so that gaps never shift the reading frame.
+    ///
+    /// Both sequences are read in frame 0 and an incomplete last codon is left out of the
+    /// alignment. A pair of codons scores the sum of its three nucleotide pairs, unless they
+    /// are synonymous under the genetic `code` and then score like identical codons: a silent
+    /// change costs nothing, though its columns are still reported as mismatches. A gap of `L` codons
+    /// scores like one of `3L` nucleotides, and the band is rounded up to whole codons.
+    /// Alignment always uses dynamic programming, with circular targets aligned as linear.
+    pub fn align_codons(
+        &self,
+        query: &PackedDna,
+        target: &PackedDna,
+        code: GeneticCode,
+    ) -> Alignment {
+        let codons = |dna: &PackedDna| -> Vec<[Nuc; 3]> {
+            let nucs: Vec<Nuc> = dna.iter().collect();
+            nucs.chunks_exact(3)
+                .map(|codon| [codon[0], codon[1], codon[2]])
+                .collect()
+        };
+        let (query_codons, target_codons) = (codons(query), codons(target));
+        let aligner = Aligner {
+            scoring: Scoring {
+                gap_extend: 3 * self.scoring.gap_extend,
+                ..self.scoring
+            },
+            band: self.band.map(|width| width.div_ceil(3)),
+            ..*self
+        };
+        let substitution = |i: usize, j: usize| {
+            let (a, b) = (query_codons[i], target_codons[j]);
+            // Synonymous codons score as if the query codon were found in the target.
+            let b = if code.translate_codon(a) == code.translate_codon(b) {
+                a
+            } else {
+                b
+            };
+            (0..3)
+                .map(|k| self.substitution(a[k].into(), b[k].into()))
+                .sum()
+        };
+        let mut alignment = aligner.align_units(
+            (query_codons.len(), target_codons.len()),
+            3,
+            substitution,
+            |i, j, ops| {
+                for k in (0..3).rev() {
+                    ops.push(if query_codons[i][k] == target_codons[j][k] {
+                        AlignOp::Match
+                    } else {
+                        AlignOp::Mismatch
+                    });
+                }
+            },
+        );
+        alignment.query_len = query.len();
+        alignment
+    }
+
+    /// Score the best alignment of `query` against `target` without tracing it back.
+    ///
+    /// The score equals that of [`align`](Self::align) with dynamic programming, but only two
+    /// rows of the matrix are kept, so memory grows with the target length alone rather than
+    /// the product of the lengths. This suits filtering and screening stages that discard
+    /// most candidates.
+    pub fn score(&self, query: &PackedDna, target: &PackedDna) -> i32 {
+        self.score_codes(
+            query.iter().map(Iupac::from).collect(),
+            target.iter().map(Iupac::from).collect(),
+        )
+    }
+
+    /// Score the best alignment of sequences with ambiguity codes like
+    /// [`align_iupac`](Self::align_iupac), without tracing it back
+    pub fn score_iupac(&self, query: &IupacDna, target: &IupacDna) -> i32 {
+        self.score_codes(query.iter().collect(), target.iter().collect())
+    }
+
+    /// Score the best local (Smith–Waterman) alignment of `query` against `target` whatever the
+    /// mode, without tracing it back.
+    ///
+    /// This runs a striped SIMD kernel and suits quickly screening many short reads against
+    /// candidate regions. Among equally good alignments, the one ending first in the target,
+    /// then in the query, is reported.
+    pub fn local_score(&self, query: &PackedDna, target: &PackedDna) -> LocalScore {
+        let query: Vec<Nuc> = query.iter().collect();
+        let target: Vec<Nuc> = target.iter().collect();
+        let mut substitutions: Substitutions = [[0; 4]; 4];
+        for (row, a) in substitutions.iter_mut().zip(Iupac::ALL) {
+            for (score, b) in row.iter_mut().zip(Iupac::ALL) {
+                *score = self.substitution(a, b);
+            }
+        }
+        striped::local_score(&query, &target, &substitutions, &self.scoring)
+    }
+
+    /// The score of query code `a` against target code `b`
+    fn substitution(&self, a: Iupac, b: Iupac) -> i32 {
+        match &self.matrix {
+            Some(matrix) => matrix.score(a, b),
+            None if a == b => self.scoring.match_score,
+            None => self.scoring.mismatch,
+        }
+    }
+
+    /// Align with dynamic programming, wrapping circular targets
+    fn align_codes(&self, query: Vec<Iupac>, target: Vec<Iupac>) -> Alignment {
+        self.align_dp(&query, &self.wrap_circular(target))
+    }
+
+    /// The target to align against: for circular semi-global alignment, `target` followed by
+    /// all but its last nucleotide again
+    fn wrap_circular(&self, mut target: Vec<Iupac>) -> Vec<Iupac> {
+        if self.circular && self.mode == AlignMode::SemiGlobal {
+            // Any wrapping alignment lies within the doubled target. An alignment starting in
+            // the copy also occurs one length earlier, ending sooner, so the one found starts
+            // within the target.
+            let len = target.len();
+            target.extend_from_within(..len.saturating_sub(1));
+        }
+        target
+    }
+
+    /// Score with dynamic programming, wrapping circular targets
+    fn score_codes(&self, query: Vec<Iupac>, target: Vec<Iupac>) -> i32 {
+        let target = self.wrap_circular(target);
+        let layout = self.layout(query.len(), target.len());
+        let substitution = |i: usize, j: usize| self.substitution(query[i], target[j]);
+        self.fill((query.len(), target.len()), substitution, &layout, None)
+            .2
+    }
+
+    /// The cells filled when aligning `n` query against `m` target nucleotides
+    fn layout(&self, n: usize, m: usize) -> Layout {
+        let diagonals = m as isize - n as isize;
+        let (lo, hi) = match self.band {
+            Some(width) => (
+                diagonals.min(0) - width as isize,
+                diagonals.max(0) + width as isize,
+            ),
+            None => (-(n as isize), m as isize),
+        };
+        Layout {
+            m,
+            lo,
+            hi,
+            banded: self.band.is_some(),
+        }
+    }
+
+    /// Fill the matrix of `n` query against `m` target units with Gotoh's algorithm, returning
+    /// the best end cell as (row, column, score). `substitution` scores 0-based query unit `i`
+    /// against target unit `j`. With `trace`, one traceback byte per cell of `layout` is
+    /// recorded in it.
+    fn fill(
+        &self,
+        (n, m): (usize, usize),
+        substitution: impl Fn(usize, usize) -> i32,
+        layout: &Layout,
+        mut trace: Option<&mut [u8]>,
+    ) -> (usize, usize, i32) {
+        let scoring = &self.scoring;
+        let local = self.mode == AlignMode::Local;
+        let free_target_ends = self.mode != AlignMode::Global;
+        let free_query_ends = matches!(self.mode, AlignMode::Overlap | AlignMode::Local);
+        let open = scoring.gap_open + scoring.gap_extend;
+
+        // Row i of the matrices: best score overall (h), and ending in an insertion (ins).
+        // Deletions run along the row, so their matrix is a single running value.
+        let mut h: Vec<i32> = (0..=m)
+            .map(|j| match j {
+                _ if !layout.cols(0).contains(&j) => NEG_INF,
+                _ if free_target_ends => 0,
+                _ => scoring.gap(j),
+            })
+            .collect();
+        let mut ins = vec![NEG_INF; m + 1];
+        // Best end cell so far as (row, column, score)
+        let mut end = (0, 0, NEG_INF);
+        self.update_end(&mut end, (0, n), layout.cols(0), &h);
+        // Traceback bits of the current cell when not recording them
+        let mut scratch = 0;
+        for i in 1..=n {
+            let row = layout.cols(i);
+            let first = (*row.start()).max(1);
+            let mut diagonal = h[first - 1];
+            // Left of the band is out of reach, except for the boundary column.
+            h[first - 1] = match first - 1 {
+                0 if row.contains(&0) && free_query_ends => 0,
+                0 if row.contains(&0) => scoring.gap(i),
+                _ => NEG_INF,
+            };
+            let mut del = NEG_INF;
+            for j in first..=*row.end() {
+                let cell = match trace.as_deref_mut() {
+                    Some(trace) => &mut trace[layout.cell_idx(i, j)],
+                    None => &mut scratch,
+                };
+                let (open_ins, extend_ins) = (h[j] + open, ins[j] + scoring.gap_extend);
+                if extend_ins > open_ins {
+                    *cell |= INSERTION_EXTENDS;
+                }
+                ins[j] = open_ins.max(extend_ins);
+                let (open_del, extend_del) = (h[j - 1] + open, del + scoring.gap_extend);
+                if extend_del > open_del {
+                    *cell |= DELETION_EXTENDS;
+                }
+                del = open_del.max(extend_del);
+
+                let mut best = diagonal + substitution(i - 1, j - 1);
+                if ins[j] > best {
+                    best = ins[j];
+                    *cell |= FROM_INSERTION;
+                }
+                if del > best {
+                    best = del;
+                    *cell = (*cell & !0b11) | FROM_DELETION;
+                }
+                if local && best <= 0 {
+                    best = 0;
+                    *cell |= FROM_START;
+                }
+                diagonal = h[j];
+                h[j] = best;
+            }
+            self.update_end(&mut end, (i, n), row, &h);
+        }
+        end
+    }
+
+    /// Align with Gotoh's algorithm, then trace back from the best end cell
+    fn align_dp(&self, query: &[Iupac], target: &[Iupac]) -> Alignment {
+        self.align_units(
+            (query.len(), target.len()),
+            1,
+            |i, j| self.substitution(query[i], target[j]),
+            |i, j, ops| {
+                ops.push(if query[i] == target[j] {
+                    AlignOp::Match
+                } else {
+                    AlignOp::Mismatch
+                })
+            },
+        )
+    }
+
+    /// Align `n` query against `m` target units of `width` nucleotides each, scored by
+    /// `substitution` as in [`fill`](Self::fill), then trace back from the best end cell.
+    ///
+    /// `columns` pushes the columns of 0-based query unit `i` against target unit `j`, last
+    /// first; every gap column stands for `width` nucleotides.
+    fn align_units(
+        &self,
+        (n, m): (usize, usize),
+        width: usize,
+        substitution: impl Fn(usize, usize) -> i32,
+        columns: impl Fn(usize, usize, &mut Vec<AlignOp>),
+    ) -> Alignment {
+        let layout = self.layout(n, m);
+        let mut trace = vec![0u8; (n + 1) * layout.stride()];
+        let (end_i, end_j, score) = self.fill((n, m), substitution, &layout, Some(&mut trace));
+        let (mut i, mut j) = (end_i, end_j);
+        let mut ops = Vec::new();
+        #[derive(PartialEq)]
+        enum State {
+            Best,
+            Insertion,
+            Deletion,
+        }
+        let mut state = State::Best;
+        while i > 0 && j > 0 {
+            let cell = trace[layout.cell_idx(i, j)];
+            match state {
+                State::Best => match cell & 0b11 {
+                    FROM_START => break,
+                    FROM_DIAGONAL => {
+                        columns(i - 1, j - 1, &mut ops);
+                        i -= 1;
+                        j -= 1;
+                    }
+                    FROM_INSERTION => state = State::Insertion,
+                    FROM_DELETION => state = State::Deletion,
+                    _ => unreachable!("two-bit traceback source"),
+                },
+                State::Insertion => {
+                    ops.extend(std::iter::repeat_n(AlignOp::Insertion, width));
+                    if cell & INSERTION_EXTENDS == 0 {
+                        state = State::Best;
+                    }
+                    i -= 1;
+                }
+                State::Deletion => {
+                    ops.extend(std::iter::repeat_n(AlignOp::Deletion, width));
+                    if cell & DELETION_EXTENDS == 0 {
+                        state = State::Best;
+                    }
+                    j -= 1;
+                }
+            }
+        }
+        // Leading end gaps: charged ones become columns, free ones are left out.
+        if !matches!(self.mode, AlignMode::Overlap | AlignMode::Local) {
+            ops.extend(std::iter::repeat_n(AlignOp::Insertion, i * width));
+            i = 0;
+        }
+        if self.mode == AlignMode::Global {
+            ops.extend(std::iter::repeat_n(AlignOp::Deletion, j * width));
+            j = 0;
+        }
+        ops.reverse();
+        Alignment {
+            score,
+            query_range: i * width..end_i * width,
+            target_range: j * width..end_j * width,
+            ops,
+            query_len: n * width,
+        }
+    }
+}
+
+/// The cells of an `n` by `m` dynamic programming matrix that are filled: those with
+/// `lo <= j - i <= hi`, which without a band is all of them
+struct Layout {
+    m: usize,
+    lo: isize,
+    hi: isize,
+    banded: bool,
+}
+
+impl Layout {
+    /// The filled columns of row `i`
+    fn cols(&self, i: usize) -> RangeInclusive<usize> {
+        let first = (i as isize + self.lo).max(0) as usize;
+        let last = (i as isize + self.hi).min(self.m as isize) as usize;
+        first..=last
+    }
+
+    /// Traceback bytes per row: whole rows, or the band's diagonals
+    fn stride(&self) -> usize {
+        if self.banded {
+            (self.hi - self.lo + 1) as usize
+        } else {
+            self.m + 1
+        }
+    }
+
+    /// Index of the traceback byte of cell (`i`, `j`)
+    fn cell_idx(&self, i: usize, j: usize) -> usize {
+        let col = if self.banded {
+            (j as isize - i as isize - self.lo) as usize
+        } else {
+            j
+        };
+        i * self.stride() + col
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::{dna, Lcg};
+    use std::str::FromStr;
+
+    fn align(mode: AlignMode, query: &str, target: &str) -> Alignment {
+        Aligner::new(mode).align(&dna(query), &dna(target))
+    }
+
+    #[test]
+    fn global_identical_and_mismatch() {
+        let alignment = align(AlignMode::Global, "ACGTACGT", "ACGTACGT");
+        assert_eq!(alignment.score, 8);
+        assert_eq!(alignment.cigar(), "8=");
+        let alignment = align(AlignMode::Global, "ACGTTGCA", "AGGTTGCA");
+        assert_eq!(alignment.score, 7 - 4);
+        assert_eq!(alignment.cigar(), "1=1X6=");
+    }
+
+    #[test]
+    fn global_affine_gap() {
+        let alignment = align(AlignMode::Global, "ACGTACGTACGT", "ACGTGGGACGTACGT");
+        assert_eq!(alignment.cigar(), "4=3D8=");
+        assert_eq!(alignment.score, 12 - 6 - 3);
+        assert_eq!(alignment.query_range, 0..12);
+        assert_eq!(alignment.target_range, 0..15);
+    }
+
+    #[test]
+    fn affine_prefers_one_long_gap() {
+        // Two separate 1-nucleotide gaps would cost two openings.
+        let query = "GATCGATCAAGGTCCA";
+        let target = "GATCGATCAGGTCCA";
+        let linear = Scoring {
+            gap_open: 0,
+            ..Scoring::default()
+        };
+        let affine = Aligner::new(AlignMode::Global).align(&dna(target), &dna(query));
+        assert_eq!(affine.score, 15 - 7);
+        assert_eq!(
+            affine
+                .ops
+                .iter()
+                .filter(|&&op| op == AlignOp::Deletion)
+                .count(),
+            1
+        );
+        let linear = Aligner::new(AlignMode::Global)
+            .scoring(linear)
+            .align(&dna(target), &dna(query));
+        assert_eq!(linear.score, 15 - 1);
+    }
+
+    #[test]
+    fn global_charges_end_gaps() {
+        let alignment = align(AlignMode::Global, "TTACA", "GATTACAG");
+        assert_eq!(alignment.cigar(), "2D5=1D");
+        assert_eq!(alignment.score, 5 - 8 - 7);
+        let alignment = align(AlignMode::Global, "", "ACG");
+        assert_eq!(alignment.cigar(), "3D");
+        assert_eq!(alignment.score, -9);
+        let alignment = align(AlignMode::Global, "", "");
+        assert_eq!(alignment.cigar(), "");
+        assert_eq!(alignment.score, 0);
+    }
+
+    #[test]
+    fn semi_global_places_read() {
+        let alignment = align(AlignMode::SemiGlobal, "TTACA", "GGGGATTACAGGGG");
+        assert_eq!(alignment.score, 5);
+        assert_eq!(alignment.cigar(), "5=");
+        assert_eq!(alignment.query_range, 0..5);
+        assert_eq!(alignment.target_range, 5..10);
+        // The read must still be aligned end to end.
+        let alignment = align(AlignMode::SemiGlobal, "CCTTACA", "GGGGATTACAGGGG");
+        assert_eq!(alignment.query_range, 0..7);
+        assert_eq!(alignment.cigar(), "2X5=");
+    }
+
+    #[test]
+    fn overlap_joins_contig_ends() {
+        let alignment = align(AlignMode::Overlap, "CCCCGATTACA", "GATTACATTTT");
+        assert_eq!(alignment.score, 7);
+        assert_eq!(alignment.cigar(), "7=");
+        assert_eq!(alignment.query_range, 4..11);
+        assert_eq!(alignment.target_range, 0..7);
+        let alignment = align(AlignMode::Overlap, "GATTACATTTT", "CCCCGATTACA");
+        assert_eq!(alignment.query_range, 0..7);
+        assert_eq!(alignment.target_range, 4..11);
+    }
+
+    /// Score `ops` as a global alignment
+    fn rescore(ops: &[AlignOp], scoring: &Scoring) -> i32 {
+        let mut score = 0;
+        for (idx, &op) in ops.iter().enumerate() {
+            score += match op {
+                AlignOp::Match => scoring.match_score,
+                AlignOp::Mismatch => scoring.mismatch,
+                _ if idx > 0 && ops[idx - 1] == op => scoring.gap_extend,
+                _ => scoring.gap_open + scoring.gap_extend,
+            };
+        }
+        score
+    }
+
+    #[test]
+    fn wavefront_matches_dynamic_programming() {
+        let mut rng = Lcg::new(0x2545_f491_4f6c_dd1d);
+        let mut next = |bound: u64| rng.below(bound);
+        let nucs = [Nuc::A, Nuc::C, Nuc::G, Nuc::T];
+        let scorings = [
+            Scoring::default(),
+            Scoring {
+                match_score: 0,
+                mismatch: -1,
+                gap_open: 0,
+                gap_extend: -1,
+            },
+            Scoring {
+                match_score: 2,
+                mismatch: -3,
+                gap_open: -5,
+                gap_extend: -2,
+            },
+        ];
+        for case in 0..200 {
+            let len = next(60) as usize;
+            let target: PackedDna = (0..len).map(|_| nucs[next(4) as usize]).collect();
+            let mut query = PackedDna::empty();
+            for nuc in target.iter() {
+                match next(12) {
+                    0 => {}
+                    1 => {
+                        query.push(nuc);
+                        query.push(nucs[next(4) as usize]);
+                    }
+                    2 => query.push(nucs[next(4) as usize]),
+                    _ => query.push(nuc),
+                }
+            }
+            let scoring = scorings[case % scorings.len()];
+            let dp = Aligner::new(AlignMode::Global)
+                .scoring(scoring)
+                .align(&query, &target);
+            let wfa = Aligner::new(AlignMode::Global)
+                .scoring(scoring)
+                .kind(AlignerKind::Wavefront)
+                .align(&query, &target);
+            assert_eq!(wfa.score, dp.score, "{} vs {}", query, target);
+            assert_eq!(rescore(&wfa.ops, &scoring), wfa.score);
+            let consumed = |skip: AlignOp| wfa.ops.iter().filter(|&&op| op != skip).count();
+            assert_eq!(consumed(AlignOp::Deletion), query.len());
+            assert_eq!(consumed(AlignOp::Insertion), target.len());
+        }
+    }
+
+    #[test]
+    fn wavefront_falls_back_outside_global() {
+        let aligner = Aligner::new(AlignMode::SemiGlobal).kind(AlignerKind::Wavefront);
+        let alignment = aligner.align(&dna("TTACA"), &dna("GGGGATTACAGGGG"));
+        assert_eq!(alignment.target_range, 5..10);
+        let alignment = Aligner::new(AlignMode::Global)
+            .kind(AlignerKind::Wavefront)
+            .align(&dna("GATTACA"), &dna("GATCTACA"));
+        assert_eq!(alignment.cigar(), "3=1D4=");
+    }
+
+    #[test]
+    fn local_score_finds_read() {
+        let aligner = Aligner::new(AlignMode::Global);
+        let score = aligner.local_score(&dna("CCCGATTACACCC"), &dna("TTTTGATTACATTTT"));
+        assert_eq!(score.score, 7);
+        assert_eq!((score.query_end, score.target_end), (10, 11));
+        let score = aligner.local_score(&dna("AAAA"), &dna("CCCC"));
+        assert_eq!(score.score, 0);
+    }
+
+    #[test]
+    fn circular_target_wraps_origin() {
+        let query = dna("GATTACA");
+        let target = dna("TTACACCCCCCCCGA");
+        let aligner = Aligner::new(AlignMode::SemiGlobal).circular(true);
+        let alignment = aligner.align(&query, &target);
+        assert_eq!(alignment.score, 7);
+        assert_eq!(alignment.cigar(), "7=");
+        assert_eq!(alignment.target_range, 13..20);
+        let linear = Aligner::new(AlignMode::SemiGlobal).align(&query, &target);
+        assert!(linear.score < 7);
+        // Alignments that do not wrap are unaffected.
+        let alignment = aligner.align(&dna("CCCCG"), &target);
+        assert_eq!(alignment.target_range, 9..14);
+        let alignment = aligner.align(&query, &dna(""));
+        assert_eq!(alignment.cigar(), "7I");
+    }
+
+    #[test]
+    fn local_finds_shared_region() {
+        let alignment = align(AlignMode::Local, "CCCCCGATTACACCCCC", "TTTTGATTTACATTTT");
+        assert_eq!(alignment.cigar(), "5=");
+        assert_eq!(alignment.score, 5);
+        assert_eq!(alignment.query_range, 7..12);
+        assert_eq!(alignment.target_range, 7..12);
+        // With cheaper gaps the alignment bridges the extra T.
+        let alignment = Aligner::new(AlignMode::Local)
+            .scoring(Scoring {
+                match_score: 2,
+                gap_open: -1,
+                ..Scoring::default()
+            })
+            .align(&dna("CCCCCGATTACACCCCC"), &dna("TTTTGATTTACATTTT"));
+        assert_eq!(alignment.cigar(), "2=1D5=");
+        assert_eq!(alignment.score, 14 - 2);
+        assert_eq!(alignment.query_range, 5..12);
+        assert_eq!(alignment.target_range, 4..12);
+        let alignment = align(AlignMode::Local, "AAAA", "CCCC");
+        assert_eq!(alignment.score, 0);
+        assert_eq!(alignment.cigar(), "");
+        assert_eq!(alignment.query_range, 0..0);
+    }
+
+    #[test]
+    fn codon_alignment_keeps_frame() {
+        let aligner = Aligner::new(AlignMode::Global);
+        let code = GeneticCode::Standard;
+        let alignment = aligner.align_codons(&dna("ATGAAACCCGGGTAA"), &dna("ATGAAAGGGTAA"), code);
+        assert_eq!(alignment.cigar(), "6=3I6=");
+        assert_eq!(alignment.score, 12 - 6 - 3);
+
+        // Two deleted nucleotides cannot be a gap of their own, and the trailing T of the
+        // incomplete codon is left out.
+        let query = dna("ATGAAACCCGGGTTT");
+        let target = dna("ATGAAACGGGTTT");
+        assert_eq!(aligner.align(&query, &target).cigar(), "6=2I7=");
+        let alignment = aligner.align_codons(&query, &target, code);
+        assert_eq!(alignment.target_range, 0..12);
+        assert_eq!(
+            (alignment.query_range.clone(), alignment.query_len),
+            (0..15, 15)
+        );
+        let mut ops = alignment.ops.iter().peekable();
+        while let Some(&op) = ops.next() {
+            let mut run = 1;
+            while ops.next_if_eq(&&op).is_some() {
+                run += 1;
+            }
+            if matches!(op, AlignOp::Insertion | AlignOp::Deletion) {
+                assert_eq!(run % 3, 0, "{}", alignment.cigar());
+            }
+        }
+    }
+
+    #[test]
+    fn codon_alignment_scores_silent_changes_as_matches() {
+        // CTG and TTA both encode leucine, GAA and GAT glutamate and aspartate.
+        let aligner = Aligner::new(AlignMode::Global);
+        let alignment = aligner.align_codons(&dna("CTGAAA"), &dna("TTAAAA"), GeneticCode::Standard);
+        assert_eq!(alignment.cigar(), "1X1=1X3=");
+        assert_eq!(alignment.score, 6);
+        let alignment = aligner.align_codons(&dna("GAAAAA"), &dna("GATAAA"), GeneticCode::Standard);
+        assert_eq!(alignment.score, 5 - 4);
+        let alignment = Aligner::new(AlignMode::SemiGlobal).align_codons(
+            &dna("AAAGGG"),
+            &dna("CCCAAAGGGCCC"),
+            GeneticCode::Standard,
+        );
+        assert_eq!(alignment.target_range, 3..9);
+    }
+
+    #[test]
+    fn sam_cigar_clips_unaligned_ends() {
+        let alignment = align(AlignMode::Local, "CCCCCGATTACACCCCC", "TTTTGATTTACATTTT");
+        assert_eq!(alignment.clips(), (7, 5));
+        assert_eq!(alignment.sam_cigar(Clip::Soft), "7S5=5S");
+        assert_eq!(alignment.sam_cigar(Clip::Hard), "7H5=5H");
+        let alignment = align(AlignMode::Global, "GATTACA", "GATCTACA");
+        assert_eq!(alignment.clips(), (0, 0));
+        assert_eq!(alignment.sam_cigar(Clip::Soft), alignment.cigar());
+        let alignment = align(AlignMode::Overlap, "GGGGGACGTACGT", "ACGTACGTTTTTT");
+        assert_eq!(alignment.sam_cigar(Clip::Soft), "5S8=");
+    }
+
+    #[test]
+    fn split_alignment_of_chimeric_read() {
+        let first = "GATTACAGGCTTACCGATAC";
+        let second = "TTGCAAGCTCGTAAGCTTCA";
+        let target = dna(&format!("{}CCCCCCCCCC{}", first, second));
+        // The read joins the end of the target to its start.
+        let query = dna(&format!("{}{}", second, first));
+        let aligner = Aligner::new(AlignMode::Global);
+        let split = aligner.align_split(&query, &target, 10).unwrap();
+        assert!(split.is_split());
+        assert_eq!(split.supplementary.len(), 1);
+        let parts = split.parts();
+        assert_eq!(parts[0].query_range, 0..20);
+        assert_eq!(parts[0].target_range, 30..50);
+        assert_eq!(parts[1].query_range, 20..40);
+        assert_eq!(parts[1].target_range, 0..20);
+        assert_eq!(parts[0].sam_cigar(Clip::Soft), "20=20S");
+        assert_eq!(parts[1].sam_cigar(Clip::Hard), "20H20=");
+
+        let whole = aligner.align_split(&dna(first), &target, 10).unwrap();
+        assert!(!whole.is_split());
+        assert_eq!(whole.primary.cigar(), "20=");
+        assert_eq!(aligner.align_split(&dna("AAAA"), &target, 10), None);
+    }
+
+    #[test]
+    fn banded_matches_full_near_diagonal() {
+        let query = dna("ACGTTGCACTGGATTACAACGTTGCATTGACCAGT");
+        let target = dna("ACGTTGCACTGGTTACAACGTTTGCATTGACCAGTA");
+        for mode in [
+            AlignMode::Global,
+            AlignMode::SemiGlobal,
+            AlignMode::Overlap,
+            AlignMode::Local,
+        ] {
+            let full = Aligner::new(mode).align(&query, &target);
+            let banded = Aligner::new(mode).band(2).align(&query, &target);
+            assert_eq!(banded, full, "{:?}", mode);
+        }
+        // A 6-nucleotide deletion, then an insertion, stray outside a band of 2.
+        let target = dna("ACGTTGCACTGGGGGGGGATTACAACGTTGCACAGT");
+        let full = Aligner::new(AlignMode::Global).align(&query, &target);
+        assert!(full.cigar().contains("6D"));
+        let banded = Aligner::new(AlignMode::Global)
+            .band(2)
+            .align(&query, &target);
+        assert!(banded.score < full.score);
+        assert_eq!(banded.query_range, 0..35);
+        assert_eq!(banded.target_range, 0..36);
+    }
+
+    #[test]
+    fn matrix_scores_ambiguity_partially() {
+        let matrix = SubstitutionMatrix::new(1, -4);
+        assert_eq!(matrix.score(Iupac::A, Iupac::A), 1);
+        assert_eq!(matrix.score(Iupac::N, Iupac::T), -3);
+        assert_eq!(matrix.score(Iupac::R, Iupac::G), -2);
+        assert_eq!(matrix.score(Iupac::R, Iupac::Y), -4);
+
+        let query = IupacDna::from_str("GATNACA").unwrap();
+        let target = IupacDna::from_str("GATTACA").unwrap();
+        let flat = Aligner::new(AlignMode::Global).align_iupac(&query, &target);
+        assert_eq!(flat.score, 6 - 4);
+        assert_eq!(flat.cigar(), "3=1X3=");
+        let aligner = Aligner::new(AlignMode::Global).matrix(matrix);
+        assert_eq!(aligner.align_iupac(&query, &target).score, 6 - 3);
+    }
+
+    #[test]
+    fn custom_nucleotide_matrix() {
+        // Transitions (A <-> G, C <-> T) cost less than transversions.
+        let matrix = SubstitutionMatrix::from_nucs([
+            [2, -3, -1, -3],
+            [-3, 2, -3, -1],
+            [-1, -3, 2, -3],
+            [-3, -1, -3, 2],
+        ]);
+        let aligner = Aligner::new(AlignMode::Global).matrix(matrix);
+        let transition = aligner.align(&dna("GATTACA"), &dna("GATTGCA"));
+        let transversion = aligner.align(&dna("GATTACA"), &dna("GATTCCA"));
+        assert_eq!(transition.score, 12 - 1);
+        assert_eq!(transversion.score, 12 - 3);
+        assert_eq!(transition.cigar(), "4=1X2=");
+        let aligner = Aligner::new(AlignMode::Local).matrix(matrix);
+        let score = aligner.local_score(&dna("GATTACA"), &dna("CCGATTGCACC"));
+        assert_eq!(score.score, 11);
+        assert_eq!((score.query_end, score.target_end), (7, 9));
+    }
+
+    #[test]
+    fn batch_keeps_query_order() {
+        let target = dna("GGGGATTACAGGGGCCCCTTTTGGGG");
+        let queries: Vec<PackedDna> = ["TTACA", "CCCCTT", "GGGG", "", "ACAGGGGCC"]
+            .iter()
+            .map(|query| dna(query))
+            .collect();
+        let aligner = Aligner::new(AlignMode::SemiGlobal);
+        let expected: Vec<Alignment> = queries
+            .iter()
+            .map(|query| aligner.align(query, &target))
+            .collect();
+        assert_eq!(aligner.align_batch(&queries, &target), expected);
+        assert_eq!(expected[1].target_range, 14..20);
+    }
+
+    #[test]
+    fn score_only_matches_alignment() {
+        let query = dna("ACGTTGCACTGGATTACAACGTTGCATTGACCAGT");
+        let target = dna("TTACGTTGCACTGGTTACAACGTTTGCATTGACCAGTA");
+        for mode in [
+            AlignMode::Global,
+            AlignMode::SemiGlobal,
+            AlignMode::Overlap,
+            AlignMode::Local,
+        ] {
+            for aligner in [
+                Aligner::new(mode),
+                Aligner::new(mode).band(3),
+                Aligner::new(mode).circular(true),
+            ] {
+                assert_eq!(
+                    aligner.score(&query, &target),
+                    aligner.align(&query, &target).score,
+                    "{:?}",
+                    aligner
+                );
+                assert_eq!(
+                    aligner.score(&target, &query),
+                    aligner.align(&target, &query).score,
+                    "{:?}",
+                    aligner
+                );
+            }
+        }
+        let aligner = Aligner::new(AlignMode::Global).matrix(SubstitutionMatrix::new(1, -4));
+        let (query, target) = (
+            IupacDna::from_str("GATNACA").unwrap(),
+            IupacDna::from_str("GATTACA").unwrap(),
+        );
+        assert_eq!(aligner.score_iupac(&query, &target), 6 - 3);
+        assert_eq!(
+            Aligner::new(AlignMode::Global).score(&dna(""), &dna("ACG")),
+            -6 - 3
+        );
+    }
+}
@@ -0,0 +1,181 @@
+//! Cache-line aligned, zero-padded byte storage for packed sequences.
+
+use std::{
+    fmt,
+    hash::{Hash, Hasher},
+    ops::{Deref, DerefMut},
+    slice,
+};
+
+/// Alignment (and padding granularity) of the buffer in bytes
+pub(crate) const ALIGNMENT: usize = 64;
+
+/// One aligned block of storage
+#[derive(Clone, Copy)]
+#[repr(C, align(64))]
+struct Block([u8; ALIGNMENT]);
+
+const ZERO_BLOCK: Block = Block([0; ALIGNMENT]);
+
+/// A growable byte buffer whose storage starts on a 64-byte boundary.
+///
+/// The storage is always a whole number of 64-byte blocks and every byte past the logical length
+/// is zero, so vectorized kernels can load full 32- or 64-byte lanes up to [`padded`] without any
+/// tail handling.
+///
+/// [`padded`]: AlignedBuf::padded
+#[derive(Clone, Default)]
+pub(crate) struct AlignedBuf {
+    blocks: Vec<Block>,
+    len: usize,
+}
+
+impl AlignedBuf {
+    /// Create an empty buffer without allocating
+    pub(crate) fn new() -> Self {
+        Self::default()
+    }
+
+    /// Create an empty buffer able to hold `bytes` bytes without reallocating
+    pub(crate) fn with_capacity(bytes: usize) -> Self {
+        AlignedBuf {
+            blocks: Vec::with_capacity(bytes.div_ceil(ALIGNMENT)),
+            len: 0,
+        }
+    }
+
+    /// Number of bytes the buffer can hold without reallocating
+    pub(crate) fn capacity(&self) -> usize {
+        self.blocks.capacity() * ALIGNMENT
+    }
+
+    /// Make room for at least `additional` more bytes without reallocating
+    pub(crate) fn reserve(&mut self, additional: usize) {
+        let blocks = (self.len + additional).div_ceil(ALIGNMENT);
+        self.blocks
+            .reserve(blocks.saturating_sub(self.blocks.len()));
+    }
+
+    /// Append a byte
+    pub(crate) fn push(&mut self, byte: u8) {
+        if self.len == self.blocks.len() * ALIGNMENT {
+            self.blocks.push(ZERO_BLOCK);
+        }
+        self.blocks[self.len / ALIGNMENT].0[self.len % ALIGNMENT] = byte;
+        self.len += 1;
+    }
+
+    /// Shorten the buffer to `len` bytes, zeroing the removed bytes
+    pub(crate) fn truncate(&mut self, len: usize) {
+        if len >= self.len {
+            return;
+        }
+        let old_len = self.len;
+        self.len = len;
+        self.padded_mut()[len..old_len].fill(0);
+        self.blocks.truncate(len.div_ceil(ALIGNMENT));
+    }
+
+    /// The whole storage, a multiple of 64 bytes long and zero past the logical length
+    pub(crate) fn padded(&self) -> &[u8] {
+        // SAFETY: `Block` is `repr(C)` around a `[u8; ALIGNMENT]`, so the blocks form one
+        // contiguous, initialized run of `blocks.len() * ALIGNMENT` bytes.
+        unsafe { slice::from_raw_parts(self.blocks.as_ptr().cast(), self.blocks.len() * ALIGNMENT) }
+    }
+
+    fn padded_mut(&mut self) -> &mut [u8] {
+        // SAFETY: as in `padded`, and the exclusive borrow of `self` guarantees unique access.
+        unsafe {
+            slice::from_raw_parts_mut(
+                self.blocks.as_mut_ptr().cast(),
+                self.blocks.len() * ALIGNMENT,
+            )
+        }
+    }
+}
+
+impl Deref for AlignedBuf {
+    type Target = [u8];
+
+    fn deref(&self) -> &[u8] {
+        &self.padded()[..self.len]
+    }
+}
+
+impl DerefMut for AlignedBuf {
+    fn deref_mut(&mut self) -> &mut [u8] {
+        let len = self.len;
+        &mut self.padded_mut()[..len]
+    }
+}
+
+impl fmt::Debug for AlignedBuf {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Debug::fmt(&**self, f)
+    }
+}
+
+impl PartialEq for AlignedBuf {
+    fn eq(&self, other: &Self) -> bool {
+        **self == **other
+    }
+}
+
+impl Eq for AlignedBuf {}
+
+impl Hash for AlignedBuf {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        (**self).hash(state)
+    }
+}
+
+impl<const N: usize> PartialEq<[u8; N]> for AlignedBuf {
+    fn eq(&self, other: &[u8; N]) -> bool {
+        **self == other[..]
+    }
+}
+
+impl From<&[u8]> for AlignedBuf {
+    fn from(bytes: &[u8]) -> Self {
+        let mut buf = AlignedBuf::with_capacity(bytes.len());
+        for &byte in bytes {
+            buf.push(byte);
+        }
+        buf
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn aligned_buf_is_aligned() {
+        let mut buf = AlignedBuf::new();
+        buf.push(1);
+        assert_eq!(buf.padded().as_ptr() as usize % ALIGNMENT, 0);
+    }
+
+    #[test]
+    fn aligned_buf_padding_is_zeroed() {
+        let mut buf = AlignedBuf::new();
+        for byte in 1..=70 {
+            buf.push(byte);
+        }
+        assert_eq!(buf.len(), 70);
+        assert_eq!(buf.padded().len(), 128);
+        assert!(buf.padded()[70..].iter().all(|&b| b == 0));
+        buf.truncate(3);
+        assert_eq!(buf, [1, 2, 3]);
+        assert_eq!(buf.padded().len(), 64);
+        assert!(buf.padded()[3..].iter().all(|&b| b == 0));
+    }
+
+    #[test]
+    fn aligned_buf_truncate_to_empty() {
+        let mut buf = AlignedBuf::from(&[5u8, 6, 7][..]);
+        buf.truncate(0);
+        assert!(buf.is_empty());
+        assert!(buf.padded().is_empty());
+    }
+}
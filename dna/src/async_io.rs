@@ -0,0 +1,309 @@
+//! Asynchronous reading and packing for tokio-based services, with the `async` feature.
+//!
+//! [`FastaReader`] and [`FastqReader`] read records from any [`AsyncRead`], such as a socket or
+//! the body of a request, like their blocking counterparts in [`fasta`](crate::fasta) and
+//! [`fastq`](crate::fastq). Each line is packed as soon as it is read, so only one line of text
+//! is buffered however long the record, and a slow consumer holds back the sender instead of
+//! filling memory. Bare sequence text can be packed with [`pack`], or by copying it into a
+//! [`StreamingPacker`], which is an [`AsyncWrite`].
+
+use crate::{
+    fasta::{FastaError, Record},
+    fastq::{self, FastqError, Read},
+    stream::StreamingPacker,
+    DnaError, Nuc, PackedDna,
+};
+use std::{
+    convert::TryFrom,
+    io,
+    pin::Pin,
+    task::{Context, Poll},
+};
+use tokio::io::{AsyncBufReadExt, AsyncRead, AsyncReadExt, AsyncWrite, BufReader};
+
+/// Size of the chunks [`pack`] reads
+const CHUNK_SIZE: usize = 8 * 1024;
+
+/// Read the next line of `inner` into `line` without its trailing whitespace, counting it in
+/// `line_number`, and return `false` at end of input
+async fn read_line<R: AsyncRead + Unpin>(
+    inner: &mut BufReader<R>,
+    line: &mut String,
+    line_number: &mut usize,
+) -> io::Result<bool> {
+    line.clear();
+    if inner.read_line(line).await? == 0 {
+        return Ok(false);
+    }
+    *line_number += 1;
+    let trimmed = line.trim_end().len();
+    line.truncate(trimmed);
+    Ok(true)
+}
+
+/// An async FASTA reader yielding one [`Record`] at a time, see
+/// [`fasta::Reader`](crate::fasta::Reader)
+#[derive(Debug)]
+pub struct FastaReader<R> {
+    inner: BufReader<R>,
+    line: String,
+    line_number: usize,
+    next_header: Option<String>,
+}
+
+impl<R: AsyncRead + Unpin> FastaReader<R> {
+    /// Create a reader over any async byte source
+    pub fn new(inner: R) -> Self {
+        FastaReader {
+            inner: BufReader::new(inner),
+            line: String::new(),
+            line_number: 0,
+            next_header: None,
+        }
+    }
+
+    async fn read_line(&mut self) -> io::Result<bool> {
+        read_line(&mut self.inner, &mut self.line, &mut self.line_number).await
+    }
+
+    /// Read the next record, or `None` at the end of the input
+    pub async fn next_record(&mut self) -> Result<Option<Record>, FastaError> {
+        let header = match self.next_header.take() {
+            Some(header) => header,
+            None => loop {
+                if !self.read_line().await? {
+                    return Ok(None);
+                }
+                if let Some(header) = self.line.strip_prefix('>') {
+                    break header.to_string();
+                }
+                if !self.line.is_empty() && !self.line.starts_with(';') {
+                    return Err(FastaError::MissingHeader(self.line_number));
+                }
+            },
+        };
+        let mut sequence = PackedDna::empty();
+        while self.read_line().await? {
+            if let Some(next) = self.line.strip_prefix('>') {
+                self.next_header = Some(next.to_string());
+                break;
+            }
+            if self.line.starts_with(';') {
+                continue;
+            }
+            for c in self.line.chars() {
+                let nuc = Nuc::try_from(c).map_err(|_| FastaError::InvalidNuc {
+                    line: self.line_number,
+                    nuc: c,
+                })?;
+                sequence.push(nuc);
+            }
+        }
+        Ok(Some(Record::new(header, sequence)))
+    }
+}
+
+/// An async FASTQ reader yielding one [`Read`] at a time, see
+/// [`fastq::Reader`](crate::fastq::Reader)
+#[derive(Debug)]
+pub struct FastqReader<R> {
+    inner: BufReader<R>,
+    line: String,
+    line_number: usize,
+}
+
+impl<R: AsyncRead + Unpin> FastqReader<R> {
+    /// Create a reader over any async byte source
+    pub fn new(inner: R) -> Self {
+        FastqReader {
+            inner: BufReader::new(inner),
+            line: String::new(),
+            line_number: 0,
+        }
+    }
+
+    async fn read_line(&mut self) -> io::Result<bool> {
+        read_line(&mut self.inner, &mut self.line, &mut self.line_number).await
+    }
+
+    /// Read the next line of a record that has already started
+    async fn expect_line(&mut self) -> Result<(), FastqError> {
+        if self.read_line().await? {
+            Ok(())
+        } else {
+            Err(FastqError::Truncated(self.line_number))
+        }
+    }
+
+    /// Read the next read, or `None` at the end of the input
+    pub async fn next_read(&mut self) -> Result<Option<Read>, FastqError> {
+        loop {
+            if !self.read_line().await? {
+                return Ok(None);
+            }
+            if !self.line.is_empty() {
+                break;
+            }
+        }
+        let header = match self.line.strip_prefix('@') {
+            Some(header) => header.to_string(),
+            None => return Err(FastqError::MissingHeader(self.line_number)),
+        };
+
+        self.expect_line().await?;
+        let sequence = fastq::parse_sequence(&self.line, self.line_number)?;
+
+        self.expect_line().await?;
+        if !self.line.starts_with('+') {
+            return Err(FastqError::MissingSeparator(self.line_number));
+        }
+
+        self.expect_line().await?;
+        let qualities = fastq::parse_qualities(&self.line, self.line_number, sequence.len())?;
+        Ok(Some(Read::new(header, sequence, qualities)))
+    }
+}
+
+/// Feeds every write, so an async reader can be packed with [`tokio::io::copy`]. Errors are
+/// [`io::ErrorKind::InvalidData`] wrapping the [`DnaError`].
+impl AsyncWrite for StreamingPacker {
+    fn poll_write(
+        self: Pin<&mut Self>,
+        _cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<io::Result<usize>> {
+        Poll::Ready(io::Write::write(self.get_mut(), buf))
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, _cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        Poll::Ready(Ok(()))
+    }
+
+    fn poll_shutdown(self: Pin<&mut Self>, _cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        Poll::Ready(Ok(()))
+    }
+}
+
+/// Pack all of `input` with `packer`, feeding each chunk as it arrives
+pub async fn pack<R: AsyncRead + Unpin>(
+    mut input: R,
+    mut packer: StreamingPacker,
+) -> Result<PackedDna, DnaError> {
+    let mut chunk = vec![0; CHUNK_SIZE];
+    loop {
+        let len = input.read(&mut chunk).await?;
+        if len == 0 {
+            return packer.finish();
+        }
+        packer.feed(&chunk[..len])?;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{fasta, fastq};
+    use tokio::io::AsyncWriteExt;
+
+    const FASTA: &str = "; comment\n>seq1 first\nACGT\r\nTG\n\n>seq2\n>seq3\nacgtt\n";
+
+    #[tokio::test]
+    async fn fasta_matches_blocking_reader() {
+        let mut reader = FastaReader::new(FASTA.as_bytes());
+        let expected: Vec<Record> = fasta::Reader::new(FASTA.as_bytes())
+            .collect::<Result<_, _>>()
+            .unwrap();
+        for record in &expected {
+            let read = reader.next_record().await.unwrap().unwrap();
+            assert_eq!(read.header(), record.header());
+            assert_eq!(read.sequence(), record.sequence());
+        }
+        assert!(reader.next_record().await.unwrap().is_none());
+
+        let err = FastaReader::new(">a\nAC\nGX\n".as_bytes())
+            .next_record()
+            .await
+            .unwrap_err();
+        assert_eq!(
+            "failed to parse nucleotide from X on line 3",
+            err.to_string()
+        );
+        let err = FastaReader::new("ACGT\n".as_bytes())
+            .next_record()
+            .await
+            .unwrap_err();
+        assert!(matches!(err, FastaError::MissingHeader(1)));
+    }
+
+    #[tokio::test]
+    async fn fastq_matches_blocking_reader() {
+        let text = "@read1 lane 1\nACGTTG\n+\nII?#!I\n\n@read2\nacg\n+read2\n+++\n";
+        let mut reader = FastqReader::new(text.as_bytes());
+        for expected in fastq::Reader::new(text.as_bytes()) {
+            assert_eq!(reader.next_read().await.unwrap(), Some(expected.unwrap()));
+        }
+        assert!(reader.next_read().await.unwrap().is_none());
+
+        let err = FastqReader::new("@a\nAC\n+\n".as_bytes())
+            .next_read()
+            .await
+            .unwrap_err();
+        assert_eq!("unexpected end of input after line 3", err.to_string());
+    }
+
+    #[tokio::test]
+    async fn large_record_streams_through_small_pipe() {
+        // A server receiving a record over a connection that holds only 64 bytes in flight: the
+        // client can only send more as the reader packs what arrived, never the whole record.
+        const LINES: usize = 16 * 1024;
+        let (client, server) = tokio::io::duplex(64);
+        let sender = tokio::spawn(async move {
+            let mut client = client;
+            client.write_all(b">large record\n").await?;
+            for _ in 0..LINES {
+                client
+                    .write_all(b"ACGTACGTACGTACGTACGTACGTACGTACGTACGTACGTACGTACGTACGTACGTACGT\n")
+                    .await?;
+            }
+            client.shutdown().await
+        });
+        let mut reader = FastaReader::new(server);
+        let record = reader.next_record().await.unwrap().unwrap();
+        assert_eq!(record.header(), "large record");
+        assert_eq!(record.sequence().len(), LINES * 60);
+        assert_eq!(record.sequence().get_counts()[0], ('A', LINES * 15));
+        assert!(reader.next_record().await.unwrap().is_none());
+        sender.await.unwrap().unwrap();
+    }
+
+    #[tokio::test]
+    async fn pack_async_input() {
+        let (mut client, server) = tokio::io::duplex(16);
+        let sender = tokio::spawn(async move {
+            // The second line arrives in two writes.
+            client.write_all(b"ACGT\nAC").await?;
+            client.write_all(b"GT\n").await
+        });
+        let dna = pack(server, StreamingPacker::new().skip_whitespace(true))
+            .await
+            .unwrap();
+        assert_eq!(dna.to_string(), "ACGTACGT");
+        sender.await.unwrap().unwrap();
+
+        let mut packer = StreamingPacker::new();
+        let err = tokio::io::copy(&mut "ACGN".as_bytes(), &mut packer)
+            .await
+            .unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+        let err = pack("AC\u{e9}".as_bytes(), StreamingPacker::new())
+            .await
+            .unwrap_err();
+        assert!(matches!(
+            err,
+            DnaError::InvalidNuc {
+                character: '\u{e9}',
+                position: 2
+            }
+        ));
+    }
+}
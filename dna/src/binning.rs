@@ -0,0 +1,299 @@
+//! Composition-based binning of contigs and windows.
+//!
+//! Sequences are summarised as normalised k-mer frequency vectors (tetranucleotide frequencies
+//! with `k = 4` are the usual choice for metagenomic binning) and grouped with k-means.
+
+use crate::{kmer::KmerCounter, PackedDna};
+use std::ops::Range;
+
+/// Normalised k-mer frequencies of `dna`, indexed by packed k-mer.
+///
+/// When `canonical` is set each k-mer is merged with its reverse complement, so the vector does
+/// not depend on which strand was sequenced. An input shorter than `k` yields all zeros.
+///
+/// # Panics
+///
+/// Panics if `k` is zero or greater than 12, since the vector has `4^k` entries.
+pub fn composition(dna: &PackedDna, k: usize, canonical: bool) -> Vec<f64> {
+    assert!(
+        (1..=12).contains(&k),
+        "composition k must be between 1 and 12, got {}",
+        k
+    );
+    let counts = KmerCounter::new(k).canonical(canonical).count(dna);
+    let mut vector = vec![0.0; 1 << (2 * k)];
+    let total: usize = counts.iter().map(|(_, count)| count).sum();
+    if total == 0 {
+        return vector;
+    }
+    for (kmer, count) in &counts {
+        vector[kmer as usize] = count as f64 / total as f64;
+    }
+    vector
+}
+
+/// Composition vectors of successive windows of `dna`, `window` long and `step` apart.
+///
+/// Only full windows are reported.
+///
+/// # Panics
+///
+/// Panics if `window` or `step` is zero, or under the conditions of [`composition`].
+pub fn window_compositions(
+    dna: &PackedDna,
+    k: usize,
+    canonical: bool,
+    window: usize,
+    step: usize,
+) -> Vec<(Range<usize>, Vec<f64>)> {
+    assert!(window > 0 && step > 0, "window and step must be non-zero");
+    let len = dna.len();
+    (0..)
+        .map(|i| i * step)
+        .take_while(|start| start + window <= len)
+        .map(|start| {
+            let range = start..start + window;
+            let vector = composition(&dna.to_slice(range.clone()), k, canonical);
+            (range, vector)
+        })
+        .collect()
+}
+
+/// An error that can occur when clustering composition vectors.
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
+pub enum BinningError {
+    /// Fewer vectors were given than bins requested
+    #[error("cannot form {bins} bins from {points} vectors")]
+    TooFewPoints {
+        /// Requested number of bins
+        bins: usize,
+        /// Number of vectors given
+        points: usize,
+    },
+    /// The vectors do not all have the same length
+    #[error("composition vectors have mismatched lengths")]
+    MismatchedLengths,
+}
+
+/// K-means clustering of composition vectors
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct KMeans {
+    bins: usize,
+    max_iterations: usize,
+}
+
+impl KMeans {
+    /// Cluster into `bins` bins, with at most 100 refinement iterations
+    pub fn new(bins: usize) -> Self {
+        KMeans {
+            bins,
+            max_iterations: 100,
+        }
+    }
+
+    /// Limit the number of refinement iterations
+    pub fn max_iterations(mut self, max_iterations: usize) -> Self {
+        self.max_iterations = max_iterations;
+        self
+    }
+
+    /// Assign every vector to a bin.
+    ///
+    /// Centroids are seeded deterministically by farthest-point selection starting from the
+    /// first vector, so the same input always gives the same bins.
+    pub fn fit(&self, vectors: &[Vec<f64>]) -> Result<Binning, BinningError> {
+        if self.bins == 0 || vectors.len() < self.bins {
+            return Err(BinningError::TooFewPoints {
+                bins: self.bins,
+                points: vectors.len(),
+            });
+        }
+        let dims = vectors[0].len();
+        if vectors.iter().any(|v| v.len() != dims) {
+            return Err(BinningError::MismatchedLengths);
+        }
+
+        let mut centroids = vec![vectors[0].clone()];
+        while centroids.len() < self.bins {
+            let farthest = vectors
+                .iter()
+                .max_by(|a, b| {
+                    nearest(&centroids, a)
+                        .1
+                        .total_cmp(&nearest(&centroids, b).1)
+                })
+                .cloned()
+                .unwrap_or_default();
+            centroids.push(farthest);
+        }
+
+        let mut assignments = vec![0; vectors.len()];
+        let mut iterations = 0;
+        let mut converged = false;
+        while iterations < self.max_iterations {
+            iterations += 1;
+            let mut changed = false;
+            for (assignment, vector) in assignments.iter_mut().zip(vectors) {
+                let (bin, _) = nearest(&centroids, vector);
+                changed |= *assignment != bin;
+                *assignment = bin;
+            }
+            if !changed && iterations > 1 {
+                converged = true;
+                break;
+            }
+            for (bin, centroid) in centroids.iter_mut().enumerate() {
+                let members: Vec<&Vec<f64>> = vectors
+                    .iter()
+                    .zip(&assignments)
+                    .filter(|(_, &a)| a == bin)
+                    .map(|(v, _)| v)
+                    .collect();
+                // An emptied bin keeps its previous centroid.
+                if members.is_empty() {
+                    continue;
+                }
+                for (d, value) in centroid.iter_mut().enumerate() {
+                    *value = members.iter().map(|m| m[d]).sum::<f64>() / members.len() as f64;
+                }
+            }
+        }
+
+        let bins = centroids
+            .into_iter()
+            .enumerate()
+            .map(|(bin, centroid)| {
+                let distances: Vec<f64> = vectors
+                    .iter()
+                    .zip(&assignments)
+                    .filter(|(_, &a)| a == bin)
+                    .map(|(v, _)| squared_distance(v, &centroid))
+                    .collect();
+                BinSummary {
+                    size: distances.len(),
+                    within_ss: distances.iter().sum(),
+                    centroid,
+                }
+            })
+            .collect();
+        Ok(Binning {
+            assignments,
+            bins,
+            iterations,
+            converged,
+        })
+    }
+}
+
+/// Summary of a single bin
+#[derive(Debug, Clone, PartialEq)]
+pub struct BinSummary {
+    /// Number of vectors assigned to the bin
+    pub size: usize,
+    /// Sum of squared distances from members to the centroid
+    pub within_ss: f64,
+    /// Mean composition vector of the members
+    pub centroid: Vec<f64>,
+}
+
+/// The result of [`KMeans::fit`]
+#[derive(Debug, Clone, PartialEq)]
+pub struct Binning {
+    /// Bin index of each input vector, in input order
+    pub assignments: Vec<usize>,
+    /// Per-bin summaries, indexed by bin
+    pub bins: Vec<BinSummary>,
+    /// Number of refinement iterations performed
+    pub iterations: usize,
+    /// Whether assignments stopped changing before the iteration limit
+    pub converged: bool,
+}
+
+impl Binning {
+    /// Total within-bin sum of squares, a measure of how tight the bins are
+    pub fn total_within_ss(&self) -> f64 {
+        self.bins.iter().map(|bin| bin.within_ss).sum()
+    }
+}
+
+fn squared_distance(a: &[f64], b: &[f64]) -> f64 {
+    a.iter().zip(b).map(|(x, y)| (x - y) * (x - y)).sum()
+}
+
+/// Index of and squared distance to the centroid closest to `vector`
+fn nearest(centroids: &[Vec<f64>], vector: &[f64]) -> (usize, f64) {
+    centroids
+        .iter()
+        .map(|c| squared_distance(c, vector))
+        .enumerate()
+        .min_by(|a, b| a.1.total_cmp(&b.1))
+        .unwrap_or((0, f64::INFINITY))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::kmer::encode;
+    use std::str::FromStr;
+
+    #[test]
+    fn composition_sums_to_one() {
+        let dna = PackedDna::from_str("ACGTTGCAACGT").unwrap();
+        let vector = composition(&dna, 2, false);
+        assert_eq!(vector.len(), 16);
+        assert!((vector.iter().sum::<f64>() - 1.0).abs() < 1e-12);
+        assert!((vector[encode("AC").unwrap() as usize] - 2.0 / 11.0).abs() < 1e-12);
+    }
+
+    #[test]
+    fn composition_canonical_is_strand_independent() {
+        let dna = PackedDna::from_str("AACGTTTGCA").unwrap();
+        assert_eq!(
+            composition(&dna, 3, true),
+            composition(&dna.reverse_complement(), 3, true)
+        );
+    }
+
+    #[test]
+    fn window_compositions_full_windows_only() {
+        let dna = PackedDna::from_str("ACGTACGTAC").unwrap();
+        let windows = window_compositions(&dna, 1, false, 4, 3);
+        let ranges: Vec<Range<usize>> = windows.into_iter().map(|(r, _)| r).collect();
+        assert_eq!(ranges, vec![0..4, 3..7, 6..10]);
+    }
+
+    #[test]
+    fn kmeans_separates_compositions() {
+        let seqs = [
+            "ATATATATAT",
+            "TATATAATTA",
+            "GCGCGCGGCC",
+            "CGCGCCGGCG",
+            "ATTATAATAT",
+        ];
+        let vectors: Vec<Vec<f64>> = seqs
+            .iter()
+            .map(|s| composition(&PackedDna::from_str(s).unwrap(), 1, false))
+            .collect();
+        let binning = KMeans::new(2).fit(&vectors).unwrap();
+        let a = binning.assignments[0];
+        assert_eq!(binning.assignments, vec![a, a, 1 - a, 1 - a, a]);
+        assert!(binning.converged);
+        assert_eq!(binning.bins[a].size, 3);
+        assert_eq!(binning.bins[1 - a].size, 2);
+    }
+
+    #[test]
+    fn kmeans_too_few_points() {
+        let err = KMeans::new(3).fit(&[vec![0.0], vec![1.0]]).unwrap_err();
+        assert_eq!("cannot form 3 bins from 2 vectors", err.to_string());
+    }
+
+    #[test]
+    fn kmeans_mismatched_lengths() {
+        let err = KMeans::new(1)
+            .fit(&[vec![0.0], vec![1.0, 2.0]])
+            .unwrap_err();
+        assert_eq!(err, BinningError::MismatchedLengths);
+    }
+}
@@ -0,0 +1,256 @@
+//! Cached statistics of a sequence that is being edited.
+//!
+//! [`PackedDna`] keeps its base counts up to date through every edit, so its GC content and
+//! [`Stats`](crate::stats::Stats) are always current and cheap. Costlier derived values, k-mer
+//! spectra and windowed GC content, are cached by [`CachedDna`], which owns the sequence and
+//! sees every edit: substitutions update the cached values in place, touching only the k-mers
+//! and windows that cover the changed position, and other edits invalidate them. When an
+//! invalidated value is computed again is set by [`Recompute`].
+
+use crate::{
+    kmer::{KmerCounter, KmerCounts, Kmers},
+    DnaError, IndexError, Nuc, PackedDna,
+};
+use std::collections::HashMap;
+
+/// When cached statistics invalidated by an edit are computed again
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum Recompute {
+    /// Drop them, and compute each again the next time it is asked for; suits bursts of edits
+    #[default]
+    Lazy,
+    /// Compute every cached value again right after each edit, so reading never waits
+    Eager,
+}
+
+/// A sequence with cached k-mer spectra and windowed GC content, kept current across edits
+#[derive(Debug, Clone)]
+pub struct CachedDna {
+    dna: PackedDna,
+    recompute: Recompute,
+    spectra: HashMap<KmerCounter, KmerCounts>,
+    /// GC bases of each window, keyed by window length and step
+    gc_windows: HashMap<(usize, usize), Vec<usize>>,
+}
+
+impl CachedDna {
+    /// Wrap `dna`, with nothing cached yet
+    pub fn new(dna: PackedDna) -> Self {
+        CachedDna {
+            dna,
+            recompute: Recompute::default(),
+            spectra: HashMap::new(),
+            gc_windows: HashMap::new(),
+        }
+    }
+
+    /// Recompute invalidated statistics with `strategy` instead of lazily
+    pub fn recompute(mut self, strategy: Recompute) -> Self {
+        self.recompute = strategy;
+        self
+    }
+
+    /// The sequence
+    pub fn dna(&self) -> &PackedDna {
+        &self.dna
+    }
+
+    /// Unwrap the sequence, dropping the cache
+    pub fn into_inner(self) -> PackedDna {
+        self.dna
+    }
+
+    /// The k-mer counts of the sequence with `counter`'s length and canonical setting, counted
+    /// on first use
+    pub fn kmer_counts(&mut self, counter: KmerCounter) -> &KmerCounts {
+        let dna = &self.dna;
+        self.spectra
+            .entry(counter)
+            .or_insert_with(|| counter.count(dna))
+    }
+
+    /// GC content of successive windows like [`PackedDna::gc_content_windows`], computed on
+    /// first use.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `window` or `step` is zero.
+    pub fn gc_content_windows(&mut self, window: usize, step: usize) -> Vec<f64> {
+        let dna = &self.dna;
+        self.gc_windows
+            .entry((window, step))
+            .or_insert_with(|| gc_counts(dna, window, step))
+            .iter()
+            .map(|&gc| gc as f64 / window as f64)
+            .collect()
+    }
+
+    /// Drop every cached value
+    pub fn invalidate(&mut self) {
+        self.spectra.clear();
+        self.gc_windows.clear();
+    }
+
+    /// Set the nucleotide at the 0-based position `idx`, updating the cached values in place
+    pub fn set(&mut self, idx: usize, nuc: Nuc) -> Result<(), IndexError> {
+        let len = self.dna.len();
+        let old = self
+            .dna
+            .get(idx)
+            .ok_or(IndexError::OutOfBounds { index: idx, len })?;
+        if old == nuc {
+            return Ok(());
+        }
+        // The k-mers covering `idx`, counted out before the change and back in after it
+        let covering = |dna: &PackedDna, k: usize| {
+            let start = (idx + 1).saturating_sub(k);
+            dna.to_slice(start..(idx + k).min(len))
+        };
+        for (counter, counts) in &mut self.spectra {
+            let k = counter.k();
+            for kmer in Kmers::new(&covering(&self.dna, k), k, counter.is_canonical()) {
+                counts.remove(kmer);
+            }
+        }
+        self.dna.set(idx, nuc)?;
+        for (counter, counts) in &mut self.spectra {
+            let k = counter.k();
+            for kmer in Kmers::new(&covering(&self.dna, k), k, counter.is_canonical()) {
+                counts.add(kmer);
+            }
+        }
+
+        let is_gc = |nuc| matches!(nuc, Nuc::C | Nuc::G);
+        if is_gc(old) != is_gc(nuc) {
+            for (&(window, step), counts) in &mut self.gc_windows {
+                // Windows `i` with `i * step <= idx < i * step + window`
+                let first = (idx + 1).saturating_sub(window).div_ceil(step);
+                for gc in counts.iter_mut().take(idx / step + 1).skip(first) {
+                    if is_gc(nuc) {
+                        *gc += 1;
+                    } else {
+                        *gc -= 1;
+                    }
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Append a nucleotide to the end of the sequence
+    pub fn push(&mut self, nuc: Nuc) {
+        self.edit(|dna| dna.push(nuc))
+    }
+
+    /// Remove and return the last nucleotide, or `None` if the sequence is empty
+    pub fn pop(&mut self) -> Option<Nuc> {
+        self.edit(PackedDna::pop)
+    }
+
+    /// Append the nucleotides of `s`, see [`PackedDna::extend_from_str`]
+    pub fn extend_from_str(&mut self, s: &str) -> Result<(), DnaError> {
+        self.edit(|dna| dna.extend_from_str(s))
+    }
+
+    /// Append all nucleotides of `other` to the end of the sequence
+    pub fn append(&mut self, other: &PackedDna) {
+        self.edit(|dna| dna.append(other))
+    }
+
+    /// Replace the sequence with its reverse complement
+    pub fn reverse_complement_in_place(&mut self) {
+        self.edit(PackedDna::reverse_complement_in_place)
+    }
+
+    /// Apply any edit to the sequence, then invalidate the cached values, or recompute them with
+    /// [`Recompute::Eager`]
+    pub fn edit<R>(&mut self, edit: impl FnOnce(&mut PackedDna) -> R) -> R {
+        let result = edit(&mut self.dna);
+        match self.recompute {
+            Recompute::Lazy => self.invalidate(),
+            Recompute::Eager => {
+                let dna = &self.dna;
+                for (counter, counts) in &mut self.spectra {
+                    *counts = counter.count(dna);
+                }
+                for (&(window, step), counts) in &mut self.gc_windows {
+                    *counts = gc_counts(dna, window, step);
+                }
+            }
+        }
+        result
+    }
+}
+
+impl From<PackedDna> for CachedDna {
+    fn from(dna: PackedDna) -> Self {
+        CachedDna::new(dna)
+    }
+}
+
+/// GC bases of each window of [`PackedDna::gc_content_windows`]
+fn gc_counts(dna: &PackedDna, window: usize, step: usize) -> Vec<usize> {
+    // Each fraction is a count over the window length, so scaling it back is exact.
+    dna.gc_content_windows(window, step)
+        .map(|gc| (gc * window as f64).round() as usize)
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::str::FromStr;
+
+    /// Whether every cached value matches one computed afresh
+    fn assert_current(cached: &mut CachedDna) {
+        let dna = cached.dna().clone();
+        for counter in [KmerCounter::new(3), KmerCounter::new(4).canonical(true)] {
+            assert_eq!(cached.kmer_counts(counter), &counter.count(&dna));
+        }
+        for (window, step) in [(4, 2), (5, 3)] {
+            let fresh: Vec<f64> = dna.gc_content_windows(window, step).collect();
+            assert_eq!(cached.gc_content_windows(window, step), fresh);
+        }
+    }
+
+    #[test]
+    fn substitutions_update_in_place() {
+        let mut cached = CachedDna::new(PackedDna::from_str("ACGTTAGCATTACGGA").unwrap());
+        assert_current(&mut cached);
+        for (idx, nuc) in [
+            (0, Nuc::G),
+            (7, Nuc::A),
+            (15, Nuc::C),
+            (8, Nuc::C),
+            (8, Nuc::C),
+        ] {
+            cached.set(idx, nuc).unwrap();
+            assert_eq!(cached.spectra.len(), 2);
+            assert_current(&mut cached);
+        }
+        assert_eq!(cached.dna().to_string(), "GCGTTAGACTTACGGC");
+        assert!(cached.set(16, Nuc::A).is_err());
+    }
+
+    #[test]
+    fn other_edits_invalidate_or_recompute() {
+        let dna = PackedDna::from_str("ACGTTAGCATTACGGA").unwrap();
+        let mut lazy = CachedDna::new(dna.clone());
+        let mut eager = CachedDna::new(dna).recompute(Recompute::Eager);
+        for cached in [&mut lazy, &mut eager] {
+            assert_current(cached);
+            cached.push(Nuc::G);
+            cached.append(&PackedDna::from_str("CCA").unwrap());
+            assert_eq!(cached.pop(), Some(Nuc::A));
+            cached.reverse_complement_in_place();
+            assert!(cached.extend_from_str("GAN").is_err());
+        }
+        assert!(lazy.spectra.is_empty() && lazy.gc_windows.is_empty());
+        assert_eq!((eager.spectra.len(), eager.gc_windows.len()), (2, 2));
+        let fresh = KmerCounter::new(3).count(eager.dna());
+        assert_eq!(eager.spectra[&KmerCounter::new(3)], fresh);
+        assert_current(&mut lazy);
+        assert_current(&mut eager);
+        assert_eq!(lazy.into_inner(), eager.into_inner());
+    }
+}
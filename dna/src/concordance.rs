@@ -0,0 +1,278 @@
+//! Concordance checks between two FASTA references.
+//!
+//! Pipelines often ship a reference in several places, e.g. next to an index and inside a
+//! container image, and silently differing copies make results irreproducible.
+//! [`compare_references`] matches the contigs of two references by name and checks their
+//! lengths and digests; contigs of equal length that differ are compared position by position,
+//! telling plain substitutions from disagreements about `N` and other ambiguity codes.
+
+use crate::{
+    fasta::{self, FastaError},
+    iupac::{Iupac, IupacDna},
+    summary,
+};
+use std::{collections::HashMap, io::Read};
+
+/// An error that can occur when comparing two references.
+#[derive(Debug, thiserror::Error)]
+pub enum ConcordanceError {
+    /// A reference could not be read
+    #[error(transparent)]
+    Fasta(#[from] FastaError),
+    /// A reference has two contigs of the same name
+    #[error("contig {name} occurs more than once in reference {reference}")]
+    DuplicateName {
+        /// Which reference, 1 or 2
+        reference: usize,
+        /// The repeated name
+        name: String,
+    },
+}
+
+/// Length and digest of a contig in one reference
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct ContigInfo {
+    /// Number of positions
+    pub len: usize,
+    /// FNV-1a digest of the upper case IUPAC codes, as for
+    /// [`SequenceSummary`](crate::summary::SequenceSummary)
+    pub digest: u64,
+}
+
+/// Positions at which two contigs of equal length differ, by kind
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub struct Discrepancies {
+    /// Both are A, C, G or T
+    pub substitutions: usize,
+    /// One is `N` and the other is not
+    pub n: usize,
+    /// At least one is an ambiguity code other than `N`, and neither is `N`
+    pub ambiguous: usize,
+}
+
+impl Discrepancies {
+    /// Total number of differing positions
+    pub fn total(&self) -> usize {
+        self.substitutions + self.n + self.ambiguous
+    }
+}
+
+/// How a contig compares between the two references
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ContigStatus {
+    /// Same length and sequence in both
+    Identical,
+    /// Only the first reference has the contig
+    OnlyInFirst,
+    /// Only the second reference has the contig
+    OnlyInSecond,
+    /// The lengths differ, so positions are not compared
+    LengthDiffers,
+    /// Same length, different sequence
+    SequenceDiffers,
+}
+
+/// The comparison of one contig name
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ContigComparison {
+    /// The contig name, the header up to the first whitespace
+    pub name: String,
+    /// The contig in the first reference, if it has one
+    pub first: Option<ContigInfo>,
+    /// The contig in the second reference, if it has one
+    pub second: Option<ContigInfo>,
+    /// Differing positions, all zero unless the status is [`ContigStatus::SequenceDiffers`]
+    pub discrepancies: Discrepancies,
+}
+
+impl ContigComparison {
+    /// How the contig compares
+    pub fn status(&self) -> ContigStatus {
+        match (self.first, self.second) {
+            (Some(_), None) => ContigStatus::OnlyInFirst,
+            (None, Some(_)) => ContigStatus::OnlyInSecond,
+            (Some(first), Some(second)) if first.len != second.len => ContigStatus::LengthDiffers,
+            (Some(first), Some(second)) if first.digest == second.digest => ContigStatus::Identical,
+            _ => ContigStatus::SequenceDiffers,
+        }
+    }
+}
+
+/// The comparison of two references
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Concordance {
+    /// Each contig name, in the order of the first reference, then those only in the second in
+    /// their order
+    pub contigs: Vec<ContigComparison>,
+    /// Whether the contigs the references share come in the same order in both
+    pub same_order: bool,
+}
+
+impl Concordance {
+    /// Whether both references have the same contigs with the same sequences, in any order
+    pub fn is_concordant(&self) -> bool {
+        self.contigs
+            .iter()
+            .all(|contig| contig.status() == ContigStatus::Identical)
+    }
+
+    /// The contigs that are not identical in both references
+    pub fn differences(&self) -> impl Iterator<Item = &ContigComparison> {
+        self.contigs
+            .iter()
+            .filter(|contig| contig.status() != ContigStatus::Identical)
+    }
+}
+
+/// Compare the FASTA references read from `first` and `second`, see the
+/// [module documentation](self).
+///
+/// Both references are held in memory as IUPAC sequences, at about a quarter of a byte per
+/// position.
+pub fn compare_references(
+    first: impl Read,
+    second: impl Read,
+) -> Result<Concordance, ConcordanceError> {
+    let first = read_contigs(first, 1)?;
+    let second = read_contigs(second, 2)?;
+    let second_idx: HashMap<&str, usize> = second
+        .iter()
+        .enumerate()
+        .map(|(idx, (name, _))| (name.as_str(), idx))
+        .collect();
+
+    let mut contigs = Vec::with_capacity(first.len().max(second.len()));
+    let mut in_first = vec![false; second.len()];
+    let mut shared_order = Vec::new();
+    for (name, dna) in &first {
+        let other = second_idx.get(name.as_str()).map(|&idx| {
+            in_first[idx] = true;
+            shared_order.push(idx);
+            &second[idx].1
+        });
+        let discrepancies = match other {
+            Some(other) if other.len() == dna.len() && other != dna => discrepancies(dna, other),
+            _ => Discrepancies::default(),
+        };
+        contigs.push(ContigComparison {
+            name: name.clone(),
+            first: Some(info(dna)),
+            second: other.map(info),
+            discrepancies,
+        });
+    }
+    for ((name, dna), _) in second
+        .iter()
+        .zip(in_first)
+        .filter(|&(_, in_first)| !in_first)
+    {
+        contigs.push(ContigComparison {
+            name: name.clone(),
+            first: None,
+            second: Some(info(dna)),
+            discrepancies: Discrepancies::default(),
+        });
+    }
+    Ok(Concordance {
+        contigs,
+        same_order: shared_order.windows(2).all(|pair| pair[0] < pair[1]),
+    })
+}
+
+/// The contigs of a reference by name, in file order
+fn read_contigs(
+    input: impl Read,
+    reference: usize,
+) -> Result<Vec<(String, IupacDna)>, ConcordanceError> {
+    let mut contigs: Vec<(String, IupacDna)> = Vec::new();
+    let mut seen = HashMap::new();
+    for record in fasta::Reader::new(input).iupac() {
+        let (header, dna) = record?;
+        let name = header
+            .split_whitespace()
+            .next()
+            .unwrap_or_default()
+            .to_string();
+        if seen.insert(name.clone(), ()).is_some() {
+            return Err(ConcordanceError::DuplicateName { reference, name });
+        }
+        contigs.push((name, dna));
+    }
+    Ok(contigs)
+}
+
+fn info(dna: &IupacDna) -> ContigInfo {
+    ContigInfo {
+        len: dna.len(),
+        digest: summary::symbols_digest(dna.iter().map(Iupac::to_char)),
+    }
+}
+
+/// The positions at which `a` and `b`, of equal length, differ
+fn discrepancies(a: &IupacDna, b: &IupacDna) -> Discrepancies {
+    let mut found = Discrepancies::default();
+    for (x, y) in a.iter().zip(b.iter()).filter(|(x, y)| x != y) {
+        if (x == Iupac::N) != (y == Iupac::N) {
+            found.n += 1;
+        } else if x.is_ambiguous() || y.is_ambiguous() {
+            found.ambiguous += 1;
+        } else {
+            found.substitutions += 1;
+        }
+    }
+    found
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const FIRST: &str = ">chr1 v1\nACGTACGTAC\n>chr2\nACGTNNNNAC\n>chrM\nGATTACA\n>extra\nAC\n";
+    const SECOND: &str = ">chr2\nTCGTANNNRC\n>chr1 v2\nACGTACGTAC\n>chrM\nGATTACAA\n>alt\nG\n";
+
+    #[test]
+    fn reports_each_contig() {
+        let concordance = compare_references(FIRST.as_bytes(), SECOND.as_bytes()).unwrap();
+        let statuses: Vec<(&str, ContigStatus)> = concordance
+            .contigs
+            .iter()
+            .map(|contig| (contig.name.as_str(), contig.status()))
+            .collect();
+        assert_eq!(
+            statuses,
+            [
+                ("chr1", ContigStatus::Identical),
+                ("chr2", ContigStatus::SequenceDiffers),
+                ("chrM", ContigStatus::LengthDiffers),
+                ("extra", ContigStatus::OnlyInFirst),
+                ("alt", ContigStatus::OnlyInSecond),
+            ]
+        );
+        assert_eq!(
+            concordance.contigs[1].discrepancies,
+            Discrepancies {
+                substitutions: 1,
+                n: 1,
+                ambiguous: 1
+            }
+        );
+        assert_eq!(concordance.contigs[2].second.unwrap().len, 8);
+        assert!(!concordance.same_order);
+        assert!(!concordance.is_concordant());
+        assert_eq!(concordance.differences().count(), 4);
+
+        let same = compare_references(FIRST.as_bytes(), FIRST.as_bytes()).unwrap();
+        assert!(same.is_concordant() && same.same_order);
+    }
+
+    #[test]
+    fn duplicate_names_are_errors() {
+        let duplicated = ">chr1\nAC\n>chr1 again\nGT\n";
+        assert_eq!(
+            compare_references(FIRST.as_bytes(), duplicated.as_bytes())
+                .unwrap_err()
+                .to_string(),
+            "contig chr1 occurs more than once in reference 2"
+        );
+    }
+}
@@ -0,0 +1,336 @@
+//! Detection of the format of sequence files from their first bytes.
+//!
+//! [`detect`] peeks at buffered input without consuming it: gzip and `.2bit` files are told
+//! apart by their magic numbers, and text formats by how their first line starts, after any
+//! whitespace and byte order mark. With the `flate2` feature, the start of gzip-compressed input
+//! is decompressed to detect the format inside. [`open`] and [`read`] use this to read the
+//! records of a FASTA, FASTQ or `.2bit` file, compressed or not, without being told its format,
+//! and [`read_detected`] reads input whose format is already known. The FASTA and FASTQ readers
+//! skip a byte order mark at the start of their input, so such files read like any other.
+
+use crate::{fasta, fastq, iupac::IupacDna, twobit, DnaError};
+use std::{
+    fmt,
+    fs::File,
+    io::{self, BufRead, BufReader, Cursor, Read, Seek},
+    path::Path,
+};
+
+const GZIP_MAGIC: [u8; 2] = [0x1f, 0x8b];
+
+/// The `.2bit` signature as stored on little-endian machines, byte-swapped on others
+const TWOBIT_MAGIC: [u8; 4] = 0x1A41_2743u32.to_le_bytes();
+
+/// The UTF-8 byte order mark some editors write at the start of text files
+pub(crate) const BOM: &[u8] = "\u{feff}".as_bytes();
+
+/// An error that can occur when detecting the format of input or opening it.
+#[derive(Debug, thiserror::Error)]
+pub enum DetectError {
+    /// The input could not be opened or read
+    #[error("failed to read input")]
+    Io(#[from] io::Error),
+    /// The input does not start like any known format
+    #[error("input format not recognized")]
+    Unrecognized,
+    /// The format was recognized, but its records cannot be read
+    #[error("reading {0} input is not supported")]
+    Unsupported(FileFormat),
+    /// The input is gzip-compressed, which needs the `flate2` feature
+    #[error("gzip-compressed input needs the flate2 feature")]
+    Compressed,
+}
+
+/// A sequence file format
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum FileFormat {
+    /// FASTA, starting with a `>` header line
+    Fasta,
+    /// FASTQ, starting with an `@` header line
+    Fastq,
+    /// UCSC `.2bit`, see [`twobit`](crate::twobit)
+    TwoBit,
+    /// GenBank flat file, starting with a `LOCUS` line
+    GenBank,
+}
+
+impl fmt::Display for FileFormat {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(match self {
+            FileFormat::Fasta => "FASTA",
+            FileFormat::Fastq => "FASTQ",
+            FileFormat::TwoBit => "2bit",
+            FileFormat::GenBank => "GenBank",
+        })
+    }
+}
+
+/// How a file is compressed
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Compression {
+    /// Not at all
+    None,
+    /// With gzip, or bgzip which is read the same way
+    Gzip,
+}
+
+/// The detected format and compression of input
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Detected {
+    /// The format, or `None` if it was not recognized, such as for plain text or for
+    /// gzip-compressed input without the `flate2` feature
+    pub format: Option<FileFormat>,
+    /// The compression
+    pub compression: Compression,
+}
+
+/// The format of uncompressed data starting with `prefix`, or `None` if it is not recognized
+pub fn sniff(prefix: &[u8]) -> Option<FileFormat> {
+    if prefix.starts_with(&TWOBIT_MAGIC) || prefix.starts_with(&swapped(TWOBIT_MAGIC)) {
+        return Some(FileFormat::TwoBit);
+    }
+    let text = prefix.strip_prefix(BOM).unwrap_or(prefix);
+    let start = text.iter().position(|b| !b.is_ascii_whitespace())?;
+    match &text[start..] {
+        [b'>', ..] => Some(FileFormat::Fasta),
+        [b'@', ..] => Some(FileFormat::Fastq),
+        rest if rest.starts_with(b"LOCUS") => Some(FileFormat::GenBank),
+        _ => None,
+    }
+}
+
+fn swapped(mut magic: [u8; 4]) -> [u8; 4] {
+    magic.reverse();
+    magic
+}
+
+/// Detect the format and compression of `input` from its buffered start, without consuming it.
+///
+/// Only what a single read fills the buffer with is looked at, which for a [`BufReader`] over
+/// a file is its first 8 KiB.
+pub fn detect<R: BufRead>(input: &mut R) -> io::Result<Detected> {
+    let prefix = input.fill_buf()?;
+    if !prefix.starts_with(&GZIP_MAGIC) {
+        return Ok(Detected {
+            format: sniff(prefix),
+            compression: Compression::None,
+        });
+    }
+    #[cfg(feature = "flate2")]
+    let format = {
+        let mut decoder = flate2::bufread::MultiGzDecoder::new(prefix);
+        let mut decompressed = [0u8; 512];
+        let mut filled = 0;
+        // The prefix usually ends mid-stream, so decompression ends with an error.
+        while let Ok(n @ 1..) = decoder.read(&mut decompressed[filled..]) {
+            filled += n;
+        }
+        sniff(&decompressed[..filled])
+    };
+    #[cfg(not(feature = "flate2"))]
+    let format = None;
+    Ok(Detected {
+        format,
+        compression: Compression::Gzip,
+    })
+}
+
+/// An iterator over records of any format
+type BoxedRecords = Box<dyn Iterator<Item = Result<(String, IupacDna), DnaError>>>;
+
+/// The records of a sequence file of any detected format, as IUPAC sequences with their FASTA or
+/// FASTQ header or `.2bit` sequence name
+pub struct Records {
+    detected: Detected,
+    inner: BoxedRecords,
+}
+
+impl Records {
+    /// The detected format and compression of the file
+    pub fn detected(&self) -> Detected {
+        self.detected
+    }
+}
+
+impl Iterator for Records {
+    type Item = Result<(String, IupacDna), DnaError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.inner.next()
+    }
+}
+
+impl fmt::Debug for Records {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("Records")
+            .field("detected", &self.detected)
+            .finish_non_exhaustive()
+    }
+}
+
+/// Open the sequence file at `path` and read its records, detecting its format.
+///
+/// Uncompressed `.2bit` files are read in place; compressed ones are decompressed into memory
+/// first, since reading them needs random access.
+pub fn open(path: impl AsRef<Path>) -> Result<Records, DetectError> {
+    let mut input = BufReader::new(File::open(path)?);
+    let detected = detect(&mut input)?;
+    if detected
+        == (Detected {
+            format: Some(FileFormat::TwoBit),
+            compression: Compression::None,
+        })
+    {
+        return Ok(Records {
+            detected,
+            inner: twobit_records(input)?,
+        });
+    }
+    read_detected(input, detected)
+}
+
+/// Read the records of `input`, detecting its format.
+///
+/// `.2bit` input is read into memory first, since reading it needs random access.
+pub fn read(mut input: impl BufRead + 'static) -> Result<Records, DetectError> {
+    let detected = detect(&mut input)?;
+    read_detected(input, detected)
+}
+
+/// The decompressed data of `input`, compressed with `compression`
+pub fn decompress(
+    input: impl BufRead + 'static,
+    compression: Compression,
+) -> Result<Box<dyn BufRead>, DetectError> {
+    Ok(match compression {
+        Compression::None => Box::new(input),
+        #[cfg(feature = "flate2")]
+        Compression::Gzip => Box::new(BufReader::new(flate2::bufread::MultiGzDecoder::new(input))),
+        #[cfg(not(feature = "flate2"))]
+        Compression::Gzip => return Err(DetectError::Compressed),
+    })
+}
+
+/// Read the records of `input`, whose format and compression [`detect`] found to be `detected`
+pub fn read_detected(
+    input: impl BufRead + 'static,
+    detected: Detected,
+) -> Result<Records, DetectError> {
+    let mut input = decompress(input, detected.compression)?;
+    let inner: BoxedRecords = match detected.format {
+        Some(FileFormat::Fasta) => Box::new(
+            fasta::Reader::new(input)
+                .iupac()
+                .map(|record| record.map_err(DnaError::from)),
+        ),
+        Some(FileFormat::Fastq) => Box::new(fastq::Reader::new(input).iupac().map(|read| {
+            let (header, dna, _) = read?.into_parts();
+            Ok((header, dna))
+        })),
+        Some(FileFormat::TwoBit) => {
+            let mut data = Vec::new();
+            input.read_to_end(&mut data)?;
+            twobit_records(Cursor::new(data))?
+        }
+        Some(format @ FileFormat::GenBank) => return Err(DetectError::Unsupported(format)),
+        None => return Err(DetectError::Unrecognized),
+    };
+    Ok(Records { detected, inner })
+}
+
+fn twobit_records(input: impl Read + Seek + 'static) -> Result<BoxedRecords, DetectError> {
+    let mut reader = twobit::Reader::new(input).map_err(|e| match e {
+        twobit::TwoBitError::Io(e) => DetectError::Io(e),
+        _ => DetectError::Unrecognized,
+    })?;
+    let names = reader.names().to_vec();
+    Ok(Box::new(names.into_iter().map(move |name| {
+        let record = reader.read(&name)?;
+        Ok((record.name, record.sequence))
+    })))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn sniff_formats() {
+        assert_eq!(sniff(b"\n>chr1\nACGT\n"), Some(FileFormat::Fasta));
+        assert_eq!(
+            sniff("\u{feff}@read\nACGT\n+\nIIII\n".as_bytes()),
+            Some(FileFormat::Fastq)
+        );
+        assert_eq!(
+            sniff(b"LOCUS       NC_001422  5386 bp"),
+            Some(FileFormat::GenBank)
+        );
+        assert_eq!(
+            sniff(&[0x43, 0x27, 0x41, 0x1a, 0, 0]),
+            Some(FileFormat::TwoBit)
+        );
+        assert_eq!(sniff(&[0x1a, 0x41, 0x27, 0x43]), Some(FileFormat::TwoBit));
+        assert_eq!(sniff(b"ACGT"), None);
+        assert_eq!(sniff(b"  \n"), None);
+    }
+
+    #[test]
+    fn read_detected_formats() {
+        let fastq = "@read1\nACGNT\n+\nII!II\n".as_bytes();
+        let records: Vec<_> = read(fastq).unwrap().map(Result::unwrap).collect();
+        assert_eq!(records, [("read1".to_string(), "ACGNT".parse().unwrap())]);
+
+        let mut twobit = Vec::new();
+        let record = twobit::Record {
+            name: "chrN".to_string(),
+            sequence: "ACNNGT".parse().unwrap(),
+            mask_blocks: Vec::new(),
+        };
+        twobit::write(&mut twobit, &[record]).unwrap();
+        let mut records = read(Cursor::new(twobit)).unwrap();
+        assert_eq!(
+            records.detected(),
+            Detected {
+                format: Some(FileFormat::TwoBit),
+                compression: Compression::None
+            }
+        );
+        assert_eq!(
+            records.next().unwrap().unwrap(),
+            ("chrN".to_string(), "ACNNGT".parse().unwrap())
+        );
+
+        assert!(matches!(
+            read(b"LOCUS x".as_slice()),
+            Err(DetectError::Unsupported(FileFormat::GenBank))
+        ));
+        assert!(matches!(
+            read(b"ACGT".as_slice()),
+            Err(DetectError::Unrecognized)
+        ));
+    }
+
+    #[cfg(feature = "flate2")]
+    #[test]
+    fn detect_gzip_wrapped() {
+        use flate2::{write::GzEncoder, Compression as Level};
+        use std::io::Write;
+
+        let mut encoder = GzEncoder::new(Vec::new(), Level::default());
+        encoder.write_all(b">chr1\nACGT\n>chr2\nGG\n").unwrap();
+        let compressed = encoder.finish().unwrap();
+        let mut input = compressed.as_slice();
+        assert_eq!(
+            detect(&mut input).unwrap(),
+            Detected {
+                format: Some(FileFormat::Fasta),
+                compression: Compression::Gzip
+            }
+        );
+        let names: Vec<String> = read(Cursor::new(compressed))
+            .unwrap()
+            .map(|record| record.unwrap().0)
+            .collect();
+        assert_eq!(names, ["chr1", "chr2"]);
+    }
+}
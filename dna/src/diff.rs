@@ -0,0 +1,373 @@
+//! Compact edit scripts between similar sequences.
+//!
+//! [`PackedDna::diff`] computes a minimal insert/delete alignment with Myers' O(ND) algorithm and
+//! folds it into [`Edit`]s, and [`PackedDna::apply_patch`] replays them. Edit positions always
+//! refer to the original sequence, and edits are ordered by position.
+//!
+//! The search costs O((N + M) D) time and O(D²) memory for D differences, which is prohibitive
+//! for long unrelated inputs. [`PackedDna::diff_limited`] caps D and degrades to a valid but
+//! non-minimal script instead.
+
+use crate::{Nuc, PackedDna};
+
+/// One step of an edit script
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum Edit {
+    /// Overwrite `nucs.len()` nucleotides starting at `pos`
+    Substitute {
+        /// 0-based position in the original sequence
+        pos: usize,
+        /// The replacement nucleotides
+        nucs: PackedDna,
+    },
+    /// Insert nucleotides before `pos`
+    Insert {
+        /// 0-based position in the original sequence
+        pos: usize,
+        /// The inserted nucleotides
+        nucs: PackedDna,
+    },
+    /// Remove `len` nucleotides starting at `pos`
+    Delete {
+        /// 0-based position in the original sequence
+        pos: usize,
+        /// Number of removed nucleotides
+        len: usize,
+    },
+}
+
+impl Edit {
+    /// The 0-based position in the original sequence the edit applies to
+    pub fn pos(&self) -> usize {
+        match *self {
+            Edit::Substitute { pos, .. } | Edit::Insert { pos, .. } | Edit::Delete { pos, .. } => {
+                pos
+            }
+        }
+    }
+}
+
+/// An error that can occur when applying an edit script.
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
+pub enum PatchError {
+    /// An edit starts before the end of the previous one
+    #[error("edit at position {0} overlaps or precedes the previous edit")]
+    Unordered(usize),
+    /// An edit reaches past the end of the original sequence
+    #[error("edit at position {pos} extends past the end of the sequence of length {len}")]
+    OutOfBounds {
+        /// Position of the offending edit
+        pos: usize,
+        /// Length of the original sequence
+        len: usize,
+    },
+}
+
+/// An edit script from [`PackedDna::diff_limited`]
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct LimitedDiff {
+    /// Edits turning the original sequence into the other one, ordered by position
+    pub edits: Vec<Edit>,
+    /// Whether `edits` is minimal, i.e. the search finished within the limit
+    pub minimal: bool,
+}
+
+/// A single step of the raw alignment between two sequences
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Op {
+    Equal,
+    Delete,
+    Insert,
+}
+
+impl PackedDna {
+    /// Compute an edit script turning `self` into `other`.
+    ///
+    /// The script has the minimal number of inserted plus deleted nucleotides; where a deletion
+    /// and an insertion meet they are folded into a substitution. Runtime and memory grow with
+    /// the number of differences, so this is intended for closely related sequences.
+    pub fn diff(&self, other: &PackedDna) -> Vec<Edit> {
+        self.diff_limited(other, usize::MAX).edits
+    }
+
+    /// Compute an edit script like [`diff`](PackedDna::diff), giving up on minimality past
+    /// `max_differences` inserted plus deleted nucleotides.
+    ///
+    /// This bounds the work to O((N + M) × `max_differences`) time and O(`max_differences`²)
+    /// memory. When the limit is hit, the script follows the furthest point the search reached
+    /// and then replaces the rest of the sequence wholesale, so it still turns `self` into
+    /// `other` but [`LimitedDiff::minimal`] is `false`.
+    pub fn diff_limited(&self, other: &PackedDna, max_differences: usize) -> LimitedDiff {
+        let old: Vec<Nuc> = self.iter().collect();
+        let new: Vec<Nuc> = other.iter().collect();
+        let (ops, minimal) = myers(&old, &new, max_differences);
+
+        let mut edits = Vec::new();
+        let (mut x, mut y) = (0, 0);
+        let mut i = 0;
+        while i < ops.len() {
+            if ops[i] == Op::Equal {
+                x += 1;
+                y += 1;
+                i += 1;
+                continue;
+            }
+            let (start_x, start_y) = (x, y);
+            while i < ops.len() && ops[i] != Op::Equal {
+                match ops[i] {
+                    Op::Delete => x += 1,
+                    _ => y += 1,
+                }
+                i += 1;
+            }
+            let (deleted, inserted) = (x - start_x, y - start_y);
+            let common = deleted.min(inserted);
+            if common > 0 {
+                edits.push(Edit::Substitute {
+                    pos: start_x,
+                    nucs: new[start_y..start_y + common].iter().copied().collect(),
+                });
+            }
+            if deleted > common {
+                edits.push(Edit::Delete {
+                    pos: start_x + common,
+                    len: deleted - common,
+                });
+            }
+            if inserted > common {
+                edits.push(Edit::Insert {
+                    pos: start_x + common,
+                    nucs: new[start_y + common..y].iter().copied().collect(),
+                });
+            }
+        }
+        LimitedDiff { edits, minimal }
+    }
+
+    /// Apply an edit script produced by [`diff`](PackedDna::diff), returning the edited sequence
+    pub fn apply_patch(&self, edits: &[Edit]) -> Result<PackedDna, PatchError> {
+        let len = self.len();
+        let mut patched = PackedDna::empty();
+        let mut cursor = 0;
+        for edit in edits {
+            let pos = edit.pos();
+            if pos < cursor {
+                return Err(PatchError::Unordered(pos));
+            }
+            let end = match edit {
+                Edit::Substitute { nucs, .. } => pos + nucs.len(),
+                Edit::Delete { len, .. } => pos + len,
+                Edit::Insert { .. } => pos,
+            };
+            if end > len {
+                return Err(PatchError::OutOfBounds { pos, len });
+            }
+            for idx in cursor..pos {
+                patched.push(self.nuc_at(idx));
+            }
+            if let Edit::Substitute { nucs, .. } | Edit::Insert { nucs, .. } = edit {
+                for nuc in nucs {
+                    patched.push(nuc);
+                }
+            }
+            cursor = end;
+        }
+        for idx in cursor..len {
+            patched.push(self.nuc_at(idx));
+        }
+        Ok(patched)
+    }
+}
+
+/// Myers' greedy O(ND) shortest edit script between `old` and `new`, searching up to
+/// `max_d` differences.
+///
+/// Returns the script and whether it is minimal. If the limit is reached first, the script
+/// follows the path that got furthest and then deletes and inserts the remaining nucleotides.
+fn myers(old: &[Nuc], new: &[Nuc], max_d: usize) -> (Vec<Op>, bool) {
+    let (n, m) = (old.len() as isize, new.len() as isize);
+    let max = (n + m).min(max_d.min(isize::MAX as usize) as isize);
+    // trace[d][k + d] is the furthest x reached on diagonal k after d differences.
+    let mut trace: Vec<Vec<isize>> = Vec::new();
+    let mut previous: Vec<isize> = vec![0];
+    let mut end = None;
+    'search: for d in 0..=max {
+        let mut current = vec![0isize; 2 * d as usize + 1];
+        for k in (-d..=d).step_by(2) {
+            let from_above = |k: isize| previous[(k + d - 1) as usize];
+            let mut x = if d == 0 {
+                0
+            } else if k == -d || (k != d && from_above(k - 1) < from_above(k + 1)) {
+                from_above(k + 1)
+            } else {
+                from_above(k - 1) + 1
+            };
+            let mut y = x - k;
+            while x < n && y < m && old[x as usize] == new[y as usize] {
+                x += 1;
+                y += 1;
+            }
+            current[(k + d) as usize] = x;
+            if x >= n && y >= m {
+                trace.push(current);
+                end = Some((n, m));
+                break 'search;
+            }
+        }
+        trace.push(current);
+        previous = trace.last().cloned().unwrap_or_default();
+    }
+    let minimal = end.is_some();
+    // Out of budget: continue from the in-bounds point with the most progress.
+    let (end_x, end_y) = end.unwrap_or_else(|| {
+        let d = trace.len() as isize - 1;
+        (-d..=d)
+            .step_by(2)
+            .map(|k| (previous[(k + d) as usize], k))
+            .map(|(x, k)| (x, x - k))
+            .filter(|&(x, y)| x <= n && (0..=m).contains(&y))
+            .max_by_key(|&(x, y)| x + y)
+            .unwrap_or((0, 0))
+    });
+
+    let mut ops: Vec<Op> = std::iter::repeat_n(Op::Insert, (m - end_y) as usize)
+        .chain(std::iter::repeat_n(Op::Delete, (n - end_x) as usize))
+        .collect();
+    let (mut x, mut y) = (end_x, end_y);
+    for d in (1..trace.len() as isize).rev() {
+        let k = x - y;
+        let furthest = |k: isize| trace[d as usize - 1][(k + d - 1) as usize];
+        let prev_k = if k == -d || (k != d && furthest(k - 1) < furthest(k + 1)) {
+            k + 1
+        } else {
+            k - 1
+        };
+        let prev_x = furthest(prev_k);
+        let prev_y = prev_x - prev_k;
+        while x > prev_x && y > prev_y {
+            ops.push(Op::Equal);
+            x -= 1;
+            y -= 1;
+        }
+        ops.push(if x == prev_x { Op::Insert } else { Op::Delete });
+        x = prev_x;
+        y = prev_y;
+    }
+    ops.extend(std::iter::repeat_n(Op::Equal, x as usize));
+    ops.reverse();
+    (ops, minimal)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::dna;
+
+    #[test]
+    fn diff_identical() {
+        assert!(dna("ACGTTGCA").diff(&dna("ACGTTGCA")).is_empty());
+    }
+
+    #[test]
+    fn diff_substitution() {
+        let edits = dna("ACGTTGCA").diff(&dna("ACCTTGCA"));
+        assert_eq!(
+            edits,
+            vec![Edit::Substitute {
+                pos: 2,
+                nucs: dna("C")
+            }]
+        );
+    }
+
+    #[test]
+    fn diff_insertion_and_deletion() {
+        let edits = dna("ACGTTGCA").diff(&dna("AGTTGGGCA"));
+        assert_eq!(
+            edits,
+            vec![
+                Edit::Delete { pos: 1, len: 1 },
+                Edit::Insert {
+                    pos: 6,
+                    nucs: dna("GG")
+                }
+            ]
+        );
+    }
+
+    #[test]
+    fn diff_from_empty() {
+        let edits = dna("").diff(&dna("ACG"));
+        assert_eq!(
+            edits,
+            vec![Edit::Insert {
+                pos: 0,
+                nucs: dna("ACG")
+            }]
+        );
+    }
+
+    #[test]
+    fn diff_apply_round_trip() {
+        let pairs = [
+            ("GATTACA", "GATTACA"),
+            ("GATTACA", "GCATGCT"),
+            ("ACGTACGTACGT", "TACGTTACG"),
+            ("", "ACGT"),
+            ("ACGT", ""),
+            ("AAAAAAAAAA", "AAAATAAAAAA"),
+        ];
+        for (old, new) in pairs.iter() {
+            let edits = dna(old).diff(&dna(new));
+            assert_eq!(dna(old).apply_patch(&edits).unwrap(), dna(new));
+        }
+    }
+
+    #[test]
+    fn diff_limited_within_limit_is_minimal() {
+        let limited = dna("ACGTTGCA").diff_limited(&dna("AGTTGGGCA"), 3);
+        assert!(limited.minimal);
+        assert_eq!(limited.edits, dna("ACGTTGCA").diff(&dna("AGTTGGGCA")));
+    }
+
+    #[test]
+    fn diff_limited_degrades_to_valid_script() {
+        let old = dna("GATTACAGATTACACCCCGGGG");
+        let new = dna("GATTACAGATTACATTTTAAAA");
+        for limit in 0..8 {
+            let limited = old.diff_limited(&new, limit);
+            assert!(!limited.minimal);
+            assert_eq!(old.apply_patch(&limited.edits).unwrap(), new);
+        }
+        // The shared prefix is still aligned before the search gives up.
+        let limited = old.diff_limited(&new, 2);
+        assert_eq!(
+            limited.edits,
+            vec![Edit::Substitute {
+                pos: 14,
+                nucs: dna("TTTTAAAA")
+            }]
+        );
+    }
+
+    #[test]
+    fn apply_patch_out_of_bounds() {
+        let edits = [Edit::Delete { pos: 2, len: 3 }];
+        assert_eq!(
+            dna("ACGT").apply_patch(&edits),
+            Err(PatchError::OutOfBounds { pos: 2, len: 4 })
+        );
+    }
+
+    #[test]
+    fn apply_patch_unordered() {
+        let edits = [
+            Edit::Delete { pos: 2, len: 2 },
+            Edit::Delete { pos: 1, len: 1 },
+        ];
+        assert_eq!(
+            dna("ACGT").apply_patch(&edits),
+            Err(PatchError::Unordered(1))
+        );
+    }
+}
@@ -0,0 +1,260 @@
+//! Restriction enzyme digestion: cut positions and fragments of linear sequences.
+//!
+//! An [`Enzyme`] recognises an [`IupacPattern`] and cuts each strand at a fixed offset from it,
+//! as in REBASE's `GAATTC (1/5)` notation. Common enzymes are built in, see
+//! [`Enzyme::by_name`], and others can be defined with [`Enzyme::new`]. A [`Digest`] applies one
+//! or several enzymes at once, e.g. for a double digest, and reports where the top strand is cut
+//! and the fragments this leaves.
+
+use crate::{motif::IupacPattern, translate::Strand, DnaError, PackedDna};
+use std::{ops::Range, str::FromStr};
+
+/// Name, recognition site, and top and bottom strand cut offsets of the built-in enzymes
+const COMMON: [(&str, &str, isize, isize); 21] = [
+    ("AluI", "AGCT", 2, 2),
+    ("BamHI", "GGATCC", 1, 5),
+    ("BsaI", "GGTCTC", 7, 11),
+    ("DpnII", "GATC", 0, 4),
+    ("EcoRI", "GAATTC", 1, 5),
+    ("EcoRV", "GATATC", 3, 3),
+    ("HaeIII", "GGCC", 2, 2),
+    ("HindIII", "AAGCTT", 1, 5),
+    ("HinfI", "GANTC", 1, 4),
+    ("KpnI", "GGTACC", 5, 1),
+    ("MspI", "CCGG", 1, 3),
+    ("NcoI", "CCATGG", 1, 5),
+    ("NdeI", "CATATG", 2, 4),
+    ("NotI", "GCGGCCGC", 2, 6),
+    ("PstI", "CTGCAG", 5, 1),
+    ("SacI", "GAGCTC", 5, 1),
+    ("SalI", "GTCGAC", 1, 5),
+    ("SmaI", "CCCGGG", 3, 3),
+    ("SpeI", "ACTAGT", 1, 5),
+    ("XbaI", "TCTAGA", 1, 5),
+    ("XhoI", "CTCGAG", 1, 5),
+];
+
+/// A restriction enzyme
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Enzyme {
+    name: String,
+    site: IupacPattern,
+    top_cut: isize,
+    bottom_cut: isize,
+}
+
+impl Enzyme {
+    /// Define an enzyme recognising `site` that cuts the top strand `top_cut` and the bottom
+    /// strand `bottom_cut` nucleotides after the start of the site, both read on the top strand.
+    ///
+    /// Offsets may lie outside the site, e.g. EcoRI is `("EcoRI", "GAATTC", 1, 5)` and BsaI,
+    /// cutting after its site, `("BsaI", "GGTCTC", 7, 11)`. Sites on the bottom strand are cut
+    /// at the mirrored offsets.
+    pub fn new(
+        name: impl Into<String>,
+        site: &str,
+        top_cut: isize,
+        bottom_cut: isize,
+    ) -> Result<Self, DnaError> {
+        Ok(Enzyme {
+            name: name.into(),
+            site: IupacPattern::from_str(site)?,
+            top_cut,
+            bottom_cut,
+        })
+    }
+
+    /// The built-in enzyme called `name`, ignoring case, e.g. `EcoRI`, `BamHI` or `HindIII`
+    pub fn by_name(name: &str) -> Option<Enzyme> {
+        Enzyme::common()
+            .into_iter()
+            .find(|enzyme| enzyme.name.eq_ignore_ascii_case(name))
+    }
+
+    /// All built-in enzymes, by name
+    pub fn common() -> Vec<Enzyme> {
+        COMMON
+            .iter()
+            .map(|&(name, site, top_cut, bottom_cut)| Enzyme {
+                name: name.to_string(),
+                site: site.parse().expect("built-in sites are valid"),
+                top_cut,
+                bottom_cut,
+            })
+            .collect()
+    }
+
+    /// The name of the enzyme
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// The recognition site, read on the top strand
+    pub fn site(&self) -> &IupacPattern {
+        &self.site
+    }
+
+    /// The top and bottom strand cut offsets from the start of the site
+    pub fn cut_offsets(&self) -> (isize, isize) {
+        (self.top_cut, self.bottom_cut)
+    }
+}
+
+/// A cut of the top strand, see [`Digest::cuts`]
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Cut {
+    /// 0-based position of the first nucleotide after the cut
+    pub position: usize,
+    /// Name of the enzyme cutting there
+    pub enzyme: String,
+    /// The recognition site that was cut, on the forward strand
+    pub site: Range<usize>,
+}
+
+/// Digestion of linear sequences with one or more enzymes
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Digest {
+    enzymes: Vec<Enzyme>,
+    overlapping_sites: bool,
+}
+
+impl Digest {
+    /// Digest with every enzyme of `enzymes` at once
+    pub fn new(enzymes: Vec<Enzyme>) -> Self {
+        Digest {
+            enzymes,
+            overlapping_sites: true,
+        }
+    }
+
+    /// Whether sites of one enzyme that overlap, such as the two `GCGC` in `GCGCGC`, are all
+    /// cut, as by default.
+    ///
+    /// Without it, a site overlapping an earlier cut site of the same enzyme is left uncut, as
+    /// the enzyme bound to the first one blocks it.
+    pub fn overlapping_sites(mut self, overlapping: bool) -> Self {
+        self.overlapping_sites = overlapping;
+        self
+    }
+
+    /// The cuts of the top strand of `dna`, by position then enzyme order.
+    ///
+    /// Cuts that would fall outside the sequence, or at either end of it, are left out.
+    pub fn cuts(&self, dna: &PackedDna) -> Vec<Cut> {
+        let len = dna.len();
+        let mut cuts = Vec::new();
+        for enzyme in &self.enzymes {
+            let site_len = enzyme.site.len();
+            let mut last_site: Option<Range<usize>> = None;
+            for found in enzyme.site.find_all(dna) {
+                let site = found.start..found.start + site_len;
+                if !self.overlapping_sites
+                    && last_site.as_ref().is_some_and(|last| site.start < last.end)
+                {
+                    continue;
+                }
+                let position = match found.strand {
+                    Strand::Forward => site.start as isize + enzyme.top_cut,
+                    Strand::Reverse => site.end as isize - enzyme.bottom_cut,
+                };
+                last_site = Some(site.clone());
+                if position > 0 && position < len as isize {
+                    cuts.push(Cut {
+                        position: position as usize,
+                        enzyme: enzyme.name.clone(),
+                        site,
+                    });
+                }
+            }
+        }
+        // The sort is stable, so cuts at one position keep the order of the enzymes.
+        cuts.sort_by_key(|cut| cut.position);
+        cuts
+    }
+
+    /// The fragments `dna` is cut into, in order along the sequence
+    pub fn fragments(&self, dna: &PackedDna) -> Vec<Range<usize>> {
+        let mut positions: Vec<usize> = self.cuts(dna).iter().map(|cut| cut.position).collect();
+        positions.dedup();
+        let mut start = 0;
+        let mut fragments = Vec::with_capacity(positions.len() + 1);
+        for position in positions.into_iter().chain(std::iter::once(dna.len())) {
+            fragments.push(start..position);
+            start = position;
+        }
+        fragments
+    }
+
+    /// The lengths of the fragments, longest first, as they would run on a gel
+    pub fn fragment_lengths(&self, dna: &PackedDna) -> Vec<usize> {
+        let mut lengths: Vec<usize> = self
+            .fragments(dna)
+            .iter()
+            .map(|fragment| fragment.len())
+            .collect();
+        lengths.sort_unstable_by(|a, b| b.cmp(a));
+        lengths
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::dna;
+
+    #[test]
+    fn single_digest() {
+        let ecori = Enzyme::by_name("ecori").unwrap();
+        assert_eq!(ecori.name(), "EcoRI");
+        let digest = Digest::new(vec![ecori]);
+        let seq = dna("AAGAATTCAAAGAATTCAA");
+        let cuts = digest.cuts(&seq);
+        assert_eq!(
+            cuts.iter().map(|cut| cut.position).collect::<Vec<_>>(),
+            vec![3, 12]
+        );
+        assert_eq!(cuts[1].site, 11..17);
+        assert_eq!(digest.fragments(&seq), vec![0..3, 3..12, 12..19]);
+        assert_eq!(digest.fragment_lengths(&seq), vec![9, 7, 3]);
+        assert_eq!(digest.fragments(&dna("ACGT")), vec![0..4]);
+        assert_eq!(digest.fragments(&dna("")), vec![0..0]);
+    }
+
+    #[test]
+    fn double_digest() {
+        let digest = Digest::new(vec![
+            Enzyme::by_name("EcoRI").unwrap(),
+            Enzyme::by_name("BamHI").unwrap(),
+        ]);
+        let seq = dna("TTGGATCCTTTTGAATTCTTTTGGATCCTT");
+        let cuts = digest.cuts(&seq);
+        let found: Vec<(usize, &str)> = cuts
+            .iter()
+            .map(|cut| (cut.position, cut.enzyme.as_str()))
+            .collect();
+        assert_eq!(found, vec![(3, "BamHI"), (13, "EcoRI"), (23, "BamHI")]);
+        assert_eq!(digest.fragment_lengths(&seq), vec![10, 10, 7, 3]);
+    }
+
+    #[test]
+    fn non_palindromic_site_cuts_both_strands() {
+        let digest = Digest::new(vec![Enzyme::by_name("BsaI").unwrap()]);
+        // GGTCTC on the top strand, then GAGACC, the same site on the bottom strand.
+        let seq = dna("AAGGTCTCAAAAAAAAAAAAAAGAGACCAA");
+        let cuts: Vec<usize> = digest.cuts(&seq).iter().map(|cut| cut.position).collect();
+        assert_eq!(cuts, vec![2 + 7, 28 - 11]);
+        // Cuts beyond the end are dropped.
+        assert!(digest.cuts(&dna("AAGGTCTCA")).is_empty());
+    }
+
+    #[test]
+    fn overlapping_sites() {
+        let hhai = Enzyme::new("HhaI", "GCGC", 3, 1).unwrap();
+        let seq = dna("AAGCGCGCAA");
+        let all = Digest::new(vec![hhai.clone()]);
+        assert_eq!(all.fragments(&seq), vec![0..5, 5..7, 7..10]);
+        let blocked = Digest::new(vec![hhai]).overlapping_sites(false);
+        assert_eq!(blocked.fragments(&seq), vec![0..5, 5..10]);
+        assert!(Enzyme::new("bad", "GAXTC", 1, 4).is_err());
+    }
+}
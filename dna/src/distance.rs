@@ -0,0 +1,177 @@
+//! Distances between sequences, e.g. for matching barcodes and UMIs.
+//!
+//! [`PackedDna::hamming_distance`] compares the packed bytes directly: XOR leaves a non-zero
+//! 2-bit pair exactly where the nucleotides differ, and those pairs are counted 32 at a time
+//! with a popcount. [`PackedDna::edit_distance`] uses Myers' bit-parallel algorithm, with the
+//! multi-word extension of Hyyrö, so each nucleotide of one sequence updates 64 positions of the
+//! other at once.
+
+use crate::PackedDna;
+use std::convert::TryInto;
+
+/// The low bit of every 2-bit pair
+const LOW_BITS: u64 = 0x5555_5555_5555_5555;
+
+impl PackedDna {
+    /// Number of positions at which the sequences differ, or `None` if their lengths differ
+    pub fn hamming_distance(&self, other: &PackedDna) -> Option<usize> {
+        if self.len() != other.len() {
+            return None;
+        }
+        // Sequences of one length share their byte layout, padding included.
+        let (ours, theirs) = (self.used_bytes(), other.used_bytes());
+        let mut ours_words = ours.chunks_exact(8);
+        let mut theirs_words = theirs.chunks_exact(8);
+        let mut distance = 0;
+        for (a, b) in (&mut ours_words).zip(&mut theirs_words) {
+            let a = u64::from_ne_bytes(a.try_into().unwrap());
+            let b = u64::from_ne_bytes(b.try_into().unwrap());
+            distance += differing_pairs(a ^ b);
+        }
+        for (a, b) in ours_words.remainder().iter().zip(theirs_words.remainder()) {
+            distance += differing_pairs(u64::from(a ^ b));
+        }
+        Some(distance)
+    }
+
+    /// Minimum number of substitutions, insertions and deletions turning one sequence into the
+    /// other (Levenshtein distance)
+    pub fn edit_distance(&self, other: &PackedDna) -> usize {
+        // The shorter sequence is held in bit vectors, the longer one is streamed past it.
+        let (pattern, text) = if self.len() <= other.len() {
+            (self, other)
+        } else {
+            (other, self)
+        };
+        let len = pattern.len();
+        if len == 0 {
+            return text.len();
+        }
+        let blocks = len.div_ceil(64);
+        // Bit i of block b of `peq[nuc]` is set where the pattern holds `nuc` at 64 * b + i.
+        let mut peq = vec![vec![0u64; blocks]; 4];
+        for (idx, nuc) in pattern.iter().enumerate() {
+            peq[nuc as usize][idx / 64] |= 1 << (idx % 64);
+        }
+        let last_bit = 1u64 << ((len - 1) % 64);
+
+        // Vertical differences between consecutive rows of the current column, as bit vectors of
+        // +1 (`plus`) and -1 (`minus`). The first column counts up by one per row.
+        let mut plus = vec![u64::MAX; blocks];
+        let mut minus = vec![0u64; blocks];
+        let mut distance = len;
+        for nuc in text.iter() {
+            let eq = &peq[nuc as usize];
+            // The top row counts up by one per column.
+            let mut carry = 1;
+            for b in 0..blocks {
+                let high = if b + 1 == blocks { last_bit } else { 1 << 63 };
+                carry = advance_block(&mut plus[b], &mut minus[b], eq[b], carry, high);
+            }
+            distance = (distance as isize + carry as isize) as usize;
+        }
+        distance
+    }
+}
+
+/// Number of non-zero 2-bit pairs in `x`
+fn differing_pairs(x: u64) -> usize {
+    ((x | x >> 1) & LOW_BITS).count_ones() as usize
+}
+
+/// Advance one 64-row block of a column by one text nucleotide.
+///
+/// `carry` is the horizontal difference entering the top of the block, -1, 0 or +1. Returns
+/// the horizontal difference leaving it at the row `high`.
+fn advance_block(plus: &mut u64, minus: &mut u64, eq: u64, carry: i8, high: u64) -> i8 {
+    let carry_minus = u64::from(carry < 0);
+    let carry_plus = u64::from(carry > 0);
+    let xv = eq | *minus;
+    let eq = eq | carry_minus;
+    let xh = ((eq & *plus).wrapping_add(*plus) ^ *plus) | eq;
+    let mut horizontal_plus = *minus | !(xh | *plus);
+    let mut horizontal_minus = *plus & xh;
+    let out = if horizontal_plus & high != 0 {
+        1
+    } else if horizontal_minus & high != 0 {
+        -1
+    } else {
+        0
+    };
+    horizontal_plus = horizontal_plus << 1 | carry_plus;
+    horizontal_minus = horizontal_minus << 1 | carry_minus;
+    *plus = horizontal_minus | !(xv | horizontal_plus);
+    *minus = horizontal_plus & xv;
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::{dna, Lcg};
+    use crate::Nuc;
+
+    fn naive_edit_distance(a: &[Nuc], b: &[Nuc]) -> usize {
+        let mut row: Vec<usize> = (0..=b.len()).collect();
+        for (i, &x) in a.iter().enumerate() {
+            let mut diagonal = row[0];
+            row[0] = i + 1;
+            for (j, &y) in b.iter().enumerate() {
+                let value = (diagonal + usize::from(x != y))
+                    .min(row[j] + 1)
+                    .min(row[j + 1] + 1);
+                diagonal = row[j + 1];
+                row[j + 1] = value;
+            }
+        }
+        row[b.len()]
+    }
+
+    #[test]
+    fn hamming() {
+        assert_eq!(
+            dna("ACGTACGTA").hamming_distance(&dna("ACGTACGTA")),
+            Some(0)
+        );
+        assert_eq!(
+            dna("ACGTACGTA").hamming_distance(&dna("TCGAACGTC")),
+            Some(3)
+        );
+        assert_eq!(dna("ACGT").hamming_distance(&dna("ACG")), None);
+        assert_eq!(dna("").hamming_distance(&dna("")), Some(0));
+        let a = "ACGT".repeat(30) + "GA";
+        let b = "ACCT".repeat(30) + "GT";
+        assert_eq!(dna(&a).hamming_distance(&dna(&b)), Some(31));
+    }
+
+    #[test]
+    fn edit_distance_matches_naive() {
+        assert_eq!(dna("GATTACA").edit_distance(&dna("GCATGCT")), 4);
+        assert_eq!(dna("").edit_distance(&dna("ACG")), 3);
+        assert_eq!(dna("ACG").edit_distance(&dna("")), 3);
+        let nucs = [Nuc::A, Nuc::C, Nuc::G, Nuc::T];
+        let mut rng = Lcg::new(42);
+        let mut next = |bound: u64| rng.below(bound);
+        for _ in 0..200 {
+            let a: Vec<Nuc> = (0..next(150)).map(|_| nucs[next(4) as usize]).collect();
+            let mut b = a.clone();
+            for _ in 0..next(20) {
+                let pos = next(b.len() as u64 + 1) as usize;
+                match next(3) {
+                    0 if pos < b.len() => b[pos] = nucs[next(4) as usize],
+                    1 if pos < b.len() => {
+                        b.remove(pos);
+                    }
+                    _ => b.insert(pos, nucs[next(4) as usize]),
+                }
+            }
+            let (packed_a, packed_b) = (
+                a.iter().copied().collect::<PackedDna>(),
+                b.iter().copied().collect::<PackedDna>(),
+            );
+            let expected = naive_edit_distance(&a, &b);
+            assert_eq!(packed_a.edit_distance(&packed_b), expected);
+            assert_eq!(packed_b.edit_distance(&packed_a), expected);
+        }
+    }
+}
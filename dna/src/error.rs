@@ -0,0 +1,233 @@
+//! The crate-wide error type.
+//!
+//! Errors are layered: an I/O error is the source of the format error of the file being read,
+//! such as [`FastaError::Io`], which converts into a [`DnaError`], and code calling the crate can
+//! wrap that in turn with what it was doing through [`ErrorContext`]. Walking
+//! [`Error::source`](std::error::Error::source), or [`DnaError::chain`], yields every layer from
+//! the outermost down, and [`DnaError::code`] names the kind of error for programs to act on.
+//!
+//! Parsing sequences returns a [`DnaError`] directly, but the readers, writers and algorithms of
+//! individual modules keep their own error types, such as [`FastaError`] or [`TwoBitError`], on
+//! purpose: their variants say exactly what can go wrong in that call, so callers can match on
+//! them without a catch-all arm, and none of them has to change when another module gains a kind
+//! of error. Every one of them converts into [`DnaError`], so `?` still works across modules.
+//! The exceptions are [`ParseNucError`](crate::ParseNucError) and
+//! [`ParseAminoAcidError`](crate::protein::ParseAminoAcidError), which only carry the single
+//! rejected value of a `TryFrom` conversion, and the `noodles` conversion error
+//! `interop::RecordError`, which already wraps a [`DnaError`].
+
+#[cfg(feature = "fm-index")]
+use crate::fm_index::ReadError;
+use crate::{
+    binning::BinningError, concordance::ConcordanceError, detect::DetectError, diff::PatchError,
+    extract::ExtractError, fasta::FastaError, fastq::FastqError, gff::GffError,
+    iupac::AmbiguousNucError, kmer::KmerLengthError, motif::LengthMismatchError, regex::RegexError,
+    storage::DecodeError, summary::SummaryError, twobit::TwoBitError, IndexError,
+};
+use std::error::Error;
+
+/// An error from any part of the crate.
+///
+/// Sequence parsing reports it directly, pointing at the offending character. The errors of
+/// individual modules convert into it, so code mixing several of them can use `?` throughout.
+#[derive(Debug, thiserror::Error)]
+pub enum DnaError {
+    /// A character of the input is not a nucleotide
+    #[error("failed to parse nucleotide from {character:?} at position {position}")]
+    InvalidNuc {
+        /// The offending character
+        character: char,
+        /// Its 0-based position in the input
+        position: usize,
+    },
+    /// A position outside the sequence was accessed
+    #[error(transparent)]
+    Index(#[from] IndexError),
+    /// Reading or writing failed
+    #[error(transparent)]
+    Io(#[from] std::io::Error),
+    /// A FASTA file could not be read
+    #[error(transparent)]
+    Fasta(#[from] FastaError),
+    /// A FASTQ file could not be read
+    #[error(transparent)]
+    Fastq(#[from] FastqError),
+    /// An annotated region could not be extracted
+    #[error(transparent)]
+    Extract(#[from] ExtractError),
+    /// A GFF3 file could not be read
+    #[error(transparent)]
+    Gff(#[from] GffError),
+    /// A 2bit file could not be read or written
+    #[error(transparent)]
+    TwoBit(#[from] TwoBitError),
+    /// A binary encoding could not be decoded
+    #[error(transparent)]
+    Decode(#[from] DecodeError),
+    /// A patch could not be applied
+    #[error(transparent)]
+    Patch(#[from] PatchError),
+    /// An ambiguous nucleotide could not be resolved
+    #[error(transparent)]
+    Ambiguous(#[from] AmbiguousNucError),
+    /// A sequence could not become a fixed-length k-mer
+    #[error(transparent)]
+    KmerLength(#[from] KmerLengthError),
+    /// Composition vectors could not be binned
+    #[error(transparent)]
+    Binning(#[from] BinningError),
+    /// Sequences expected to have the same length do not
+    #[error(transparent)]
+    LengthMismatch(#[from] LengthMismatchError),
+    /// A regular expression over IUPAC codes could not be compiled
+    #[error(transparent)]
+    Regex(#[from] RegexError),
+    /// A FASTA summary could not be computed, read or written
+    #[error(transparent)]
+    Summary(#[from] SummaryError),
+    /// Two references could not be compared
+    #[error(transparent)]
+    Concordance(#[from] ConcordanceError),
+    /// The format of input could not be detected or read
+    #[error(transparent)]
+    Detect(#[from] DetectError),
+    /// A saved FM-index could not be read
+    #[cfg(feature = "fm-index")]
+    #[error(transparent)]
+    FmIndex(#[from] ReadError),
+    /// Another error, with what was being done when it occurred
+    #[error("{context}")]
+    Context {
+        /// What was being done, such as `reading genome.fa`
+        context: String,
+        /// The error that occurred
+        source: Box<DnaError>,
+    },
+}
+
+impl DnaError {
+    /// A stable code for the kind of error, such as `fasta.invalid-nuc`.
+    ///
+    /// Codes are lower case and dot-separated: the subsystem, then for the format errors of
+    /// FASTA, FASTQ, GFF3 and 2bit input and for positional access the kind of error. Added
+    /// [context](ErrorContext) does not change the code.
+    pub fn code(&self) -> &'static str {
+        match self {
+            DnaError::InvalidNuc { .. } => "sequence.invalid-nuc",
+            DnaError::Index(e) => e.code(),
+            DnaError::Io(_) => "io",
+            DnaError::Fasta(e) => e.code(),
+            DnaError::Fastq(e) => e.code(),
+            DnaError::Extract(_) => "extract",
+            DnaError::Gff(e) => e.code(),
+            DnaError::TwoBit(e) => e.code(),
+            DnaError::Decode(_) => "decode",
+            DnaError::Patch(_) => "patch",
+            DnaError::Ambiguous(_) => "ambiguous",
+            DnaError::KmerLength(_) => "kmer-length",
+            DnaError::Binning(_) => "binning",
+            DnaError::LengthMismatch(_) => "length-mismatch",
+            DnaError::Regex(_) => "regex",
+            DnaError::Summary(_) => "summary",
+            DnaError::Concordance(ConcordanceError::Fasta(e)) => e.code(),
+            DnaError::Concordance(_) => "concordance",
+            DnaError::Detect(_) => "detect",
+            #[cfg(feature = "fm-index")]
+            DnaError::FmIndex(_) => "fm-index",
+            DnaError::Context { source, .. } => source.code(),
+        }
+    }
+
+    /// This error followed by each of its sources, outermost first
+    pub fn chain(&self) -> impl Iterator<Item = &(dyn Error + 'static)> {
+        std::iter::successors(Some(self as &(dyn Error + 'static)), |&e| e.source())
+    }
+}
+
+/// Adds context to the errors of results, see [`DnaError::Context`]
+pub trait ErrorContext<T> {
+    /// Wrap an error with `context`, a description of what was being done
+    fn context(self, context: impl Into<String>) -> Result<T, DnaError>;
+
+    /// Wrap an error with the context returned by `context`, which is only called on error
+    fn with_context<C: Into<String>>(self, context: impl FnOnce() -> C) -> Result<T, DnaError>;
+}
+
+impl<T, E: Into<DnaError>> ErrorContext<T> for Result<T, E> {
+    fn context(self, context: impl Into<String>) -> Result<T, DnaError> {
+        self.with_context(|| context)
+    }
+
+    fn with_context<C: Into<String>>(self, context: impl FnOnce() -> C) -> Result<T, DnaError> {
+        self.map_err(|e| DnaError::Context {
+            context: context().into(),
+            source: Box::new(e.into()),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Nuc, PackedDna};
+    use std::str::FromStr;
+
+    fn set_g(text: &str, idx: usize) -> Result<PackedDna, DnaError> {
+        let mut dna = PackedDna::from_str(text)?;
+        dna.set(idx, Nuc::G)?;
+        Ok(dna)
+    }
+
+    #[test]
+    fn question_mark_converts_errors() {
+        assert_eq!(set_g("ACGT", 3).unwrap().to_string(), "ACGG");
+        assert!(matches!(
+            set_g("ACGT", 9),
+            Err(DnaError::Index(IndexError::OutOfBounds {
+                index: 9,
+                len: 4
+            }))
+        ));
+        assert!(matches!(
+            set_g("ACNT", 1),
+            Err(DnaError::InvalidNuc {
+                character: 'N',
+                position: 2
+            })
+        ));
+    }
+
+    #[test]
+    fn context_chains_down_to_the_io_error() {
+        let input = crate::fasta::Reader::new(FailingReader).next().unwrap();
+        let err = input.context("reading genome.fa").unwrap_err();
+        assert_eq!(err.code(), "fasta.io");
+        let messages: Vec<String> = err.chain().map(ToString::to_string).collect();
+        assert_eq!(
+            messages,
+            [
+                "reading genome.fa",
+                "failed to read FASTA input",
+                "disk on fire"
+            ]
+        );
+
+        let err = set_g("ACGT", 4).context("editing").unwrap_err();
+        assert_eq!(err.code(), "index.out-of-bounds");
+        assert_eq!(err.chain().count(), 2);
+    }
+
+    struct FailingReader;
+
+    impl std::io::Read for FailingReader {
+        fn read(&mut self, _: &mut [u8]) -> std::io::Result<usize> {
+            Err(std::io::Error::other("disk on fire"))
+        }
+    }
+
+    #[test]
+    fn wrapped_errors_keep_their_message() {
+        let err = DnaError::from(IndexError::Zero);
+        assert_eq!(err.to_string(), IndexError::Zero.to_string());
+    }
+}
@@ -0,0 +1,482 @@
+//! K-mer counting in bounded memory, spilling to disk.
+//!
+//! [`KmerCounter`](crate::kmer::KmerCounter) keeps every distinct k-mer in a hash map, which
+//! for 31-mers of a large read set takes far more memory than the reads themselves. An
+//! [`ExternalKmerCounter`] instead buffers k-mers up to a memory limit, split into partitions by
+//! their minimizer, the m-mer of smallest hash they contain. Whenever the buffer fills, each
+//! partition is sorted, its repeats are collapsed into counts, and it is appended to the
+//! partition's file as a sorted run. [`finish`](ExternalKmerCounter::finish) then merges the
+//! runs one partition at a time, holding only a read buffer per run.
+//!
+//! A k-mer always falls into the same partition, so each is counted exactly once, and k-mers
+//! overlapping in a read mostly share a minimizer and hence a partition.
+
+use crate::{
+    kmer::{Kmers, MAX_K},
+    sketch, PackedDna,
+};
+use std::{
+    cmp::Reverse,
+    collections::BinaryHeap,
+    convert::TryInto,
+    fs::{self, File, OpenOptions},
+    io::{self, BufReader, BufWriter, Read, Seek, SeekFrom, Take, Write},
+    path::PathBuf,
+    sync::atomic::{AtomicUsize, Ordering},
+};
+
+/// Length of the minimizers partitioning k-mers, or `k` for shorter k-mers
+const MINIMIZER_LEN: usize = 11;
+
+/// Bytes of a counted k-mer in a run: the packed k-mer and its count, little-endian
+const ENTRY_LEN: usize = 16;
+
+/// Counters created by this process, naming their files apart
+static COUNTERS: AtomicUsize = AtomicUsize::new(0);
+
+/// Counts the k-mers of any number of sequences in bounded memory, see the
+/// [module documentation](self)
+#[derive(Debug)]
+pub struct ExternalKmerCounter {
+    k: usize,
+    canonical: bool,
+    max_memory: usize,
+    temp_dir: PathBuf,
+    /// Buffered k-mers of each partition
+    buffers: Vec<Vec<u64>>,
+    buffered: usize,
+    /// Byte offset and number of entries of the runs spilled for each partition
+    runs: Vec<Vec<(u64, u64)>>,
+    files: SpillFiles,
+}
+
+impl ExternalKmerCounter {
+    /// Create a counter for k-mers of length `k`, using 1 GiB of memory and 64 partitions in the
+    /// system's temporary directory.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `k` is zero or greater than [`MAX_K`].
+    pub fn new(k: usize) -> Self {
+        assert!(
+            (1..=MAX_K).contains(&k),
+            "k-mer length must be between 1 and {}, got {}",
+            MAX_K,
+            k
+        );
+        ExternalKmerCounter {
+            k,
+            canonical: false,
+            max_memory: 1 << 30,
+            temp_dir: std::env::temp_dir(),
+            buffers: vec![Vec::new(); 64],
+            buffered: 0,
+            runs: vec![Vec::new(); 64],
+            files: SpillFiles::default(),
+        }
+    }
+
+    /// Count each k-mer together with its reverse complement, keyed by the smaller of the two
+    pub fn canonical(mut self, canonical: bool) -> Self {
+        self.canonical = canonical;
+        self
+    }
+
+    /// Buffer at most about `bytes` of k-mers before spilling them to disk.
+    ///
+    /// Each buffered k-mer takes 8 bytes. Merging takes a few kilobytes per run instead, and there
+    /// is one run per partition each time the buffer fills.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `bytes` cannot hold a single k-mer.
+    pub fn max_memory(mut self, bytes: usize) -> Self {
+        assert!(bytes >= 8, "memory limit must hold at least one k-mer");
+        self.max_memory = bytes;
+        self
+    }
+
+    /// Split k-mers into `partitions` files; more partitions make each file smaller.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `partitions` is zero, or k-mers were already added.
+    pub fn partitions(mut self, partitions: usize) -> Self {
+        assert!(partitions > 0, "there must be at least one partition");
+        assert_eq!(
+            self.buffered, 0,
+            "partitions must be set before adding k-mers"
+        );
+        self.buffers = vec![Vec::new(); partitions];
+        self.runs = vec![Vec::new(); partitions];
+        self
+    }
+
+    /// Write the spilled runs to files in `dir` instead of the system's temporary directory
+    pub fn temp_dir(mut self, dir: impl Into<PathBuf>) -> Self {
+        self.temp_dir = dir.into();
+        self
+    }
+
+    /// The k-mer length
+    pub fn k(&self) -> usize {
+        self.k
+    }
+
+    /// Count the k-mers of `dna`, spilling the buffer to disk if it fills
+    pub fn add(&mut self, dna: &PackedDna) -> io::Result<()> {
+        let capacity = self.max_memory / 8;
+        for kmer in Kmers::new(dna, self.k, self.canonical) {
+            let partition = self.partition(kmer);
+            self.buffers[partition].push(kmer);
+            self.buffered += 1;
+            if self.buffered >= capacity {
+                self.spill()?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Stop adding sequences, to merge the counts as they are iterated
+    pub fn finish(self) -> ExternalKmerCounts {
+        let ExternalKmerCounter {
+            k,
+            buffers,
+            runs,
+            files,
+            ..
+        } = self;
+        // Runs already on disk are merged with what is still buffered, which stays in memory.
+        let mut partitions: Vec<Partition> = buffers
+            .into_iter()
+            .zip(runs)
+            .enumerate()
+            .map(|(idx, (mut buffer, runs))| {
+                buffer.sort_unstable();
+                let path = (!runs.is_empty()).then(|| files.path(idx));
+                Partition { buffer, path, runs }
+            })
+            .collect();
+        partitions.reverse();
+        ExternalKmerCounts {
+            k,
+            partitions,
+            sources: Vec::new(),
+            heap: BinaryHeap::new(),
+            _files: files,
+        }
+    }
+
+    /// The partition of `kmer`, from the hash of its minimizer
+    fn partition(&self, kmer: u64) -> usize {
+        let m = MINIMIZER_LEN.min(self.k);
+        let mask = (1 << (2 * m)) - 1;
+        let minimizer = (0..=self.k - m)
+            .map(|shift| sketch::hash(kmer >> (2 * shift) & mask))
+            .min()
+            .expect("a k-mer has at least one m-mer");
+        (minimizer % self.buffers.len() as u64) as usize
+    }
+
+    /// Append the sorted and collapsed buffer of each partition to its file as a run.
+    ///
+    /// Each buffer then keeps at most its share of the memory limit, so that partitions filling
+    /// up in turn do not together hold on to more than the limit.
+    fn spill(&mut self) -> io::Result<()> {
+        let share = self.max_memory / 8 / self.buffers.len();
+        for (idx, buffer) in self.buffers.iter_mut().enumerate() {
+            if buffer.is_empty() {
+                continue;
+            }
+            buffer.sort_unstable();
+            let path = self.files.create(&self.temp_dir, idx);
+            let file = OpenOptions::new().create(true).append(true).open(path)?;
+            let offset = file.metadata()?.len();
+            let mut out = BufWriter::new(file);
+            let mut entries = 0;
+            for (kmer, count) in Collapse::new(buffer) {
+                out.write_all(&kmer.to_le_bytes())?;
+                out.write_all(&count.to_le_bytes())?;
+                entries += 1;
+            }
+            out.flush()?;
+            self.runs[idx].push((offset, entries));
+            buffer.clear();
+            buffer.shrink_to(share);
+        }
+        self.buffered = 0;
+        Ok(())
+    }
+}
+
+/// The merged counts of an [`ExternalKmerCounter`], as `(packed_kmer, count)` pairs.
+///
+/// Pairs come partition by partition, by ascending k-mer within each. The spilled files are
+/// deleted when the counts are dropped.
+#[derive(Debug)]
+pub struct ExternalKmerCounts {
+    k: usize,
+    /// Partitions still to merge, last first
+    partitions: Vec<Partition>,
+    /// Runs of the partition being merged
+    sources: Vec<Run>,
+    /// The next k-mer and count of each run of `sources` with any left
+    heap: BinaryHeap<Reverse<(u64, u64, usize)>>,
+    _files: SpillFiles,
+}
+
+impl ExternalKmerCounts {
+    /// The k-mer length
+    pub fn k(&self) -> usize {
+        self.k
+    }
+
+    /// Start merging the next partition, returning `false` if there are none left
+    fn next_partition(&mut self) -> io::Result<bool> {
+        let partition = match self.partitions.pop() {
+            Some(partition) => partition,
+            None => return Ok(false),
+        };
+        self.sources.clear();
+        if let Some(path) = &partition.path {
+            for &(offset, entries) in &partition.runs {
+                let mut file = File::open(path)?;
+                file.seek(SeekFrom::Start(offset))?;
+                let reader = BufReader::new(file.take(entries * ENTRY_LEN as u64));
+                self.sources.push(Run::File(reader));
+            }
+        }
+        self.sources.push(Run::Memory(partition.buffer, 0));
+        for idx in 0..self.sources.len() {
+            self.advance(idx)?;
+        }
+        Ok(true)
+    }
+
+    /// Move the next entry of run `idx`, if any, onto the heap
+    fn advance(&mut self, idx: usize) -> io::Result<()> {
+        if let Some((kmer, count)) = self.sources[idx].next()? {
+            self.heap.push(Reverse((kmer, count, idx)));
+        }
+        Ok(())
+    }
+
+    fn next_count(&mut self) -> io::Result<Option<(u64, usize)>> {
+        while self.heap.is_empty() {
+            if !self.next_partition()? {
+                return Ok(None);
+            }
+        }
+        let Reverse((kmer, mut count, idx)) = self.heap.pop().expect("the heap is not empty");
+        self.advance(idx)?;
+        while let Some(&Reverse((next, more, idx))) = self.heap.peek() {
+            if next != kmer {
+                break;
+            }
+            self.heap.pop();
+            count += more;
+            self.advance(idx)?;
+        }
+        Ok(Some((kmer, count as usize)))
+    }
+}
+
+impl Iterator for ExternalKmerCounts {
+    type Item = io::Result<(u64, usize)>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let next = self.next_count();
+        if next.is_err() {
+            // A failed read leaves the merge in an unknown state.
+            self.partitions.clear();
+            self.heap.clear();
+        }
+        next.transpose()
+    }
+}
+
+/// The k-mers of one partition: its spilled runs and its sorted final buffer
+#[derive(Debug)]
+struct Partition {
+    buffer: Vec<u64>,
+    path: Option<PathBuf>,
+    runs: Vec<(u64, u64)>,
+}
+
+/// A sorted run of counted k-mers being merged
+#[derive(Debug)]
+enum Run {
+    /// Spilled entries read back from a partition file
+    File(BufReader<Take<File>>),
+    /// Sorted k-mers still in memory, with the position of the next one
+    Memory(Vec<u64>, usize),
+}
+
+impl Run {
+    fn next(&mut self) -> io::Result<Option<(u64, u64)>> {
+        match self {
+            Run::File(reader) => {
+                let mut entry = [0; ENTRY_LEN];
+                match reader.read_exact(&mut entry) {
+                    Ok(()) => {}
+                    Err(e) if e.kind() == io::ErrorKind::UnexpectedEof => return Ok(None),
+                    Err(e) => return Err(e),
+                }
+                let (kmer, count) = entry.split_at(8);
+                Ok(Some((
+                    u64::from_le_bytes(kmer.try_into().expect("8 bytes")),
+                    u64::from_le_bytes(count.try_into().expect("8 bytes")),
+                )))
+            }
+            Run::Memory(kmers, pos) => {
+                Ok(Collapse::new(&kmers[*pos..]).next().map(|(kmer, count)| {
+                    *pos += count as usize;
+                    (kmer, count)
+                }))
+            }
+        }
+    }
+}
+
+/// The distinct k-mers of a sorted slice, with how often each occurs
+struct Collapse<'a>(&'a [u64]);
+
+impl<'a> Collapse<'a> {
+    fn new(sorted: &'a [u64]) -> Self {
+        Collapse(sorted)
+    }
+}
+
+impl Iterator for Collapse<'_> {
+    type Item = (u64, u64);
+
+    fn next(&mut self) -> Option<(u64, u64)> {
+        let &kmer = self.0.first()?;
+        let count = self.0.partition_point(|&other| other == kmer);
+        self.0 = &self.0[count..];
+        Some((kmer, count as u64))
+    }
+}
+
+/// The partition files of a counter, deleted when dropped
+#[derive(Debug, Default)]
+struct SpillFiles {
+    id: Option<usize>,
+    dir: PathBuf,
+    created: Vec<usize>,
+}
+
+impl SpillFiles {
+    /// The file of `partition`, noting it for deletion
+    fn create(&mut self, dir: &std::path::Path, partition: usize) -> PathBuf {
+        if self.id.is_none() {
+            self.id = Some(COUNTERS.fetch_add(1, Ordering::Relaxed));
+            self.dir = dir.to_path_buf();
+        }
+        if !self.created.contains(&partition) {
+            self.created.push(partition);
+        }
+        self.path(partition)
+    }
+
+    fn path(&self, partition: usize) -> PathBuf {
+        self.dir.join(format!(
+            "dna-kmers-{}-{}-{}.bin",
+            std::process::id(),
+            self.id.unwrap_or_default(),
+            partition
+        ))
+    }
+}
+
+impl Drop for SpillFiles {
+    fn drop(&mut self) {
+        for &partition in &self.created {
+            let _ = fs::remove_file(self.path(partition));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::kmer::KmerCounter;
+    use crate::test_util::random;
+
+    /// The counts of `reads` from a counter spilling every `max_memory` bytes
+    fn count_external(reads: &[PackedDna], k: usize, max_memory: usize) -> Vec<(u64, usize)> {
+        let mut counter = ExternalKmerCounter::new(k)
+            .canonical(true)
+            .max_memory(max_memory)
+            .partitions(7);
+        for read in reads {
+            counter.add(read).unwrap();
+        }
+        let counts: Vec<(u64, usize)> = counter.finish().map(Result::unwrap).collect();
+        counts
+    }
+
+    #[test]
+    fn matches_in_memory_counts() {
+        // Reads overlapping a short genome, so most k-mers occur several times.
+        let genome = random(2000, 3);
+        let reads: Vec<PackedDna> = (0..60)
+            .map(|i| genome.to_slice(i * 29..i * 29 + 150))
+            .collect();
+        let mut expected: Vec<(u64, usize)> = Vec::new();
+        for read in &reads {
+            let counts = KmerCounter::new(21).canonical(true).count(read);
+            expected.extend(counts.iter());
+        }
+        expected.sort_unstable();
+        let mut merged: Vec<(u64, usize)> = Vec::new();
+        for (kmer, count) in expected {
+            match merged.last_mut() {
+                Some((last, total)) if *last == kmer => *total += count,
+                _ => merged.push((kmer, count)),
+            }
+        }
+
+        for max_memory in [1 << 20, 4096, 64] {
+            let mut counts = count_external(&reads, 21, max_memory);
+            // Ascending within each of the 7 partitions
+            let descents = counts
+                .windows(2)
+                .filter(|pair| pair[0].0 > pair[1].0)
+                .count();
+            assert!(descents < 7);
+            counts.sort_unstable();
+            assert_eq!(counts, merged);
+        }
+        assert!(count_external(&[], 31, 64).is_empty());
+    }
+
+    #[test]
+    fn spilling_frees_memory() {
+        let max_memory = 8 * 64;
+        let mut counter = ExternalKmerCounter::new(5).max_memory(max_memory);
+        // Each repeat puts all its k-mers in a partition of its own, one after the other.
+        for repeat in ["A", "C", "G", "T", "AC", "AG", "CT", "GT"] {
+            counter.add(&repeat.repeat(200).parse().unwrap()).unwrap();
+        }
+        let retained: usize = counter.buffers.iter().map(Vec::capacity).sum();
+        assert!(retained * 8 <= max_memory, "{} k-mers retained", retained);
+        let total: usize = counter.finish().map(|entry| entry.unwrap().1).sum();
+        assert_eq!(total, 4 * 196 + 4 * 396);
+    }
+
+    #[test]
+    fn spilled_files_are_removed() {
+        let dir = std::env::temp_dir().join(format!("external-kmers-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let mut counter = ExternalKmerCounter::new(5).max_memory(64).temp_dir(&dir);
+        counter.add(&random(300, 5)).unwrap();
+        assert!(fs::read_dir(&dir).unwrap().count() > 0);
+        let mut counts = counter.finish();
+        let total: usize = counts.by_ref().map(|entry| entry.unwrap().1).sum();
+        assert_eq!(total, 296);
+        drop(counts);
+        assert_eq!(fs::read_dir(&dir).unwrap().count(), 0);
+        fs::remove_dir(dir).unwrap();
+    }
+}
@@ -0,0 +1,310 @@
+//! Extraction of annotated regions from a genome: the splice junctions of transcripts and the
+//! upstream regions of genes.
+//!
+//! The genome is given as FASTA records, matched to the sequence names of a [`FeatureIndex`] by
+//! their identifiers. Sequences of features on the reverse strand are reverse complemented, so
+//! each reads 5' to 3' along its feature.
+
+use crate::{
+    fasta,
+    gff::{Feature, FeatureIndex},
+    translate::Strand,
+    PackedDna,
+};
+use std::{collections::HashMap, ops::Range};
+
+/// An error that can occur when extracting annotated regions.
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
+pub enum ExtractError {
+    /// A feature lies on a sequence missing from the genome
+    #[error("no sequence named {0} in the genome")]
+    UnknownSequence(String),
+    /// A feature extends past the end of its sequence
+    #[error("feature {range:?} out of bounds for sequence {seqid} of length {len}")]
+    OutOfBounds {
+        /// Name of the sequence
+        seqid: String,
+        /// The 0-based range of the feature
+        range: Range<usize>,
+        /// Length of the sequence
+        len: usize,
+    },
+}
+
+/// A splice junction of a transcript, with the exon sequence on either side
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Junction {
+    /// The transcript whose consecutive exons the junction joins
+    pub transcript: String,
+    /// The sequence the transcript lies on
+    pub seqid: String,
+    /// The strand of the transcript, `None` if unknown
+    pub strand: Option<Strand>,
+    /// 0-based half-open range of the intron
+    pub intron: Range<usize>,
+    /// The end of the upstream exon joined to the start of the downstream one, reverse
+    /// complemented on the reverse strand
+    pub sequence: PackedDna,
+}
+
+impl Junction {
+    /// A name such as `tx1:chr1:101-250:+`, with the 1-based inclusive intron coordinates and
+    /// `.` for an unknown strand
+    pub fn name(&self) -> String {
+        format!(
+            "{}:{}:{}-{}:{}",
+            self.transcript,
+            self.seqid,
+            self.intron.start + 1,
+            self.intron.end,
+            strand_char(self.strand)
+        )
+    }
+
+    /// The junction as a FASTA record named by [`name`](Self::name)
+    pub fn to_record(&self) -> fasta::Record {
+        fasta::Record::new(self.name(), self.sequence.clone())
+    }
+}
+
+/// The splice junctions of every transcript of `annotation`, with up to `overhang` nucleotides
+/// of exon on each side.
+///
+/// Exons are grouped by their `Parent` transcripts and joined in genome order; exons that touch
+/// or overlap have no junction. The overhang is cut short at exons shorter than it, so each
+/// sequence covers exactly two exons. Junctions are sorted by sequence, intron and transcript,
+/// and one shared by several transcripts is listed for each.
+pub fn junctions(
+    genome: &[fasta::Record],
+    annotation: &FeatureIndex,
+    overhang: usize,
+) -> Result<Vec<Junction>, ExtractError> {
+    let genome = genome_by_id(genome);
+    let mut transcripts: HashMap<(&str, &str), Vec<&Feature>> = HashMap::new();
+    for exon in annotation.iter().filter(|feature| feature.kind == "exon") {
+        for parent in exon.parents() {
+            transcripts
+                .entry((exon.seqid.as_str(), parent))
+                .or_default()
+                .push(exon);
+        }
+    }
+
+    let mut junctions = Vec::new();
+    for ((seqid, transcript), mut exons) in transcripts {
+        let contig = contig(&genome, seqid)?;
+        exons.sort_by_key(|exon| exon.range.start);
+        for pair in exons.windows(2) {
+            let (upstream, downstream) = (&pair[0].range, &pair[1].range);
+            if upstream.end >= downstream.start {
+                continue;
+            }
+            let left = upstream.end - overhang.min(upstream.len())..upstream.end;
+            let right = downstream.start..downstream.start + overhang.min(downstream.len());
+            let mut sequence = slice(contig, seqid, left)?;
+            sequence.append(&slice(contig, seqid, right)?);
+            let strand = pair[0].strand;
+            if strand == Some(Strand::Reverse) {
+                sequence = sequence.reverse_complement();
+            }
+            junctions.push(Junction {
+                transcript: transcript.to_string(),
+                seqid: seqid.to_string(),
+                strand,
+                intron: upstream.end..downstream.start,
+                sequence,
+            });
+        }
+    }
+    junctions.sort_by(|a, b| {
+        (&a.seqid, a.intron.start, a.intron.end, &a.transcript).cmp(&(
+            &b.seqid,
+            b.intron.start,
+            b.intron.end,
+            &b.transcript,
+        ))
+    });
+    Ok(junctions)
+}
+
+/// The `length` nucleotides upstream of every gene of `genes`, such as its promoter, as FASTA
+/// records.
+///
+/// Upstream is before the start of genes on the forward strand and after the end of those on the
+/// reverse strand, whose regions are reverse complemented; genes of unknown strand are taken as
+/// forward. Regions are cut short at the ends of their sequence, possibly to nothing. Each record
+/// is named like `gene1:chr1:101-200:+`, after the gene's `ID` or `Name` and the 1-based
+/// inclusive range of the region, and records follow the genes by sequence and start.
+pub fn upstream_regions(
+    genome: &[fasta::Record],
+    genes: &FeatureIndex,
+    length: usize,
+) -> Result<Vec<fasta::Record>, ExtractError> {
+    let genome = genome_by_id(genome);
+    let mut regions = Vec::new();
+    for gene in genes.iter().filter(|feature| feature.kind == "gene") {
+        let contig = contig(&genome, &gene.seqid)?;
+        if gene.range.end > contig.len() {
+            return Err(ExtractError::OutOfBounds {
+                seqid: gene.seqid.clone(),
+                range: gene.range.clone(),
+                len: contig.len(),
+            });
+        }
+        let reverse = gene.strand == Some(Strand::Reverse);
+        let range = if reverse {
+            gene.range.end..gene.range.end.saturating_add(length).min(contig.len())
+        } else {
+            gene.range.start.saturating_sub(length)..gene.range.start
+        };
+        let mut sequence = contig.to_slice(range.clone());
+        if reverse {
+            sequence = sequence.reverse_complement();
+        }
+        let location = format!(
+            "{}:{}-{}:{}",
+            gene.seqid,
+            range.start + 1,
+            range.end,
+            strand_char(gene.strand)
+        );
+        let name = match gene.id().or_else(|| gene.attribute("Name")) {
+            Some(id) => format!("{}:{}", id, location),
+            None => location,
+        };
+        regions.push(fasta::Record::new(name, sequence));
+    }
+    Ok(regions)
+}
+
+/// The records of `genome` by identifier
+fn genome_by_id(genome: &[fasta::Record]) -> HashMap<&str, &PackedDna> {
+    genome
+        .iter()
+        .map(|record| (record.id(), record.sequence()))
+        .collect()
+}
+
+fn contig<'a>(
+    genome: &HashMap<&str, &'a PackedDna>,
+    seqid: &str,
+) -> Result<&'a PackedDna, ExtractError> {
+    genome
+        .get(seqid)
+        .copied()
+        .ok_or_else(|| ExtractError::UnknownSequence(seqid.to_string()))
+}
+
+/// Copy `range` of `contig`, failing if it extends past the end
+fn slice(contig: &PackedDna, seqid: &str, range: Range<usize>) -> Result<PackedDna, ExtractError> {
+    if range.end > contig.len() {
+        return Err(ExtractError::OutOfBounds {
+            seqid: seqid.to_string(),
+            range,
+            len: contig.len(),
+        });
+    }
+    Ok(contig.to_slice(range))
+}
+
+fn strand_char(strand: Option<Strand>) -> char {
+    match strand {
+        Some(Strand::Forward) => '+',
+        Some(Strand::Reverse) => '-',
+        None => '.',
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::str::FromStr;
+
+    fn genome() -> Vec<fasta::Record> {
+        vec![fasta::Record::new(
+            "chr1 test",
+            PackedDna::from_str("AAACCCGTAAGTTTTTTAGGGGTTTGTACAGAAATTT").unwrap(),
+        )]
+    }
+
+    const GFF: &str = "chr1\tt\texon\t1\t6\t.\t+\t.\tParent=tx1,tx2\n\
+        chr1\tt\texon\t19\t22\t.\t+\t.\tParent=tx1\n\
+        chr1\tt\texon\t27\t31\t.\t+\t.\tParent=tx1\n\
+        chr1\tt\texon\t27\t31\t.\t+\t.\tParent=tx2\n\
+        chr1\tt\texon\t2\t4\t.\t-\t.\tParent=anti\n\
+        chr1\tt\texon\t30\t37\t.\t-\t.\tParent=anti\n\
+        chr1\tt\tgene\t1\t37\t.\t+\t.\tID=g1\n";
+
+    #[test]
+    fn junction_sequences() {
+        let annotation = FeatureIndex::from_reader(GFF.as_bytes()).unwrap();
+        let found = junctions(&genome(), &annotation, 3).unwrap();
+        let summary: Vec<(String, String)> = found
+            .iter()
+            .map(|junction| (junction.name(), junction.sequence.to_string()))
+            .collect();
+        assert_eq!(
+            summary,
+            [
+                ("anti:chr1:5-29:-".to_string(), "TCTGTT".to_string()),
+                ("tx1:chr1:7-18:+".to_string(), "CCCGGG".to_string()),
+                ("tx2:chr1:7-26:+".to_string(), "CCCTAC".to_string()),
+                ("tx1:chr1:23-26:+".to_string(), "GGGTAC".to_string()),
+            ]
+        );
+        assert_eq!(found[1].intron, 6..18);
+        assert_eq!(found[1].to_record().header(), "tx1:chr1:7-18:+");
+    }
+
+    #[test]
+    fn upstream_sequences() {
+        let genes = "chr1\tt\tgene\t11\t20\t.\t+\t.\tID=g1\n\
+            chr1\tt\texon\t11\t15\t.\t+\t.\tParent=g1\n\
+            chr1\tt\tgene\t21\t30\t.\t-\t.\tName=g2\n\
+            chr1\tt\tgene\t3\t5\t.\t.\t.\tID=g3\n\
+            chr1\tt\tgene\t30\t34\t.\t-\t.\t.\n\
+            chr1\tt\tgene\t1\t4\t.\t+\t.\tID=g5\n";
+        let genes = FeatureIndex::from_reader(genes.as_bytes()).unwrap();
+        let regions: Vec<(String, String)> = upstream_regions(&genome(), &genes, 5)
+            .unwrap()
+            .iter()
+            .map(|record| (record.header().to_string(), record.sequence().to_string()))
+            .collect();
+        assert_eq!(
+            regions,
+            [
+                ("g5:chr1:1-0:+".to_string(), String::new()),
+                ("g3:chr1:1-2:.".to_string(), "AA".to_string()),
+                ("g1:chr1:6-10:+".to_string(), "CGTAA".to_string()),
+                ("g2:chr1:31-35:-".to_string(), "ATTTC".to_string()),
+                ("chr1:35-37:-".to_string(), "AAA".to_string()),
+            ]
+        );
+
+        let short = vec![fasta::Record::new(
+            "chr1",
+            PackedDna::from_str("ACGT").unwrap(),
+        )];
+        assert!(matches!(
+            upstream_regions(&short, &genes, 5),
+            Err(ExtractError::OutOfBounds { len: 4, .. })
+        ));
+    }
+
+    #[test]
+    fn junction_errors() {
+        let annotation = FeatureIndex::from_reader(GFF.as_bytes()).unwrap();
+        let short = vec![fasta::Record::new(
+            "chr1",
+            PackedDna::from_str("ACGT").unwrap(),
+        )];
+        assert!(matches!(
+            junctions(&short, &annotation, 3),
+            Err(ExtractError::OutOfBounds { len: 4, .. })
+        ));
+        assert_eq!(
+            junctions(&[], &annotation, 3).unwrap_err().to_string(),
+            "no sequence named chr1 in the genome"
+        );
+    }
+}
@@ -0,0 +1,537 @@
+//! Reading of multi-record FASTA files.
+//!
+//! Records are parsed one at a time from any [`std::io::Read`], so files do not need to fit in
+//! memory as text: each sequence line is packed as soon as it is read.
+
+use crate::{
+    detect::BOM,
+    iupac::{Iupac, IupacDna},
+    Nuc, PackedDna,
+};
+use std::{
+    convert::TryFrom,
+    fs::File,
+    io::{self, BufRead, BufReader, Read},
+    path::Path,
+};
+
+/// An error that can occur when reading FASTA input.
+#[derive(Debug, thiserror::Error)]
+pub enum FastaError {
+    /// The underlying reader failed
+    #[error("failed to read FASTA input")]
+    Io(#[from] io::Error),
+    /// Sequence data was found before any `>` header line
+    #[error("sequence data before the first header on line {0}")]
+    MissingHeader(usize),
+    /// A sequence line contained a character that is not a nucleotide
+    #[error("failed to parse nucleotide from {nuc} on line {line}")]
+    InvalidNuc {
+        /// The 1-based line number
+        line: usize,
+        /// The offending character
+        nuc: char,
+    },
+}
+
+impl FastaError {
+    /// A stable code for the kind of error, such as `fasta.invalid-nuc`, see
+    /// [`DnaError::code`](crate::DnaError::code)
+    pub fn code(&self) -> &'static str {
+        match self {
+            FastaError::Io(_) => "fasta.io",
+            FastaError::MissingHeader(_) => "fasta.missing-header",
+            FastaError::InvalidNuc { .. } => "fasta.invalid-nuc",
+        }
+    }
+}
+
+/// A single FASTA record
+#[derive(Debug)]
+pub struct Record {
+    header: String,
+    sequence: PackedDna,
+}
+
+impl Record {
+    /// Create a record from a header (without the leading `>`) and a sequence
+    pub fn new(header: impl Into<String>, sequence: PackedDna) -> Self {
+        Record {
+            header: header.into(),
+            sequence,
+        }
+    }
+
+    /// The full header line, without the leading `>`
+    pub fn header(&self) -> &str {
+        &self.header
+    }
+
+    /// The record identifier, i.e. the header up to the first whitespace
+    pub fn id(&self) -> &str {
+        self.header.split_whitespace().next().unwrap_or("")
+    }
+
+    /// The packed sequence of the record
+    pub fn sequence(&self) -> &PackedDna {
+        &self.sequence
+    }
+
+    /// Split the record into its header and sequence
+    pub fn into_parts(self) -> (String, PackedDna) {
+        (self.header, self.sequence)
+    }
+}
+
+/// A character a lenient reader skipped, see [`Reader::lenient`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Skipped {
+    /// The 1-based line number
+    pub line: usize,
+    /// The 1-based column, in characters
+    pub column: usize,
+    /// The skipped character
+    pub nuc: char,
+}
+
+/// A streaming FASTA reader yielding one [`Record`] at a time
+#[derive(Debug)]
+pub struct Reader<R> {
+    inner: BufReader<R>,
+    line: String,
+    line_number: usize,
+    next_header: Option<String>,
+    lenient: bool,
+    skipped: Vec<Skipped>,
+}
+
+impl Reader<File> {
+    /// Open the FASTA file at `path`
+    pub fn from_path(path: impl AsRef<Path>) -> io::Result<Self> {
+        Ok(Reader::new(File::open(path)?))
+    }
+}
+
+impl<R: Read> Reader<R> {
+    /// Create a reader over any byte source
+    pub fn new(inner: R) -> Self {
+        Reader {
+            inner: BufReader::new(inner),
+            line: String::new(),
+            line_number: 0,
+            next_header: None,
+            lenient: false,
+            skipped: Vec::new(),
+        }
+    }
+
+    /// Skip characters that are not nucleotides instead of failing on them, so sequences are
+    /// read without them. Each is recorded in [`Reader::skipped`].
+    pub fn lenient(mut self) -> Self {
+        self.lenient = true;
+        self
+    }
+
+    /// Every character a lenient reader has skipped so far, in input order
+    pub fn skipped(&self) -> &[Skipped] {
+        &self.skipped
+    }
+
+    /// Read the next line into the internal buffer, returning `false` at end of input
+    fn read_line(&mut self) -> io::Result<bool> {
+        self.line.clear();
+        if self.inner.read_line(&mut self.line)? == 0 {
+            return Ok(false);
+        }
+        self.line_number += 1;
+        if self.line_number == 1 && self.line.as_bytes().starts_with(BOM) {
+            self.line.drain(..BOM.len());
+        }
+        let trimmed = self.line.trim_end().len();
+        self.line.truncate(trimmed);
+        Ok(true)
+    }
+
+    /// Skip ahead to the first header of the input
+    fn find_header(&mut self) -> Result<Option<String>, FastaError> {
+        while self.read_line()? {
+            if let Some(header) = self.line.strip_prefix('>') {
+                return Ok(Some(header.to_string()));
+            }
+            if !self.line.is_empty() && !self.line.starts_with(';') {
+                return Err(FastaError::MissingHeader(self.line_number));
+            }
+        }
+        Ok(None)
+    }
+
+    /// Read the next header and the sequence lines up to the following one, adding each
+    /// sequence character to `sequence` with `push`, which returns `false` if it is invalid
+    fn read_parts<S>(
+        &mut self,
+        mut sequence: S,
+        push: impl Fn(&mut S, char) -> bool,
+    ) -> Result<Option<(String, S)>, FastaError> {
+        let header = match self.next_header.take() {
+            Some(header) => header,
+            None => match self.find_header()? {
+                Some(header) => header,
+                None => return Ok(None),
+            },
+        };
+        while self.read_line()? {
+            if let Some(next) = self.line.strip_prefix('>') {
+                self.next_header = Some(next.to_string());
+                break;
+            }
+            if self.line.starts_with(';') {
+                continue;
+            }
+            for (column, c) in self.line.chars().enumerate() {
+                if push(&mut sequence, c) {
+                    continue;
+                }
+                if self.lenient {
+                    self.skipped.push(Skipped {
+                        line: self.line_number,
+                        column: column + 1,
+                        nuc: c,
+                    });
+                } else {
+                    return Err(FastaError::InvalidNuc {
+                        line: self.line_number,
+                        nuc: c,
+                    });
+                }
+            }
+        }
+        Ok(Some((header, sequence)))
+    }
+
+    fn read_record(&mut self) -> Result<Option<Record>, FastaError> {
+        let parts = self.read_parts(PackedDna::empty(), |sequence, c| {
+            Nuc::try_from(c).map(|nuc| sequence.push(nuc)).is_ok()
+        })?;
+        Ok(parts.map(|(header, sequence)| Record::new(header, sequence)))
+    }
+
+    /// Read the records over the full IUPAC alphabet instead, as `(header, sequence)` pairs, so
+    /// `N` and the other ambiguity codes are kept rather than rejected
+    pub fn iupac(self) -> IupacRecords<R> {
+        IupacRecords(self)
+    }
+}
+
+/// Iterator over the records of a FASTA input as IUPAC sequences, see [`Reader::iupac`]
+#[derive(Debug)]
+pub struct IupacRecords<R>(Reader<R>);
+
+impl<R> IupacRecords<R> {
+    /// Every character skipped so far if the reader is lenient, see [`Reader::skipped`]
+    pub fn skipped(&self) -> &[Skipped] {
+        &self.0.skipped
+    }
+}
+
+impl<R: Read> Iterator for IupacRecords<R> {
+    type Item = Result<(String, IupacDna), FastaError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.0
+            .read_parts(IupacDna::new(), |sequence, c| {
+                Iupac::try_from(c).map(|code| sequence.push(code)).is_ok()
+            })
+            .transpose()
+    }
+}
+
+impl<R: Read> Iterator for Reader<R> {
+    type Item = Result<Record, FastaError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.read_record().transpose()
+    }
+}
+
+/// Counts of the nucleotides of a FASTA record, see [`count_bases_in_reader`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub struct BaseCounts {
+    /// Number of A
+    pub a: usize,
+    /// Number of C
+    pub c: usize,
+    /// Number of G
+    pub g: usize,
+    /// Number of T
+    pub t: usize,
+    /// Number of N
+    pub n: usize,
+    /// Number of the other ambiguity codes
+    pub ambiguous: usize,
+}
+
+impl BaseCounts {
+    /// Total number of nucleotides
+    pub fn len(&self) -> usize {
+        self.a + self.c + self.g + self.t + self.n + self.ambiguous
+    }
+
+    /// Whether no nucleotides were counted
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Fraction of G and C among A, C, G and T, or 0 if there are none
+    pub fn gc_content(&self) -> f64 {
+        let called = self.a + self.c + self.g + self.t;
+        if called == 0 {
+            0.0
+        } else {
+            (self.c + self.g) as f64 / called as f64
+        }
+    }
+
+    /// Add the nucleotides of one sequence line, returning the index of the first byte that is
+    /// not a nucleotide, if any
+    fn add_line(&mut self, line: &[u8]) -> Result<(), usize> {
+        // One pass per letter compiles to vectorised compares, unlike a lookup table.
+        let count = |upper: u8| line.iter().filter(|&&b| b | 0x20 == upper | 0x20).count();
+        let (a, c, g, t, n) = (
+            count(b'A'),
+            count(b'C'),
+            count(b'G'),
+            count(b'T'),
+            count(b'N'),
+        );
+        self.a += a;
+        self.c += c;
+        self.g += g;
+        self.t += t;
+        self.n += n;
+        if a + c + g + t + n == line.len() {
+            return Ok(());
+        }
+        for (idx, &b) in line.iter().enumerate() {
+            if b"ACGTNacgtn".contains(&b) {
+                continue;
+            }
+            if !b.is_ascii() || Iupac::try_from(b as char).is_err() {
+                return Err(idx);
+            }
+            self.ambiguous += 1;
+        }
+        Ok(())
+    }
+}
+
+/// Count the nucleotides of every record of FASTA input, as `(header, counts)` pairs.
+///
+/// This reads the same input as [`Reader::iupac`] and fails the same way, but works on the raw
+/// bytes without building sequences, which makes it several times faster when only the counts
+/// are needed.
+pub fn count_bases_in_reader(input: impl Read) -> Result<Vec<(String, BaseCounts)>, FastaError> {
+    let mut input = BufReader::with_capacity(1 << 16, input);
+    let mut records: Vec<(String, BaseCounts)> = Vec::new();
+    let mut line = Vec::new();
+    let mut line_number = 0;
+    loop {
+        line.clear();
+        if input.read_until(b'\n', &mut line)? == 0 {
+            return Ok(records);
+        }
+        line_number += 1;
+        let mut trimmed = line.trim_ascii_end();
+        if line_number == 1 {
+            trimmed = trimmed.strip_prefix(BOM).unwrap_or(trimmed);
+        }
+        if let Some(header) = trimmed.strip_prefix(b">") {
+            let header = String::from_utf8(header.to_vec())
+                .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+            records.push((header, BaseCounts::default()));
+            continue;
+        }
+        if trimmed.starts_with(b";") {
+            continue;
+        }
+        let counts = match records.last_mut() {
+            Some((_, counts)) => counts,
+            None if trimmed.is_empty() => continue,
+            None => return Err(FastaError::MissingHeader(line_number)),
+        };
+        if let Err(idx) = counts.add_line(trimmed) {
+            let nuc = String::from_utf8_lossy(&trimmed[idx..])
+                .chars()
+                .next()
+                .unwrap_or(char::REPLACEMENT_CHARACTER);
+            return Err(FastaError::InvalidNuc {
+                line: line_number,
+                nuc,
+            });
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const TWO_RECORDS: &str = ">seq1 first record\nACGT\nTGCA\n>seq2\nacgtt\n";
+
+    #[test]
+    fn read_two_records() {
+        let records: Vec<Record> = Reader::new(TWO_RECORDS.as_bytes())
+            .collect::<Result<_, _>>()
+            .unwrap();
+        assert_eq!(records.len(), 2);
+        assert_eq!(records[0].header(), "seq1 first record");
+        assert_eq!(records[0].id(), "seq1");
+        assert_eq!(records[0].sequence().packed_dna, [27, 228]);
+        assert_eq!(records[1].id(), "seq2");
+        assert_eq!(records[1].sequence().packed_dna, [27, 3]);
+        assert_eq!(records[1].sequence().last_nuc_set_count, 1);
+    }
+
+    #[test]
+    fn read_matches_from_str() {
+        let input = ">chr\nACGTTGC\nACT\n";
+        let (_, sequence) = Reader::new(input.as_bytes())
+            .next()
+            .unwrap()
+            .unwrap()
+            .into_parts();
+        let expected: PackedDna = "ACGTTGCACT".parse().unwrap();
+        assert_eq!(sequence.packed_dna, expected.packed_dna);
+        assert_eq!(sequence.get_counts(), expected.get_counts());
+    }
+
+    #[test]
+    fn read_crlf_and_blank_lines() {
+        let input = "\r\n>a\r\nAC\r\n\r\nGT\r\n";
+        let record = Reader::new(input.as_bytes()).next().unwrap().unwrap();
+        assert_eq!(record.sequence().packed_dna, [27]);
+    }
+
+    #[test]
+    fn read_after_byte_order_mark() {
+        let input = "\u{feff}>a\nACGT\n";
+        let record = Reader::new(input.as_bytes()).next().unwrap().unwrap();
+        assert_eq!(record.header(), "a");
+        let counts = count_bases_in_reader(input.as_bytes()).unwrap();
+        assert_eq!(counts[0].0, "a");
+    }
+
+    #[test]
+    fn read_empty_record() {
+        let input = ">empty\n>full\nA\n";
+        let records: Vec<Record> = Reader::new(input.as_bytes())
+            .collect::<Result<_, _>>()
+            .unwrap();
+        assert_eq!(records[0].sequence().get_counts()[0], ('A', 0));
+        assert_eq!(records[1].sequence().get_counts()[0], ('A', 1));
+    }
+
+    #[test]
+    fn read_iupac_records() {
+        let input = ">a\nACNN\nRT\n>b\nacgt\n";
+        let records: Vec<(String, IupacDna)> = Reader::new(input.as_bytes())
+            .iupac()
+            .collect::<Result<_, _>>()
+            .unwrap();
+        assert_eq!(records.len(), 2);
+        assert_eq!(records[0].0, "a");
+        assert_eq!(records[0].1, "ACNNRT".parse().unwrap());
+        assert_eq!(records[0].1.count(Iupac::N), 2);
+        assert!(records[1].1.is_unambiguous());
+        let err = Reader::new(">a\nACNX\n".as_bytes())
+            .iupac()
+            .next()
+            .unwrap()
+            .unwrap_err();
+        assert_eq!(
+            "failed to parse nucleotide from X on line 2",
+            err.to_string()
+        );
+    }
+
+    #[test]
+    fn count_bases_matches_iupac_records() {
+        let input = "; comment\n>a first\nACNN\r\nRt  \n\n>b\n>c\nacgtnnY\n";
+        let counts = count_bases_in_reader(input.as_bytes()).unwrap();
+        let records: Vec<(String, IupacDna)> = Reader::new(input.as_bytes())
+            .iupac()
+            .collect::<Result<_, _>>()
+            .unwrap();
+        assert_eq!(counts.len(), records.len());
+        for ((header, counts), (expected_header, dna)) in counts.iter().zip(&records) {
+            assert_eq!(header, expected_header);
+            assert_eq!(counts.a, dna.count(Iupac::A));
+            assert_eq!(counts.c, dna.count(Iupac::C));
+            assert_eq!(counts.g, dna.count(Iupac::G));
+            assert_eq!(counts.t, dna.count(Iupac::T));
+            assert_eq!(counts.n, dna.count(Iupac::N));
+            assert_eq!(counts.len(), dna.len());
+        }
+        assert_eq!(counts[0].1.ambiguous, 1);
+        assert_eq!(counts[0].1.gc_content(), 1.0 / 3.0);
+        assert!(counts[1].1.is_empty());
+    }
+
+    #[test]
+    fn lenient_reader_skips_invalid() {
+        let input = ">a\nAC-GT\nAN\n>b\nTé\n";
+        let mut reader = Reader::new(input.as_bytes()).lenient();
+        let records: Vec<Record> = reader.by_ref().collect::<Result<_, _>>().unwrap();
+        assert_eq!(records[0].sequence().to_string(), "ACGTA");
+        assert_eq!(records[1].sequence().to_string(), "T");
+        let skipped: Vec<(usize, usize, char)> = reader
+            .skipped()
+            .iter()
+            .map(|s| (s.line, s.column, s.nuc))
+            .collect();
+        assert_eq!(skipped, vec![(2, 3, '-'), (3, 2, 'N'), (5, 2, 'é')]);
+        let mut records = Reader::new(input.as_bytes()).lenient().iupac();
+        assert_eq!(records.next().unwrap().unwrap().1.count(Iupac::N), 1);
+        assert_eq!(records.skipped().len(), 1);
+    }
+
+    #[test]
+    fn count_bases_errors() {
+        let err = count_bases_in_reader(">a\nAC\nGéT\n".as_bytes()).unwrap_err();
+        assert_eq!(
+            "failed to parse nucleotide from é on line 3",
+            err.to_string()
+        );
+        let err = count_bases_in_reader("\nACGT\n".as_bytes()).unwrap_err();
+        assert!(matches!(err, FastaError::MissingHeader(2)));
+        assert!(count_bases_in_reader("".as_bytes()).unwrap().is_empty());
+    }
+
+    #[test]
+    fn read_empty_input() {
+        assert!(Reader::new("".as_bytes()).next().is_none());
+    }
+
+    #[test]
+    fn read_missing_header() {
+        let err = Reader::new("ACGT\n".as_bytes())
+            .next()
+            .unwrap()
+            .unwrap_err();
+        assert_eq!(
+            "sequence data before the first header on line 1",
+            err.to_string()
+        );
+    }
+
+    #[test]
+    fn read_invalid_nuc() {
+        let err = Reader::new(">a\nAC\nGX\n".as_bytes())
+            .next()
+            .unwrap()
+            .unwrap_err();
+        assert_eq!(
+            "failed to parse nucleotide from X on line 3",
+            err.to_string()
+        );
+    }
+}
@@ -0,0 +1,562 @@
+//! Reading of FASTQ files with per-base quality scores.
+//!
+//! Records are the usual four lines: an `@` header, the sequence, a `+` separator and one
+//! quality character per nucleotide. Sequences are packed, and [`Reader::iupac`] reads them over
+//! the full IUPAC alphabet instead, keeping the `N`s of uncalled bases. Qualities are stored as
+//! Phred scores with the ASCII offset of 33 removed, one byte per base rather than packed:
+//! scores run up to 93 and so need 7 of the 8 bits, and a plain slice keeps them cheap to scan and
+//! trim. With the `flate2` feature, gzip-compressed input can be read directly. A [`Writer`]
+//! writes reads back out.
+
+use crate::{
+    detect::BOM,
+    iupac::{Iupac, IupacDna},
+    Nuc, PackedDna,
+};
+use std::{
+    convert::TryFrom,
+    fmt::Display,
+    fs::File,
+    io::{self, BufRead, BufReader, Write},
+    ops::Range,
+    path::Path,
+};
+
+/// The ASCII offset of Phred+33 quality characters
+pub const PHRED_OFFSET: u8 = b'!';
+
+/// An error that can occur when reading FASTQ input.
+#[derive(Debug, thiserror::Error)]
+pub enum FastqError {
+    /// The underlying reader failed
+    #[error("failed to read FASTQ input")]
+    Io(#[from] io::Error),
+    /// A record did not start with a `@` header line
+    #[error("expected a '@' header on line {0}")]
+    MissingHeader(usize),
+    /// The sequence line was not followed by a `+` separator line
+    #[error("expected a '+' separator on line {0}")]
+    MissingSeparator(usize),
+    /// The input ended in the middle of a record
+    #[error("unexpected end of input after line {0}")]
+    Truncated(usize),
+    /// A sequence line contained a character that is not a nucleotide
+    #[error("failed to parse nucleotide from {nuc} on line {line}")]
+    InvalidNuc {
+        /// The 1-based line number
+        line: usize,
+        /// The offending character
+        nuc: char,
+    },
+    /// A quality line contained a character outside `!` to `~`
+    #[error("invalid quality character {quality:?} on line {line}")]
+    InvalidQuality {
+        /// The 1-based line number
+        line: usize,
+        /// The offending character
+        quality: char,
+    },
+    /// The quality line and the sequence line have different lengths
+    #[error("{quality} quality scores for {sequence} nucleotides on line {line}")]
+    LengthMismatch {
+        /// The 1-based line number of the quality line
+        line: usize,
+        /// The number of nucleotides
+        sequence: usize,
+        /// The number of quality scores
+        quality: usize,
+    },
+}
+
+impl FastqError {
+    /// A stable code for the kind of error, such as `fastq.truncated`, see
+    /// [`DnaError::code`](crate::DnaError::code)
+    pub fn code(&self) -> &'static str {
+        match self {
+            FastqError::Io(_) => "fastq.io",
+            FastqError::MissingHeader(_) => "fastq.missing-header",
+            FastqError::MissingSeparator(_) => "fastq.missing-separator",
+            FastqError::Truncated(_) => "fastq.truncated",
+            FastqError::InvalidNuc { .. } => "fastq.invalid-nuc",
+            FastqError::InvalidQuality { .. } => "fastq.invalid-quality",
+            FastqError::LengthMismatch { .. } => "fastq.length-mismatch",
+        }
+    }
+}
+
+/// A sequencing read: a sequence with one quality score per nucleotide, packed unless it was
+/// read with [`Reader::iupac`]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Read<T = PackedDna> {
+    header: String,
+    sequence: T,
+    qualities: Vec<u8>,
+}
+
+impl Read {
+    /// Create a read from a header (without the leading `@`), a sequence and its Phred scores.
+    ///
+    /// # Panics
+    ///
+    /// Panics if there is not exactly one quality score per nucleotide.
+    pub fn new(header: impl Into<String>, sequence: PackedDna, qualities: Vec<u8>) -> Self {
+        let len = sequence.len();
+        Read::with_len(header.into(), sequence, qualities, len)
+    }
+
+    /// Remove the bases scoring below `threshold` from both ends of the read
+    pub fn trim_quality(&mut self, threshold: u8) {
+        let range = self.quality_range(threshold);
+        if range.len() != self.len() {
+            self.sequence = self.sequence.to_slice(range.clone());
+            self.qualities.truncate(range.end);
+            self.qualities.drain(..range.start);
+        }
+    }
+}
+
+impl<T> Read<T> {
+    fn with_len(header: String, sequence: T, qualities: Vec<u8>, len: usize) -> Self {
+        assert_eq!(
+            len,
+            qualities.len(),
+            "read needs one quality score per nucleotide"
+        );
+        Read {
+            header,
+            sequence,
+            qualities,
+        }
+    }
+
+    /// The full header line, without the leading `@`
+    pub fn header(&self) -> &str {
+        &self.header
+    }
+
+    /// The read identifier, i.e. the header up to the first whitespace
+    pub fn id(&self) -> &str {
+        self.header.split_whitespace().next().unwrap_or("")
+    }
+
+    /// The sequence of the read
+    pub fn sequence(&self) -> &T {
+        &self.sequence
+    }
+
+    /// The Phred quality score of each nucleotide
+    pub fn qualities(&self) -> &[u8] {
+        &self.qualities
+    }
+
+    /// Number of nucleotides in the read
+    pub fn len(&self) -> usize {
+        self.qualities.len()
+    }
+
+    /// Whether the read has no nucleotides
+    pub fn is_empty(&self) -> bool {
+        self.qualities.is_empty()
+    }
+
+    /// Mean Phred score of the read, or `None` if it is empty
+    pub fn mean_quality(&self) -> Option<f64> {
+        if self.is_empty() {
+            return None;
+        }
+        let total: u64 = self.qualities.iter().map(|&q| u64::from(q)).sum();
+        Some(total as f64 / self.len() as f64)
+    }
+
+    /// The 0-based range left after removing the bases scoring below `threshold` from both ends.
+    ///
+    /// The range is empty if no base reaches the threshold.
+    pub fn quality_range(&self, threshold: u8) -> Range<usize> {
+        match self.qualities.iter().position(|&q| q >= threshold) {
+            Some(start) => {
+                let end = self
+                    .qualities
+                    .iter()
+                    .rposition(|&q| q >= threshold)
+                    .unwrap()
+                    + 1;
+                start..end
+            }
+            None => 0..0,
+        }
+    }
+
+    /// Split the read into its header, sequence and quality scores
+    pub fn into_parts(self) -> (String, T, Vec<u8>) {
+        (self.header, self.sequence, self.qualities)
+    }
+}
+
+/// A streaming FASTQ reader yielding one [`Read`] at a time
+#[derive(Debug)]
+pub struct Reader<R> {
+    inner: BufReader<R>,
+    line: String,
+    line_number: usize,
+}
+
+impl Reader<File> {
+    /// Open the FASTQ file at `path`
+    pub fn from_path(path: impl AsRef<Path>) -> io::Result<Self> {
+        Ok(Reader::new(File::open(path)?))
+    }
+}
+
+#[cfg(feature = "flate2")]
+impl<R: io::Read> Reader<flate2::read::MultiGzDecoder<R>> {
+    /// Create a reader over gzip-compressed input
+    pub fn gzip(inner: R) -> Self {
+        Reader::new(flate2::read::MultiGzDecoder::new(inner))
+    }
+}
+
+#[cfg(feature = "flate2")]
+impl Reader<flate2::read::MultiGzDecoder<File>> {
+    /// Open the gzip-compressed FASTQ file at `path`
+    pub fn from_gzip_path(path: impl AsRef<Path>) -> io::Result<Self> {
+        Ok(Reader::gzip(File::open(path)?))
+    }
+}
+
+impl<R: io::Read> Reader<R> {
+    /// Create a reader over any byte source
+    pub fn new(inner: R) -> Self {
+        Reader {
+            inner: BufReader::new(inner),
+            line: String::new(),
+            line_number: 0,
+        }
+    }
+
+    /// Read the next line into the internal buffer, returning `false` at end of input
+    fn read_line(&mut self) -> io::Result<bool> {
+        self.line.clear();
+        if self.inner.read_line(&mut self.line)? == 0 {
+            return Ok(false);
+        }
+        self.line_number += 1;
+        if self.line_number == 1 && self.line.as_bytes().starts_with(BOM) {
+            self.line.drain(..BOM.len());
+        }
+        let trimmed = self.line.trim_end().len();
+        self.line.truncate(trimmed);
+        Ok(true)
+    }
+
+    /// Read the next line of a record that has already started
+    fn expect_line(&mut self) -> Result<(), FastqError> {
+        if self.read_line()? {
+            Ok(())
+        } else {
+            Err(FastqError::Truncated(self.line_number))
+        }
+    }
+
+    /// Read the next record, parsing its sequence line with `parse` and checking it against
+    /// the qualities with `len`
+    fn read_record<T>(
+        &mut self,
+        parse: impl Fn(&str, usize) -> Result<T, FastqError>,
+        len: impl Fn(&T) -> usize,
+    ) -> Result<Option<Read<T>>, FastqError> {
+        loop {
+            if !self.read_line()? {
+                return Ok(None);
+            }
+            if !self.line.is_empty() {
+                break;
+            }
+        }
+        let header = match self.line.strip_prefix('@') {
+            Some(header) => header.to_string(),
+            None => return Err(FastqError::MissingHeader(self.line_number)),
+        };
+
+        self.expect_line()?;
+        let sequence = parse(&self.line, self.line_number)?;
+
+        self.expect_line()?;
+        if !self.line.starts_with('+') {
+            return Err(FastqError::MissingSeparator(self.line_number));
+        }
+
+        self.expect_line()?;
+        let len = len(&sequence);
+        let qualities = parse_qualities(&self.line, self.line_number, len)?;
+        Ok(Some(Read::with_len(header, sequence, qualities, len)))
+    }
+
+    /// Read the records over the full IUPAC alphabet instead, so `N` and the other ambiguity
+    /// codes are kept rather than rejected
+    pub fn iupac(self) -> IupacReads<R> {
+        IupacReads(self)
+    }
+}
+
+/// Iterator over the reads of a FASTQ input with IUPAC sequences, see [`Reader::iupac`]
+#[derive(Debug)]
+pub struct IupacReads<R>(Reader<R>);
+
+impl<R: io::Read> Iterator for IupacReads<R> {
+    type Item = Result<Read<IupacDna>, FastqError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.0
+            .read_record(parse_iupac_sequence, IupacDna::len)
+            .transpose()
+    }
+}
+
+/// Pack the sequence line of a record, numbered `line_number`
+pub(crate) fn parse_sequence(line: &str, line_number: usize) -> Result<PackedDna, FastqError> {
+    let mut sequence = PackedDna::empty();
+    for c in line.chars() {
+        let nuc = Nuc::try_from(c).map_err(|_| FastqError::InvalidNuc {
+            line: line_number,
+            nuc: c,
+        })?;
+        sequence.push(nuc);
+    }
+    Ok(sequence)
+}
+
+/// Parse the sequence line of a record, numbered `line_number`, over the IUPAC alphabet
+fn parse_iupac_sequence(line: &str, line_number: usize) -> Result<IupacDna, FastqError> {
+    let mut sequence = IupacDna::new();
+    for c in line.chars() {
+        let code = Iupac::try_from(c).map_err(|_| FastqError::InvalidNuc {
+            line: line_number,
+            nuc: c,
+        })?;
+        sequence.push(code);
+    }
+    Ok(sequence)
+}
+
+/// Decode the quality line, numbered `line_number`, of a record of `len` nucleotides
+pub(crate) fn parse_qualities(
+    line: &str,
+    line_number: usize,
+    len: usize,
+) -> Result<Vec<u8>, FastqError> {
+    let qualities = line
+        .chars()
+        .map(|c| match c {
+            '!'..='~' => Ok(c as u8 - PHRED_OFFSET),
+            _ => Err(FastqError::InvalidQuality {
+                line: line_number,
+                quality: c,
+            }),
+        })
+        .collect::<Result<Vec<u8>, _>>()?;
+    if qualities.len() != len {
+        return Err(FastqError::LengthMismatch {
+            line: line_number,
+            sequence: len,
+            quality: qualities.len(),
+        });
+    }
+    Ok(qualities)
+}
+
+impl<R: io::Read> Iterator for Reader<R> {
+    type Item = Result<Read, FastqError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.read_record(parse_sequence, PackedDna::len).transpose()
+    }
+}
+
+/// A FASTQ writer, writing each [`Read`] as four lines
+#[derive(Debug)]
+pub struct Writer<W> {
+    inner: W,
+    qualities: Vec<u8>,
+}
+
+impl<W: Write> Writer<W> {
+    /// Create a writer over any byte sink, which is best buffered
+    pub fn new(inner: W) -> Self {
+        Writer {
+            inner,
+            qualities: Vec::new(),
+        }
+    }
+
+    /// Write `read`, packed or over the IUPAC alphabet. Scores above 93, which Phred+33 cannot
+    /// encode, are written as `~`.
+    pub fn write<T: Display>(&mut self, read: &Read<T>) -> io::Result<()> {
+        self.qualities.clear();
+        self.qualities.extend(
+            read.qualities()
+                .iter()
+                .map(|&quality| quality.saturating_add(PHRED_OFFSET).min(b'~')),
+        );
+        self.qualities.push(b'\n');
+        writeln!(self.inner, "@{}\n{}\n+", read.header(), read.sequence())?;
+        self.inner.write_all(&self.qualities)
+    }
+
+    /// Unwrap the underlying sink
+    pub fn into_inner(self) -> W {
+        self.inner
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const TWO_READS: &str = "@read1 lane 1\nACGTTG\n+\nII?#!I\n@read2\nacg\n+read2\n+++\n";
+
+    fn read(text: &str) -> Result<Read, FastqError> {
+        Reader::new(text.as_bytes()).next().unwrap()
+    }
+
+    #[test]
+    fn read_two_records() {
+        let reads: Vec<Read> = Reader::new(TWO_READS.as_bytes())
+            .collect::<Result<_, _>>()
+            .unwrap();
+        assert_eq!(reads.len(), 2);
+        assert_eq!(reads[0].header(), "read1 lane 1");
+        assert_eq!(reads[0].id(), "read1");
+        assert_eq!(reads[0].sequence().to_string(), "ACGTTG");
+        assert_eq!(reads[0].qualities(), [40, 40, 30, 2, 0, 40]);
+        assert_eq!(reads[1].sequence().to_string(), "ACG");
+        assert_eq!(reads[1].qualities(), [10, 10, 10]);
+    }
+
+    #[test]
+    fn read_iupac() {
+        let text = "@r1\nACGTNACGT\n+\nIIII!IIII\n@r2\nrn\n+\n##\n";
+        let reads: Vec<Read<IupacDna>> = Reader::new(text.as_bytes())
+            .iupac()
+            .collect::<Result<_, _>>()
+            .unwrap();
+        assert_eq!(reads[0].sequence().to_string(), "ACGTNACGT");
+        assert_eq!(reads[0].qualities()[4], 0);
+        assert_eq!(reads[1].id(), "r2");
+        assert_eq!(reads[1].sequence().to_string(), "RN");
+        assert_eq!(
+            read("@r1\nACGTNACGT\n+\nIIII!IIII\n")
+                .unwrap_err()
+                .to_string(),
+            "failed to parse nucleotide from N on line 2"
+        );
+        let err = Reader::new("@r\nAXG\n+\nIII\n".as_bytes())
+            .iupac()
+            .next()
+            .unwrap()
+            .unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "failed to parse nucleotide from X on line 2"
+        );
+    }
+
+    #[test]
+    fn write_round_trip() {
+        let mut writer = Writer::new(Vec::new());
+        for read in Reader::new(TWO_READS.as_bytes()) {
+            writer.write(&read.unwrap()).unwrap();
+        }
+        let iupac = Reader::new("@r3\nANN\n+\n#!I\n".as_bytes()).iupac();
+        writer.write(&iupac.last().unwrap().unwrap()).unwrap();
+        writer
+            .write(&Read::new("r4", "AC".parse().unwrap(), vec![93, 200]))
+            .unwrap();
+        assert_eq!(
+            String::from_utf8(writer.into_inner()).unwrap(),
+            "@read1 lane 1\nACGTTG\n+\nII?#!I\n@read2\nACG\n+\n+++\n@r3\nANN\n+\n#!I\n@r4\nAC\n+\n~~\n"
+        );
+    }
+
+    #[test]
+    fn read_crlf_and_blank_lines() {
+        let reads: Vec<Read> =
+            Reader::new("\r\n@a\r\nAC\r\n+\r\nII\r\n\r\n@b\nG\n+\n!\n".as_bytes())
+                .collect::<Result<_, _>>()
+                .unwrap();
+        assert_eq!(reads.len(), 2);
+        assert_eq!(reads[0].sequence().to_string(), "AC");
+        assert_eq!(reads[1].qualities(), [0]);
+        let read = read("\u{feff}@c\nT\n+\nI\n").unwrap();
+        assert_eq!(read.header(), "c");
+    }
+
+    #[test]
+    fn read_empty_input() {
+        assert!(Reader::new("".as_bytes()).next().is_none());
+    }
+
+    #[test]
+    fn read_errors() {
+        let cases = [
+            (">a\nAC\n+\nII\n", "expected a '@' header on line 1"),
+            ("@a\nAC\n-\nII\n", "expected a '+' separator on line 3"),
+            ("@a\nAC\n+\n", "unexpected end of input after line 3"),
+            (
+                "@a\nAX\n+\nII\n",
+                "failed to parse nucleotide from X on line 2",
+            ),
+            (
+                "@a\nAC\n+\nI\u{7f}\n",
+                "invalid quality character '\\u{7f}' on line 4",
+            ),
+            (
+                "@a\nAC\n+\nIII\n",
+                "3 quality scores for 2 nucleotides on line 4",
+            ),
+        ];
+        for (input, message) in cases {
+            assert_eq!(read(input).unwrap_err().to_string(), message);
+        }
+    }
+
+    #[test]
+    fn trim_quality_both_ends() {
+        let mut read = read("@a\nACGTTGCA\n+\n#+II5II#\n").unwrap();
+        assert_eq!(read.quality_range(20), 2..7);
+        read.trim_quality(20);
+        assert_eq!(read.sequence().to_string(), "GTTGC");
+        assert_eq!(read.qualities(), [40, 40, 20, 40, 40]);
+        read.trim_quality(20);
+        assert_eq!(read.len(), 5);
+    }
+
+    #[test]
+    fn trim_quality_everything() {
+        let mut read = read("@a\nACG\n+\n###\n").unwrap();
+        read.trim_quality(3);
+        assert!(read.is_empty());
+        assert_eq!(read.mean_quality(), None);
+    }
+
+    #[test]
+    fn mean_quality() {
+        let read = read("@a\nACGT\n+\n!+5?\n").unwrap();
+        assert_eq!(read.mean_quality(), Some(15.0));
+    }
+
+    #[cfg(feature = "flate2")]
+    #[test]
+    fn read_gzip() {
+        use flate2::{write::GzEncoder, Compression};
+        use std::io::Write;
+
+        let mut encoder = GzEncoder::new(Vec::new(), Compression::default());
+        encoder.write_all(TWO_READS.as_bytes()).unwrap();
+        let compressed = encoder.finish().unwrap();
+        let reads: Vec<Read> = Reader::gzip(compressed.as_slice())
+            .collect::<Result<_, _>>()
+            .unwrap();
+        assert_eq!(reads.len(), 2);
+        assert_eq!(reads[1].sequence().to_string(), "ACG");
+    }
+}
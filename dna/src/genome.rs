@@ -0,0 +1,371 @@
+//! Random access to the sequences of a `.2bit` genome without loading them.
+//!
+//! A [`GenomeStore`] reads only the index and record headers up front; nucleotides are decoded
+//! from the underlying bytes when a range is fetched. With the `memmap2` feature,
+//! [`GenomeStore::open`] maps the file into memory, so only the pages covering fetched ranges
+//! are ever read from disk and many large genomes can be open at once.
+
+use crate::{
+    iupac::{AmbiguousRun, Iupac, IupacDna},
+    twobit::{decode_payload, Reader, RecordHeader, TwoBitError},
+    Nuc,
+};
+use std::{collections::HashMap, convert::TryFrom, io::Cursor, ops::Range};
+
+/// A `.2bit` genome held as bytes, decoded range by range
+#[derive(Debug)]
+pub struct GenomeStore<B> {
+    data: B,
+    names: Vec<String>,
+    headers: HashMap<String, RecordHeader>,
+}
+
+#[cfg(feature = "memmap2")]
+impl GenomeStore<memmap2::Mmap> {
+    /// Memory-map the `.2bit` file at `path`.
+    ///
+    /// The file must not be modified while the store is open.
+    pub fn open(path: impl AsRef<std::path::Path>) -> Result<Self, TwoBitError> {
+        let file = std::fs::File::open(path)?;
+        // SAFETY: the mapping is only read, and the caller keeps the file unchanged while it
+        // is mapped, as documented above.
+        let mmap = unsafe { memmap2::Mmap::map(&file)? };
+        GenomeStore::new(mmap)
+    }
+}
+
+impl<B: AsRef<[u8]>> GenomeStore<B> {
+    /// Read the index and record headers of the `.2bit` data in `data`
+    pub fn new(data: B) -> Result<Self, TwoBitError> {
+        let bytes = data.as_ref();
+        let mut reader = Reader::new(Cursor::new(bytes))?;
+        let names = reader.names().to_vec();
+        let mut headers = HashMap::with_capacity(names.len());
+        for name in &names {
+            let header = reader.read_header(name)?;
+            let payload_end = usize::try_from(header.payload_offset)
+                .ok()
+                .and_then(|offset| offset.checked_add(header.len.div_ceil(4)));
+            if payload_end.is_none_or(|end| end > bytes.len()) {
+                return Err(TwoBitError::Malformed(name.clone()));
+            }
+            headers.insert(name.clone(), header);
+        }
+        Ok(GenomeStore {
+            data,
+            names,
+            headers,
+        })
+    }
+
+    /// Names of the sequences in file order
+    pub fn names(&self) -> &[String] {
+        &self.names
+    }
+
+    /// Length of the sequence called `name`, or `None` if there is none
+    pub fn sequence_len(&self, name: &str) -> Option<usize> {
+        self.headers.get(name).map(|header| header.len)
+    }
+
+    /// Decode the 0-based `range` of the sequence called `name`, with `N` blocks as ambiguous
+    /// runs
+    pub fn fetch(&self, name: &str, range: Range<usize>) -> Result<IupacDna, TwoBitError> {
+        let header = self
+            .headers
+            .get(name)
+            .ok_or_else(|| TwoBitError::UnknownSequence(name.to_string()))?;
+        if range.start > range.end || range.end > header.len {
+            return Err(TwoBitError::OutOfRange {
+                name: name.to_string(),
+                range,
+                len: header.len,
+            });
+        }
+        // Decode whole payload bytes, then cut the range out of them.
+        let base = range.start / 4 * 4;
+        let covered = (range.end.div_ceil(4) * 4).min(header.len) - base;
+        let offset = header.payload_offset as usize + base / 4;
+        let mut dna = decode_payload(&self.data.as_ref()[offset..], covered)
+            .to_slice(range.start - base..range.end - base);
+
+        let first = header
+            .n_blocks
+            .partition_point(|block| block.end <= range.start);
+        let mut runs = Vec::new();
+        for block in header.n_blocks[first..]
+            .iter()
+            .take_while(|block| block.start < range.end && !range.is_empty())
+        {
+            let start = block.start.max(range.start) - range.start;
+            let end = block.end.min(range.end) - range.start;
+            // Ambiguous positions use the crate's placeholder `A`.
+            dna.fill(start..end, Nuc::A)
+                .map_err(|_| TwoBitError::Malformed(name.to_string()))?;
+            runs.push(AmbiguousRun {
+                start,
+                len: end - start,
+                code: Iupac::N,
+            });
+        }
+        Ok(IupacDna::from_parts(dna, runs))
+    }
+
+    /// Decode every sequence in full and apply `f` to each with its name, returning the results
+    /// by name in file order.
+    ///
+    /// Per-sequence work such as statistics, masking or indexing thus covers a whole genome. With
+    /// the `rayon` feature the sequences are decoded and processed on rayon's thread pool; run the
+    /// call inside `rayon::ThreadPool::install` to choose the number of threads. Each task holds
+    /// one decoded sequence at a time.
+    pub fn par_map_contigs<T, F>(&self, f: F) -> Result<Vec<(String, T)>, TwoBitError>
+    where
+        B: Sync,
+        T: Send,
+        F: Fn(&str, IupacDna) -> T + Sync,
+    {
+        let map = |name: &String| {
+            let dna = self.fetch(name, 0..self.headers[name].len)?;
+            Ok((name.clone(), f(name, dna)))
+        };
+        #[cfg(feature = "rayon")]
+        {
+            use rayon::prelude::*;
+            self.names.par_iter().map(map).collect()
+        }
+        #[cfg(not(feature = "rayon"))]
+        self.names.iter().map(map).collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::twobit::{write, Record};
+    use std::str::FromStr;
+
+    const SEQUENCES: [(&str, &str); 2] = [
+        ("chr1", "ACGTNNNNTTGCAACGGTNAC"),
+        ("chrM", "GATTACAGATTACANNNNNNNNNNNNNNTTGCA"),
+    ];
+
+    fn genome() -> Vec<u8> {
+        let records: Vec<Record> = SEQUENCES
+            .iter()
+            .map(|&(name, seq)| Record {
+                name: name.to_string(),
+                sequence: IupacDna::from_str(seq).unwrap(),
+                mask_blocks: vec![],
+            })
+            .collect();
+        let mut bytes = Vec::new();
+        write(&mut bytes, &records).unwrap();
+        bytes
+    }
+
+    fn text(dna: &IupacDna) -> String {
+        dna.iter().map(Iupac::to_char).collect()
+    }
+
+    #[test]
+    fn fetch_every_range() {
+        let store = GenomeStore::new(genome()).unwrap();
+        assert_eq!(store.names(), ["chr1", "chrM"]);
+        for (name, seq) in SEQUENCES {
+            assert_eq!(store.sequence_len(name), Some(seq.len()));
+            for start in 0..=seq.len() {
+                for end in start..=seq.len() {
+                    let fetched = store.fetch(name, start..end).unwrap();
+                    assert_eq!(text(&fetched), seq[start..end]);
+                    assert_eq!(fetched, IupacDna::from_str(&seq[start..end]).unwrap());
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn fetch_errors() {
+        let store = GenomeStore::new(genome()).unwrap();
+        assert_eq!(
+            store.fetch("chr2", 0..1).unwrap_err().to_string(),
+            "no sequence named chr2 in 2bit file"
+        );
+        assert_eq!(
+            store.fetch("chr1", 20..22).unwrap_err().to_string(),
+            "range 20..22 out of bounds for sequence chr1 of length 21"
+        );
+        assert_eq!(store.sequence_len("chr2"), None);
+    }
+
+    #[test]
+    fn map_every_contig() {
+        let store = GenomeStore::new(genome()).unwrap();
+        let counts = store
+            .par_map_contigs(|name, dna| (name == "chrM", dna.len(), dna.count(Iupac::N)))
+            .unwrap();
+        assert_eq!(
+            counts,
+            [
+                ("chr1".to_string(), (false, 21, 5)),
+                ("chrM".to_string(), (true, 33, 14)),
+            ]
+        );
+    }
+
+    #[test]
+    fn truncated_payload() {
+        let mut bytes = genome();
+        bytes.truncate(bytes.len() - 1);
+        assert!(matches!(
+            GenomeStore::new(bytes),
+            Err(TwoBitError::Malformed(name)) if name == "chrM"
+        ));
+    }
+
+    #[cfg(feature = "memmap2")]
+    #[test]
+    fn open_memory_mapped() {
+        let path = std::env::temp_dir().join(format!("genome-store-{}.2bit", std::process::id()));
+        std::fs::write(&path, genome()).unwrap();
+        let store = GenomeStore::open(&path).unwrap();
+        assert_eq!(text(&store.fetch("chrM", 5..18).unwrap()), "CAGATTACANNNN");
+        drop(store);
+        std::fs::remove_file(path).unwrap();
+    }
+
+    /// The non-zero bytes of a sparse version 1 file of over 4 GiB, by offset: `chr1` has the
+    /// most nucleotides a record can hold, all `T` but for the last 16 and an `N` block, and
+    /// `chr2` starts past 4 GiB
+    #[cfg(target_pointer_width = "64")]
+    fn huge_genome() -> Vec<(u64, Vec<u8>)> {
+        let words =
+            |words: &[u32]| -> Vec<u8> { words.iter().flat_map(|w| w.to_le_bytes()).collect() };
+        let chr1_offset = 4096u64;
+        let chr2_offset = 5u64 << 30;
+        let mut index = words(&[0x1A41_2743, 1, 2, 0]);
+        for (name, offset) in [(b"chr1", chr1_offset), (b"chr2", chr2_offset)] {
+            index.push(4);
+            index.extend_from_slice(name);
+            index.extend_from_slice(&offset.to_le_bytes());
+        }
+        let len = u32::MAX;
+        // One N block of 3 nucleotides, 10 before the end.
+        let chr1_header = words(&[len, 1, len - 10, 3, 0, 0]);
+        let payload_end = chr1_offset + chr1_header.len() as u64 + u64::from(len).div_ceil(4);
+        let mut chr2 = words(&[7, 0, 0, 0]);
+        // GATTACA
+        chr2.extend_from_slice(&[0b11_10_00_00, 0b10_01_10_00]);
+        vec![
+            (0, index),
+            (chr1_offset, chr1_header),
+            // ACGT ACGT ACGT ACG in the 2bit encoding, the last byte padded.
+            (payload_end - 4, vec![0b10_01_11_00; 4]),
+            (chr2_offset, chr2),
+        ]
+    }
+
+    /// A stream of the zero bytes of a sparse file with `chunks` written into it, read without
+    /// touching the disk
+    #[cfg(target_pointer_width = "64")]
+    struct SparseStream {
+        chunks: Vec<(u64, Vec<u8>)>,
+        pos: u64,
+    }
+
+    #[cfg(target_pointer_width = "64")]
+    impl SparseStream {
+        fn len(&self) -> u64 {
+            self.chunks
+                .iter()
+                .map(|(offset, bytes)| offset + bytes.len() as u64)
+                .max()
+                .unwrap_or(0)
+        }
+    }
+
+    #[cfg(target_pointer_width = "64")]
+    impl std::io::Read for SparseStream {
+        fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+            let n = buf.len().min(self.len().saturating_sub(self.pos) as usize);
+            let (pos, end) = (self.pos, self.pos + n as u64);
+            buf[..n].fill(0);
+            for (offset, bytes) in &self.chunks {
+                let from = pos.max(*offset);
+                let to = end.min(offset + bytes.len() as u64);
+                if from < to {
+                    buf[(from - pos) as usize..(to - pos) as usize]
+                        .copy_from_slice(&bytes[(from - offset) as usize..(to - offset) as usize]);
+                }
+            }
+            self.pos = end;
+            Ok(n)
+        }
+    }
+
+    #[cfg(target_pointer_width = "64")]
+    impl std::io::Seek for SparseStream {
+        fn seek(&mut self, pos: std::io::SeekFrom) -> std::io::Result<u64> {
+            self.pos = match pos {
+                std::io::SeekFrom::Start(pos) => pos,
+                std::io::SeekFrom::End(delta) => self.len().saturating_add_signed(delta),
+                std::io::SeekFrom::Current(delta) => self.pos.saturating_add_signed(delta),
+            };
+            Ok(self.pos)
+        }
+    }
+
+    #[cfg(target_pointer_width = "64")]
+    #[test]
+    fn read_headers_past_32_bit_offsets() {
+        let mut reader = Reader::new(SparseStream {
+            chunks: huge_genome(),
+            pos: 0,
+        })
+        .unwrap();
+        assert_eq!(reader.names(), ["chr1", "chr2"]);
+        let len = u32::MAX as usize;
+        let header = reader.read_header("chr1").unwrap();
+        assert_eq!((header.len, header.payload_offset), (len, 4096 + 24));
+        assert_eq!(header.n_blocks.first(), Some(&(len - 10..len - 7)));
+        let chr2 = reader.read("chr2").unwrap();
+        assert_eq!(text(&chr2.sequence), "GATTACA");
+    }
+
+    #[cfg(all(feature = "memmap2", target_pointer_width = "64"))]
+    fn write_huge_genome(path: &std::path::Path) -> std::io::Result<()> {
+        use std::io::{Seek, SeekFrom, Write};
+
+        let mut file = std::fs::File::create(path)?;
+        for (offset, bytes) in huge_genome() {
+            file.seek(SeekFrom::Start(offset))?;
+            file.write_all(&bytes)?;
+        }
+        Ok(())
+    }
+
+    /// Fetches through a memory map of a real file, which takes over 5 GiB of disk where sparse
+    /// files are not supported; run with `cargo test -- --ignored`
+    #[cfg(all(feature = "memmap2", target_pointer_width = "64"))]
+    #[test]
+    #[ignore = "writes a sparse file of over 5 GiB"]
+    fn fetch_past_32_bit_positions_and_offsets() {
+        let path = std::env::temp_dir().join(format!("genome-huge-{}.2bit", std::process::id()));
+        write_huge_genome(&path).unwrap();
+        let store = GenomeStore::open(&path).unwrap();
+        let len = u32::MAX as usize;
+        assert_eq!(store.sequence_len("chr1"), Some(len));
+        assert_eq!(
+            text(&store.fetch("chr1", len - 16..len).unwrap()),
+            "TACGTANNNACGTACG"
+        );
+        let past_i32 = 1 << 31;
+        assert_eq!(
+            text(&store.fetch("chr1", past_i32 - 2..past_i32 + 3).unwrap()),
+            "TTTTT"
+        );
+        assert!(store.fetch("chr1", len - 1..len + 1).is_err());
+        assert_eq!(text(&store.fetch("chr2", 0..7).unwrap()), "GATTACA");
+        drop(store);
+        std::fs::remove_file(path).unwrap();
+    }
+}
@@ -0,0 +1,374 @@
+//! Reading of GFF3 annotations and lookup of the features overlapping a range.
+//!
+//! Each feature line has nine tab-separated columns; coordinates are 1-based and inclusive in
+//! the file, and converted to 0-based half-open ranges like the rest of the crate. Attribute
+//! values are percent-decoded. Comment and directive lines are skipped, and reading stops at a
+//! `##FASTA` section.
+
+use crate::translate::Strand;
+use std::{
+    collections::HashMap,
+    fs::File,
+    io::{self, BufRead, BufReader, Read},
+    ops::Range,
+    path::Path,
+};
+
+/// An error that can occur when reading GFF3 input.
+#[derive(Debug, thiserror::Error)]
+pub enum GffError {
+    /// The underlying reader failed
+    #[error("failed to read GFF3 input")]
+    Io(#[from] io::Error),
+    /// A feature line could not be parsed
+    #[error("malformed GFF3 line {line}: {reason}")]
+    Malformed {
+        /// The 1-based line number
+        line: usize,
+        /// What is wrong with it
+        reason: &'static str,
+    },
+}
+
+impl GffError {
+    /// A stable code for the kind of error, such as `gff.malformed`, see
+    /// [`DnaError::code`](crate::DnaError::code)
+    pub fn code(&self) -> &'static str {
+        match self {
+            GffError::Io(_) => "gff.io",
+            GffError::Malformed { .. } => "gff.malformed",
+        }
+    }
+}
+
+/// A feature of a GFF3 file, such as a gene, transcript or exon
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Feature {
+    /// The sequence the feature lies on
+    pub seqid: String,
+    /// The program or database the feature comes from
+    pub source: String,
+    /// The feature type, e.g. `gene`, `mRNA` or `exon`
+    pub kind: String,
+    /// 0-based half-open range of the feature on its sequence
+    pub range: Range<usize>,
+    /// The strand, or `None` for unstranded features and those of unknown strand
+    pub strand: Option<Strand>,
+    /// Attributes as decoded key and value pairs, in file order
+    pub attributes: Vec<(String, String)>,
+}
+
+impl Feature {
+    /// The value of the attribute `key`, if present
+    pub fn attribute(&self, key: &str) -> Option<&str> {
+        self.attributes
+            .iter()
+            .find(|(other, _)| other == key)
+            .map(|(_, value)| value.as_str())
+    }
+
+    /// The `ID` attribute
+    pub fn id(&self) -> Option<&str> {
+        self.attribute("ID")
+    }
+
+    /// The IDs listed in the `Parent` attribute
+    pub fn parents(&self) -> impl Iterator<Item = &str> {
+        self.attribute("Parent")
+            .into_iter()
+            .flat_map(|parents| parents.split(','))
+    }
+}
+
+/// A streaming GFF3 reader yielding one [`Feature`] at a time
+#[derive(Debug)]
+pub struct Reader<R> {
+    inner: BufReader<R>,
+    line: String,
+    line_number: usize,
+    done: bool,
+}
+
+impl Reader<File> {
+    /// Open the GFF3 file at `path`
+    pub fn from_path(path: impl AsRef<Path>) -> io::Result<Self> {
+        Ok(Reader::new(File::open(path)?))
+    }
+}
+
+impl<R: Read> Reader<R> {
+    /// Create a reader over any byte source
+    pub fn new(inner: R) -> Self {
+        Reader {
+            inner: BufReader::new(inner),
+            line: String::new(),
+            line_number: 0,
+            done: false,
+        }
+    }
+
+    /// Read the next feature line, skipping comments and directives
+    fn read_feature(&mut self) -> Result<Option<Feature>, GffError> {
+        while !self.done {
+            self.line.clear();
+            if self.inner.read_line(&mut self.line)? == 0 {
+                break;
+            }
+            self.line_number += 1;
+            let line = self.line.trim_end_matches(['\n', '\r']);
+            if line.starts_with("##FASTA") {
+                self.done = true;
+            } else if !line.trim().is_empty() && !line.starts_with('#') {
+                return parse_feature(line, self.line_number).map(Some);
+            }
+        }
+        self.done = true;
+        Ok(None)
+    }
+}
+
+impl<R: Read> Iterator for Reader<R> {
+    type Item = Result<Feature, GffError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.read_feature().transpose()
+    }
+}
+
+/// Parse the nine columns of a feature line
+fn parse_feature(line: &str, line_number: usize) -> Result<Feature, GffError> {
+    let malformed = |reason| GffError::Malformed {
+        line: line_number,
+        reason,
+    };
+    let columns: Vec<&str> = line.split('\t').collect();
+    if columns.len() != 9 {
+        return Err(malformed("expected 9 tab-separated columns"));
+    }
+    let position = |column: &str| column.parse::<usize>().ok().filter(|&pos| pos > 0);
+    let start = position(columns[3]).ok_or_else(|| malformed("invalid start"))?;
+    let end = position(columns[4]).ok_or_else(|| malformed("invalid end"))?;
+    if end < start {
+        return Err(malformed("end before start"));
+    }
+    let strand = match columns[6] {
+        "+" => Some(Strand::Forward),
+        "-" => Some(Strand::Reverse),
+        "." | "?" => None,
+        _ => return Err(malformed("invalid strand")),
+    };
+    let mut attributes = Vec::new();
+    let attribute_column = if columns[8] == "." { "" } else { columns[8] };
+    for attribute in attribute_column.split(';').filter(|a| !a.trim().is_empty()) {
+        let (key, value) = attribute
+            .split_once('=')
+            .ok_or_else(|| malformed("attribute without a value"))?;
+        attributes.push((decode(key.trim()), decode(value)));
+    }
+    Ok(Feature {
+        seqid: decode(columns[0]),
+        source: decode(columns[1]),
+        kind: decode(columns[2]),
+        range: start - 1..end,
+        strand,
+        attributes,
+    })
+}
+
+/// Replace the `%XX` escapes of a column with the bytes they stand for
+fn decode(text: &str) -> String {
+    if !text.contains('%') {
+        return text.to_string();
+    }
+    let bytes = text.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut idx = 0;
+    while idx < bytes.len() {
+        let escaped = bytes
+            .get(idx + 1..idx + 3)
+            .filter(|_| bytes[idx] == b'%')
+            .and_then(|hex| std::str::from_utf8(hex).ok())
+            .and_then(|hex| u8::from_str_radix(hex, 16).ok());
+        match escaped {
+            Some(byte) => {
+                decoded.push(byte);
+                idx += 3;
+            }
+            None => {
+                decoded.push(bytes[idx]);
+                idx += 1;
+            }
+        }
+    }
+    String::from_utf8_lossy(&decoded).into_owned()
+}
+
+/// Features grouped by sequence and sorted by start, for overlap queries
+#[derive(Debug, Clone, Default)]
+pub struct FeatureIndex {
+    by_seqid: HashMap<String, Features>,
+}
+
+/// The features of one sequence
+#[derive(Debug, Clone, Default)]
+struct Features {
+    /// Sorted by start
+    features: Vec<Feature>,
+    /// Length of the longest feature, which bounds how far before a range an overlapping
+    /// feature can start
+    max_len: usize,
+}
+
+impl FeatureIndex {
+    /// Index `features`
+    pub fn new(features: impl IntoIterator<Item = Feature>) -> Self {
+        let mut by_seqid: HashMap<String, Features> = HashMap::new();
+        for feature in features {
+            let entry = by_seqid.entry(feature.seqid.clone()).or_default();
+            entry.max_len = entry.max_len.max(feature.range.len());
+            entry.features.push(feature);
+        }
+        for entry in by_seqid.values_mut() {
+            entry.features.sort_by_key(|feature| feature.range.start);
+        }
+        FeatureIndex { by_seqid }
+    }
+
+    /// Read and index every feature of GFF3 input
+    pub fn from_reader(input: impl Read) -> Result<Self, GffError> {
+        Ok(FeatureIndex::new(
+            Reader::new(input).collect::<Result<Vec<_>, _>>()?,
+        ))
+    }
+
+    /// Number of indexed features
+    pub fn len(&self) -> usize {
+        self.by_seqid
+            .values()
+            .map(|entry| entry.features.len())
+            .sum()
+    }
+
+    /// Whether no feature is indexed
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Every indexed feature, by sequence name then start
+    pub fn iter(&self) -> impl Iterator<Item = &Feature> {
+        let mut seqids: Vec<&String> = self.by_seqid.keys().collect();
+        seqids.sort();
+        seqids
+            .into_iter()
+            .flat_map(move |seqid| self.by_seqid[seqid].features.iter())
+    }
+
+    /// The features on `seqid`, sorted by start
+    pub fn features(&self, seqid: &str) -> &[Feature] {
+        self.by_seqid
+            .get(seqid)
+            .map_or(&[], |entry| entry.features.as_slice())
+    }
+
+    /// The features on `seqid` sharing at least one position with the 0-based `range`, by start
+    pub fn overlapping<'a>(
+        &'a self,
+        seqid: &str,
+        range: Range<usize>,
+    ) -> impl Iterator<Item = &'a Feature> + 'a {
+        let (features, max_len) = self.by_seqid.get(seqid).map_or((&[][..], 0), |entry| {
+            (entry.features.as_slice(), entry.max_len)
+        });
+        let Range { start, end } = range;
+        let first = features.partition_point(|f| f.range.start + max_len <= start);
+        features[first..]
+            .iter()
+            .take_while(move |f| f.range.start < end)
+            .filter(move |f| f.range.end > start && start < end)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const GFF: &str = "##gff-version 3\n\
+        chr1\tsrc\tgene\t11\t40\t.\t+\t.\tID=gene1;Name=alpha%2Cbeta\n\
+        chr1\tsrc\tmRNA\t11\t40\t.\t+\t.\tID=tx1;Parent=gene1\n\
+        chr1\tsrc\texon\t11\t20\t.\t+\t.\tParent=tx1\n\
+        chr1\tsrc\texon\t31\t40\t.\t+\t.\tParent=tx1\n\
+        # a comment\n\
+        chr1\tsrc\tgene\t101\t200\t0.5\t-\t.\tID=gene2\n\
+        chr2\tsrc\tregion\t1\t1000\t.\t.\t.\t\n\
+        ##FASTA\n\
+        >chr1\n\
+        ACGT\n";
+
+    #[test]
+    fn reads_features() {
+        let features: Vec<Feature> = Reader::new(GFF.as_bytes())
+            .collect::<Result<_, _>>()
+            .unwrap();
+        assert_eq!(features.len(), 6);
+        let gene = &features[0];
+        assert_eq!((gene.seqid.as_str(), gene.kind.as_str()), ("chr1", "gene"));
+        assert_eq!(gene.range, 10..40);
+        assert_eq!(gene.strand, Some(Strand::Forward));
+        assert_eq!(gene.id(), Some("gene1"));
+        assert_eq!(gene.attribute("Name"), Some("alpha,beta"));
+        assert_eq!(features[1].parents().collect::<Vec<_>>(), ["gene1"]);
+        assert_eq!(features[4].strand, Some(Strand::Reverse));
+        assert_eq!(features[5].strand, None);
+        assert!(features[5].attributes.is_empty());
+    }
+
+    #[test]
+    fn malformed_lines() {
+        let err = |line: &str| {
+            Reader::new(line.as_bytes())
+                .next()
+                .unwrap()
+                .unwrap_err()
+                .to_string()
+        };
+        assert_eq!(
+            err("chr1\tsrc\tgene\t10\n"),
+            "malformed GFF3 line 1: expected 9 tab-separated columns"
+        );
+        assert_eq!(
+            err("chr1\tsrc\tgene\t0\t5\t.\t+\t.\t.\n"),
+            "malformed GFF3 line 1: invalid start"
+        );
+        assert_eq!(
+            err("chr1\tsrc\tgene\t9\t5\t.\t+\t.\tID=a\n"),
+            "malformed GFF3 line 1: end before start"
+        );
+        assert_eq!(
+            err("chr1\tsrc\tgene\t1\t5\t.\tx\t.\tID=a\n"),
+            "malformed GFF3 line 1: invalid strand"
+        );
+        assert_eq!(
+            err("chr1\tsrc\tgene\t1\t5\t.\t+\t.\tID\n"),
+            "malformed GFF3 line 1: attribute without a value"
+        );
+    }
+
+    #[test]
+    fn overlapping_features() {
+        let index = FeatureIndex::from_reader(GFF.as_bytes()).unwrap();
+        assert_eq!(index.len(), 6);
+        let kinds = |seqid, range| {
+            index
+                .overlapping(seqid, range)
+                .map(|f| f.kind.clone())
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(kinds("chr1", 20..30), ["gene", "mRNA"]);
+        assert_eq!(kinds("chr1", 19..31), ["gene", "mRNA", "exon", "exon"]);
+        assert_eq!(kinds("chr1", 40..100), Vec::<String>::new());
+        assert_eq!(kinds("chr1", 150..151), ["gene"]);
+        assert_eq!(kinds("chr2", 999..2000), ["region"]);
+        assert!(kinds("chr3", 0..10).is_empty());
+        assert_eq!(index.features("chr1").len(), 5);
+    }
+}
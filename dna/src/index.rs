@@ -0,0 +1,167 @@
+//! Exact k-mer lookup over a sorted k-mer array.
+//!
+//! [`KmerIndex`] stores every k-mer occurrence of a sequence as a packed k-mer and its position,
+//! sorted by k-mer. That takes 16 bytes per position on 64-bit targets, with none of the bucket
+//! and per-key allocation overhead of a `HashMap` from k-mers to position lists. Lookups use
+//! interpolation search, which suits packed k-mers because they are spread close to uniformly
+//! over their range, interleaved with bisection so skewed sequences stay logarithmic.
+
+use crate::{
+    kmer::{Kmers, MAX_K},
+    PackedDna,
+};
+
+/// Number of candidates below which a lookup stops searching and scans them linearly
+const LINEAR_SCAN_THRESHOLD: usize = 16;
+
+/// The positions of every k-mer of a sequence, sorted by k-mer
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct KmerIndex {
+    k: usize,
+    /// Packed k-mers, ascending
+    kmers: Vec<u64>,
+    /// 0-based start of each k-mer of `kmers`, ascending among equal k-mers
+    positions: Vec<usize>,
+}
+
+impl KmerIndex {
+    /// Index the k-mers of length `k` of `dna`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `k` is zero or greater than [`MAX_K`].
+    pub fn new(dna: &PackedDna, k: usize) -> Self {
+        assert!(
+            (1..=MAX_K).contains(&k),
+            "k-mer length must be between 1 and {}, got {}",
+            MAX_K,
+            k
+        );
+        let mut entries: Vec<(u64, usize)> = Kmers::new(dna, k, false)
+            .enumerate()
+            .map(|(pos, kmer)| (kmer, pos))
+            .collect();
+        entries.sort_unstable();
+        let (kmers, positions) = entries.into_iter().unzip();
+        KmerIndex {
+            k,
+            kmers,
+            positions,
+        }
+    }
+
+    /// The k-mer length
+    pub fn k(&self) -> usize {
+        self.k
+    }
+
+    /// Number of indexed k-mer occurrences
+    pub fn len(&self) -> usize {
+        self.kmers.len()
+    }
+
+    /// Whether the sequence was shorter than `k`
+    pub fn is_empty(&self) -> bool {
+        self.kmers.is_empty()
+    }
+
+    /// 0-based positions of the packed k-mer `kmer`, in increasing order
+    pub fn positions(&self, kmer: u64) -> &[usize] {
+        let start = self.lower_bound(kmer);
+        let end = start + self.kmers[start..].partition_point(|&other| other == kmer);
+        &self.positions[start..end]
+    }
+
+    /// Occurrences of the packed k-mer `kmer`
+    pub fn count(&self, kmer: u64) -> usize {
+        self.positions(kmer).len()
+    }
+
+    /// Whether the packed k-mer `kmer` occurs at all
+    pub fn contains(&self, kmer: u64) -> bool {
+        self.kmers.get(self.lower_bound(kmer)) == Some(&kmer)
+    }
+
+    /// Index of the first k-mer not less than `kmer`
+    fn lower_bound(&self, kmer: u64) -> usize {
+        // Invariant: kmers[..lo] < kmer <= kmers[hi..]
+        let (mut lo, mut hi) = (0, self.kmers.len());
+        let mut interpolate = true;
+        while hi - lo > LINEAR_SCAN_THRESHOLD {
+            let (first, last) = (self.kmers[lo], self.kmers[hi - 1]);
+            if kmer <= first {
+                return lo;
+            }
+            if kmer > last {
+                return hi;
+            }
+            let mid = if interpolate {
+                let offset =
+                    u128::from(kmer - first) * (hi - 1 - lo) as u128 / u128::from(last - first);
+                lo + offset as usize
+            } else {
+                lo + (hi - lo) / 2
+            };
+            interpolate = !interpolate;
+            if self.kmers[mid] < kmer {
+                lo = mid + 1;
+            } else {
+                hi = mid;
+            }
+        }
+        lo + self.kmers[lo..hi]
+            .iter()
+            .take_while(|&&other| other < kmer)
+            .count()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::kmer::encode;
+    use crate::test_util::dna;
+
+    #[test]
+    fn positions_of_kmers() {
+        let index = KmerIndex::new(&dna("ACGTACGTTACG"), 3);
+        assert_eq!(index.len(), 10);
+        assert_eq!(index.positions(encode("ACG").unwrap()), [0, 4, 9]);
+        assert_eq!(index.positions(encode("GTT").unwrap()), [6]);
+        assert_eq!(index.positions(encode("AAA").unwrap()), [0usize; 0]);
+        assert_eq!(index.count(encode("CGT").unwrap()), 2);
+        assert!(index.contains(encode("TAC").unwrap()));
+        assert!(!index.contains(encode("TTT").unwrap()));
+    }
+
+    #[test]
+    fn lookup_matches_search() {
+        // Long and skewed enough that lookups take both interpolation and bisection steps
+        let text = format!(
+            "{}{}{}",
+            "A".repeat(300),
+            "ACGTTGCACTGGATTACAACGTTGCATTGACCAGT".repeat(20),
+            "T".repeat(300)
+        );
+        let haystack = dna(&text);
+        for k in [1, 5, 12, 32] {
+            let index = KmerIndex::new(&haystack, k);
+            for start in (0..=text.len() - k).step_by(37) {
+                let kmer = &text[start..start + k];
+                let expected: Vec<usize> = haystack.find_all(&dna(kmer)).collect();
+                assert_eq!(index.positions(encode(kmer).unwrap()), expected);
+            }
+            if k > 3 {
+                assert!(!index.contains(encode(&"C".repeat(k)).unwrap()));
+            }
+        }
+    }
+
+    #[test]
+    fn sequence_shorter_than_k() {
+        let index = KmerIndex::new(&dna("ACG"), 4);
+        assert!(index.is_empty());
+        assert_eq!(index.count(0), 0);
+        assert!(!index.contains(0));
+    }
+}
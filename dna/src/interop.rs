@@ -0,0 +1,337 @@
+//! Conversions to and from the types of other bioinformatics crates.
+//!
+//! Each crate has its own feature, so only the ones in use are compiled:
+//!
+//! - `bio-types`: [`Strand`] converts to and from the strands of `bio_types::strand`, and
+//!   [`fastq::Read`] implements `bio_types::sequence::SequenceRead`. A
+//!   `bio_types::sequence::Sequence` is a `Vec<u8>` of ASCII letters, which
+//!   [`PackedDna`] already converts to and from.
+//! - `noodles-fasta`: [`fasta::Record`] and [`PackedDna`] convert to and from
+//!   `noodles_fasta::Record` and `noodles_fasta::record::Sequence`.
+//! - `noodles-fastq`: [`fastq::Read`] converts to and from `noodles_fastq::Record`, whose
+//!   quality scores are Phred+33 characters.
+//!
+//! The other crates split a header into a name and a description where this crate keeps the whole
+//! line, so a header is split at its first whitespace, and joined back with a single space.
+//! Conversions into this crate fail on anything but A, C, G and T, and replace invalid UTF-8 in
+//! headers.
+
+#[cfg(feature = "noodles-fasta")]
+use crate::fasta;
+#[cfg(any(feature = "bio-types", feature = "noodles-fastq"))]
+use crate::fastq;
+#[cfg(feature = "bio-types")]
+use crate::translate::Strand;
+#[cfg(any(feature = "noodles-fasta", feature = "noodles-fastq"))]
+use crate::{DnaError, PackedDna};
+use std::convert::TryFrom;
+
+/// An error converting a record of another crate into one of this crate
+#[cfg(feature = "noodles-fastq")]
+#[derive(Debug, thiserror::Error)]
+pub enum RecordError {
+    /// The sequence is not made of A, C, G and T
+    #[error(transparent)]
+    Sequence(#[from] DnaError),
+    /// A quality character is outside the Phred+33 range of `!` to `~`
+    #[error("invalid quality character {character:?} at position {position}")]
+    InvalidQuality {
+        /// The offending character
+        character: char,
+        /// Its 0-based position in the quality line
+        position: usize,
+    },
+    /// The sequence and the quality scores differ in length
+    #[error("{nucleotides} nucleotides but {qualities} quality scores")]
+    LengthMismatch {
+        /// Length of the sequence
+        nucleotides: usize,
+        /// Number of quality scores
+        qualities: usize,
+    },
+}
+
+/// Split a header at its first whitespace into a name and a possibly empty description
+#[cfg(any(feature = "noodles-fasta", feature = "noodles-fastq"))]
+fn split_header(header: &str) -> (&str, &str) {
+    match header.split_once(char::is_whitespace) {
+        Some((name, description)) => (name, description.trim_start()),
+        None => (header, ""),
+    }
+}
+
+/// Join a name and a possibly empty description into a header
+#[cfg(any(feature = "noodles-fasta", feature = "noodles-fastq"))]
+fn join_header(name: &[u8], description: &[u8]) -> String {
+    let name = String::from_utf8_lossy(name);
+    if description.is_empty() {
+        name.into_owned()
+    } else {
+        format!("{} {}", name, String::from_utf8_lossy(description))
+    }
+}
+
+#[cfg(feature = "bio-types")]
+impl From<Strand> for bio_types::strand::ReqStrand {
+    fn from(strand: Strand) -> Self {
+        match strand {
+            Strand::Forward => bio_types::strand::ReqStrand::Forward,
+            Strand::Reverse => bio_types::strand::ReqStrand::Reverse,
+        }
+    }
+}
+
+#[cfg(feature = "bio-types")]
+impl From<bio_types::strand::ReqStrand> for Strand {
+    fn from(strand: bio_types::strand::ReqStrand) -> Self {
+        match strand {
+            bio_types::strand::ReqStrand::Forward => Strand::Forward,
+            bio_types::strand::ReqStrand::Reverse => Strand::Reverse,
+        }
+    }
+}
+
+#[cfg(feature = "bio-types")]
+impl From<Strand> for bio_types::strand::Strand {
+    fn from(strand: Strand) -> Self {
+        bio_types::strand::ReqStrand::from(strand).into()
+    }
+}
+
+/// Fails on `Strand::Unknown`, handing it back
+#[cfg(feature = "bio-types")]
+impl TryFrom<bio_types::strand::Strand> for Strand {
+    type Error = bio_types::strand::Strand;
+
+    fn try_from(strand: bio_types::strand::Strand) -> Result<Self, Self::Error> {
+        Option::<bio_types::strand::ReqStrand>::from(strand)
+            .map(Strand::from)
+            .ok_or(strand)
+    }
+}
+
+/// Bases are uppercase ASCII letters and base qualities Phred scores, without the offset of 33
+#[cfg(feature = "bio-types")]
+impl bio_types::sequence::SequenceRead for fastq::Read {
+    fn name(&self) -> &[u8] {
+        self.id().as_bytes()
+    }
+
+    fn base(&self, i: usize) -> u8 {
+        self.sequence()[i].to_char() as u8
+    }
+
+    fn base_qual(&self, i: usize) -> u8 {
+        self.qualities()[i]
+    }
+
+    fn len(&self) -> usize {
+        fastq::Read::len(self)
+    }
+}
+
+#[cfg(feature = "noodles-fasta")]
+impl From<&PackedDna> for noodles_fasta::record::Sequence {
+    fn from(dna: &PackedDna) -> Self {
+        Vec::from(dna).into()
+    }
+}
+
+#[cfg(feature = "noodles-fasta")]
+impl TryFrom<&noodles_fasta::record::Sequence> for PackedDna {
+    type Error = DnaError;
+
+    fn try_from(sequence: &noodles_fasta::record::Sequence) -> Result<Self, Self::Error> {
+        PackedDna::try_from(sequence.as_ref())
+    }
+}
+
+#[cfg(feature = "noodles-fasta")]
+impl From<&fasta::Record> for noodles_fasta::Record {
+    fn from(record: &fasta::Record) -> Self {
+        let (name, description) = split_header(record.header());
+        let description = Some(description)
+            .filter(|description| !description.is_empty())
+            .map(Into::into);
+        noodles_fasta::Record::new(
+            noodles_fasta::record::Definition::new(name, description),
+            record.sequence().into(),
+        )
+    }
+}
+
+#[cfg(feature = "noodles-fasta")]
+impl TryFrom<&noodles_fasta::Record> for fasta::Record {
+    type Error = DnaError;
+
+    fn try_from(record: &noodles_fasta::Record) -> Result<Self, Self::Error> {
+        let description = record.description().map_or(&[][..], |d| d.as_ref());
+        Ok(fasta::Record::new(
+            join_header(record.name(), description),
+            PackedDna::try_from(record.sequence())?,
+        ))
+    }
+}
+
+#[cfg(feature = "noodles-fastq")]
+impl From<&fastq::Read> for noodles_fastq::Record {
+    fn from(read: &fastq::Read) -> Self {
+        let (name, description) = split_header(read.header());
+        let qualities: Vec<u8> = read
+            .qualities()
+            .iter()
+            .map(|&quality| quality.saturating_add(fastq::PHRED_OFFSET).min(b'~'))
+            .collect();
+        noodles_fastq::Record::new(
+            noodles_fastq::record::Definition::new(name, description),
+            Vec::from(read.sequence()),
+            qualities,
+        )
+    }
+}
+
+#[cfg(feature = "noodles-fastq")]
+impl TryFrom<&noodles_fastq::Record> for fastq::Read {
+    type Error = RecordError;
+
+    fn try_from(record: &noodles_fastq::Record) -> Result<Self, Self::Error> {
+        let sequence = PackedDna::try_from(record.sequence())?;
+        let qualities = record
+            .quality_scores()
+            .iter()
+            .enumerate()
+            .map(|(position, &character)| match character {
+                b'!'..=b'~' => Ok(character - fastq::PHRED_OFFSET),
+                _ => Err(RecordError::InvalidQuality {
+                    character: char::from(character),
+                    position,
+                }),
+            })
+            .collect::<Result<Vec<u8>, _>>()?;
+        if qualities.len() != sequence.len() {
+            return Err(RecordError::LengthMismatch {
+                nucleotides: sequence.len(),
+                qualities: qualities.len(),
+            });
+        }
+        let header = join_header(record.name(), record.description());
+        Ok(fastq::Read::new(header, sequence, qualities))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[cfg(any(feature = "noodles-fasta", feature = "noodles-fastq"))]
+    #[test]
+    fn headers_split_at_first_whitespace() {
+        assert_eq!(
+            split_header("chr1 assembled\tcontig"),
+            ("chr1", "assembled\tcontig")
+        );
+        assert_eq!(split_header("chr1"), ("chr1", ""));
+        assert_eq!(join_header(b"chr1", b""), "chr1");
+        assert_eq!(join_header(b"chr1", b"len=4"), "chr1 len=4");
+    }
+
+    #[cfg(feature = "bio-types")]
+    #[test]
+    fn bio_types_round_trip() {
+        use crate::PackedDna;
+        use bio_types::{sequence::SequenceRead, strand};
+
+        for &ours in &[Strand::Forward, Strand::Reverse] {
+            assert_eq!(Strand::from(strand::ReqStrand::from(ours)), ours);
+            assert_eq!(Strand::try_from(strand::Strand::from(ours)), Ok(ours));
+        }
+        // An unknown strand is never equal to itself, so match it.
+        assert!(matches!(
+            Strand::try_from(strand::Strand::Unknown),
+            Err(strand::Strand::Unknown)
+        ));
+
+        let read = fastq::Read::new(
+            "r1 lane=2",
+            PackedDna::try_from("GATC").unwrap(),
+            vec![40, 30, 2, 0],
+        );
+        assert_eq!(SequenceRead::name(&read), b"r1");
+        assert_eq!(SequenceRead::len(&read), 4);
+        let bases: Vec<u8> = (0..4).map(|i| read.base(i)).collect();
+        assert_eq!(bases, b"GATC");
+        assert_eq!(read.base_qual(1), 30);
+    }
+
+    #[cfg(feature = "noodles-fasta")]
+    #[test]
+    fn noodles_fasta_round_trip() {
+        let record = fasta::Record::new("chr1 len=6", PackedDna::try_from("ACGTTA").unwrap());
+        let theirs = noodles_fasta::Record::from(&record);
+        assert_eq!(theirs.name(), b"chr1");
+        assert_eq!(
+            theirs.description().map(|d| d.to_vec()),
+            Some(b"len=6".to_vec())
+        );
+        assert_eq!(theirs.sequence().as_ref(), b"ACGTTA");
+        let back = fasta::Record::try_from(&theirs).unwrap();
+        assert_eq!(back.header(), "chr1 len=6");
+        assert_eq!(back.sequence(), record.sequence());
+
+        let bare = fasta::Record::new("chr2", PackedDna::empty());
+        let theirs = noodles_fasta::Record::from(&bare);
+        assert_eq!(theirs.description(), None);
+        assert_eq!(fasta::Record::try_from(&theirs).unwrap().header(), "chr2");
+
+        let ambiguous = noodles_fasta::Record::new(
+            noodles_fasta::record::Definition::new("n", None),
+            b"ACNT".to_vec().into(),
+        );
+        assert!(matches!(
+            fasta::Record::try_from(&ambiguous),
+            Err(DnaError::InvalidNuc {
+                character: 'N',
+                position: 2
+            })
+        ));
+    }
+
+    #[cfg(feature = "noodles-fastq")]
+    #[test]
+    fn noodles_fastq_round_trip() {
+        let read = fastq::Read::new(
+            "r1 lane=2",
+            PackedDna::try_from("GATC").unwrap(),
+            vec![40, 30, 2, 0],
+        );
+        let theirs = noodles_fastq::Record::from(&read);
+        assert_eq!(theirs.name(), "r1");
+        assert_eq!(theirs.description(), "lane=2");
+        assert_eq!(theirs.sequence(), b"GATC");
+        assert_eq!(theirs.quality_scores(), b"I?#!");
+        assert_eq!(fastq::Read::try_from(&theirs).unwrap(), read);
+
+        let definition = noodles_fastq::record::Definition::new("r2", "");
+        let record = noodles_fastq::Record::new(definition.clone(), "ACG", "II ");
+        assert!(matches!(
+            fastq::Read::try_from(&record),
+            Err(RecordError::InvalidQuality {
+                character: ' ',
+                position: 2
+            })
+        ));
+        let record = noodles_fastq::Record::new(definition.clone(), "ACG", "II");
+        assert!(matches!(
+            fastq::Read::try_from(&record),
+            Err(RecordError::LengthMismatch {
+                nucleotides: 3,
+                qualities: 2
+            })
+        ));
+        let record = noodles_fastq::Record::new(definition, "ACU", "III");
+        assert!(matches!(
+            fastq::Read::try_from(&record),
+            Err(RecordError::Sequence(DnaError::InvalidNuc { .. }))
+        ));
+    }
+}
@@ -0,0 +1,499 @@
+//! IUPAC nucleotide codes, including `N` and the other ambiguity symbols.
+//!
+//! [`IupacDna`] stores a sequence the way UCSC 2bit files do: the 2-bit [`PackedDna`] holds every
+//! position, with ambiguous positions filled by a placeholder `A`, and a sorted list of
+//! [`AmbiguousRun`]s records which positions are really ambiguity codes. Sequences that are mostly
+//! ACGT therefore stay at 2 bits per base.
+
+use crate::{view::PackedDnaSlice, DnaError, Nuc, PackedDna, ParseNucError};
+use std::{
+    convert::TryFrom,
+    fmt::{self, Display},
+    str::FromStr,
+};
+
+/// A nucleotide or IUPAC ambiguity code
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Iupac {
+    /// Adenine
+    A,
+    /// Cytosine
+    C,
+    /// Guanine
+    G,
+    /// Thymine
+    T,
+    /// A or G (purine)
+    R,
+    /// C or T (pyrimidine)
+    Y,
+    /// G or C (strong)
+    S,
+    /// A or T (weak)
+    W,
+    /// G or T (keto)
+    K,
+    /// A or C (amino)
+    M,
+    /// Not A
+    B,
+    /// Not C
+    D,
+    /// Not G
+    H,
+    /// Not T
+    V,
+    /// Any nucleotide
+    N,
+}
+
+impl Iupac {
+    /// All codes, in declaration order
+    pub const ALL: [Iupac; 15] = [
+        Self::A,
+        Self::C,
+        Self::G,
+        Self::T,
+        Self::R,
+        Self::Y,
+        Self::S,
+        Self::W,
+        Self::K,
+        Self::M,
+        Self::B,
+        Self::D,
+        Self::H,
+        Self::V,
+        Self::N,
+    ];
+
+    /// The one-letter symbol of the code
+    pub fn to_char(self) -> char {
+        match self {
+            Self::A => 'A',
+            Self::C => 'C',
+            Self::G => 'G',
+            Self::T => 'T',
+            Self::R => 'R',
+            Self::Y => 'Y',
+            Self::S => 'S',
+            Self::W => 'W',
+            Self::K => 'K',
+            Self::M => 'M',
+            Self::B => 'B',
+            Self::D => 'D',
+            Self::H => 'H',
+            Self::V => 'V',
+            Self::N => 'N',
+        }
+    }
+
+    /// The nucleotides the code stands for
+    pub fn nucs(self) -> &'static [Nuc] {
+        use Nuc::{A, C, G, T};
+        match self {
+            Self::A => &[A],
+            Self::C => &[C],
+            Self::G => &[G],
+            Self::T => &[T],
+            Self::R => &[A, G],
+            Self::Y => &[C, T],
+            Self::S => &[C, G],
+            Self::W => &[A, T],
+            Self::K => &[G, T],
+            Self::M => &[A, C],
+            Self::B => &[C, G, T],
+            Self::D => &[A, G, T],
+            Self::H => &[A, C, T],
+            Self::V => &[A, C, G],
+            Self::N => &[A, C, G, T],
+        }
+    }
+
+    /// Whether the code stands for more than one nucleotide
+    pub fn is_ambiguous(self) -> bool {
+        self.to_nuc().is_none()
+    }
+
+    /// The nucleotide of an unambiguous code
+    pub fn to_nuc(self) -> Option<Nuc> {
+        match self {
+            Self::A => Some(Nuc::A),
+            Self::C => Some(Nuc::C),
+            Self::G => Some(Nuc::G),
+            Self::T => Some(Nuc::T),
+            _ => None,
+        }
+    }
+
+    /// Whether `nuc` is one of the nucleotides the code stands for
+    pub fn matches(self, nuc: Nuc) -> bool {
+        self.nucs().contains(&nuc)
+    }
+}
+
+impl From<Nuc> for Iupac {
+    fn from(nuc: Nuc) -> Self {
+        match nuc {
+            Nuc::A => Self::A,
+            Nuc::C => Self::C,
+            Nuc::G => Self::G,
+            Nuc::T => Self::T,
+        }
+    }
+}
+
+impl Display for Iupac {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.to_char())
+    }
+}
+
+impl TryFrom<char> for Iupac {
+    type Error = ParseNucError<char>;
+
+    fn try_from(value: char) -> Result<Self, Self::Error> {
+        let upper = value.to_ascii_uppercase();
+        Self::ALL
+            .iter()
+            .copied()
+            .find(|code| code.to_char() == upper)
+            .ok_or(ParseNucError(value))
+    }
+}
+
+/// A run of identical ambiguity codes within an [`IupacDna`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct AmbiguousRun {
+    /// 0-based position of the first code in the run
+    pub start: usize,
+    /// Number of positions in the run
+    pub len: usize,
+    /// The ambiguity code repeated over the run
+    pub code: Iupac,
+}
+
+/// What to do with ambiguous positions when converting to a plain [`PackedDna`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum AmbiguityPolicy {
+    /// Fail on the first ambiguous position
+    Error,
+    /// Drop ambiguous positions from the sequence
+    Skip,
+    /// Replace every ambiguous position with the given nucleotide
+    Replace(Nuc),
+}
+
+/// An error returned when an ambiguous position is found under [`AmbiguityPolicy::Error`].
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
+#[error("ambiguous nucleotide {code} at position {pos}")]
+pub struct AmbiguousNucError {
+    /// 0-based position of the ambiguous code
+    pub pos: usize,
+    /// The ambiguous code
+    pub code: Iupac,
+}
+
+/// A DNA sequence over the full IUPAC alphabet
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct IupacDna {
+    packed: PackedDna,
+    runs: Vec<AmbiguousRun>,
+}
+
+impl IupacDna {
+    /// Create an empty sequence
+    pub fn new() -> Self {
+        IupacDna {
+            packed: PackedDna::empty(),
+            runs: Vec::new(),
+        }
+    }
+
+    /// Assemble a sequence from a packed sequence with placeholder `A`s and its sorted runs
+    pub(crate) fn from_parts(packed: PackedDna, runs: Vec<AmbiguousRun>) -> Self {
+        IupacDna { packed, runs }
+    }
+
+    /// Append a code to the end of the sequence
+    pub fn push(&mut self, code: Iupac) {
+        let pos = self.len();
+        match code.to_nuc() {
+            Some(nuc) => self.packed.push(nuc),
+            None => {
+                self.packed.push(Nuc::A);
+                match self.runs.last_mut() {
+                    Some(run) if run.code == code && run.start + run.len == pos => run.len += 1,
+                    _ => self.runs.push(AmbiguousRun {
+                        start: pos,
+                        len: 1,
+                        code,
+                    }),
+                }
+            }
+        }
+    }
+
+    /// Number of positions in the sequence
+    pub fn len(&self) -> usize {
+        self.packed.len()
+    }
+
+    /// Whether the sequence has no positions
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// The code at the 0-based position `idx`, or `None` if out of bounds
+    pub fn get(&self, idx: usize) -> Option<Iupac> {
+        if idx >= self.len() {
+            return None;
+        }
+        Some(
+            self.run_at(idx)
+                .map_or_else(|| Iupac::from(self.packed.nuc_at(idx)), |run| run.code),
+        )
+    }
+
+    /// The ambiguous run covering `idx`, if any
+    fn run_at(&self, idx: usize) -> Option<&AmbiguousRun> {
+        let after = self.runs.partition_point(|run| run.start <= idx);
+        self.runs[..after]
+            .last()
+            .filter(|run| idx < run.start + run.len)
+    }
+
+    /// Iterate over the codes of the sequence
+    pub fn iter(&self) -> impl Iterator<Item = Iupac> + '_ {
+        (0..self.len()).map(move |idx| self.get(idx).unwrap_or(Iupac::N))
+    }
+
+    /// The runs of ambiguous codes, sorted by position
+    pub fn ambiguous_runs(&self) -> &[AmbiguousRun] {
+        &self.runs
+    }
+
+    /// The stretches of A, C, G and T between the runs of ambiguity codes, in order and borrowed
+    /// from the packed sequence, for work such as k-mer counting that must not span an `N`
+    pub fn unambiguous_slices(&self) -> impl Iterator<Item = PackedDnaSlice<'_>> {
+        let starts = std::iter::once(0).chain(self.runs.iter().map(|run| run.start + run.len));
+        let ends = self.runs.iter().map(|run| run.start).chain([self.len()]);
+        starts
+            .zip(ends)
+            .filter(|(start, end)| start < end)
+            .map(move |(start, end)| self.packed.slice(start..end))
+    }
+
+    /// Whether the sequence contains only A, C, G and T
+    pub fn is_unambiguous(&self) -> bool {
+        self.runs.is_empty()
+    }
+
+    /// Number of positions holding `code`
+    pub fn count(&self, code: Iupac) -> usize {
+        let ambiguous = |code: Iupac| -> usize {
+            self.runs
+                .iter()
+                .filter(|run| run.code == code)
+                .map(|run| run.len)
+                .sum()
+        };
+        match code {
+            // Ambiguous positions are stored as placeholder A in the packed sequence.
+            Iupac::A => self.packed.a_count - self.runs.iter().map(|run| run.len).sum::<usize>(),
+            Iupac::C => self.packed.c_count,
+            Iupac::G => self.packed.g_count,
+            Iupac::T => self.packed.t_count,
+            _ => ambiguous(code),
+        }
+    }
+
+    /// The 2-bit packed sequence, with every ambiguous position stored as `A`
+    pub fn packed(&self) -> &PackedDna {
+        &self.packed
+    }
+
+    /// Convert to a plain [`PackedDna`], handling ambiguous positions according to `policy`
+    pub fn to_packed(&self, policy: AmbiguityPolicy) -> Result<PackedDna, AmbiguousNucError> {
+        match policy {
+            _ if self.runs.is_empty() => Ok(self.packed.clone()),
+            AmbiguityPolicy::Error => {
+                let run = self.runs[0];
+                Err(AmbiguousNucError {
+                    pos: run.start,
+                    code: run.code,
+                })
+            }
+            AmbiguityPolicy::Skip => {
+                let mut resolved = PackedDna::empty();
+                let mut cursor = 0;
+                for run in &self.runs {
+                    resolved.append(&self.packed.to_slice(cursor..run.start));
+                    cursor = run.start + run.len;
+                }
+                resolved.append(&self.packed.to_slice(cursor..self.len()));
+                Ok(resolved)
+            }
+            AmbiguityPolicy::Replace(nuc) => {
+                let mut resolved = self.packed.clone();
+                for run in &self.runs {
+                    for idx in run.start..run.start + run.len {
+                        // Positions are in bounds by construction.
+                        let _ = resolved.set(idx, nuc);
+                    }
+                }
+                Ok(resolved)
+            }
+        }
+    }
+}
+
+impl Default for IupacDna {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl From<PackedDna> for IupacDna {
+    fn from(packed: PackedDna) -> Self {
+        IupacDna {
+            packed,
+            runs: Vec::new(),
+        }
+    }
+}
+
+impl FromStr for IupacDna {
+    type Err = DnaError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut dna = IupacDna::new();
+        for (position, character) in s.chars().enumerate() {
+            let code = Iupac::try_from(character).map_err(|_| DnaError::InvalidNuc {
+                character,
+                position,
+            })?;
+            dna.push(code);
+        }
+        Ok(dna)
+    }
+}
+
+impl Display for IupacDna {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let text: String = self.iter().map(Iupac::to_char).collect();
+        f.write_str(&text)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn try_from_iupac_lowercase() {
+        assert_eq!(Iupac::try_from('n').unwrap(), Iupac::N);
+        assert_eq!(Iupac::try_from('r').unwrap(), Iupac::R);
+    }
+
+    #[test]
+    fn try_from_iupac_negative() {
+        let err = Iupac::try_from('X').unwrap_err();
+        assert_eq!("failed to parse nucleotide from X", err.to_string());
+    }
+
+    #[test]
+    fn iupac_nucs() {
+        assert_eq!(Iupac::Y.nucs(), &[Nuc::C, Nuc::T]);
+        assert!(Iupac::N.matches(Nuc::G));
+        assert!(!Iupac::B.matches(Nuc::A));
+        assert!(!Iupac::C.is_ambiguous());
+    }
+
+    #[test]
+    fn iupac_dna_runs() {
+        let dna = IupacDna::from_str("ACNNNGTRNa").unwrap();
+        assert_eq!(dna.len(), 10);
+        assert_eq!(
+            dna.ambiguous_runs(),
+            &[
+                AmbiguousRun {
+                    start: 2,
+                    len: 3,
+                    code: Iupac::N
+                },
+                AmbiguousRun {
+                    start: 7,
+                    len: 1,
+                    code: Iupac::R
+                },
+                AmbiguousRun {
+                    start: 8,
+                    len: 1,
+                    code: Iupac::N
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn iupac_dna_unambiguous_slices() {
+        let dna = IupacDna::from_str("NACNNNGTRNA").unwrap();
+        let slices: Vec<String> = dna.unambiguous_slices().map(|s| s.to_string()).collect();
+        assert_eq!(slices, ["AC", "GT", "A"]);
+        assert_eq!(dna.to_string(), "NACNNNGTRNA");
+        let clean = IupacDna::from_str("ACGT").unwrap();
+        assert_eq!(clean.unambiguous_slices().count(), 1);
+        assert_eq!(IupacDna::new().unambiguous_slices().count(), 0);
+    }
+
+    #[test]
+    fn iupac_dna_get_and_iter() {
+        let dna = IupacDna::from_str("ACNNNGTRNA").unwrap();
+        assert_eq!(dna.get(1), Some(Iupac::C));
+        assert_eq!(dna.get(4), Some(Iupac::N));
+        assert_eq!(dna.get(5), Some(Iupac::G));
+        assert_eq!(dna.get(10), None);
+        let text: String = dna.iter().map(Iupac::to_char).collect();
+        assert_eq!(text, "ACNNNGTRNA");
+    }
+
+    #[test]
+    fn iupac_dna_counts() {
+        let dna = IupacDna::from_str("ACNNNGTRNA").unwrap();
+        assert_eq!(dna.count(Iupac::A), 2);
+        assert_eq!(dna.count(Iupac::N), 4);
+        assert_eq!(dna.count(Iupac::R), 1);
+        assert_eq!(dna.count(Iupac::T), 1);
+    }
+
+    #[test]
+    fn to_packed_error() {
+        let dna = IupacDna::from_str("ACGTNA").unwrap();
+        let err = dna.to_packed(AmbiguityPolicy::Error).unwrap_err();
+        assert_eq!("ambiguous nucleotide N at position 4", err.to_string());
+    }
+
+    #[test]
+    fn to_packed_skip() {
+        let dna = IupacDna::from_str("NACNNGTN").unwrap();
+        let packed = dna.to_packed(AmbiguityPolicy::Skip).unwrap();
+        assert_eq!(packed, PackedDna::from_str("ACGT").unwrap());
+    }
+
+    #[test]
+    fn to_packed_replace() {
+        let dna = IupacDna::from_str("NACNNGTY").unwrap();
+        let packed = dna.to_packed(AmbiguityPolicy::Replace(Nuc::C)).unwrap();
+        assert_eq!(packed, PackedDna::from_str("CACCCGTC").unwrap());
+        assert_eq!(packed.get_counts()[1], ('C', 5));
+    }
+
+    #[test]
+    fn to_packed_unambiguous() {
+        let dna = IupacDna::from_str("acgt").unwrap();
+        assert!(dna.is_unambiguous());
+        let packed = dna.to_packed(AmbiguityPolicy::Error).unwrap();
+        assert_eq!(packed, PackedDna::from_str("ACGT").unwrap());
+    }
+}
@@ -0,0 +1,571 @@
+//! Counting of k-mers over packed sequences.
+//!
+//! K-mers of up to 32 nucleotides are packed into a `u64` with the same 2-bit encoding as
+//! [`PackedDna`]: A = `00`, C = `01`, G = `10`, T = `11`, first nucleotide in the most
+//! significant used bits. [`Kmer`] wraps a single packed k-mer of a length fixed at compile
+//! time, in a `u64` or, for up to 64 nucleotides, a `u128`.
+
+use crate::{nuc_from_code, Nuc, PackedDna};
+use std::{
+    collections::{hash_map, HashMap},
+    convert::TryFrom,
+    fmt,
+    hash::Hash,
+    ops::{BitAnd, BitOr, Not, Shl, Shr},
+};
+
+/// Largest supported k-mer length
+pub const MAX_K: usize = 32;
+
+/// Counts every k-mer of a fixed length in a sequence
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct KmerCounter {
+    k: usize,
+    canonical: bool,
+}
+
+impl KmerCounter {
+    /// Create a counter for k-mers of length `k`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `k` is zero or greater than [`MAX_K`].
+    pub fn new(k: usize) -> Self {
+        assert!(
+            (1..=MAX_K).contains(&k),
+            "k-mer length must be between 1 and {}, got {}",
+            MAX_K,
+            k
+        );
+        KmerCounter {
+            k,
+            canonical: false,
+        }
+    }
+
+    /// Count each k-mer together with its reverse complement, keyed by the smaller of the two
+    pub fn canonical(mut self, canonical: bool) -> Self {
+        self.canonical = canonical;
+        self
+    }
+
+    /// The k-mer length
+    pub fn k(&self) -> usize {
+        self.k
+    }
+
+    /// Whether k-mers are counted together with their reverse complements
+    pub fn is_canonical(&self) -> bool {
+        self.canonical
+    }
+
+    /// Count all k-mers of `dna`
+    pub fn count(&self, dna: &PackedDna) -> KmerCounts {
+        let mut counts = HashMap::new();
+        for kmer in Kmers::new(dna, self.k, self.canonical) {
+            *counts.entry(kmer).or_insert(0) += 1;
+        }
+        KmerCounts { k: self.k, counts }
+    }
+
+    /// Count all k-mers of `dna` like [`count`](Self::count), over chunks counted in parallel
+    #[cfg(feature = "rayon")]
+    pub fn par_count(&self, dna: &PackedDna) -> KmerCounts {
+        self.par_count_chunked(dna, crate::PAR_CHUNK_LEN)
+    }
+
+    #[cfg(feature = "rayon")]
+    fn par_count_chunked(&self, dna: &PackedDna, chunk_len: usize) -> KmerCounts {
+        use rayon::prelude::*;
+        let len = dna.len();
+        let starts: Vec<usize> = (0..len).step_by(chunk_len).collect();
+        let counts = starts
+            .into_par_iter()
+            .map(|start| {
+                // Chunks overlap by k - 1 so each k-mer is counted in the chunk it starts in.
+                let end = (start + chunk_len + self.k - 1).min(len);
+                self.count(&dna.to_slice(start..end)).counts
+            })
+            .reduce(HashMap::new, |mut merged, counts| {
+                for (kmer, count) in counts {
+                    *merged.entry(kmer).or_insert(0) += count;
+                }
+                merged
+            });
+        KmerCounts { k: self.k, counts }
+    }
+}
+
+/// Rolling iterator over the packed k-mers of a sequence
+pub(crate) struct Kmers<'a> {
+    nucs: crate::Iter<'a>,
+    k: usize,
+    canonical: bool,
+    mask: u64,
+    forward: u64,
+    reverse: u64,
+    filled: usize,
+}
+
+impl<'a> Kmers<'a> {
+    pub(crate) fn new(dna: &'a PackedDna, k: usize, canonical: bool) -> Self {
+        Kmers {
+            nucs: dna.iter(),
+            k,
+            canonical,
+            mask: mask(k),
+            forward: 0,
+            reverse: 0,
+            filled: 0,
+        }
+    }
+}
+
+impl Iterator for Kmers<'_> {
+    type Item = u64;
+
+    fn next(&mut self) -> Option<u64> {
+        loop {
+            let code = self.nucs.next()? as u64;
+            self.forward = (self.forward << 2 | code) & self.mask;
+            self.reverse = self.reverse >> 2 | (3 - code) << (2 * (self.k - 1));
+            self.filled += 1;
+            if self.filled >= self.k {
+                return Some(if self.canonical {
+                    self.forward.min(self.reverse)
+                } else {
+                    self.forward
+                });
+            }
+        }
+    }
+}
+
+fn mask(k: usize) -> u64 {
+    if k == MAX_K {
+        u64::MAX
+    } else {
+        (1 << (2 * k)) - 1
+    }
+}
+
+/// The result of [`KmerCounter::count`]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct KmerCounts {
+    k: usize,
+    counts: HashMap<u64, usize>,
+}
+
+impl KmerCounts {
+    /// The k-mer length
+    pub fn k(&self) -> usize {
+        self.k
+    }
+
+    /// Number of distinct k-mers
+    pub fn len(&self) -> usize {
+        self.counts.len()
+    }
+
+    /// Whether no k-mers were counted
+    pub fn is_empty(&self) -> bool {
+        self.counts.is_empty()
+    }
+
+    /// Occurrences of the packed k-mer `kmer`
+    pub fn get(&self, kmer: u64) -> usize {
+        self.counts.get(&kmer).copied().unwrap_or(0)
+    }
+
+    /// Iterate over `(packed_kmer, count)` pairs in unspecified order
+    pub fn iter(&self) -> KmerCountsIter<'_> {
+        KmerCountsIter(self.counts.iter())
+    }
+
+    /// Count one more occurrence of `kmer`
+    pub(crate) fn add(&mut self, kmer: u64) {
+        *self.counts.entry(kmer).or_insert(0) += 1;
+    }
+
+    /// Count one fewer occurrence of `kmer`, forgetting it at zero
+    pub(crate) fn remove(&mut self, kmer: u64) {
+        if let hash_map::Entry::Occupied(mut entry) = self.counts.entry(kmer) {
+            *entry.get_mut() -= 1;
+            if *entry.get() == 0 {
+                entry.remove();
+            }
+        }
+    }
+}
+
+/// Iterator over the `(packed_kmer, count)` pairs of [`KmerCounts`]
+#[derive(Debug, Clone)]
+pub struct KmerCountsIter<'a>(hash_map::Iter<'a, u64, usize>);
+
+impl Iterator for KmerCountsIter<'_> {
+    type Item = (u64, usize);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.0.next().map(|(&kmer, &count)| (kmer, count))
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.0.size_hint()
+    }
+}
+
+impl<'a> IntoIterator for &'a KmerCounts {
+    type Item = (u64, usize);
+    type IntoIter = KmerCountsIter<'a>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
+
+/// An unsigned integer a [`Kmer`] can be packed into: `u64` or `u128`
+pub trait KmerWord:
+    Copy
+    + Eq
+    + Ord
+    + Hash
+    + fmt::Debug
+    + From<u8>
+    + Shl<usize, Output = Self>
+    + Shr<usize, Output = Self>
+    + BitAnd<Output = Self>
+    + BitOr<Output = Self>
+    + Not<Output = Self>
+    + sealed::Sealed
+{
+    /// Number of bits of the integer
+    const BITS: usize;
+
+    /// The low two bits
+    fn low_code(self) -> u8;
+}
+
+mod sealed {
+    pub trait Sealed {}
+    impl Sealed for u64 {}
+    impl Sealed for u128 {}
+}
+
+impl KmerWord for u64 {
+    const BITS: usize = 64;
+
+    fn low_code(self) -> u8 {
+        (self & 0b11) as u8
+    }
+}
+
+impl KmerWord for u128 {
+    const BITS: usize = 128;
+
+    fn low_code(self) -> u8 {
+        (self & 0b11) as u8
+    }
+}
+
+/// A sequence had the wrong length to become a [`Kmer`]
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
+#[error("expected {expected} nucleotides for a k-mer, got {found}")]
+pub struct KmerLengthError {
+    /// The k-mer length
+    pub expected: usize,
+    /// The length of the sequence
+    pub found: usize,
+}
+
+/// A k-mer of exactly `K` nucleotides, packed into a `W`.
+///
+/// `K` can be up to 32 for the default `u64` and up to 64 for `u128`; other lengths fail to
+/// compile. Ordering follows the packed value, which is lexicographic order of the nucleotides.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Kmer<const K: usize, W: KmerWord = u64> {
+    bits: W,
+}
+
+impl<const K: usize, W: KmerWord> Kmer<K, W> {
+    const VALID_K: () = assert!(
+        K >= 1 && 2 * K <= W::BITS,
+        "k-mer length does not fit the integer type"
+    );
+
+    /// The used low `2 * K` bits
+    fn mask() -> W {
+        !W::from(0) >> (W::BITS - 2 * K)
+    }
+
+    /// The k-mer with the packed value `bits`, ignoring bits above the lowest `2 * K`
+    pub fn from_bits(bits: W) -> Self {
+        let () = Self::VALID_K;
+        Kmer {
+            bits: bits & Self::mask(),
+        }
+    }
+
+    /// The k-mer of the nucleotides of `nucs`, which must hold exactly `K` of them
+    pub fn from_slice(nucs: &[Nuc]) -> Result<Self, KmerLengthError> {
+        if nucs.len() != K {
+            return Err(KmerLengthError {
+                expected: K,
+                found: nucs.len(),
+            });
+        }
+        let bits = nucs
+            .iter()
+            .fold(W::from(0), |bits, &nuc| bits << 2 | W::from(nuc as u8));
+        Ok(Kmer::from_bits(bits))
+    }
+
+    /// The packed value, first nucleotide in the most significant used bits
+    pub fn bits(self) -> W {
+        self.bits
+    }
+
+    /// The nucleotide at 0-based position `idx`, or `None` if `idx` is not below `K`
+    pub fn get(self, idx: usize) -> Option<Nuc> {
+        (idx < K).then(|| nuc_from_code((self.bits >> (2 * (K - 1 - idx))).low_code()))
+    }
+
+    /// Iterate over the nucleotides from first to last
+    pub fn iter(self) -> impl Iterator<Item = Nuc> {
+        (0..K).map(move |idx| nuc_from_code((self.bits >> (2 * (K - 1 - idx))).low_code()))
+    }
+
+    /// Append `nuc` and drop the first nucleotide, which is returned
+    pub fn roll(&mut self, nuc: Nuc) -> Nuc {
+        let first = nuc_from_code((self.bits >> (2 * (K - 1))).low_code());
+        self.bits = (self.bits << 2 | W::from(nuc as u8)) & Self::mask();
+        first
+    }
+
+    /// Prepend `nuc` and drop the last nucleotide, which is returned
+    pub fn roll_front(&mut self, nuc: Nuc) -> Nuc {
+        let last = nuc_from_code(self.bits.low_code());
+        self.bits = self.bits >> 2 | W::from(nuc as u8) << (2 * (K - 1));
+        last
+    }
+
+    /// The reverse complement of the k-mer
+    pub fn reverse_complement(self) -> Self {
+        let complement = !self.bits;
+        let bits = (0..K).fold(W::from(0), |rc, i| {
+            rc << 2 | W::from((complement >> (2 * i)).low_code())
+        });
+        Kmer { bits }
+    }
+
+    /// The smaller of the k-mer and its reverse complement, identical for both strands
+    pub fn canonical(self) -> Self {
+        self.min(self.reverse_complement())
+    }
+}
+
+impl<const K: usize, W: KmerWord> Extend<Nuc> for Kmer<K, W> {
+    /// Roll in each nucleotide, keeping the last `K`
+    fn extend<I: IntoIterator<Item = Nuc>>(&mut self, iter: I) {
+        for nuc in iter {
+            self.roll(nuc);
+        }
+    }
+}
+
+impl<const K: usize, W: KmerWord> TryFrom<&PackedDna> for Kmer<K, W> {
+    type Error = KmerLengthError;
+
+    fn try_from(dna: &PackedDna) -> Result<Self, Self::Error> {
+        if dna.len() != K {
+            return Err(KmerLengthError {
+                expected: K,
+                found: dna.len(),
+            });
+        }
+        let bits = dna
+            .iter()
+            .fold(W::from(0), |bits, nuc| bits << 2 | W::from(nuc as u8));
+        Ok(Kmer::from_bits(bits))
+    }
+}
+
+impl<const K: usize, W: KmerWord> From<Kmer<K, W>> for PackedDna {
+    fn from(kmer: Kmer<K, W>) -> Self {
+        kmer.iter().collect()
+    }
+}
+
+impl<const K: usize, W: KmerWord> fmt::Display for Kmer<K, W> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.iter().try_for_each(|nuc| write!(f, "{}", nuc))
+    }
+}
+
+/// Pack a k-mer string into a `u64`, or `None` if it is too long or not made of nucleotides
+pub fn encode(kmer: &str) -> Option<u64> {
+    if kmer.len() > MAX_K {
+        return None;
+    }
+    kmer.chars().try_fold(0u64, |packed, c| {
+        let nuc = Nuc::try_from(c).ok()?;
+        Some(packed << 2 | nuc as u64)
+    })
+}
+
+/// Decode a packed k-mer of length `k` back into a string
+pub fn decode(kmer: u64, k: usize) -> String {
+    (0..k)
+        .rev()
+        .map(|i| match (kmer >> (2 * i)) & 0b11 {
+            0 => 'A',
+            1 => 'C',
+            2 => 'G',
+            _ => 'T',
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::str::FromStr;
+
+    fn kmer<const K: usize, W: KmerWord>(s: &str) -> Kmer<K, W> {
+        Kmer::try_from(&PackedDna::from_str(s).unwrap()).unwrap()
+    }
+
+    #[test]
+    fn kmer_from_slice_and_dna() {
+        let k: Kmer<5> = Kmer::from_slice(&[Nuc::A, Nuc::C, Nuc::G, Nuc::T, Nuc::T]).unwrap();
+        assert_eq!(k.bits(), encode("ACGTT").unwrap());
+        assert_eq!(k, kmer("ACGTT"));
+        assert_eq!(k.to_string(), "ACGTT");
+        assert_eq!(k.get(1), Some(Nuc::C));
+        assert_eq!(k.get(5), None);
+        assert_eq!(PackedDna::from(k).to_string(), "ACGTT");
+        assert_eq!(
+            Kmer::<4>::from_slice(&[Nuc::A]),
+            Err(KmerLengthError {
+                expected: 4,
+                found: 1
+            })
+        );
+        let long = PackedDna::from_str("ACGTT").unwrap();
+        assert!(Kmer::<4>::try_from(&long).is_err());
+    }
+
+    #[test]
+    fn kmer_roll() {
+        let mut k: Kmer<4> = kmer("ACGT");
+        assert_eq!(k.roll(Nuc::G), Nuc::A);
+        assert_eq!(k.to_string(), "CGTG");
+        assert_eq!(k.roll_front(Nuc::T), Nuc::G);
+        assert_eq!(k.to_string(), "TCGT");
+        k.extend("AAC".chars().map(|c| Nuc::try_from(c).unwrap()));
+        assert_eq!(k.to_string(), "TAAC");
+    }
+
+    #[test]
+    fn kmer_reverse_complement() {
+        let k: Kmer<6> = kmer("AACGTG");
+        assert_eq!(k.reverse_complement().to_string(), "CACGTT");
+        assert_eq!(k.canonical(), k);
+        assert_eq!(k.reverse_complement().canonical(), k);
+    }
+
+    #[test]
+    fn kmer_max_lengths() {
+        let text = "ACGTTGCAACGGTTCA".repeat(2);
+        let k: Kmer<32> = kmer(&text);
+        assert_eq!(k.to_string(), text);
+        assert_eq!(
+            k.reverse_complement().to_string(),
+            PackedDna::from_str(&text)
+                .unwrap()
+                .reverse_complement()
+                .to_string()
+        );
+        let text = text.repeat(2);
+        let mut k: Kmer<64, u128> = kmer(&text);
+        assert_eq!(k.to_string(), text);
+        assert_eq!(k.roll(Nuc::T), Nuc::A);
+        assert_eq!(k.to_string(), format!("{}T", &text[1..]));
+        assert_eq!(
+            Kmer::<64, u128>::from_bits(u128::MAX)
+                .reverse_complement()
+                .bits(),
+            0
+        );
+    }
+
+    #[test]
+    fn encode_decode_round_trip() {
+        let kmer = encode("ACGTT").unwrap();
+        assert_eq!(kmer, 0b00_01_10_11_11);
+        assert_eq!(decode(kmer, 5), "ACGTT");
+    }
+
+    #[test]
+    fn encode_negative() {
+        assert_eq!(encode("ACNT"), None);
+        assert_eq!(encode(&"A".repeat(33)), None);
+    }
+
+    #[test]
+    fn count_kmers_forward() {
+        let dna = PackedDna::from_str("ACGTACG").unwrap();
+        let counts = KmerCounter::new(3).count(&dna);
+        assert_eq!(counts.len(), 4);
+        assert_eq!(counts.get(encode("ACG").unwrap()), 2);
+        assert_eq!(counts.get(encode("CGT").unwrap()), 1);
+        assert_eq!(counts.get(encode("TAC").unwrap()), 1);
+        assert_eq!(counts.get(encode("GGG").unwrap()), 0);
+        assert_eq!(counts.iter().map(|(_, count)| count).sum::<usize>(), 5);
+    }
+
+    #[test]
+    fn count_kmers_canonical() {
+        let dna = PackedDna::from_str("AAACCTTT").unwrap();
+        let counts = KmerCounter::new(3).canonical(true).count(&dna);
+        assert_eq!(counts.get(encode("AAA").unwrap()), 2);
+        assert_eq!(counts.get(encode("TTT").unwrap()), 0);
+        assert_eq!(counts.get(encode("AAC").unwrap()), 1);
+        assert_eq!(counts.get(encode("AGG").unwrap()), 1);
+    }
+
+    #[test]
+    fn count_kmers_max_k() {
+        let dna = PackedDna::from_str(&"ACGT".repeat(9)).unwrap();
+        let counts = KmerCounter::new(32).count(&dna);
+        assert_eq!(counts.len(), 4);
+        assert_eq!(counts.get(encode(&"ACGT".repeat(8)).unwrap()), 2);
+    }
+
+    #[test]
+    fn count_kmers_shorter_than_k() {
+        let dna = PackedDna::from_str("ACG").unwrap();
+        assert!(KmerCounter::new(4).count(&dna).is_empty());
+    }
+
+    #[cfg(feature = "rayon")]
+    #[test]
+    fn par_count_matches_serial() {
+        let dna = PackedDna::from_str(&"AACGTTTGCAGGATTACA".repeat(7)).unwrap();
+        for (k, canonical) in [(1, false), (3, false), (3, true), (8, true)] {
+            let counter = KmerCounter::new(k).canonical(canonical);
+            for chunk_len in [1, 5, 16, 1000] {
+                assert_eq!(
+                    counter.par_count_chunked(&dna, chunk_len),
+                    counter.count(&dna)
+                );
+            }
+            assert_eq!(counter.par_count(&dna), counter.count(&dna));
+        }
+    }
+
+    #[test]
+    #[should_panic(expected = "k-mer length must be between 1 and 32, got 33")]
+    fn counter_rejects_large_k() {
+        KmerCounter::new(33);
+    }
+}
@@ -0,0 +1,353 @@
+//! Named per-position annotation layers over a sequence.
+//!
+//! Several parts of a pipeline often need to tag positions of the same sequence: masked
+//! repeats, variant sites, primer binding sites. [`AnnotatedDna`] holds any number of such
+//! layers by name, each a [`Bitmap`] with one bit per position, so they can be set and queried
+//! independently and combined with set operations.
+
+use crate::{softmask::SoftMaskedDna, PackedDna};
+use std::{collections::BTreeMap, ops::Range};
+
+/// A fixed number of bits, one per position of a sequence
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Bitmap {
+    len: usize,
+    /// Bit `i % 64` of word `i / 64` is position `i`; bits past `len` are clear
+    words: Vec<u64>,
+}
+
+impl Bitmap {
+    /// A bitmap of `len` clear bits
+    pub fn new(len: usize) -> Self {
+        Bitmap {
+            len,
+            words: vec![0; len.div_ceil(64)],
+        }
+    }
+
+    /// A bitmap of `len` bits, set within each of the 0-based `ranges`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if a range is decreasing or extends past `len`.
+    pub fn from_ranges(len: usize, ranges: impl IntoIterator<Item = Range<usize>>) -> Self {
+        let mut bitmap = Bitmap::new(len);
+        for range in ranges {
+            bitmap.set(range, true);
+        }
+        bitmap
+    }
+
+    /// Number of bits
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    /// Whether the bitmap has no bits
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Whether the bit at the 0-based position `idx` is set, or `None` if it is out of bounds
+    pub fn get(&self, idx: usize) -> Option<bool> {
+        (idx < self.len).then(|| self.words[idx / 64] >> (idx % 64) & 1 == 1)
+    }
+
+    /// Set or clear every bit in the 0-based `range`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the range is decreasing or extends past the end of the bitmap.
+    pub fn set(&mut self, range: Range<usize>, value: bool) {
+        assert!(
+            range.start <= range.end && range.end <= self.len,
+            "bitmap range {:?} out of bounds for length {}",
+            range,
+            self.len
+        );
+        let mut idx = range.start;
+        while idx < range.end {
+            let bit = idx % 64;
+            let bits = (64 - bit).min(range.end - idx);
+            let mask = (u64::MAX >> (64 - bits)) << bit;
+            if value {
+                self.words[idx / 64] |= mask;
+            } else {
+                self.words[idx / 64] &= !mask;
+            }
+            idx += bits;
+        }
+    }
+
+    /// Number of set bits
+    pub fn count_ones(&self) -> usize {
+        self.words
+            .iter()
+            .map(|word| word.count_ones() as usize)
+            .sum()
+    }
+
+    /// The positions of the set bits, ascending
+    pub fn ones(&self) -> impl Iterator<Item = usize> + '_ {
+        self.words.iter().enumerate().flat_map(|(idx, &word)| {
+            let mut rest = word;
+            std::iter::from_fn(move || {
+                (rest != 0).then(|| {
+                    let bit = rest.trailing_zeros() as usize;
+                    rest &= rest - 1;
+                    idx * 64 + bit
+                })
+            })
+        })
+    }
+
+    /// Maximal runs of set bits, in order
+    pub fn ranges(&self) -> Vec<Range<usize>> {
+        let mut ranges: Vec<Range<usize>> = Vec::new();
+        for idx in self.ones() {
+            match ranges.last_mut() {
+                Some(last) if last.end == idx => last.end += 1,
+                _ => ranges.push(idx..idx + 1),
+            }
+        }
+        ranges
+    }
+
+    /// The bits set in either bitmap.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the bitmaps differ in length, as do the other set operations.
+    pub fn union(&self, other: &Bitmap) -> Bitmap {
+        self.zip(other, |a, b| a | b)
+    }
+
+    /// The bits set in both bitmaps
+    pub fn intersection(&self, other: &Bitmap) -> Bitmap {
+        self.zip(other, |a, b| a & b)
+    }
+
+    /// The bits set in this bitmap but not in `other`
+    pub fn difference(&self, other: &Bitmap) -> Bitmap {
+        self.zip(other, |a, b| a & !b)
+    }
+
+    fn zip(&self, other: &Bitmap, op: impl Fn(u64, u64) -> u64) -> Bitmap {
+        assert_eq!(
+            self.len, other.len,
+            "bitmaps of lengths {} and {} cannot be combined",
+            self.len, other.len
+        );
+        Bitmap {
+            len: self.len,
+            words: self
+                .words
+                .iter()
+                .zip(&other.words)
+                .map(|(&a, &b)| op(a, b))
+                .collect(),
+        }
+    }
+}
+
+/// A sequence with named per-position annotation layers
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct AnnotatedDna {
+    dna: PackedDna,
+    layers: BTreeMap<String, Bitmap>,
+}
+
+impl AnnotatedDna {
+    /// Wrap `dna` without any layers
+    pub fn new(dna: PackedDna) -> Self {
+        AnnotatedDna {
+            dna,
+            layers: BTreeMap::new(),
+        }
+    }
+
+    /// The nucleotides
+    pub fn dna(&self) -> &PackedDna {
+        &self.dna
+    }
+
+    /// Drop the layers and keep the nucleotides
+    pub fn into_dna(self) -> PackedDna {
+        self.dna
+    }
+
+    /// Number of nucleotides, and of bits in each layer
+    pub fn len(&self) -> usize {
+        self.dna.len()
+    }
+
+    /// Whether the sequence holds no nucleotides
+    pub fn is_empty(&self) -> bool {
+        self.dna.is_empty()
+    }
+
+    /// The names of the layers, in order
+    pub fn layer_names(&self) -> impl Iterator<Item = &str> {
+        self.layers.keys().map(String::as_str)
+    }
+
+    /// The layer called `name`, or `None` if there is none
+    pub fn layer(&self, name: &str) -> Option<&Bitmap> {
+        self.layers.get(name)
+    }
+
+    /// The layer called `name`, added with no bit set if there is none
+    pub fn layer_mut(&mut self, name: &str) -> &mut Bitmap {
+        let len = self.len();
+        self.layers
+            .entry(name.to_string())
+            .or_insert_with(|| Bitmap::new(len))
+    }
+
+    /// Add `layer` as `name`, returning the layer it replaces, if any.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the layer differs in length from the sequence.
+    pub fn insert_layer(&mut self, name: impl Into<String>, layer: Bitmap) -> Option<Bitmap> {
+        assert_eq!(
+            layer.len(),
+            self.len(),
+            "layer of length {} for DNA of length {}",
+            layer.len(),
+            self.len()
+        );
+        self.layers.insert(name.into(), layer)
+    }
+
+    /// Remove the layer called `name`, returning it if there was one
+    pub fn remove_layer(&mut self, name: &str) -> Option<Bitmap> {
+        self.layers.remove(name)
+    }
+
+    /// Tag or untag every position in the 0-based `range` in the layer called `name`, adding the
+    /// layer if needed.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the range is decreasing or extends past the end of the sequence.
+    pub fn set(&mut self, name: &str, range: Range<usize>, value: bool) {
+        self.layer_mut(name).set(range, value)
+    }
+
+    /// Whether the 0-based position `idx` is tagged in the layer called `name`, which is never
+    /// the case for a missing layer, or `None` if it is out of bounds
+    pub fn is_set(&self, name: &str, idx: usize) -> Option<bool> {
+        match self.layers.get(name) {
+            Some(layer) => layer.get(idx),
+            None => (idx < self.len()).then_some(false),
+        }
+    }
+
+    /// The names of the layers tagging the 0-based position `idx`, in order
+    pub fn layers_at(&self, idx: usize) -> Vec<&str> {
+        self.layers
+            .iter()
+            .filter(|(_, layer)| layer.get(idx) == Some(true))
+            .map(|(name, _)| name.as_str())
+            .collect()
+    }
+
+    /// Add the tags of every layer of `other`, e.g. made by another part of a pipeline, to the
+    /// layer of the same name.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the sequences differ in length.
+    pub fn merge(&mut self, other: &AnnotatedDna) {
+        assert_eq!(
+            self.len(),
+            other.len(),
+            "cannot merge layers of DNA of length {} into DNA of length {}",
+            other.len(),
+            self.len()
+        );
+        for (name, layer) in &other.layers {
+            let merged = self.layer_mut(name).union(layer);
+            self.layers.insert(name.clone(), merged);
+        }
+    }
+}
+
+impl From<PackedDna> for AnnotatedDna {
+    fn from(dna: PackedDna) -> Self {
+        AnnotatedDna::new(dna)
+    }
+}
+
+/// Keeps the soft-masked positions as a layer called `masked`
+impl From<SoftMaskedDna> for AnnotatedDna {
+    fn from(masked: SoftMaskedDna) -> Self {
+        let layer = Bitmap::from_ranges(masked.len(), masked.masked_ranges());
+        let mut annotated = AnnotatedDna::new(masked.into_dna());
+        annotated.insert_layer("masked", layer);
+        annotated
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::str::FromStr;
+
+    #[test]
+    fn bitmap_ranges_and_set_operations() {
+        let mut bitmap = Bitmap::from_ranges(150, [3..70, 64..64, 140..150]);
+        assert_eq!(bitmap.ranges(), [3..70, 140..150]);
+        assert_eq!(bitmap.count_ones(), 77);
+        bitmap.set(10..128, false);
+        assert_eq!(bitmap.ranges(), [3..10, 140..150]);
+        assert_eq!(
+            (bitmap.get(9), bitmap.get(10), bitmap.get(150)),
+            (Some(true), Some(false), None)
+        );
+
+        let other = Bitmap::from_ranges(150, [0..5, 145..146]);
+        assert_eq!(bitmap.union(&other).ranges(), [0..10, 140..150]);
+        assert_eq!(bitmap.intersection(&other).ranges(), [3..5, 145..146]);
+        assert_eq!(
+            bitmap.difference(&other).ranges(),
+            [5..10, 140..145, 146..150]
+        );
+        assert!(Bitmap::new(0).ranges().is_empty());
+    }
+
+    #[test]
+    fn layers_are_tagged_and_merged() {
+        let masked = SoftMaskedDna::from_str("ACgtACGTac").unwrap();
+        let mut annotated = AnnotatedDna::from(masked);
+        annotated.set("primer-site", 1..4, true);
+        assert_eq!(
+            annotated.layer_names().collect::<Vec<_>>(),
+            ["masked", "primer-site"]
+        );
+        assert_eq!(annotated.layers_at(2), ["masked", "primer-site"]);
+        assert_eq!(annotated.layers_at(5), Vec::<&str>::new());
+        assert_eq!(annotated.is_set("variant", 5), Some(false));
+        assert_eq!(annotated.is_set("variant", 10), None);
+
+        let mut variants = AnnotatedDna::new(annotated.dna().clone());
+        variants.set("variant", 5..6, true);
+        variants.set("masked", 5..7, true);
+        annotated.merge(&variants);
+        assert_eq!(
+            annotated.layer("masked").unwrap().ranges(),
+            [2..4, 5..7, 8..10]
+        );
+        assert_eq!(annotated.layers_at(5), ["masked", "variant"]);
+
+        let both = annotated
+            .layer("masked")
+            .unwrap()
+            .intersection(annotated.layer("primer-site").unwrap());
+        assert_eq!(annotated.insert_layer("masked-primer", both), None);
+        let removed = annotated.remove_layer("masked-primer").unwrap();
+        assert_eq!((removed.count_ones(), removed.ones().next()), (2, Some(2)));
+        assert_eq!(annotated.into_dna().to_string(), "ACGTACGTAC");
+    }
+}
@@ -2,10 +2,81 @@
 
 #![warn(missing_docs)]
 
-use std::{convert::TryFrom, fmt::Display, iter::FromIterator, str::FromStr};
+use std::{
+    convert::TryFrom,
+    fmt::Display,
+    iter::FromIterator,
+    ops::{Index, Range},
+    str::FromStr,
+};
+
+pub mod align;
+mod aligned;
+#[cfg(feature = "async")]
+pub mod async_io;
+pub mod binning;
+pub mod cache;
+pub mod concordance;
+pub mod detect;
+pub mod diff;
+pub mod digest;
+pub mod distance;
+mod error;
+pub mod external;
+pub mod extract;
+pub mod fasta;
+pub mod fastq;
+#[cfg(feature = "fm-index")]
+pub mod fm_index;
+pub mod genome;
+pub mod gff;
+pub mod index;
+#[cfg(any(
+    feature = "bio-types",
+    feature = "noodles-fasta",
+    feature = "noodles-fastq"
+))]
+pub mod interop;
+pub mod iupac;
+pub mod kmer;
+pub mod layers;
+pub mod motif;
+pub mod normalize;
+pub mod protein;
+#[cfg(feature = "python")]
+mod python;
+pub mod redundancy;
+pub mod regex;
+pub mod search;
+pub mod sequence;
+pub mod signature;
+#[cfg(feature = "rand")]
+pub mod simulate;
+pub mod sketch;
+pub mod softmask;
+pub mod stats;
+pub mod storage;
+pub mod strandedness;
+pub mod stream;
+mod striped;
+pub mod summary;
+pub mod translate;
+pub mod twobit;
+pub mod view;
+#[cfg(feature = "wasm")]
+mod wasm;
+mod wavefront;
+
+use aligned::AlignedBuf;
+pub use error::{DnaError, ErrorContext};
+
+/// Nucleotides handled by each task of the `rayon` parallel paths, a multiple of 4
+#[cfg(feature = "rayon")]
+const PAR_CHUNK_LEN: usize = 1 << 20;
 
 /// A nucleotide
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum Nuc {
     /// Adenine
     A,
@@ -36,6 +107,24 @@ impl TryFrom<char> for Nuc {
     }
 }
 
+impl Nuc {
+    /// The uppercase letter of the nucleotide
+    pub fn to_char(self) -> char {
+        match self {
+            Self::A => 'A',
+            Self::C => 'C',
+            Self::G => 'G',
+            Self::T => 'T',
+        }
+    }
+}
+
+impl Display for Nuc {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.to_char())
+    }
+}
+
 impl FromStr for Nuc {
     type Err = ParseNucError<String>;
 
@@ -51,10 +140,65 @@ impl FromStr for Nuc {
     }
 }
 
+/// Decode the nucleotide held in the low two bits of `code`
+fn nuc_from_code(code: u8) -> Nuc {
+    match code & 0b11 {
+        0 => Nuc::A,
+        1 => Nuc::C,
+        2 => Nuc::G,
+        _ => Nuc::T,
+    }
+}
+
+/// An error that can occur when accessing a nucleotide by position.
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
+pub enum IndexError {
+    /// Position 0 was requested from a deprecated 1-based API
+    #[error("Index 0 is invalid, positions start at 1")]
+    Zero,
+    /// The position is past the end of the sequence
+    #[error("Index {index} is greater than the given DNA Length")]
+    OutOfBounds {
+        /// The requested position
+        index: usize,
+        /// The length of the sequence
+        len: usize,
+    },
+}
+
+impl IndexError {
+    /// A stable code for the kind of error, such as `index.out-of-bounds`, see
+    /// [`DnaError::code`]
+    pub fn code(&self) -> &'static str {
+        match self {
+            IndexError::Zero => "index.zero",
+            IndexError::OutOfBounds { .. } => "index.out-of-bounds",
+        }
+    }
+}
+
+/// Reverse-complement of each possible packed byte: the four 2-bit codes swapped end for end
+/// and inverted
+const REVCOMP_BYTE: [u8; 256] = {
+    let mut table = [0u8; 256];
+    let mut byte = 0;
+    while byte < 256 {
+        let b = byte as u8;
+        let reversed = (b >> 6) | ((b >> 2) & 0b1100) | ((b << 2) & 0b11_0000) | (b << 6);
+        table[byte] = !reversed;
+        byte += 1;
+    }
+    table
+};
+
 /// PackedDNA
-#[derive(Debug)]
+///
+/// A sequence of `n` nucleotides holds exactly `ceil(n / 4)` packed bytes, so an empty sequence
+/// holds none. Empty sequences are valid everywhere: they have zero counts, iterate nothing and
+/// reject every position.
+#[derive(Debug, Clone)]
 pub struct PackedDna {
-    packed_dna: Vec<u8>,
+    packed_dna: AlignedBuf,
     last_nuc_set_count: usize,
     a_count: usize,
     c_count: usize,
@@ -64,63 +208,582 @@ pub struct PackedDna {
 
 /// Implementation for PackedDNA
 impl PackedDna {
-    /// Function to get nucleotide at a given index
-    pub fn get(&self, idx: usize) -> Result<Nuc, String> {
-        let vec_index = (idx - 1) / 4;
-        let bit_index = (idx - 1) % 4;
-        if (vec_index >= self.packed_dna.len())
-            || (vec_index >= self.packed_dna.len() - 1 && bit_index >= self.last_nuc_set_count)
-        {
-            let error = format!("Index {} is greater than the given DNA Length", idx);
-            return Err(error);
-        }
-        let mut binary_rep = format!("{:08b}", self.packed_dna[vec_index]);
-        if (vec_index == self.packed_dna.len() - 1) && (self.last_nuc_set_count != 0) {
-            binary_rep = binary_rep
-                .chars()
-                .rev()
-                .take(self.last_nuc_set_count * 2)
-                .collect();
-            binary_rep = binary_rep.chars().rev().collect();
-        }
-        let char_vec: Vec<char> = binary_rep.chars().collect();
-        let str_rep = format!("{}{}", char_vec[bit_index * 2], char_vec[bit_index * 2 + 1]);
-        match str_rep.as_str() {
-            "00" => Ok(Nuc::A),
-            "01" => Ok(Nuc::C),
-            "10" => Ok(Nuc::G),
-            "11" => Ok(Nuc::T),
-            _ => Err("Invalid String Encountered".to_string()),
+    /// Get the nucleotide at the 0-based position `idx`, or `None` if it is out of bounds
+    pub fn get(&self, idx: usize) -> Option<Nuc> {
+        (idx < self.len()).then(|| self.nuc_at(idx))
+    }
+
+    /// Get the nucleotide at the 1-based position `idx`
+    #[deprecated(note = "positions are now 0-based, use `get` instead")]
+    pub fn get_1based(&self, idx: usize) -> Result<Nuc, IndexError> {
+        let len = self.len();
+        match idx {
+            0 => Err(IndexError::Zero),
+            _ if idx > len => Err(IndexError::OutOfBounds { index: idx, len }),
+            _ => Ok(self.nuc_at(idx - 1)),
         }
     }
 
+    /// Number of nucleotides in the sequence
+    pub fn len(&self) -> usize {
+        self.a_count + self.c_count + self.g_count + self.t_count
+    }
+
+    /// Whether the sequence holds no nucleotides
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
     /// Get the counts of individual nucleotides
     pub fn get_counts(&self) -> Vec<(char, usize)> {
-        return vec![
+        vec![
             ('A', self.a_count),
             ('C', self.c_count),
             ('G', self.g_count),
             ('T', self.t_count),
-        ];
+        ]
+    }
+
+    /// Visit the sequence as consecutive 2-bit packed words of up to 32 nucleotides.
+    ///
+    /// `f` is called with each word and the number of nucleotides it holds. Nucleotides are
+    /// encoded as A = `00`, C = `01`, G = `10`, T = `11` and occupy the low `2 * n_bases` bits of
+    /// the word, with the first nucleotide in the most significant of those bits. Every word but
+    /// the last holds exactly 32 nucleotides.
+    pub fn for_each_word<F: FnMut(u64, usize)>(&self, mut f: F) {
+        let len = self.len();
+        for (chunk_idx, chunk) in self.used_bytes().chunks(8).enumerate() {
+            let n_bases = (len - chunk_idx * 32).min(32);
+            let mut word = 0u64;
+            for (byte_idx, &byte) in chunk.iter().enumerate() {
+                let bases_in_byte = (n_bases - byte_idx * 4).min(4);
+                word = word << (2 * bases_in_byte) | u64::from(byte);
+            }
+            f(word, n_bases);
+        }
+    }
+
+    /// Pack the nucleotides of a fallible iterator, stopping at the first error
+    pub fn try_from_iter<E, I>(iter: I) -> Result<Self, E>
+    where
+        I: IntoIterator<Item = Result<Nuc, E>>,
+    {
+        let mut dna = PackedDna::empty();
+        for nuc in iter {
+            dna.push(nuc?);
+        }
+        Ok(dna)
+    }
+
+    /// Pack a stream of characters, failing on the first one that is not a nucleotide.
+    ///
+    /// This makes it easy to build a sequence from filtered text in one pass, e.g.
+    /// `PackedDna::try_collect_chars(text.chars().filter(|c| !c.is_whitespace()))`. The position
+    /// of an invalid character counts the characters yielded by the iterator.
+    pub fn try_collect_chars<I>(iter: I) -> Result<Self, DnaError>
+    where
+        I: IntoIterator<Item = char>,
+    {
+        PackedDna::try_from_iter(iter.into_iter().enumerate().map(|(position, character)| {
+            Nuc::try_from(character).map_err(|_| DnaError::InvalidNuc {
+                character,
+                position,
+            })
+        }))
+    }
+
+    /// The reverse complement of the sequence
+    pub fn reverse_complement(&self) -> PackedDna {
+        let mut revcomp = self.clone();
+        revcomp.reverse_complement_in_place();
+        revcomp
+    }
+
+    /// Replace the sequence with its reverse complement by reversing its bits, without
+    /// reallocating.
+    ///
+    /// To read the other strand without changing or copying the sequence, see
+    /// [`revcomp_view`](Self::revcomp_view).
+    pub fn reverse_complement_in_place(&mut self) {
+        let len = self.len();
+        if len == 0 {
+            return;
+        }
+        let bytes = &mut self.packed_dna[..len.div_ceil(4)];
+        // Left-align a partial last byte so the bytes form one continuous bit stream.
+        let padding_bits = 2 * ((4 - len % 4) % 4);
+        let last = bytes.len() - 1;
+        bytes[last] <<= padding_bits;
+        bytes.reverse();
+        for byte in bytes.iter_mut() {
+            *byte = REVCOMP_BYTE[usize::from(*byte)];
+        }
+        // The padding now sits at the front of the stream: shift it out and re-align the tail.
+        if padding_bits != 0 {
+            for i in 0..last {
+                bytes[i] = bytes[i] << padding_bits | bytes[i + 1] >> (8 - padding_bits);
+            }
+            bytes[last] = (bytes[last] << padding_bits) >> padding_bits;
+        }
+        std::mem::swap(&mut self.a_count, &mut self.t_count);
+        std::mem::swap(&mut self.c_count, &mut self.g_count);
+    }
+
+    /// Replace the sequence with its reverse complement
+    #[deprecated(note = "renamed to `reverse_complement_in_place`")]
+    pub fn reverse_complement_mut(&mut self) {
+        self.reverse_complement_in_place()
+    }
+
+    /// Copy the nucleotides in the 0-based `range` into a new sequence, which
+    /// [`slice`](Self::slice) borrows instead.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the range is decreasing or extends past the end of the sequence.
+    pub fn to_slice(&self, range: Range<usize>) -> PackedDna {
+        let len = self.len();
+        assert!(
+            range.start <= range.end && range.end <= len,
+            "slice range {:?} out of bounds for DNA of length {}",
+            range,
+            len
+        );
+        let slice_len = range.end - range.start;
+        let first_byte = range.start / 4;
+        let offset_bits = 2 * (range.start % 4);
+        let mut bytes = AlignedBuf::with_capacity(slice_len.div_ceil(4));
+        for j in 0..slice_len.div_ceil(4) {
+            let mut byte = self.stream_byte(first_byte + j) << offset_bits;
+            if offset_bits != 0 {
+                byte |= self.stream_byte(first_byte + j + 1) >> (8 - offset_bits);
+            }
+            bytes.push(byte);
+        }
+        // The tail is left-aligned in the stream; restore the right-aligned last byte.
+        let tail = slice_len % 4;
+        if tail != 0 {
+            let last = bytes.len() - 1;
+            bytes[last] >>= 2 * (4 - tail);
+        }
+        PackedDna::from_packed(bytes, slice_len)
+    }
+
+    /// Byte `idx` of the packed bit stream, with a partial last byte left-aligned and zero past
+    /// the end
+    fn stream_byte(&self, idx: usize) -> u8 {
+        let len = self.len();
+        let used_bytes = len.div_ceil(4);
+        if idx >= used_bytes {
+            return 0;
+        }
+        let byte = self.packed_dna[idx];
+        if idx == used_bytes - 1 && self.last_nuc_set_count != 0 {
+            byte << (2 * (4 - self.last_nuc_set_count))
+        } else {
+            byte
+        }
+    }
+
+    /// Build a sequence from packed bytes holding `len` nucleotides, recomputing the counts
+    fn from_packed(packed_dna: AlignedBuf, len: usize) -> PackedDna {
+        #[cfg(not(feature = "rayon"))]
+        let counts = count_packed(&packed_dna, len);
+        #[cfg(feature = "rayon")]
+        let counts = par_count_packed(&packed_dna, len, PAR_CHUNK_LEN / 4);
+        PackedDna {
+            packed_dna,
+            last_nuc_set_count: len % 4,
+            a_count: counts[0],
+            c_count: counts[1],
+            g_count: counts[2],
+            t_count: counts[3],
+        }
+    }
+
+    /// The nucleotides in the 0-based `range` as uppercase text.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the range is decreasing or extends past the end of the sequence.
+    pub fn to_string_range(&self, range: Range<usize>) -> String {
+        let len = self.len();
+        assert!(
+            range.start <= range.end && range.end <= len,
+            "range {:?} out of bounds for DNA of length {}",
+            range,
+            len
+        );
+        range.map(|idx| self.nuc_at(idx).to_char()).collect()
+    }
+
+    /// Iterate over the nucleotides of the sequence
+    pub fn iter(&self) -> Iter<'_> {
+        Iter {
+            dna: self,
+            front: 0,
+            back: self.len(),
+        }
+    }
+
+    /// Decode the nucleotide at the 0-based index `idx`, which must be in bounds
+    fn nuc_at(&self, idx: usize) -> Nuc {
+        let byte_idx = idx / 4;
+        let bases_in_byte = (self.len() - byte_idx * 4).min(4);
+        let shift = 2 * (bases_in_byte - 1 - idx % 4);
+        nuc_from_code(self.packed_dna[byte_idx] >> shift)
+    }
+
+    /// An empty sequence with room for `capacity` nucleotides, so building it up with
+    /// [`push`](Self::push), [`extend_from_str`](Self::extend_from_str) or [`Extend`] does not
+    /// reallocate until it grows past that
+    pub fn with_capacity(capacity: usize) -> Self {
+        let mut dna = PackedDna::empty();
+        dna.packed_dna = AlignedBuf::with_capacity(capacity.div_ceil(4));
+        dna
+    }
+
+    /// Number of nucleotides the sequence can hold without reallocating
+    pub fn capacity(&self) -> usize {
+        self.packed_dna.capacity() * 4
+    }
+
+    /// Make room for at least `additional` more nucleotides
+    pub fn reserve(&mut self, additional: usize) {
+        let bytes = (self.len() + additional).div_ceil(4);
+        self.packed_dna
+            .reserve(bytes.saturating_sub(self.packed_dna.len()));
+    }
+
+    /// An empty sequence, used as the starting point for incremental packing
+    pub(crate) fn empty() -> Self {
+        PackedDna {
+            packed_dna: AlignedBuf::new(),
+            last_nuc_set_count: 0,
+            a_count: 0,
+            c_count: 0,
+            g_count: 0,
+            t_count: 0,
+        }
+    }
+
+    /// The bytes actually holding nucleotides
+    fn used_bytes(&self) -> &[u8] {
+        &self.packed_dna[..self.len().div_ceil(4)]
+    }
+
+    /// The count field tracking `nuc`
+    fn count_mut(&mut self, nuc: Nuc) -> &mut usize {
+        match nuc {
+            Nuc::A => &mut self.a_count,
+            Nuc::C => &mut self.c_count,
+            Nuc::G => &mut self.g_count,
+            Nuc::T => &mut self.t_count,
+        }
+    }
+
+    /// Set the nucleotide at the 0-based position `idx`
+    pub fn set(&mut self, idx: usize, nuc: Nuc) -> Result<(), IndexError> {
+        let len = self.len();
+        let old = self
+            .get(idx)
+            .ok_or(IndexError::OutOfBounds { index: idx, len })?;
+        let byte_idx = idx / 4;
+        let bases_in_byte = (len - byte_idx * 4).min(4);
+        let shift = 2 * (bases_in_byte - 1 - idx % 4);
+        let byte = &mut self.packed_dna[byte_idx];
+        *byte = (*byte & !(0b11 << shift)) | (nuc as u8) << shift;
+        *self.count_mut(old) -= 1;
+        *self.count_mut(nuc) += 1;
+        Ok(())
+    }
+
+    /// Set every position in the 0-based `range` to `nuc`, a whole byte at a time where it can
+    pub(crate) fn fill(&mut self, range: Range<usize>, nuc: Nuc) -> Result<(), IndexError> {
+        let len = self.len();
+        if range.end > len {
+            return Err(IndexError::OutOfBounds {
+                index: range.end - 1,
+                len,
+            });
+        }
+        // Whole bytes within the range; the last byte is right-aligned when partial, so it
+        // never counts as whole.
+        let first = range.start.div_ceil(4);
+        let last = (range.end / 4).max(first);
+        for idx in range.start..(first * 4).min(range.end) {
+            self.set(idx, nuc)?;
+        }
+        let old = count_packed(&self.packed_dna[first..last], (last - first) * 4);
+        for (code, count) in old.iter().enumerate() {
+            *self.count_mut(nuc_from_code(code as u8)) -= count;
+        }
+        *self.count_mut(nuc) += (last - first) * 4;
+        self.packed_dna[first..last].fill(nuc as u8 * 0b0101_0101);
+        for idx in (last * 4).max(range.start)..range.end {
+            self.set(idx, nuc)?;
+        }
+        Ok(())
+    }
+
+    /// Append a nucleotide to the end of the sequence
+    pub fn push(&mut self, nuc: Nuc) {
+        // The partially filled last byte is kept right-aligned, so appending is a shift.
+        let len = self.len();
+        if self.last_nuc_set_count == 0 {
+            self.packed_dna.push(0);
+        }
+        let last = self.packed_dna.len() - 1;
+        self.packed_dna[last] = self.packed_dna[last] << 2 | nuc as u8;
+        *self.count_mut(nuc) += 1;
+        self.last_nuc_set_count = (len + 1) % 4;
+    }
+
+    /// Remove and return the last nucleotide, or `None` if the sequence is empty
+    pub fn pop(&mut self) -> Option<Nuc> {
+        let len = self.len();
+        if len == 0 {
+            return None;
+        }
+        let last = len.div_ceil(4) - 1;
+        let nuc = nuc_from_code(self.packed_dna[last]);
+        if len % 4 == 1 {
+            self.packed_dna.truncate(last);
+        } else {
+            self.packed_dna[last] >>= 2;
+        }
+        *self.count_mut(nuc) -= 1;
+        self.last_nuc_set_count = (len - 1) % 4;
+        Some(nuc)
+    }
+
+    /// Append the nucleotides of `s`, e.g. one chunk of a sequence being streamed in.
+    ///
+    /// If `s` holds a character that is not a nucleotide the sequence is left unchanged, and the
+    /// error gives the position of the character within `s`.
+    pub fn extend_from_str(&mut self, s: &str) -> Result<(), DnaError> {
+        let len = self.len();
+        self.reserve(s.len());
+        for (position, character) in s.chars().enumerate() {
+            match Nuc::try_from(character) {
+                Ok(nuc) => self.push(nuc),
+                Err(_) => {
+                    while self.len() > len {
+                        self.pop();
+                    }
+                    return Err(DnaError::InvalidNuc {
+                        character,
+                        position,
+                    });
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Append all nucleotides of `other` to the end of the sequence
+    pub fn append(&mut self, other: &PackedDna) {
+        if self.last_nuc_set_count != 0 {
+            other.iter().for_each(|nuc| self.push(nuc));
+            return;
+        }
+        // A byte-aligned end lets whole packed bytes be copied over.
+        for &byte in other.used_bytes() {
+            self.packed_dna.push(byte);
+        }
+        self.a_count += other.a_count;
+        self.c_count += other.c_count;
+        self.g_count += other.g_count;
+        self.t_count += other.t_count;
+        self.last_nuc_set_count = other.last_nuc_set_count;
+    }
+}
+
+/// Counts of A, C, G and T in packed bytes holding `len` nucleotides
+fn count_packed(bytes: &[u8], len: usize) -> [usize; 4] {
+    let mut counts = [0usize; 4];
+    for (idx, &byte) in bytes.iter().enumerate() {
+        let bases_in_byte = (len - idx * 4).min(4);
+        for slot in 0..bases_in_byte {
+            counts[usize::from((byte >> (2 * slot)) & 0b11)] += 1;
+        }
+    }
+    counts
+}
+
+/// [`count_packed`] over chunks of `chunk_bytes` bytes in parallel
+#[cfg(feature = "rayon")]
+fn par_count_packed(bytes: &[u8], len: usize, chunk_bytes: usize) -> [usize; 4] {
+    use rayon::prelude::*;
+    bytes
+        .par_chunks(chunk_bytes)
+        .enumerate()
+        .map(|(i, chunk)| count_packed(chunk, (len - i * chunk_bytes * 4).min(chunk_bytes * 4)))
+        .reduce(
+            || [0; 4],
+            |a, b| [a[0] + b[0], a[1] + b[1], a[2] + b[2], a[3] + b[3]],
+        )
+}
+
+#[cfg(feature = "rayon")]
+impl PackedDna {
+    /// Parse like [`from_str`](PackedDna::from_str), packing chunks of the input in parallel
+    pub fn par_from_str(s: &str) -> Result<PackedDna, DnaError> {
+        match PackedDna::par_from_ascii(s.as_bytes(), PAR_CHUNK_LEN) {
+            Some(dna) => Ok(dna),
+            // Locate the first invalid character serially; only the error path pays for it.
+            None => PackedDna::try_collect_chars(s.chars()),
+        }
+    }
+
+    /// Pack ASCII nucleotides in chunks of `chunk_len`, a multiple of 4, or `None` on any other
+    /// byte
+    fn par_from_ascii(bytes: &[u8], chunk_len: usize) -> Option<PackedDna> {
+        use rayon::prelude::*;
+        debug_assert!(chunk_len.is_multiple_of(4));
+        let chunks: Vec<(Vec<u8>, [usize; 4])> = bytes
+            .par_chunks(chunk_len)
+            .map(|chunk| {
+                let mut packed = Vec::with_capacity(chunk.len().div_ceil(4));
+                let mut counts = [0; 4];
+                for group in chunk.chunks(4) {
+                    let mut byte = 0;
+                    for &ascii in group {
+                        let nuc = Nuc::try_from(char::from(ascii)).ok()?;
+                        counts[nuc as usize] += 1;
+                        byte = byte << 2 | nuc as u8;
+                    }
+                    packed.push(byte);
+                }
+                Some((packed, counts))
+            })
+            .collect::<Option<_>>()?;
+        let mut packed_dna = AlignedBuf::with_capacity(bytes.len().div_ceil(4));
+        let mut counts = [0; 4];
+        for (packed, chunk_counts) in chunks {
+            packed.into_iter().for_each(|byte| packed_dna.push(byte));
+            for (count, chunk_count) in counts.iter_mut().zip(chunk_counts) {
+                *count += chunk_count;
+            }
+        }
+        Some(PackedDna {
+            packed_dna,
+            last_nuc_set_count: bytes.len() % 4,
+            a_count: counts[0],
+            c_count: counts[1],
+            g_count: counts[2],
+            t_count: counts[3],
+        })
+    }
+}
+
+/// Iterator over the nucleotides of a [`PackedDna`]
+#[derive(Debug, Clone)]
+pub struct Iter<'a> {
+    dna: &'a PackedDna,
+    front: usize,
+    back: usize,
+}
+
+impl Iterator for Iter<'_> {
+    type Item = Nuc;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.front == self.back {
+            return None;
+        }
+        let nuc = self.dna.nuc_at(self.front);
+        self.front += 1;
+        Some(nuc)
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let remaining = self.back - self.front;
+        (remaining, Some(remaining))
+    }
+}
+
+impl DoubleEndedIterator for Iter<'_> {
+    fn next_back(&mut self) -> Option<Self::Item> {
+        if self.front == self.back {
+            return None;
+        }
+        self.back -= 1;
+        Some(self.dna.nuc_at(self.back))
+    }
+}
+
+impl ExactSizeIterator for Iter<'_> {}
+
+impl PartialEq for PackedDna {
+    fn eq(&self, other: &Self) -> bool {
+        self.len() == other.len() && self.used_bytes() == other.used_bytes()
+    }
+}
+
+impl Eq for PackedDna {}
+
+impl std::hash::Hash for PackedDna {
+    fn hash<H: std::hash::Hasher>(&self, state: &mut H) {
+        self.len().hash(state);
+        self.used_bytes().hash(state);
+    }
+}
+
+/// Writes the sequence as uppercase text, the inverse of [`from_str`](PackedDna::from_str)
+impl Display for PackedDna {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(&self.to_string_range(0..self.len()))
+    }
+}
+
+/// Nucleotides in the order of their 2-bit codes, so indexing can hand out references
+const NUCS: [Nuc; 4] = [Nuc::A, Nuc::C, Nuc::G, Nuc::T];
+
+impl Index<usize> for PackedDna {
+    type Output = Nuc;
+
+    /// The nucleotide at the 0-based position `idx`
+    ///
+    /// # Panics
+    ///
+    /// Panics if `idx` is out of bounds; use [`get`](PackedDna::get) to check instead.
+    fn index(&self, idx: usize) -> &Nuc {
+        let len = self.len();
+        assert!(
+            idx < len,
+            "index {} out of bounds for DNA of length {}",
+            idx,
+            len
+        );
+        &NUCS[self.nuc_at(idx) as usize]
+    }
+}
+
+impl<'a> IntoIterator for &'a PackedDna {
+    type Item = Nuc;
+    type IntoIter = Iter<'a>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
     }
 }
 
 /// FromString implementation for PackedDna
 /// Takes in the string DNA as the input and stores the DNA in efficient way
 impl FromStr for PackedDna {
-    type Err = ParseNucError<String>;
+    type Err = DnaError;
     fn from_str(s: &str) -> Result<Self, Self::Err> {
-        let string_dna = s.to_ascii_uppercase();
-        let extra_nuc = string_dna.len() % 4;
-        let mut vec: Vec<u8> = Vec::new();
+        let extra_nuc = s.len() % 4;
+        let mut vec = AlignedBuf::with_capacity(s.len() / 4 + 1);
         let mut curr = 0;
         let (mut a, mut c, mut g, mut t) = (0, 0, 0, 0);
-        for (i, char) in string_dna.chars().enumerate() {
+        for (i, char) in s.chars().enumerate() {
             if (i != 0) && (i % 4) == 0 {
                 vec.push(curr);
                 curr = 0;
             }
-            match char {
+            match char.to_ascii_uppercase() {
                 'A' => {
                     curr <<= 2;
                     a += 1
@@ -137,10 +800,17 @@ impl FromStr for PackedDna {
                     curr = curr << 2 | 3;
                     t += 1
                 }
-                _ => return Err(ParseNucError(string_dna)),
+                _ => {
+                    return Err(DnaError::InvalidNuc {
+                        character: char,
+                        position: i,
+                    })
+                }
             }
         }
-        vec.push(curr);
+        if !s.is_empty() {
+            vec.push(curr);
+        }
         Ok(PackedDna {
             packed_dna: vec,
             last_nuc_set_count: extra_nuc,
@@ -152,12 +822,81 @@ impl FromStr for PackedDna {
     }
 }
 
+impl TryFrom<&str> for PackedDna {
+    type Error = DnaError;
+
+    fn try_from(value: &str) -> Result<Self, Self::Error> {
+        value.parse()
+    }
+}
+
+/// Options for parsing text into a [`PackedDna`] more leniently than [`FromStr`]
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct ParseOptions {
+    accept_uracil: bool,
+}
+
+impl ParseOptions {
+    /// The options used by [`FromStr`]: A, C, G and T in either case
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Read `U` as `T`, so RNA text parses as the DNA coding strand it was transcribed from
+    pub fn accept_uracil(mut self, accept: bool) -> Self {
+        self.accept_uracil = accept;
+        self
+    }
+
+    /// Parse `s` with these options
+    pub fn parse(&self, s: &str) -> Result<PackedDna, DnaError> {
+        let mut dna = PackedDna::with_capacity(s.len());
+        for (position, character) in s.chars().enumerate() {
+            let nuc = match character {
+                'U' | 'u' if self.accept_uracil => Nuc::T,
+                _ => Nuc::try_from(character).map_err(|_| DnaError::InvalidNuc {
+                    character,
+                    position,
+                })?,
+            };
+            dna.push(nuc);
+        }
+        Ok(dna)
+    }
+}
+
+/// Parses ASCII nucleotide bytes, e.g. a sequence line read straight from a file
+impl TryFrom<&[u8]> for PackedDna {
+    type Error = DnaError;
+
+    fn try_from(value: &[u8]) -> Result<Self, Self::Error> {
+        PackedDna::try_collect_chars(value.iter().map(|&byte| char::from(byte)))
+    }
+}
+
+/// The uppercase ASCII letters of the nucleotides, the inverse of `TryFrom<&[u8]>`
+impl From<&PackedDna> for Vec<u8> {
+    fn from(dna: &PackedDna) -> Self {
+        dna.iter().map(|nuc| nuc.to_char() as u8).collect()
+    }
+}
+
+impl Extend<Nuc> for PackedDna {
+    fn extend<I: IntoIterator<Item = Nuc>>(&mut self, iter: I) {
+        let iter = iter.into_iter();
+        self.reserve(iter.size_hint().0);
+        for nuc in iter {
+            self.push(nuc);
+        }
+    }
+}
+
 /// FromIterator implementation for PackedDna
 /// Takes in the vector of Nuc as the input and stores the DNA in efficient way
 impl FromIterator<Nuc> for PackedDna {
     fn from_iter<I: IntoIterator<Item = Nuc>>(iter: I) -> Self {
         let mut extra_nuc = 0;
-        let mut vec: Vec<u8> = Vec::new();
+        let mut vec = AlignedBuf::new();
         let mut curr = 0;
         let (mut a, mut c, mut g, mut t) = (0, 0, 0, 0);
         for (counter, nuc) in iter.into_iter().enumerate() {
@@ -187,7 +926,9 @@ impl FromIterator<Nuc> for PackedDna {
             extra_nuc += 1;
         }
         extra_nuc %= 4;
-        vec.push(curr);
+        if a + c + g + t != 0 {
+            vec.push(curr);
+        }
         PackedDna {
             packed_dna: vec,
             last_nuc_set_count: extra_nuc,
@@ -199,6 +940,52 @@ impl FromIterator<Nuc> for PackedDna {
     }
 }
 
+/// Fixtures shared by the tests of the crate's modules
+#[cfg(test)]
+mod test_util {
+    use crate::{Nuc, PackedDna};
+    use std::str::FromStr;
+
+    /// Parse `s`, which must hold only nucleotides
+    pub(crate) fn dna(s: &str) -> PackedDna {
+        PackedDna::from_str(s).unwrap()
+    }
+
+    /// A linear congruential generator, keeping randomized cases reproducible
+    pub(crate) struct Lcg(u64);
+
+    impl Lcg {
+        pub(crate) fn new(seed: u64) -> Self {
+            Lcg(seed)
+        }
+
+        /// The next state
+        pub(crate) fn next_u64(&mut self) -> u64 {
+            self.0 = self
+                .0
+                .wrapping_mul(6_364_136_223_846_793_005)
+                .wrapping_add(1_442_695_040_888_963_407);
+            self.0
+        }
+
+        /// A number below `bound`
+        pub(crate) fn below(&mut self, bound: u64) -> u64 {
+            (self.next_u64() >> 33) % bound
+        }
+
+        /// A nucleotide, each equally likely
+        pub(crate) fn nuc(&mut self) -> Nuc {
+            [Nuc::A, Nuc::C, Nuc::G, Nuc::T][(self.next_u64() >> 62) as usize]
+        }
+    }
+
+    /// A pseudo-random sequence of `len` nucleotides
+    pub(crate) fn random(len: usize, seed: u64) -> PackedDna {
+        let mut rng = Lcg::new(seed);
+        (0..len).map(|_| rng.nuc()).collect()
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -351,7 +1138,10 @@ mod tests {
         match packed_dna {
             Ok(ref _x) => {}
             Err(e) => {
-                assert_eq!("failed to parse nucleotide from ACGTX", e.to_string());
+                assert_eq!(
+                    "failed to parse nucleotide from 'x' at position 4",
+                    e.to_string()
+                );
             }
         }
     }
@@ -394,55 +1184,738 @@ mod tests {
     }
 
     #[test]
+    #[allow(deprecated)]
     fn get_nuc_test_positive_a() {
         let dna_from_string = PackedDna::from_str("ACGTTGCACT").unwrap();
-        match dna_from_string.get(1) {
+        match dna_from_string.get_1based(1) {
             Ok(x) => assert_eq!(x, Nuc::A),
             Err(e) => println!("Oops! You ran into an error: {e:?}"),
         }
     }
 
     #[test]
+    #[allow(deprecated)]
     fn get_nuc_test_positive_c() {
         let dna_from_string = PackedDna::from_str("ACGTTGCACT").unwrap();
-        match dna_from_string.get(7) {
+        match dna_from_string.get_1based(7) {
             Ok(x) => assert_eq!(x, Nuc::C),
             Err(e) => println!("Oops! You ran into an error: {e:?}"),
         }
     }
 
     #[test]
+    #[allow(deprecated)]
     fn get_nuc_test_positive_g() {
         let dna_from_string = PackedDna::from_str("ACGTTGCACT").unwrap();
-        match dna_from_string.get(3) {
+        match dna_from_string.get_1based(3) {
             Ok(x) => assert_eq!(x, Nuc::G),
             Err(e) => println!("Oops! You ran into an error: {e:?}"),
         }
     }
 
     #[test]
+    #[allow(deprecated)]
     fn get_nuc_test_positive_t() {
         let dna_from_string = PackedDna::from_str("ACGTTGCACT").unwrap();
-        match dna_from_string.get(10) {
+        match dna_from_string.get_1based(10) {
             Ok(x) => assert_eq!(x, Nuc::T),
             Err(e) => println!("Oops! You ran into an error: {e:?}"),
         }
     }
 
     #[test]
+    #[allow(deprecated)]
     fn get_nuc_test_negative() {
         let dna_from_string = PackedDna::from_str("ACGTTGCACT").unwrap();
-        let get11 = dna_from_string.get(11);
+        let get11 = dna_from_string.get_1based(11);
         match get11 {
             Ok(_x) => {}
-            Err(e) => assert_eq!("Index 11 is greater than the given DNA Length", e),
+            Err(e) => assert_eq!(
+                "Index 11 is greater than the given DNA Length",
+                e.to_string()
+            ),
         }
     }
 
+    #[test]
+    #[allow(deprecated)]
+    fn get_nuc_test_zero() {
+        let dna_from_string = PackedDna::from_str("ACGTTGCACT").unwrap();
+        assert_eq!(dna_from_string.get_1based(0), Err(IndexError::Zero));
+    }
+
+    #[test]
+    #[allow(deprecated)]
+    fn get_nuc_test_last_of_full_byte() {
+        let dna_from_string = PackedDna::from_str("ACGTTGCA").unwrap();
+        assert_eq!(dna_from_string.get_1based(8), Ok(Nuc::A));
+        assert_eq!(
+            dna_from_string.get_1based(9),
+            Err(IndexError::OutOfBounds { index: 9, len: 8 })
+        );
+    }
+
+    #[test]
+    fn get_zero_based() {
+        let dna_from_string = PackedDna::from_str("ACGTTGCACT").unwrap();
+        assert_eq!(dna_from_string.get(0), Some(Nuc::A));
+        assert_eq!(dna_from_string.get(2), Some(Nuc::G));
+        assert_eq!(dna_from_string.get(9), Some(Nuc::T));
+        assert_eq!(dna_from_string.get(10), None);
+        assert_eq!(dna_from_string.len(), 10);
+        assert!(!dna_from_string.is_empty());
+    }
+
+    #[test]
+    fn index_zero_based() {
+        let dna_from_string = PackedDna::from_str("ACGTTGCA").unwrap();
+        assert_eq!(dna_from_string[0], Nuc::A);
+        assert_eq!(dna_from_string[7], Nuc::A);
+        assert_eq!(dna_from_string[3], Nuc::T);
+    }
+
+    #[test]
+    #[should_panic(expected = "index 8 out of bounds for DNA of length 8")]
+    fn index_out_of_bounds() {
+        let dna_from_string = PackedDna::from_str("ACGTTGCA").unwrap();
+        let _ = dna_from_string[8];
+    }
+
     #[test]
     fn get_nuc_count_test() {
         let dna_from_string = PackedDna::from_str("ACGTTGCACT").unwrap();
         let counts = dna_from_string.get_counts();
         assert_eq!(counts, vec![('A', 2), ('C', 3), ('G', 2), ('T', 3)]);
     }
+
+    #[test]
+    fn iter_len10() {
+        let dna_from_string = PackedDna::from_str("ACGTTGCACT").unwrap();
+        let nucs: Vec<Nuc> = dna_from_string.iter().collect();
+        assert_eq!(
+            nucs,
+            [
+                Nuc::A,
+                Nuc::C,
+                Nuc::G,
+                Nuc::T,
+                Nuc::T,
+                Nuc::G,
+                Nuc::C,
+                Nuc::A,
+                Nuc::C,
+                Nuc::T
+            ]
+        );
+    }
+
+    #[test]
+    fn iter_matches_from_iter() {
+        let nucs = [Nuc::G, Nuc::A, Nuc::T, Nuc::T, Nuc::A, Nuc::C, Nuc::A];
+        let dna_from_iter = PackedDna::from_iter(nucs);
+        let collected: Vec<Nuc> = (&dna_from_iter).into_iter().collect();
+        assert_eq!(collected, nucs);
+    }
+
+    #[test]
+    fn iter_reversed() {
+        let dna_from_string = PackedDna::from_str("ACGTT").unwrap();
+        let nucs: Vec<Nuc> = dna_from_string.iter().rev().collect();
+        assert_eq!(nucs, [Nuc::T, Nuc::T, Nuc::G, Nuc::C, Nuc::A]);
+    }
+
+    #[test]
+    fn iter_len() {
+        let dna_from_string = PackedDna::from_str("ACGTTGCA").unwrap();
+        assert_eq!(dna_from_string.iter().len(), 8);
+        assert_eq!(PackedDna::from_str("").unwrap().iter().len(), 0);
+    }
+
+    #[test]
+    fn try_from_iter_positive() {
+        let nucs = "ACGTTGCACT".chars().map(Nuc::try_from);
+        let dna_from_iter = PackedDna::try_from_iter(nucs).unwrap();
+        assert_eq!(dna_from_iter.packed_dna, [27, 228, 7]);
+        assert_eq!(dna_from_iter.last_nuc_set_count, 2);
+    }
+
+    #[test]
+    fn try_from_iter_negative() {
+        let nucs = "ACXT".chars().map(Nuc::try_from);
+        let err = PackedDna::try_from_iter(nucs).unwrap_err();
+        assert_eq!("failed to parse nucleotide from X", err.to_string());
+    }
+
+    #[test]
+    fn try_collect_chars_filtered() {
+        let text = "ACGT TGCA\nCT";
+        let dna_from_chars =
+            PackedDna::try_collect_chars(text.chars().filter(|c| !c.is_whitespace())).unwrap();
+        assert_eq!(dna_from_chars.packed_dna, [27, 228, 7]);
+        assert_eq!(dna_from_chars.last_nuc_set_count, 2);
+    }
+
+    #[test]
+    fn try_collect_chars_negative() {
+        let err = PackedDna::try_collect_chars("AC GT".chars()).unwrap_err();
+        assert_eq!(
+            "failed to parse nucleotide from ' ' at position 2",
+            err.to_string()
+        );
+    }
+
+    #[test]
+    fn try_from_str_positive() {
+        let dna_from_str = PackedDna::try_from("acgttgca").unwrap();
+        assert_eq!(dna_from_str.packed_dna, [27, 228]);
+    }
+
+    #[test]
+    fn try_from_bytes_positive() {
+        let dna_from_bytes = PackedDna::try_from(&b"ACGTTGCACT"[..]).unwrap();
+        assert_eq!(dna_from_bytes.packed_dna, [27, 228, 7]);
+        assert_eq!(dna_from_bytes.get_counts()[3], ('T', 3));
+        assert_eq!(Vec::from(&dna_from_bytes), b"ACGTTGCACT");
+    }
+
+    #[test]
+    fn try_from_bytes_negative() {
+        let err = PackedDna::try_from(&b"acgtn"[..]).unwrap_err();
+        assert_eq!(
+            "failed to parse nucleotide from 'n' at position 4",
+            err.to_string()
+        );
+    }
+
+    #[test]
+    fn slice_unaligned() {
+        let dna_from_string = PackedDna::from_str("ACGTTGCACT").unwrap();
+        let slice = dna_from_string.to_slice(1..7);
+        let expected = PackedDna::from_str("CGTTGC").unwrap();
+        assert_eq!(slice.packed_dna, expected.packed_dna);
+        assert_eq!(slice.last_nuc_set_count, 2);
+        assert_eq!(slice.get_counts(), expected.get_counts());
+    }
+
+    #[test]
+    fn slice_all_ranges() {
+        let input = "GATTACAGATTACAC";
+        let dna_from_string = PackedDna::from_str(input).unwrap();
+        for start in 0..=input.len() {
+            for end in start..=input.len() {
+                let slice = dna_from_string.to_slice(start..end);
+                let expected = PackedDna::from_str(&input[start..end]).unwrap();
+                assert_eq!(
+                    slice.iter().collect::<Vec<_>>(),
+                    expected.iter().collect::<Vec<_>>()
+                );
+                assert_eq!(slice.get_counts(), expected.get_counts());
+                if end > start {
+                    assert_eq!(slice.packed_dna, expected.packed_dna);
+                }
+            }
+        }
+    }
+
+    #[test]
+    #[should_panic(expected = "slice range 8..11 out of bounds for DNA of length 10")]
+    fn slice_out_of_bounds() {
+        let dna_from_string = PackedDna::from_str("ACGTTGCACT").unwrap();
+        dna_from_string.to_slice(8..11);
+    }
+
+    #[test]
+    fn reverse_complement_len10() {
+        let dna_from_string = PackedDna::from_str("ACGTTGCACT").unwrap();
+        let revcomp = dna_from_string.reverse_complement();
+        let expected = PackedDna::from_str("AGTGCAACGT").unwrap();
+        assert_eq!(revcomp.packed_dna, expected.packed_dna);
+        assert_eq!(revcomp.get_counts(), expected.get_counts());
+    }
+
+    #[test]
+    fn reverse_complement_all_tail_lengths() {
+        let input = "GATTACAGATTACAC";
+        for len in 0..=input.len() {
+            let dna_from_string = PackedDna::from_str(&input[..len]).unwrap();
+            let expected: Vec<Nuc> = dna_from_string
+                .iter()
+                .rev()
+                .map(|nuc| match nuc {
+                    Nuc::A => Nuc::T,
+                    Nuc::C => Nuc::G,
+                    Nuc::G => Nuc::C,
+                    Nuc::T => Nuc::A,
+                })
+                .collect();
+            let revcomp = dna_from_string.reverse_complement();
+            assert_eq!(revcomp.iter().collect::<Vec<_>>(), expected);
+            assert_eq!(
+                revcomp.packed_dna,
+                PackedDna::from_iter(expected).packed_dna
+            );
+        }
+    }
+
+    #[test]
+    fn reverse_complement_in_place_twice_is_identity() {
+        let mut dna_from_string = PackedDna::from_str("ACGTTGCAC").unwrap();
+        dna_from_string.reverse_complement_in_place();
+        #[allow(deprecated)]
+        dna_from_string.reverse_complement_mut();
+        assert_eq!(
+            dna_from_string.packed_dna,
+            PackedDna::from_str("ACGTTGCAC").unwrap().packed_dna
+        );
+    }
+
+    #[test]
+    fn set_updates_nuc_and_counts() {
+        let mut dna_from_string = PackedDna::from_str("ACGTTGCACT").unwrap();
+        dna_from_string.set(0, Nuc::G).unwrap();
+        dna_from_string.set(9, Nuc::A).unwrap();
+        assert_eq!(dna_from_string, PackedDna::from_str("GCGTTGCACA").unwrap());
+        assert_eq!(
+            dna_from_string.get_counts(),
+            vec![('A', 2), ('C', 3), ('G', 3), ('T', 2)]
+        );
+    }
+
+    #[test]
+    fn set_out_of_bounds() {
+        let mut dna_from_string = PackedDna::from_str("ACGT").unwrap();
+        assert_eq!(
+            dna_from_string.set(4, Nuc::A),
+            Err(IndexError::OutOfBounds { index: 4, len: 4 })
+        );
+    }
+
+    #[test]
+    fn fill_matches_set() {
+        let seq = "ACGTTGCACTGGACT";
+        for start in 0..=seq.len() {
+            for end in start..=seq.len() {
+                let mut filled = PackedDna::from_str(seq).unwrap();
+                filled.fill(start..end, Nuc::C).unwrap();
+                let mut set = PackedDna::from_str(seq).unwrap();
+                for idx in start..end {
+                    set.set(idx, Nuc::C).unwrap();
+                }
+                assert_eq!(filled, set);
+                assert_eq!(filled.get_counts(), set.get_counts());
+            }
+        }
+        let mut dna = PackedDna::from_str(seq).unwrap();
+        assert!(dna.fill(3..16, Nuc::A).is_err());
+    }
+
+    #[test]
+    fn push_matches_from_str() {
+        let mut dna = PackedDna::from_str("").unwrap();
+        for nuc in [
+            Nuc::A,
+            Nuc::C,
+            Nuc::G,
+            Nuc::T,
+            Nuc::T,
+            Nuc::G,
+            Nuc::C,
+            Nuc::A,
+            Nuc::C,
+        ] {
+            dna.push(nuc);
+        }
+        let expected = PackedDna::from_str("ACGTTGCAC").unwrap();
+        assert_eq!(dna.packed_dna, expected.packed_dna);
+        assert_eq!(dna.last_nuc_set_count, expected.last_nuc_set_count);
+        assert_eq!(dna.get_counts(), expected.get_counts());
+    }
+
+    #[test]
+    fn pop_until_empty() {
+        let mut dna = PackedDna::from_str("ACGTT").unwrap();
+        assert_eq!(dna.pop(), Some(Nuc::T));
+        assert_eq!(dna, PackedDna::from_str("ACGT").unwrap());
+        assert_eq!(dna.pop(), Some(Nuc::T));
+        assert_eq!(dna.packed_dna, [6]);
+        assert_eq!(dna.pop(), Some(Nuc::G));
+        assert_eq!(dna.pop(), Some(Nuc::C));
+        assert_eq!(dna.pop(), Some(Nuc::A));
+        assert_eq!(dna.pop(), None);
+        assert_eq!(
+            dna.get_counts(),
+            vec![('A', 0), ('C', 0), ('G', 0), ('T', 0)]
+        );
+    }
+
+    #[test]
+    fn append_aligned_and_unaligned() {
+        for split in 0..=10 {
+            let input = "ACGTTGCACT";
+            let mut dna = PackedDna::from_str(&input[..split]).unwrap();
+            dna.append(&PackedDna::from_str(&input[split..]).unwrap());
+            let expected = PackedDna::from_str(input).unwrap();
+            assert_eq!(dna.packed_dna, expected.packed_dna);
+            assert_eq!(dna.last_nuc_set_count, expected.last_nuc_set_count);
+            assert_eq!(dna.get_counts(), expected.get_counts());
+        }
+    }
+
+    #[test]
+    fn extend_from_str_in_chunks() {
+        let input = "ACGTTGCACTGGATTACA";
+        let mut dna = PackedDna::with_capacity(input.len());
+        let capacity = dna.capacity();
+        assert!(capacity >= input.len());
+        for chunk in ["ACG", "TTGCA", "", "CTGGATTAC", "a"] {
+            dna.extend_from_str(chunk).unwrap();
+        }
+        assert_eq!(dna, PackedDna::from_str(input).unwrap());
+        assert_eq!(dna.capacity(), capacity);
+    }
+
+    #[test]
+    fn extend_from_str_invalid_leaves_sequence_unchanged() {
+        let mut dna = PackedDna::from_str("ACGTT").unwrap();
+        let err = dna.extend_from_str("GCANT").unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "failed to parse nucleotide from 'N' at position 3"
+        );
+        assert_eq!(dna, PackedDna::from_str("ACGTT").unwrap());
+        assert_eq!(
+            dna.packed_dna,
+            PackedDna::from_str("ACGTT").unwrap().packed_dna
+        );
+    }
+
+    #[test]
+    fn parse_options_uracil() {
+        let options = ParseOptions::new().accept_uracil(true);
+        assert_eq!(
+            options.parse("AUGcu").unwrap(),
+            PackedDna::from_str("ATGCT").unwrap()
+        );
+        assert!(ParseOptions::new().parse("AUG").is_err());
+        assert_eq!(
+            ParseOptions::new().parse("ACG").unwrap(),
+            PackedDna::from_str("ACG").unwrap()
+        );
+        assert!(matches!(
+            options.parse("ANU"),
+            Err(DnaError::InvalidNuc {
+                character: 'N',
+                position: 1
+            })
+        ));
+    }
+
+    #[test]
+    fn extend_with_nucs() {
+        let mut dna = PackedDna::from_str("AC").unwrap();
+        dna.extend(PackedDna::from_str("GTTGC").unwrap().iter());
+        dna.extend(vec![Nuc::A]);
+        assert_eq!(dna, PackedDna::from_str("ACGTTGCA").unwrap());
+        assert_eq!(dna.get_counts()[0], ('A', 2));
+    }
+
+    #[test]
+    fn eq_ignores_construction_path() {
+        let nucs = [Nuc::C, Nuc::A, Nuc::T];
+        assert_eq!(
+            PackedDna::from_str("cat").unwrap(),
+            PackedDna::from_iter(nucs)
+        );
+        assert_eq!(
+            PackedDna::from_str("").unwrap(),
+            PackedDna::try_collect_chars("".chars()).unwrap()
+        );
+        assert_ne!(
+            PackedDna::from_str("A").unwrap(),
+            PackedDna::from_str("AA").unwrap()
+        );
+    }
+
+    #[test]
+    fn for_each_word_partial_word() {
+        let dna_from_string = PackedDna::from_str("ACGTTGCACT").unwrap();
+        let mut words = Vec::new();
+        dna_from_string.for_each_word(|word, n_bases| words.push((word, n_bases)));
+        assert_eq!(words, vec![(0b0001_1011_1110_0100_0111, 10)]);
+    }
+
+    #[test]
+    fn for_each_word_multiple_words() {
+        let dna_from_string = PackedDna::from_str(&"T".repeat(65)).unwrap();
+        let mut words = Vec::new();
+        dna_from_string.for_each_word(|word, n_bases| words.push((word, n_bases)));
+        assert_eq!(words, vec![(u64::MAX, 32), (u64::MAX, 32), (0b11, 1)]);
+    }
+
+    #[test]
+    fn for_each_word_empty() {
+        let dna_from_string = PackedDna::from_str("").unwrap();
+        let mut calls = 0;
+        dna_from_string.for_each_word(|_, _| calls += 1);
+        assert_eq!(calls, 0);
+    }
+
+    #[test]
+    fn empty_parse_holds_no_bytes() {
+        for dna in [
+            PackedDna::from_str("").unwrap(),
+            PackedDna::from_iter(Vec::new()),
+            PackedDna::try_from(&b""[..]).unwrap(),
+            PackedDna::try_collect_chars("".chars()).unwrap(),
+        ] {
+            assert!(dna.packed_dna.is_empty());
+            assert_eq!(dna.last_nuc_set_count, 0);
+            assert_eq!(dna, PackedDna::empty());
+        }
+    }
+
+    #[test]
+    fn empty_access() {
+        let mut dna = PackedDna::from_str("").unwrap();
+        assert!(dna.is_empty());
+        assert_eq!(dna.get(0), None);
+        assert_eq!(
+            dna.set(0, Nuc::A),
+            Err(IndexError::OutOfBounds { index: 0, len: 0 })
+        );
+        assert_eq!(
+            dna.get_counts(),
+            vec![('A', 0), ('C', 0), ('G', 0), ('T', 0)]
+        );
+        assert_eq!(dna.iter().next(), None);
+        assert_eq!(dna.iter().next_back(), None);
+        assert_eq!(dna.pop(), None);
+    }
+
+    #[test]
+    fn empty_transforms() {
+        let empty = PackedDna::from_str("").unwrap();
+        assert_eq!(empty.to_slice(0..0), empty);
+        assert_eq!(PackedDna::from_str("ACGT").unwrap().to_slice(4..4), empty);
+        assert_eq!(empty.reverse_complement(), empty);
+
+        let mut dna = empty.clone();
+        dna.append(&empty);
+        assert_eq!(dna, empty);
+        dna.append(&PackedDna::from_str("ACG").unwrap());
+        dna.append(&empty);
+        assert_eq!(dna, PackedDna::from_str("ACG").unwrap());
+    }
+
+    #[test]
+    fn empty_push_then_pop() {
+        let mut dna = PackedDna::from_str("").unwrap();
+        dna.push(Nuc::G);
+        assert_eq!(dna.packed_dna, [2]);
+        assert_eq!(dna.pop(), Some(Nuc::G));
+        assert!(dna.packed_dna.is_empty());
+        assert_eq!(dna, PackedDna::from_str("").unwrap());
+    }
+
+    #[cfg(feature = "rayon")]
+    #[test]
+    fn par_from_str_matches_serial() {
+        let text = "ACGTTGCACTGGATTACAACGTTGCATTGACCAGTacgt";
+        for len in [0, 1, 3, 4, 5, 17, text.len()] {
+            let serial = PackedDna::from_str(&text[..len]).unwrap();
+            for chunk_len in [4, 8, 32, PAR_CHUNK_LEN] {
+                let parallel =
+                    PackedDna::par_from_ascii(&text.as_bytes()[..len], chunk_len).unwrap();
+                assert_eq!(parallel, serial);
+                assert_eq!(parallel.packed_dna, serial.packed_dna);
+                assert_eq!(parallel.get_counts(), serial.get_counts());
+                assert_eq!(parallel.last_nuc_set_count, serial.last_nuc_set_count);
+            }
+        }
+    }
+
+    #[cfg(feature = "rayon")]
+    #[test]
+    fn par_from_str_negative() {
+        let err = PackedDna::par_from_str("acgx").unwrap_err();
+        assert_eq!(
+            "failed to parse nucleotide from 'x' at position 3",
+            err.to_string()
+        );
+        assert!(PackedDna::par_from_ascii("ACGT\u{e9}".as_bytes(), 4).is_none());
+    }
+
+    #[cfg(feature = "rayon")]
+    #[test]
+    fn par_count_packed_matches_serial() {
+        let dna = PackedDna::from_str(&"ACGTTGCACTGGA".repeat(11)).unwrap();
+        let len = dna.len();
+        for chunk_bytes in [1, 2, 5, 1000] {
+            assert_eq!(
+                par_count_packed(dna.used_bytes(), len, chunk_bytes),
+                count_packed(dna.used_bytes(), len)
+            );
+        }
+    }
+
+    #[test]
+    fn nuc_display() {
+        let text: String = [Nuc::A, Nuc::C, Nuc::G, Nuc::T]
+            .iter()
+            .map(|nuc| nuc.to_string())
+            .collect();
+        assert_eq!(text, "ACGT");
+    }
+
+    #[test]
+    fn display_round_trip() {
+        for input in ["", "a", "ACGT", "acgtTGCACt", "GATTACAGATTACAG"] {
+            let dna = PackedDna::from_str(input).unwrap();
+            assert_eq!(dna.to_string(), input.to_uppercase());
+        }
+    }
+
+    #[test]
+    fn to_string_range() {
+        let dna = PackedDna::from_str("ACGTTGCACT").unwrap();
+        assert_eq!(dna.to_string_range(3..9), "TTGCAC");
+        assert_eq!(dna.to_string_range(10..10), "");
+    }
+
+    #[test]
+    #[should_panic(expected = "range 8..11 out of bounds for DNA of length 10")]
+    fn to_string_range_out_of_bounds() {
+        PackedDna::from_str("ACGTTGCACT")
+            .unwrap()
+            .to_string_range(8..11);
+    }
+}
+
+/// Properties of the packed representation over generated inputs
+#[cfg(all(test, not(target_arch = "wasm32")))]
+mod proptests {
+    use super::*;
+    use proptest::prelude::*;
+
+    /// Check the representation invariants: exactly `ceil(len / 4)` bytes, a right-aligned
+    /// last byte with zeroed padding, and counts matching the nucleotides
+    fn assert_invariants(dna: &PackedDna) {
+        let len = dna.len();
+        assert_eq!(dna.packed_dna.len(), len.div_ceil(4));
+        assert_eq!(dna.last_nuc_set_count, len % 4);
+        if !len.is_multiple_of(4) {
+            let last = dna.packed_dna[dna.packed_dna.len() - 1];
+            assert_eq!(
+                last >> (2 * (len % 4)),
+                0,
+                "padding bits set in {:08b}",
+                last
+            );
+        }
+        let mut counts = [0; 4];
+        for nuc in dna.iter() {
+            counts[nuc as usize] += 1;
+        }
+        assert_eq!(counts, [dna.a_count, dna.c_count, dna.g_count, dna.t_count]);
+    }
+
+    fn complement(c: char) -> char {
+        match c {
+            'A' => 'T',
+            'C' => 'G',
+            'G' => 'C',
+            _ => 'A',
+        }
+    }
+
+    proptest! {
+        #[test]
+        fn from_str_round_trip(s in "[ACGTacgt]{0,200}") {
+            let dna = PackedDna::from_str(&s).unwrap();
+            assert_invariants(&dna);
+            prop_assert_eq!(dna.len(), s.len());
+            prop_assert_eq!(dna.to_string(), s.to_uppercase());
+            let chars: String = dna.iter().map(Nuc::to_char).collect();
+            prop_assert_eq!(chars, s.to_uppercase());
+        }
+
+        #[test]
+        fn from_str_accepts_only_nucleotides(s in "\\PC{0,40}") {
+            let valid = s.chars().all(|c| "ACGTacgt".contains(c));
+            match PackedDna::from_str(&s) {
+                Ok(dna) => {
+                    prop_assert!(valid);
+                    assert_invariants(&dna);
+                }
+                Err(DnaError::InvalidNuc { character, position }) => {
+                    prop_assert!(!valid);
+                    prop_assert_eq!(s.chars().nth(position), Some(character));
+                }
+                Err(err) => prop_assert!(false, "unexpected error {}", err),
+            }
+        }
+
+        #[test]
+        fn slice_and_append_reassemble(s in "[ACGT]{0,100}", split in 0usize..=100) {
+            let dna = PackedDna::from_str(&s).unwrap();
+            let split = split.min(s.len());
+            let mut left = dna.to_slice(0..split);
+            let right = dna.to_slice(split..s.len());
+            assert_invariants(&left);
+            assert_invariants(&right);
+            prop_assert_eq!(left.to_string(), &s[..split]);
+            prop_assert_eq!(right.to_string(), &s[split..]);
+            left.append(&right);
+            assert_invariants(&left);
+            prop_assert_eq!(left, dna);
+        }
+
+        #[test]
+        fn push_then_pop(s in "[ACGT]{0,100}") {
+            let mut dna = PackedDna::from_str("").unwrap();
+            for (idx, nuc) in s.chars().map(|c| Nuc::try_from(c).unwrap()).enumerate() {
+                dna.push(nuc);
+                assert_invariants(&dna);
+                prop_assert_eq!(dna.to_string(), &s[..=idx]);
+            }
+            prop_assert_eq!(&dna, &PackedDna::from_str(&s).unwrap());
+            for idx in (0..s.len()).rev() {
+                let nuc = dna.pop().unwrap();
+                assert_invariants(&dna);
+                prop_assert_eq!(nuc.to_char(), s.as_bytes()[idx] as char);
+            }
+            prop_assert_eq!(dna.pop(), None);
+        }
+
+        #[test]
+        fn set_then_get(s in "[ACGT]{1,100}", idx in 0usize..100, code in 0u8..4) {
+            let mut dna = PackedDna::from_str(&s).unwrap();
+            let idx = idx % s.len();
+            let nuc = nuc_from_code(code);
+            dna.set(idx, nuc).unwrap();
+            assert_invariants(&dna);
+            prop_assert_eq!(dna.get(idx), Some(nuc));
+            let mut expected = s.into_bytes();
+            expected[idx] = nuc.to_char() as u8;
+            prop_assert_eq!(dna.to_string().into_bytes(), expected);
+        }
+
+        #[test]
+        fn reverse_complement_involution(s in "[ACGT]{0,100}") {
+            let dna = PackedDna::from_str(&s).unwrap();
+            let revcomp = dna.reverse_complement();
+            assert_invariants(&revcomp);
+            let expected: String = s.chars().rev().map(complement).collect();
+            prop_assert_eq!(revcomp.to_string(), expected);
+            prop_assert_eq!(revcomp.reverse_complement(), dna);
+        }
+
+        #[test]
+        fn storage_round_trip(s in "[ACGT]{0,100}") {
+            let dna = PackedDna::from_str(&s).unwrap();
+            let decoded = PackedDna::from_bytes(&dna.to_bytes()).unwrap();
+            assert_invariants(&decoded);
+            prop_assert_eq!(decoded, dna);
+        }
+    }
 }
@@ -0,0 +1,520 @@
+//! Motif scanning with IUPAC consensus patterns and position weight matrices.
+//!
+//! An [`IupacPattern`] such as `GAANTC` matches wherever every position is one of the
+//! nucleotides its code allows. A [`Pwm`] (position weight matrix, or PSSM) scores every window
+//! instead, e.g. as log-odds of a transcription factor binding there, and reports windows scoring
+//! at least a threshold. Both scan the forward and reverse strands in one pass over the
+//! nucleotides by also matching the motif's reverse complement, and report positions on the
+//! forward strand.
+//!
+//! A [`Pfm`] (position frequency matrix) counts the nucleotides at each position of
+//! equal-length sequences such as aligned sites, reads or barcodes, giving their consensus,
+//! the information at each position and a [`Pwm`] to scan for more sites.
+
+use crate::{iupac::Iupac, translate::Strand, DnaError, Nuc, PackedDna};
+use std::{convert::TryFrom, str::FromStr};
+
+/// Bit `nuc` is set for each nucleotide `code` allows
+pub(crate) fn mask(code: Iupac) -> u8 {
+    code.nucs()
+        .iter()
+        .fold(0, |mask, &nuc| mask | 1 << nuc as u8)
+}
+
+/// The code allowing the complements of the nucleotides `code` allows
+fn complement(code: Iupac) -> Iupac {
+    // A and T, C and G sit at mirrored bits: complementing reverses the 4-bit mask.
+    let complemented = mask(code).reverse_bits() >> 4;
+    Iupac::ALL
+        .iter()
+        .copied()
+        .find(|&other| mask(other) == complemented)
+        .unwrap_or(Iupac::N)
+}
+
+/// A sequence of a [`Pfm`] differs in length from the first one
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
+#[error("sequence {index} has {found} nucleotides, expected {expected}")]
+pub struct LengthMismatchError {
+    /// 0-based index of the sequence
+    pub index: usize,
+    /// Length of the first sequence
+    pub expected: usize,
+    /// Length of the sequence
+    pub found: usize,
+}
+
+/// A match of an [`IupacPattern`], see [`IupacPattern::find_all`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct PatternMatch {
+    /// 0-based position of the first matched nucleotide on the forward strand
+    pub start: usize,
+    /// The strand the pattern reads on
+    pub strand: Strand,
+}
+
+/// A consensus sequence over the IUPAC alphabet, e.g. `GAANTC`
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct IupacPattern {
+    codes: Vec<Iupac>,
+}
+
+impl IupacPattern {
+    /// Create a pattern from its codes
+    pub fn new(codes: Vec<Iupac>) -> Self {
+        IupacPattern { codes }
+    }
+
+    /// Number of positions in the pattern
+    pub fn len(&self) -> usize {
+        self.codes.len()
+    }
+
+    /// Whether the pattern has no positions
+    pub fn is_empty(&self) -> bool {
+        self.codes.is_empty()
+    }
+
+    /// The codes of the pattern
+    pub fn codes(&self) -> &[Iupac] {
+        &self.codes
+    }
+
+    /// The pattern matching the reverse complements of the sequences this one matches
+    pub fn reverse_complement(&self) -> IupacPattern {
+        IupacPattern::new(
+            self.codes
+                .iter()
+                .rev()
+                .map(|&code| complement(code))
+                .collect(),
+        )
+    }
+
+    /// Whether the pattern is its own reverse complement, like most restriction sites
+    pub fn is_palindromic(&self) -> bool {
+        self.reverse_complement() == *self
+    }
+
+    /// Every match of the pattern on either strand of `dna`, by position then strand.
+    ///
+    /// A palindromic pattern matches both strands wherever it matches, so its matches are only
+    /// reported on the forward strand. An empty pattern matches nowhere.
+    pub fn find_all(&self, dna: &PackedDna) -> Vec<PatternMatch> {
+        let forward: Vec<u8> = self.codes.iter().map(|&code| mask(code)).collect();
+        let reverse: Vec<u8> = if self.is_palindromic() {
+            Vec::new()
+        } else {
+            self.reverse_complement()
+                .codes
+                .iter()
+                .map(|&code| mask(code))
+                .collect()
+        };
+        let nucs: Vec<Nuc> = dna.iter().collect();
+        let matches = |masks: &[u8], window: &[Nuc]| {
+            !masks.is_empty()
+                && masks
+                    .iter()
+                    .zip(window)
+                    .all(|(mask, &nuc)| mask >> nuc as u8 & 1 == 1)
+        };
+        let mut found = Vec::new();
+        if self.is_empty() {
+            return found;
+        }
+        for (start, window) in nucs.windows(self.len()).enumerate() {
+            for (strand, masks) in [(Strand::Forward, &forward), (Strand::Reverse, &reverse)] {
+                if matches(masks, window) {
+                    found.push(PatternMatch { start, strand });
+                }
+            }
+        }
+        found
+    }
+}
+
+impl FromStr for IupacPattern {
+    type Err = DnaError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        s.chars()
+            .enumerate()
+            .map(|(position, character)| {
+                Iupac::try_from(character).map_err(|_| DnaError::InvalidNuc {
+                    character,
+                    position,
+                })
+            })
+            .collect::<Result<_, _>>()
+            .map(IupacPattern::new)
+    }
+}
+
+/// A window scoring at least the threshold of a [`Pwm`] scan, see [`Pwm::scan`]
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct PwmHit {
+    /// 0-based position of the first nucleotide of the window on the forward strand
+    pub start: usize,
+    /// The strand the matrix reads on
+    pub strand: Strand,
+    /// Score of the window on that strand
+    pub score: f64,
+}
+
+/// A position weight matrix: one score per nucleotide at each position of a motif
+#[derive(Debug, Clone, PartialEq)]
+pub struct Pwm {
+    /// Scores of A, C, G and T at each position
+    scores: Vec<[f64; 4]>,
+}
+
+impl Pwm {
+    /// Create a matrix from the scores of A, C, G and T at each position
+    pub fn new(scores: Vec<[f64; 4]>) -> Self {
+        Pwm { scores }
+    }
+
+    /// Log-odds matrix from the counts of A, C, G and T at each position of aligned sites, as
+    /// in JASPAR or TRANSFAC.
+    ///
+    /// Each score is `log2(p / 0.25)`, where `p` is the frequency of the nucleotide with
+    /// `pseudocount` added to every count, against a uniform background. A positive
+    /// pseudocount keeps nucleotides never seen at a position from scoring minus infinity.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `pseudocount` is negative or not finite.
+    pub fn from_counts(counts: &[[f64; 4]], pseudocount: f64) -> Self {
+        assert!(
+            pseudocount.is_finite() && pseudocount >= 0.0,
+            "pseudocount must be non-negative, got {}",
+            pseudocount
+        );
+        let scores = counts
+            .iter()
+            .map(|row| {
+                let total: f64 = row.iter().sum::<f64>() + 4.0 * pseudocount;
+                let mut scores = [0.0; 4];
+                for (score, count) in scores.iter_mut().zip(row) {
+                    *score = ((count + pseudocount) / total / 0.25).log2();
+                }
+                scores
+            })
+            .collect();
+        Pwm { scores }
+    }
+
+    /// Number of positions in the motif
+    pub fn len(&self) -> usize {
+        self.scores.len()
+    }
+
+    /// Whether the motif has no positions
+    pub fn is_empty(&self) -> bool {
+        self.scores.is_empty()
+    }
+
+    /// The scores of A, C, G and T at each position
+    pub fn scores(&self) -> &[[f64; 4]] {
+        &self.scores
+    }
+
+    /// The highest score any window can reach
+    pub fn max_score(&self) -> f64 {
+        self.scores
+            .iter()
+            .map(|row| row.iter().copied().fold(f64::NEG_INFINITY, f64::max))
+            .sum()
+    }
+
+    /// The lowest score any window can reach
+    pub fn min_score(&self) -> f64 {
+        self.scores
+            .iter()
+            .map(|row| row.iter().copied().fold(f64::INFINITY, f64::min))
+            .sum()
+    }
+
+    /// The matrix scoring the reverse complement of each window as this one scores the window
+    pub fn reverse_complement(&self) -> Pwm {
+        Pwm::new(
+            self.scores
+                .iter()
+                .rev()
+                .map(|&[a, c, g, t]| [t, g, c, a])
+                .collect(),
+        )
+    }
+
+    /// Every window of `dna` scoring at least `threshold` on either strand, by position then
+    /// strand
+    pub fn scan(&self, dna: &PackedDna, threshold: f64) -> Vec<PwmHit> {
+        let reverse = self.reverse_complement();
+        let nucs: Vec<Nuc> = dna.iter().collect();
+        let score = |pwm: &Pwm, window: &[Nuc]| -> f64 {
+            pwm.scores
+                .iter()
+                .zip(window)
+                .map(|(row, &nuc)| row[nuc as usize])
+                .sum()
+        };
+        let mut hits = Vec::new();
+        if self.is_empty() {
+            return hits;
+        }
+        for (start, window) in nucs.windows(self.len()).enumerate() {
+            for (strand, pwm) in [(Strand::Forward, self), (Strand::Reverse, &reverse)] {
+                let score = score(pwm, window);
+                if score >= threshold {
+                    hits.push(PwmHit {
+                        start,
+                        strand,
+                        score,
+                    });
+                }
+            }
+        }
+        hits
+    }
+}
+
+/// A position frequency matrix: the counts of each nucleotide at each position of equal-length
+/// sequences
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Pfm {
+    /// Counts of A, C, G and T at each position
+    counts: Vec<[usize; 4]>,
+    /// Number of sequences counted
+    depth: usize,
+}
+
+impl Pfm {
+    /// Count the nucleotides at each position of `seqs`, which must all have the same length
+    pub fn from_sequences<'a>(
+        seqs: impl IntoIterator<Item = &'a PackedDna>,
+    ) -> Result<Self, LengthMismatchError> {
+        let mut counts: Vec<[usize; 4]> = Vec::new();
+        let mut depth = 0;
+        for (index, dna) in seqs.into_iter().enumerate() {
+            if index == 0 {
+                counts = vec![[0; 4]; dna.len()];
+            } else if dna.len() != counts.len() {
+                return Err(LengthMismatchError {
+                    index,
+                    expected: counts.len(),
+                    found: dna.len(),
+                });
+            }
+            for (row, nuc) in counts.iter_mut().zip(dna.iter()) {
+                row[nuc as usize] += 1;
+            }
+            depth += 1;
+        }
+        Ok(Pfm { counts, depth })
+    }
+
+    /// Number of positions
+    pub fn len(&self) -> usize {
+        self.counts.len()
+    }
+
+    /// Whether there are no positions, because the sequences were empty or there were none
+    pub fn is_empty(&self) -> bool {
+        self.counts.is_empty()
+    }
+
+    /// Number of sequences counted
+    pub fn depth(&self) -> usize {
+        self.depth
+    }
+
+    /// The counts of A, C, G and T at each position
+    pub fn counts(&self) -> &[[usize; 4]] {
+        &self.counts
+    }
+
+    /// The fractions of A, C, G and T at each position
+    pub fn frequencies(&self) -> Vec<[f64; 4]> {
+        self.counts
+            .iter()
+            .map(|row| row.map(|count| count as f64 / self.depth as f64))
+            .collect()
+    }
+
+    /// The most frequent nucleotide at each position, or the code allowing all of them where
+    /// several are tied
+    pub fn consensus(&self) -> IupacPattern {
+        let codes = self
+            .counts
+            .iter()
+            .map(|row| {
+                let max = row.iter().copied().max().unwrap_or(0);
+                let tied = (0..4)
+                    .filter(|&nuc| row[nuc] == max)
+                    .fold(0, |mask, nuc| mask | 1 << nuc);
+                Iupac::ALL
+                    .iter()
+                    .copied()
+                    .find(|&code| mask(code) == tied)
+                    .unwrap_or(Iupac::N)
+            })
+            .collect();
+        IupacPattern::new(codes)
+    }
+
+    /// Shannon entropy of each position in bits: 0 where every sequence has the same
+    /// nucleotide, up to 2 where all four are equally frequent
+    pub fn entropy(&self) -> Vec<f64> {
+        self.frequencies()
+            .iter()
+            .map(|row| {
+                row.iter()
+                    .filter(|&&p| p > 0.0)
+                    .map(|&p| p * (1.0 / p).log2())
+                    .sum()
+            })
+            .collect()
+    }
+
+    /// The log-odds matrix of the counts, see [`Pwm::from_counts`]
+    pub fn to_pwm(&self, pseudocount: f64) -> Pwm {
+        let counts: Vec<[f64; 4]> = self
+            .counts
+            .iter()
+            .map(|row| row.map(|count| count as f64))
+            .collect();
+        Pwm::from_counts(&counts, pseudocount)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::dna;
+
+    #[test]
+    fn pattern_matches_both_strands() {
+        let pattern = IupacPattern::from_str("GAANTC").unwrap();
+        assert_eq!(pattern.reverse_complement(), "GANTTC".parse().unwrap());
+        assert!(!pattern.is_palindromic());
+        let matches = pattern.find_all(&dna("CCGAACTCTTGAGTTCAGAAGTC"));
+        assert_eq!(
+            matches,
+            vec![
+                PatternMatch {
+                    start: 2,
+                    strand: Strand::Forward
+                },
+                PatternMatch {
+                    start: 10,
+                    strand: Strand::Reverse
+                },
+                PatternMatch {
+                    start: 17,
+                    strand: Strand::Forward
+                },
+            ]
+        );
+        assert!(IupacPattern::from_str("GAXTC").is_err());
+        assert!(IupacPattern::new(Vec::new())
+            .find_all(&dna("ACGT"))
+            .is_empty());
+    }
+
+    #[test]
+    fn palindromic_pattern_reported_once() {
+        let ecori = IupacPattern::from_str("GAATTC").unwrap();
+        assert!(ecori.is_palindromic());
+        assert_eq!(
+            ecori.find_all(&dna("AGAATTCAAGAATTC")),
+            vec![
+                PatternMatch {
+                    start: 1,
+                    strand: Strand::Forward
+                },
+                PatternMatch {
+                    start: 9,
+                    strand: Strand::Forward
+                },
+            ]
+        );
+        assert!(IupacPattern::from_str("RNY").unwrap().is_palindromic());
+    }
+
+    #[test]
+    fn pwm_scans_both_strands() {
+        // Sites of TATA: strongly conserved, with a weaker third position.
+        let pwm = Pwm::from_counts(
+            &[
+                [0.0, 0.0, 0.0, 10.0],
+                [10.0, 0.0, 0.0, 0.0],
+                [2.0, 0.0, 0.0, 8.0],
+                [10.0, 0.0, 0.0, 0.0],
+            ],
+            0.1,
+        );
+        assert_eq!(pwm.len(), 4);
+        assert!(pwm.max_score() > 7.0 && pwm.min_score() < -18.0);
+        // TATA is its own reverse complement; TAAA is a weaker site, and TTTA is one on the
+        // reverse strand.
+        let hits = pwm.scan(&dna("CCTATACCCTAAAGGGTTTAGG"), 5.0);
+        let found: Vec<(usize, Strand)> = hits.iter().map(|hit| (hit.start, hit.strand)).collect();
+        assert_eq!(
+            found,
+            vec![
+                (2, Strand::Forward),
+                (2, Strand::Reverse),
+                (9, Strand::Forward),
+                (16, Strand::Reverse),
+            ]
+        );
+        assert!((hits[0].score - pwm.max_score()).abs() < 1e-12);
+        assert_eq!(hits[1].score, hits[0].score);
+        assert!(hits[2].score < hits[0].score);
+        assert_eq!(hits[3].score, hits[2].score);
+        assert!(pwm.scan(&dna("CCCCCCC"), 0.0).is_empty());
+    }
+
+    #[test]
+    fn pwm_reverse_complement_scores_reverse_strand() {
+        let pwm = Pwm::new(vec![[1.0, 2.0, 3.0, 4.0], [0.5, 0.0, -1.0, 2.0]]);
+        let reverse = pwm.reverse_complement();
+        assert_eq!(
+            reverse.scores(),
+            &[[2.0, -1.0, 0.0, 0.5], [4.0, 3.0, 2.0, 1.0]]
+        );
+        assert_eq!(reverse.reverse_complement(), pwm);
+    }
+
+    #[test]
+    fn frequency_matrix() {
+        let sites: Vec<PackedDna> = ["TATAAT", "TATGAT", "TACAAT", "GATACT"]
+            .iter()
+            .map(|site| dna(site))
+            .collect();
+        let pfm = Pfm::from_sequences(&sites).unwrap();
+        assert_eq!((pfm.len(), pfm.depth()), (6, 4));
+        assert_eq!(pfm.counts()[0], [0, 0, 1, 3]);
+        assert_eq!(pfm.frequencies()[3], [0.75, 0.0, 0.25, 0.0]);
+        assert_eq!(pfm.consensus(), "TATAAT".parse().unwrap());
+        let entropy = pfm.entropy();
+        assert_eq!(entropy[1], 0.0);
+        assert!((entropy[0] - 0.8113).abs() < 1e-4);
+        assert_eq!(pfm.to_pwm(0.1).scan(&dna("CCTATAATCC"), 5.0)[0].start, 2);
+
+        let tied = Pfm::from_sequences(&[dna("AC"), dna("GC")]).unwrap();
+        assert_eq!(tied.consensus(), "RC".parse().unwrap());
+        assert_eq!(
+            Pfm::from_sequences(&[dna("AC"), dna("GCT")]),
+            Err(LengthMismatchError {
+                index: 1,
+                expected: 2,
+                found: 3
+            })
+        );
+        let none = Pfm::from_sequences(&[]).unwrap();
+        assert!(none.is_empty());
+        assert_eq!(none.depth(), 0);
+    }
+}
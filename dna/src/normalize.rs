@@ -0,0 +1,85 @@
+//! Normalisation of sequence text typed, pasted or piped in by users.
+//!
+//! Text copied from browsers, word processors and terminals often carries more than the
+//! sequence: a FASTA header line, line breaks, indentation, non-breaking or other Unicode spaces,
+//! zero-width characters or a byte order mark. [`normalize`] strips all of these so the rest can
+//! be parsed as a sequence, with [`Normalization::Strict`] for callers that would rather reject
+//! anything unexpected.
+
+/// How much of the text around a sequence [`normalize`] strips
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum Normalization {
+    /// Strip a leading FASTA header line, all whitespace and invisible formatting characters
+    #[default]
+    Lenient,
+    /// Only join lines: every other character is kept, so it fails to parse as a nucleotide
+    Strict,
+}
+
+/// A sequence extracted from text by [`normalize`]
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct SequenceText {
+    /// The FASTA header before the sequence, without the leading `>`, if there was one
+    pub header: Option<String>,
+    /// The characters of the sequence, still to be parsed
+    pub sequence: String,
+}
+
+/// Whether `c` is invisible when displayed: zero-width spaces and joiners, the word joiner and
+/// the byte order mark, none of which `char::is_whitespace` covers
+fn is_invisible(c: char) -> bool {
+    matches!(c, '\u{200b}'..='\u{200d}' | '\u{2060}' | '\u{feff}')
+}
+
+/// Extract the sequence from `text` as `mode` allows
+pub fn normalize(text: &str, mode: Normalization) -> SequenceText {
+    match mode {
+        Normalization::Strict => SequenceText {
+            header: None,
+            sequence: text.lines().collect(),
+        },
+        Normalization::Lenient => {
+            let text = text.trim_start_matches(|c: char| c.is_whitespace() || is_invisible(c));
+            let (header, body) = match text.strip_prefix('>') {
+                Some(rest) => {
+                    let (header, body) = rest.split_once('\n').unwrap_or((rest, ""));
+                    (Some(header.trim().to_string()), body)
+                }
+                None => (None, text),
+            };
+            SequenceText {
+                header,
+                sequence: body
+                    .chars()
+                    .filter(|&c| !c.is_whitespace() && !is_invisible(c))
+                    .collect(),
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn lenient_strips_header_and_spacing() {
+        let text = "\u{feff}  >chr1 test\r\nACGT acgt\r\n\tGG\u{a0}CC\u{200b}T\n";
+        let normalized = normalize(text, Normalization::Lenient);
+        assert_eq!(normalized.header.as_deref(), Some("chr1 test"));
+        assert_eq!(normalized.sequence, "ACGTacgtGGCCT");
+        let normalized = normalize(" AC GT\n", Normalization::default());
+        assert_eq!(normalized.header, None);
+        assert_eq!(normalized.sequence, "ACGT");
+        assert_eq!(normalize(">only", Normalization::Lenient).sequence, "");
+    }
+
+    #[test]
+    fn strict_only_joins_lines() {
+        let normalized = normalize("ACGT\r\nAC\n", Normalization::Strict);
+        assert_eq!(normalized.sequence, "ACGTAC");
+        let normalized = normalize(">chr1\nAC GT", Normalization::Strict);
+        assert_eq!(normalized.header, None);
+        assert_eq!(normalized.sequence, ">chr1AC GT");
+    }
+}
@@ -0,0 +1,384 @@
+//! Amino acids and a compact packed protein sequence.
+
+use std::{convert::TryFrom, fmt::Display, iter::FromIterator, str::FromStr};
+
+/// An amino acid residue, including the stop and unknown symbols
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum AminoAcid {
+    /// Alanine
+    Ala,
+    /// Arginine
+    Arg,
+    /// Asparagine
+    Asn,
+    /// Aspartic acid
+    Asp,
+    /// Cysteine
+    Cys,
+    /// Glutamine
+    Gln,
+    /// Glutamic acid
+    Glu,
+    /// Glycine
+    Gly,
+    /// Histidine
+    His,
+    /// Isoleucine
+    Ile,
+    /// Leucine
+    Leu,
+    /// Lysine
+    Lys,
+    /// Methionine
+    Met,
+    /// Phenylalanine
+    Phe,
+    /// Proline
+    Pro,
+    /// Serine
+    Ser,
+    /// Threonine
+    Thr,
+    /// Tryptophan
+    Trp,
+    /// Tyrosine
+    Tyr,
+    /// Valine
+    Val,
+    /// Selenocysteine
+    Sec,
+    /// Pyrrolysine
+    Pyl,
+    /// Unknown or ambiguous residue
+    Xaa,
+    /// Translation stop
+    Stop,
+}
+
+impl AminoAcid {
+    /// All residues in code order
+    pub const ALL: [AminoAcid; 24] = [
+        Self::Ala,
+        Self::Arg,
+        Self::Asn,
+        Self::Asp,
+        Self::Cys,
+        Self::Gln,
+        Self::Glu,
+        Self::Gly,
+        Self::His,
+        Self::Ile,
+        Self::Leu,
+        Self::Lys,
+        Self::Met,
+        Self::Phe,
+        Self::Pro,
+        Self::Ser,
+        Self::Thr,
+        Self::Trp,
+        Self::Tyr,
+        Self::Val,
+        Self::Sec,
+        Self::Pyl,
+        Self::Xaa,
+        Self::Stop,
+    ];
+
+    /// The one-letter IUPAC code of the residue (`*` for stop)
+    pub fn to_char(self) -> char {
+        match self {
+            Self::Ala => 'A',
+            Self::Arg => 'R',
+            Self::Asn => 'N',
+            Self::Asp => 'D',
+            Self::Cys => 'C',
+            Self::Gln => 'Q',
+            Self::Glu => 'E',
+            Self::Gly => 'G',
+            Self::His => 'H',
+            Self::Ile => 'I',
+            Self::Leu => 'L',
+            Self::Lys => 'K',
+            Self::Met => 'M',
+            Self::Phe => 'F',
+            Self::Pro => 'P',
+            Self::Ser => 'S',
+            Self::Thr => 'T',
+            Self::Trp => 'W',
+            Self::Tyr => 'Y',
+            Self::Val => 'V',
+            Self::Sec => 'U',
+            Self::Pyl => 'O',
+            Self::Xaa => 'X',
+            Self::Stop => '*',
+        }
+    }
+
+    /// The 5-bit code used for packing
+    fn code(self) -> u64 {
+        self as u64
+    }
+
+    fn from_code(code: u64) -> Self {
+        Self::ALL[code as usize]
+    }
+}
+
+impl Display for AminoAcid {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.to_char())
+    }
+}
+
+/// An error that can occur when parsing an amino acid.
+#[derive(Debug, thiserror::Error)]
+#[error("failed to parse amino acid from {0}")]
+pub struct ParseAminoAcidError<T: Display>(T);
+
+impl TryFrom<char> for AminoAcid {
+    type Error = ParseAminoAcidError<char>;
+
+    fn try_from(value: char) -> Result<Self, Self::Error> {
+        let upper = value.to_ascii_uppercase();
+        Self::ALL
+            .iter()
+            .copied()
+            .find(|aa| aa.to_char() == upper)
+            .ok_or(ParseAminoAcidError(value))
+    }
+}
+
+impl FromStr for AminoAcid {
+    type Err = ParseAminoAcidError<String>;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut chars = s.chars();
+        match (chars.next(), chars.next()) {
+            (Some(c), None) => {
+                AminoAcid::try_from(c).map_err(|_| ParseAminoAcidError(s.to_ascii_uppercase()))
+            }
+            _ => Err(ParseAminoAcidError(s.to_ascii_uppercase())),
+        }
+    }
+}
+
+/// Number of bits used per residue
+const BITS_PER_RESIDUE: usize = 5;
+/// Number of residues stored in each `u64` word
+const RESIDUES_PER_WORD: usize = 64 / BITS_PER_RESIDUE;
+const RESIDUE_MASK: u64 = (1 << BITS_PER_RESIDUE) - 1;
+
+/// A protein sequence packed at 5 bits per residue
+#[derive(Debug, Clone, Default, PartialEq, Eq, Hash)]
+pub struct PackedProtein {
+    words: Vec<u64>,
+    len: usize,
+    counts: [usize; 24],
+}
+
+impl PackedProtein {
+    /// Create an empty protein sequence
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Number of residues in the sequence
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    /// Whether the sequence has no residues
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Residue at the 0-based index `idx`, or `None` if out of bounds
+    pub fn get(&self, idx: usize) -> Option<AminoAcid> {
+        if idx >= self.len {
+            return None;
+        }
+        let word = self.words[idx / RESIDUES_PER_WORD];
+        let shift = (idx % RESIDUES_PER_WORD) * BITS_PER_RESIDUE;
+        Some(AminoAcid::from_code((word >> shift) & RESIDUE_MASK))
+    }
+
+    /// Append a residue to the end of the sequence
+    pub fn push(&mut self, aa: AminoAcid) {
+        let slot = self.len % RESIDUES_PER_WORD;
+        if slot == 0 {
+            self.words.push(0);
+        }
+        let last = self.words.len() - 1;
+        self.words[last] |= aa.code() << (slot * BITS_PER_RESIDUE);
+        self.counts[aa as usize] += 1;
+        self.len += 1;
+    }
+
+    /// Number of occurrences of `aa` in the sequence
+    pub fn count(&self, aa: AminoAcid) -> usize {
+        self.counts[aa as usize]
+    }
+
+    /// Counts of every residue, in code order
+    pub fn get_counts(&self) -> Vec<(AminoAcid, usize)> {
+        AminoAcid::ALL
+            .iter()
+            .map(|&aa| (aa, self.count(aa)))
+            .collect()
+    }
+
+    /// Iterate over the residues of the sequence
+    pub fn iter(&self) -> Iter<'_> {
+        Iter {
+            protein: self,
+            idx: 0,
+        }
+    }
+}
+
+/// Iterator over the residues of a [`PackedProtein`]
+#[derive(Debug, Clone)]
+pub struct Iter<'a> {
+    protein: &'a PackedProtein,
+    idx: usize,
+}
+
+impl Iterator for Iter<'_> {
+    type Item = AminoAcid;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let aa = self.protein.get(self.idx)?;
+        self.idx += 1;
+        Some(aa)
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let remaining = self.protein.len - self.idx;
+        (remaining, Some(remaining))
+    }
+}
+
+impl ExactSizeIterator for Iter<'_> {}
+
+impl<'a> IntoIterator for &'a PackedProtein {
+    type Item = AminoAcid;
+    type IntoIter = Iter<'a>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
+
+impl FromIterator<AminoAcid> for PackedProtein {
+    fn from_iter<I: IntoIterator<Item = AminoAcid>>(iter: I) -> Self {
+        let mut protein = PackedProtein::new();
+        for aa in iter {
+            protein.push(aa);
+        }
+        protein
+    }
+}
+
+impl FromStr for PackedProtein {
+    type Err = ParseAminoAcidError<String>;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        s.chars()
+            .map(|c| {
+                AminoAcid::try_from(c).map_err(|_| ParseAminoAcidError(s.to_ascii_uppercase()))
+            })
+            .collect()
+    }
+}
+
+impl Display for PackedProtein {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        for aa in self {
+            write!(f, "{}", aa)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn try_from_amino_acid_uppercase() {
+        assert_eq!(AminoAcid::try_from('W').unwrap(), AminoAcid::Trp);
+    }
+
+    #[test]
+    fn try_from_amino_acid_lowercase() {
+        assert_eq!(AminoAcid::try_from('m').unwrap(), AminoAcid::Met);
+    }
+
+    #[test]
+    fn try_from_amino_acid_stop() {
+        assert_eq!(AminoAcid::try_from('*').unwrap(), AminoAcid::Stop);
+    }
+
+    #[test]
+    fn try_from_amino_acid_negative() {
+        let err = AminoAcid::try_from('Z').unwrap_err();
+        assert_eq!("failed to parse amino acid from Z", err.to_string());
+    }
+
+    #[test]
+    fn from_str_amino_acid_multiple_chars_negative() {
+        let err = AminoAcid::from_str("mk").unwrap_err();
+        assert_eq!("failed to parse amino acid from MK", err.to_string());
+    }
+
+    #[test]
+    fn char_round_trip_all_residues() {
+        for &aa in AminoAcid::ALL.iter() {
+            assert_eq!(AminoAcid::try_from(aa.to_char()).unwrap(), aa);
+        }
+    }
+
+    #[test]
+    fn packed_protein_get_across_words() {
+        let protein = PackedProtein::from_str("MKTAYIAKQRQISFVKSHFSRQ*").unwrap();
+        assert_eq!(protein.len(), 23);
+        assert_eq!(protein.get(0), Some(AminoAcid::Met));
+        assert_eq!(protein.get(12), Some(AminoAcid::Ser));
+        assert_eq!(protein.get(22), Some(AminoAcid::Stop));
+        assert_eq!(protein.get(23), None);
+    }
+
+    #[test]
+    fn packed_protein_counts() {
+        let protein = PackedProtein::from_str("MKKW").unwrap();
+        assert_eq!(protein.count(AminoAcid::Lys), 2);
+        assert_eq!(protein.count(AminoAcid::Met), 1);
+        assert_eq!(protein.count(AminoAcid::Ala), 0);
+    }
+
+    #[test]
+    fn packed_protein_display_round_trip() {
+        let protein = PackedProtein::from_str("mktayiakqrqisfvkshfsrq").unwrap();
+        assert_eq!(protein.to_string(), "MKTAYIAKQRQISFVKSHFSRQ");
+    }
+
+    #[test]
+    fn packed_protein_from_iter_matches_iter() {
+        let residues = [AminoAcid::Gly, AminoAcid::Pro, AminoAcid::Xaa];
+        let protein: PackedProtein = residues.iter().copied().collect();
+        assert_eq!(protein.iter().collect::<Vec<_>>(), residues);
+    }
+
+    #[test]
+    fn packed_protein_invalid_residue() {
+        let err = PackedProtein::from_str("MKZ").unwrap_err();
+        assert_eq!("failed to parse amino acid from MKZ", err.to_string());
+    }
+
+    #[test]
+    fn packed_protein_empty() {
+        let protein = PackedProtein::from_str("").unwrap();
+        assert!(protein.is_empty());
+        assert_eq!(protein.iter().count(), 0);
+    }
+}
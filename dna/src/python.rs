@@ -0,0 +1,222 @@
+//! Python bindings, with the `python` feature.
+//!
+//! Built with [maturin](https://www.maturin.rs) (`maturin develop` or `pip install .` from the
+//! crate directory), which builds it as a `cdylib`, this crate becomes the Python extension
+//! module `dna`. It exposes `Nuc`, `PackedDna` with counting, reverse complement, indexing and
+//! slicing, and `read_fasta`.
+//! Sequences stay packed on the Rust side, so a `PackedDna` object takes a quarter of a byte
+//! per nucleotide however it is used from Python.
+
+use crate::{
+    fasta::{FastaError, Reader},
+    Nuc, PackedDna,
+};
+use pyo3::{
+    exceptions::{PyIOError, PyIndexError, PyValueError},
+    prelude::*,
+    types::PySlice,
+};
+use std::{collections::HashMap, convert::TryFrom, str::FromStr};
+
+/// A nucleotide
+#[pyclass(name = "Nuc", module = "dna", eq, eq_int, frozen, hash)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+enum PyNuc {
+    A,
+    C,
+    G,
+    T,
+}
+
+impl From<Nuc> for PyNuc {
+    fn from(nuc: Nuc) -> Self {
+        match nuc {
+            Nuc::A => PyNuc::A,
+            Nuc::C => PyNuc::C,
+            Nuc::G => PyNuc::G,
+            Nuc::T => PyNuc::T,
+        }
+    }
+}
+
+impl From<PyNuc> for Nuc {
+    fn from(nuc: PyNuc) -> Self {
+        match nuc {
+            PyNuc::A => Nuc::A,
+            PyNuc::C => Nuc::C,
+            PyNuc::G => Nuc::G,
+            PyNuc::T => Nuc::T,
+        }
+    }
+}
+
+#[pymethods]
+impl PyNuc {
+    fn __str__(&self) -> String {
+        Nuc::from(*self).to_char().to_string()
+    }
+}
+
+/// A position or a slice, as passed to `__getitem__`
+#[derive(FromPyObject)]
+enum Key<'py> {
+    Index(isize),
+    Slice(Bound<'py, PySlice>),
+}
+
+/// A nucleotide or a subsequence, as returned by `__getitem__`
+#[derive(IntoPyObject)]
+enum Item {
+    Nuc(PyNuc),
+    Dna(PyPackedDna),
+}
+
+/// A DNA sequence packed at 2 bits per nucleotide
+#[pyclass(name = "PackedDna", module = "dna", eq)]
+#[derive(Debug, Clone, PartialEq)]
+struct PyPackedDna(PackedDna);
+
+#[pymethods]
+impl PyPackedDna {
+    /// Parse a sequence of `A`, `C`, `G` and `T`, in either case
+    #[new]
+    #[pyo3(signature = (sequence = ""))]
+    fn new(sequence: &str) -> PyResult<Self> {
+        PackedDna::from_str(sequence)
+            .map(PyPackedDna)
+            .map_err(|err| PyValueError::new_err(err.to_string()))
+    }
+
+    fn __len__(&self) -> usize {
+        self.0.len()
+    }
+
+    fn __str__(&self) -> String {
+        self.0.to_string()
+    }
+
+    fn __repr__(&self) -> String {
+        format!("PackedDna('{}')", self.0)
+    }
+
+    fn __getitem__(&self, key: Key<'_>) -> PyResult<Item> {
+        let len = self.0.len();
+        match key {
+            Key::Index(idx) => {
+                let idx = if idx < 0 { idx + len as isize } else { idx };
+                usize::try_from(idx)
+                    .ok()
+                    .and_then(|idx| self.0.get(idx))
+                    .map(|nuc| Item::Nuc(nuc.into()))
+                    .ok_or_else(|| PyIndexError::new_err("PackedDna index out of range"))
+            }
+            Key::Slice(slice) => {
+                let indices = slice.indices(len as isize)?;
+                let dna = if indices.step == 1 {
+                    let start = indices.start as usize;
+                    self.0.to_slice(start..start + indices.slicelength)
+                } else {
+                    (0..indices.slicelength as isize)
+                        .map(|k| self.0[(indices.start + k * indices.step) as usize])
+                        .collect()
+                };
+                Ok(Item::Dna(PyPackedDna(dna)))
+            }
+        }
+    }
+
+    /// Number of each nucleotide, keyed by its letter
+    fn counts(&self) -> HashMap<char, usize> {
+        self.0.get_counts().into_iter().collect()
+    }
+
+    /// Number of occurrences of `nuc`
+    fn count(&self, nuc: PyNuc) -> usize {
+        let nuc = Nuc::from(nuc).to_char();
+        self.0
+            .get_counts()
+            .into_iter()
+            .find(|&(letter, _)| letter == nuc)
+            .map_or(0, |(_, count)| count)
+    }
+
+    /// Fraction of positions that are G or C, or 0 for an empty sequence
+    fn gc_content(&self) -> f64 {
+        self.0.gc_content()
+    }
+
+    /// The reverse complement of the sequence
+    fn reverse_complement(&self) -> PyPackedDna {
+        PyPackedDna(self.0.reverse_complement())
+    }
+}
+
+/// Read every record of a FASTA file as a list of `(header, PackedDna)` pairs
+#[pyfunction]
+fn read_fasta(path: &str) -> PyResult<Vec<(String, PyPackedDna)>> {
+    let reader = Reader::from_path(path)?;
+    reader
+        .map(|record| {
+            let (header, sequence) = record
+                .map_err(|err| match err {
+                    FastaError::Io(err) => PyIOError::new_err(err.to_string()),
+                    err => PyValueError::new_err(err.to_string()),
+                })?
+                .into_parts();
+            Ok((header, PyPackedDna(sequence)))
+        })
+        .collect()
+}
+
+/// The `dna` Python module
+#[pymodule]
+fn dna(module: &Bound<'_, PyModule>) -> PyResult<()> {
+    module.add_class::<PyNuc>()?;
+    module.add_class::<PyPackedDna>()?;
+    module.add_function(wrap_pyfunction!(read_fasta, module)?)?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn nucs_convert_both_ways() {
+        for nuc in [Nuc::A, Nuc::C, Nuc::G, Nuc::T] {
+            let py = PyNuc::from(nuc);
+            assert_eq!(Nuc::from(py), nuc);
+            assert_eq!(py.__str__(), nuc.to_char().to_string());
+        }
+    }
+
+    #[test]
+    fn packed_dna_methods() {
+        let dna = PyPackedDna::new("acGGTT").unwrap();
+        assert_eq!((dna.__len__(), dna.__str__()), (6, "ACGGTT".to_string()));
+        assert_eq!(dna.__repr__(), "PackedDna('ACGGTT')");
+        assert_eq!(
+            [PyNuc::A, PyNuc::C, PyNuc::G, PyNuc::T].map(|nuc| dna.count(nuc)),
+            [1, 1, 2, 2]
+        );
+        assert_eq!(dna.counts()[&'G'], 2);
+        assert_eq!(dna.reverse_complement().__str__(), "AACCGT");
+        assert!(PyPackedDna::new("ACNT").is_err());
+    }
+
+    #[test]
+    fn read_fasta_records() {
+        let path = std::env::temp_dir().join(format!("python-{}.fa", std::process::id()));
+        std::fs::write(&path, ">one\nACGT\n>two\nGG\n").unwrap();
+        let records = read_fasta(path.to_str().unwrap()).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(
+            records,
+            [
+                ("one".to_string(), PyPackedDna::new("ACGT").unwrap()),
+                ("two".to_string(), PyPackedDna::new("GG").unwrap()),
+            ]
+        );
+        assert!(read_fasta("/nonexistent/seq.fa").is_err());
+    }
+}
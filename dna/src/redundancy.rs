@@ -0,0 +1,225 @@
+//! Redundant contigs of an assembly: those contained in, or nearly identical to, another one.
+//!
+//! Assemblers often emit haplotype duplicates and fragments that a longer contig already covers.
+//! A [`RedundancyFinder`] compares FracMinHash sketches first, so that only pairs sharing most
+//! of their k-mers are aligned, then confirms each candidate with a semi-global alignment of
+//! the shorter contig against the longer one on both strands.
+//!
+//! Confirming a candidate takes time proportional to the product of the two lengths, with
+//! memory proportional to the longer one, so this suits fragments up to tens of kilobases
+//! against contigs of a few megabases.
+
+use crate::{
+    align::{AlignMode, Aligner, Scoring},
+    sketch::Sketch,
+    translate::Strand,
+    PackedDna,
+};
+
+/// A contig found within another one, see [`RedundancyFinder::find`]
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct RedundantContig {
+    /// Index of the redundant contig
+    pub contig: usize,
+    /// Index of the longest contig found to contain it
+    pub container: usize,
+    /// The strand of the container the contig aligns to
+    pub strand: Strand,
+    /// Lower bound on the fraction of the contig's nucleotides matching the container
+    pub identity: f64,
+}
+
+/// Finds contigs contained in other contigs
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct RedundancyFinder {
+    k: usize,
+    scaled: u64,
+    min_containment: f64,
+    min_identity: f64,
+}
+
+impl Default for RedundancyFinder {
+    fn default() -> Self {
+        RedundancyFinder::new()
+    }
+}
+
+impl RedundancyFinder {
+    /// Compare 21-mers sampled at one in 20, aligning pairs sharing 80% of their sampled
+    /// k-mers, and report contigs matching at an identity of 95% or more
+    pub fn new() -> Self {
+        RedundancyFinder {
+            k: 21,
+            scaled: 20,
+            min_containment: 0.8,
+            min_identity: 0.95,
+        }
+    }
+
+    /// Sketch k-mers of length `k`, see [`Sketch::frac_minhash`].
+    ///
+    /// # Panics
+    ///
+    /// Panics if `k` is zero or greater than [`MAX_K`](crate::kmer::MAX_K).
+    pub fn k(mut self, k: usize) -> Self {
+        assert!(
+            (1..=crate::kmer::MAX_K).contains(&k),
+            "k-mer length must be between 1 and {}, got {}",
+            crate::kmer::MAX_K,
+            k
+        );
+        self.k = k;
+        self
+    }
+
+    /// Keep about one k-mer hash in `scaled` in each sketch.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `scaled` is zero.
+    pub fn scaled(mut self, scaled: u64) -> Self {
+        assert!(scaled > 0, "sketch scale must not be zero");
+        self.scaled = scaled;
+        self
+    }
+
+    /// Only align pairs where at least this fraction of the shorter contig's sampled k-mers
+    /// occur in the longer one.
+    ///
+    /// Contigs too short to sample any k-mer are aligned against every longer contig.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `min_containment` is not between 0 and 1.
+    pub fn min_containment(mut self, min_containment: f64) -> Self {
+        assert!(
+            (0.0..=1.0).contains(&min_containment),
+            "containment {} not between 0 and 1",
+            min_containment
+        );
+        self.min_containment = min_containment;
+        self
+    }
+
+    /// Report contigs matching a container at this identity or more.
+    ///
+    /// The identity is a lower bound on the fraction of the contig's nucleotides matching the
+    /// container: `(score + len) / (2 * len)` for an alignment scoring 1 per match and -1 per
+    /// mismatch or gap position.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `min_identity` is not between 0 and 1.
+    pub fn min_identity(mut self, min_identity: f64) -> Self {
+        assert!(
+            (0.0..=1.0).contains(&min_identity),
+            "identity {} not between 0 and 1",
+            min_identity
+        );
+        self.min_identity = min_identity;
+        self
+    }
+
+    /// The contigs that could be removed without losing sequence, by index.
+    ///
+    /// Each contig is only compared with longer ones, or with earlier ones of the same length,
+    /// so of two identical contigs the first is kept. A contig's container may itself be
+    /// redundant, but the longest contig of each chain is never reported.
+    pub fn find(&self, contigs: &[PackedDna]) -> Vec<RedundantContig> {
+        let sketches: Vec<Sketch> = contigs
+            .iter()
+            .map(|contig| Sketch::frac_minhash(contig, self.k, self.scaled))
+            .collect();
+        let mut by_length: Vec<usize> = (0..contigs.len()).collect();
+        by_length.sort_by_key(|&idx| (std::cmp::Reverse(contigs[idx].len()), idx));
+        let aligner = Aligner::new(AlignMode::SemiGlobal).scoring(Scoring {
+            match_score: 1,
+            mismatch: -1,
+            gap_open: 0,
+            gap_extend: -1,
+        });
+
+        let mut redundant = Vec::new();
+        for (rank, &contig) in by_length.iter().enumerate() {
+            let query = &contigs[contig];
+            if query.is_empty() {
+                continue;
+            }
+            let reverse = query.reverse_complement();
+            for &container in &by_length[..rank] {
+                let shared = sketches[contig].containment(&sketches[container]);
+                if shared.is_some_and(|shared| shared < self.min_containment) {
+                    continue;
+                }
+                let target = &contigs[container];
+                let forward = aligner.score(query, target);
+                let backward = aligner.score(&reverse, target);
+                let (strand, score) = if backward > forward {
+                    (Strand::Reverse, backward)
+                } else {
+                    (Strand::Forward, forward)
+                };
+                let len = query.len() as f64;
+                let identity = (f64::from(score) + len) / (2.0 * len);
+                if identity >= self.min_identity {
+                    redundant.push(RedundantContig {
+                        contig,
+                        container,
+                        strand,
+                        identity,
+                    });
+                    break;
+                }
+            }
+        }
+        redundant.sort_by_key(|found| found.contig);
+        redundant
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::random;
+    use crate::Nuc;
+
+    #[test]
+    fn finds_contained_and_duplicate_contigs() {
+        let long = random(1200, 1);
+        // A fragment with up to two substitutions, reverse complemented
+        let mut fragment = long.to_slice(300..700);
+        fragment.set(10, Nuc::A).unwrap();
+        fragment.set(200, Nuc::C).unwrap();
+        let fragment = fragment.reverse_complement();
+        let unrelated = random(600, 2);
+        let contigs = vec![
+            fragment,
+            long.clone(),
+            unrelated,
+            long.clone(),
+            random(15, 3),
+        ];
+
+        let found = RedundancyFinder::new().find(&contigs);
+        assert_eq!(found.len(), 2);
+        assert_eq!((found[0].contig, found[0].container), (0, 1));
+        assert_eq!(found[0].strand, Strand::Reverse);
+        assert!(found[0].identity > 0.99 && found[0].identity < 1.0);
+        // Of the two copies of `long`, the first is kept.
+        assert_eq!((found[1].contig, found[1].container), (3, 1));
+        assert_eq!(found[1].identity, 1.0);
+
+        let strict = RedundancyFinder::new().min_identity(1.0).find(&contigs);
+        assert_eq!(strict.len(), 1);
+        assert!(RedundancyFinder::new().find(&[]).is_empty());
+    }
+
+    #[test]
+    fn short_contigs_skip_the_prefilter() {
+        let long = random(500, 4);
+        let contigs = vec![long.clone(), long.to_slice(100..115)];
+        let found = RedundancyFinder::new().find(&contigs);
+        assert_eq!(found.len(), 1);
+        assert_eq!((found[0].contig, found[0].container), (1, 0));
+    }
+}
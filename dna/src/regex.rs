@@ -0,0 +1,623 @@
+//! Regular expressions over IUPAC codes, e.g. `GGWCC{2,4}N{10}GG`.
+//!
+//! An [`IupacRegex`] extends the fixed-length [`IupacPattern`](crate::motif::IupacPattern)
+//! with the usual regular expression syntax:
+//!
+//! - any IUPAC code in either case, with `.` standing for `N`;
+//! - classes such as `[AG]` or `[^C]`, matching any nucleotide one of their codes allows, or,
+//!   negated, none of them;
+//! - groups `(...)` and alternatives `A|B`;
+//! - repetitions `*`, `+`, `?`, `{n}`, `{n,}` and `{n,m}` of the preceding code, class or
+//!   group.
+//!
+//! Patterns are compiled to a nondeterministic automaton that runs over the nucleotides once,
+//! following every possible state at the same time, so the search time grows with the sequence
+//! length times the number of states, never exponentially. As with motifs, both strands are
+//! searched by also running the reverse complement of the pattern.
+
+use crate::{iupac::Iupac, motif::mask, translate::Strand, PackedDna};
+use std::{convert::TryFrom, ops::Range, str::FromStr};
+
+/// Largest count of a bounded repetition
+pub const MAX_REPEAT: usize = 1000;
+/// Largest number of automaton states a pattern may compile to
+pub const MAX_STATES: usize = 100_000;
+
+/// An error compiling an [`IupacRegex`]
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
+pub enum RegexError {
+    /// The pattern is not valid syntax
+    #[error("invalid pattern at position {position}: {reason}")]
+    Syntax {
+        /// 0-based position of the offending character, or the pattern length if it ended early
+        position: usize,
+        /// What is wrong there
+        reason: &'static str,
+    },
+    /// The pattern matches the empty sequence, so it would match everywhere
+    #[error("pattern matches the empty sequence")]
+    MatchesEmpty,
+    /// The repetitions of the pattern expand to too many states
+    #[error("pattern is too large, expanding to more than {MAX_STATES} states")]
+    TooLarge,
+}
+
+/// A match of an [`IupacRegex`], see [`IupacRegex::find_all`]
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct RegexMatch {
+    /// 0-based range of the matched nucleotides on the forward strand
+    pub range: Range<usize>,
+    /// The strand the pattern reads on
+    pub strand: Strand,
+}
+
+/// A regular expression over IUPAC codes, compiled for searching packed sequences
+#[derive(Debug, Clone)]
+pub struct IupacRegex {
+    forward: Automaton,
+    /// `None` when the pattern is its own reverse complement, or only the forward strand is
+    /// searched
+    reverse: Option<Automaton>,
+}
+
+impl IupacRegex {
+    /// Compile `pattern`
+    pub fn new(pattern: &str) -> Result<Self, RegexError> {
+        let chars: Vec<char> = pattern.chars().collect();
+        let mut parser = Parser {
+            chars: &chars,
+            pos: 0,
+        };
+        let node = parser.alternation()?;
+        if parser.pos < chars.len() {
+            return Err(parser.error("unmatched closing parenthesis"));
+        }
+        if node.matches_empty() {
+            return Err(RegexError::MatchesEmpty);
+        }
+        if node.states() > MAX_STATES {
+            return Err(RegexError::TooLarge);
+        }
+        let reverse = node.reverse_complement();
+        Ok(IupacRegex {
+            forward: Automaton::new(&node),
+            reverse: (reverse != node).then(|| Automaton::new(&reverse)),
+        })
+    }
+
+    /// Only search the forward strand
+    pub fn forward_only(mut self) -> Self {
+        self.reverse = None;
+        self
+    }
+
+    /// Every match on either strand, by start then strand.
+    ///
+    /// On each strand the leftmost match is reported, extended as far as it goes, then the
+    /// search resumes after it, so the matches of one strand never overlap. A pattern that is
+    /// its own reverse complement, such as `GAATTC`, reports its matches on the forward strand
+    /// only.
+    pub fn find_all(&self, dna: &PackedDna) -> Vec<RegexMatch> {
+        let mut found: Vec<RegexMatch> = self
+            .forward
+            .find_all(dna)
+            .into_iter()
+            .map(|range| RegexMatch {
+                range,
+                strand: Strand::Forward,
+            })
+            .collect();
+        if let Some(reverse) = &self.reverse {
+            found.extend(reverse.find_all(dna).into_iter().map(|range| RegexMatch {
+                range,
+                strand: Strand::Reverse,
+            }));
+            found.sort_by_key(|m| (m.range.start, m.strand == Strand::Reverse));
+        }
+        found
+    }
+}
+
+impl FromStr for IupacRegex {
+    type Err = RegexError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        IupacRegex::new(s)
+    }
+}
+
+/// A parsed pattern
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Node {
+    /// One nucleotide among those whose bits are set
+    Nucs(u8),
+    Concat(Vec<Node>),
+    Alternation(Vec<Node>),
+    Repeat {
+        node: Box<Node>,
+        min: usize,
+        max: Option<usize>,
+    },
+}
+
+impl Node {
+    fn matches_empty(&self) -> bool {
+        match self {
+            Node::Nucs(_) => false,
+            Node::Concat(nodes) => nodes.iter().all(Node::matches_empty),
+            Node::Alternation(nodes) => nodes.iter().any(Node::matches_empty),
+            Node::Repeat { node, min, .. } => *min == 0 || node.matches_empty(),
+        }
+    }
+
+    /// Number of automaton states the node compiles to, saturating
+    fn states(&self) -> usize {
+        match self {
+            Node::Nucs(_) => 1,
+            Node::Concat(nodes) => nodes
+                .iter()
+                .map(Node::states)
+                .fold(0, usize::saturating_add),
+            Node::Alternation(nodes) => nodes
+                .iter()
+                .map(Node::states)
+                .fold(nodes.len(), usize::saturating_add),
+            Node::Repeat { node, min, max } => {
+                let copies = max.unwrap_or(min + 1);
+                node.states().saturating_add(1).saturating_mul(copies)
+            }
+        }
+    }
+
+    /// The node matching the reverse complements of what this one matches
+    fn reverse_complement(&self) -> Node {
+        match self {
+            // A and T, C and G sit at mirrored bits: complementing reverses the 4-bit mask.
+            Node::Nucs(nucs) => Node::Nucs(nucs.reverse_bits() >> 4),
+            Node::Concat(nodes) => {
+                Node::Concat(nodes.iter().rev().map(Node::reverse_complement).collect())
+            }
+            Node::Alternation(nodes) => {
+                Node::Alternation(nodes.iter().map(Node::reverse_complement).collect())
+            }
+            Node::Repeat { node, min, max } => Node::Repeat {
+                node: Box::new(node.reverse_complement()),
+                min: *min,
+                max: *max,
+            },
+        }
+    }
+}
+
+/// Recursive descent over the characters of a pattern
+struct Parser<'a> {
+    chars: &'a [char],
+    pos: usize,
+}
+
+impl Parser<'_> {
+    fn error(&self, reason: &'static str) -> RegexError {
+        RegexError::Syntax {
+            position: self.pos,
+            reason,
+        }
+    }
+
+    fn peek(&self) -> Option<char> {
+        self.chars.get(self.pos).copied()
+    }
+
+    fn eat(&mut self, c: char) -> bool {
+        let found = self.peek() == Some(c);
+        self.pos += found as usize;
+        found
+    }
+
+    /// Alternatives separated by `|`
+    fn alternation(&mut self) -> Result<Node, RegexError> {
+        let mut alternatives = vec![self.concatenation()?];
+        while self.eat('|') {
+            alternatives.push(self.concatenation()?);
+        }
+        Ok(if alternatives.len() == 1 {
+            alternatives.remove(0)
+        } else {
+            Node::Alternation(alternatives)
+        })
+    }
+
+    /// Repeated atoms up to the end of the pattern, group or alternative
+    fn concatenation(&mut self) -> Result<Node, RegexError> {
+        let mut nodes = Vec::new();
+        while let Some(c) = self.peek() {
+            if c == '|' || c == ')' {
+                break;
+            }
+            let atom = self.atom()?;
+            nodes.push(self.repetition(atom)?);
+        }
+        Ok(if nodes.len() == 1 {
+            nodes.remove(0)
+        } else {
+            Node::Concat(nodes)
+        })
+    }
+
+    fn atom(&mut self) -> Result<Node, RegexError> {
+        let c = self.peek().ok_or_else(|| self.error("unexpected end"))?;
+        if self.eat('(') {
+            let node = self.alternation()?;
+            if !self.eat(')') {
+                return Err(self.error("unclosed group"));
+            }
+            return Ok(node);
+        }
+        if self.eat('[') {
+            return self.class();
+        }
+        if self.eat('.') {
+            return Ok(Node::Nucs(mask(Iupac::N)));
+        }
+        if matches!(c, '*' | '+' | '?' | '{') {
+            return Err(self.error("nothing to repeat"));
+        }
+        let code = Iupac::try_from(c).map_err(|_| self.error("not an IUPAC code"))?;
+        self.pos += 1;
+        Ok(Node::Nucs(mask(code)))
+    }
+
+    /// The rest of a class after its `[`
+    fn class(&mut self) -> Result<Node, RegexError> {
+        let negated = self.eat('^');
+        let mut nucs = 0;
+        loop {
+            match self.peek() {
+                None => return Err(self.error("unclosed class")),
+                Some(']') => break,
+                Some(c) => {
+                    let code = Iupac::try_from(c).map_err(|_| self.error("not an IUPAC code"))?;
+                    nucs |= mask(code);
+                    self.pos += 1;
+                }
+            }
+        }
+        if negated {
+            nucs = !nucs & 0b1111;
+        }
+        if nucs == 0 {
+            return Err(self.error("class matches no nucleotide"));
+        }
+        self.pos += 1;
+        Ok(Node::Nucs(nucs))
+    }
+
+    /// `node` with the repetition following it, if any
+    fn repetition(&mut self, node: Node) -> Result<Node, RegexError> {
+        let (min, max) = if self.eat('*') {
+            (0, None)
+        } else if self.eat('+') {
+            (1, None)
+        } else if self.eat('?') {
+            (0, Some(1))
+        } else if self.eat('{') {
+            let min = self.count()?;
+            let max = if self.eat(',') {
+                match self.peek() {
+                    Some('}') => None,
+                    _ => Some(self.count()?),
+                }
+            } else {
+                Some(min)
+            };
+            if !self.eat('}') {
+                return Err(self.error("unclosed repetition"));
+            }
+            if max.is_some_and(|max| max < min) {
+                return Err(self.error("repetition maximum below its minimum"));
+            }
+            (min, max)
+        } else {
+            return Ok(node);
+        };
+        if matches!(self.peek(), Some('*' | '+' | '?' | '{')) {
+            return Err(self.error("nothing to repeat"));
+        }
+        Ok(Node::Repeat {
+            node: Box::new(node),
+            min,
+            max,
+        })
+    }
+
+    /// A repetition count of up to [`MAX_REPEAT`]
+    fn count(&mut self) -> Result<usize, RegexError> {
+        let start = self.pos;
+        while self.peek().is_some_and(|c| c.is_ascii_digit()) {
+            self.pos += 1;
+        }
+        if start == self.pos {
+            return Err(self.error("expected a repetition count"));
+        }
+        let digits: String = self.chars[start..self.pos].iter().collect();
+        match digits.parse::<usize>() {
+            Ok(count) if count <= MAX_REPEAT => Ok(count),
+            _ => {
+                self.pos = start;
+                Err(self.error("repetition count too large"))
+            }
+        }
+    }
+}
+
+/// A state of the automaton
+#[derive(Debug, Clone, Copy)]
+enum State {
+    /// Consume a nucleotide among those whose bits are set, then go to `next`
+    Nucs {
+        nucs: u8,
+        next: usize,
+    },
+    /// Go to both states without consuming anything
+    Split(usize, usize),
+    Match,
+}
+
+/// A Thompson automaton, run by following all its states at once
+#[derive(Debug, Clone)]
+struct Automaton {
+    states: Vec<State>,
+    start: usize,
+}
+
+impl Automaton {
+    fn new(node: &Node) -> Self {
+        let mut states = vec![State::Match];
+        let start = compile(node, 0, &mut states);
+        Automaton { states, start }
+    }
+
+    /// The leftmost-longest, non-overlapping matches in `dna`
+    fn find_all(&self, dna: &PackedDna) -> Vec<Range<usize>> {
+        let mut found = Vec::new();
+        // Live states with the start of the match they belong to, by increasing start. Each
+        // state is kept once, with its leftmost start: the rest of the match cannot depend on
+        // where it began.
+        let mut threads: Vec<(usize, usize)> = Vec::new();
+        let mut next_threads = Vec::new();
+        let mut added = vec![0usize; self.states.len()];
+        let mut generation = 1;
+        let mut best: Option<Range<usize>> = None;
+        let mut pos = 0;
+        loop {
+            if best.is_none() {
+                self.add(
+                    &mut threads,
+                    self.start,
+                    (pos, pos),
+                    &mut best,
+                    (&mut added, generation),
+                );
+            }
+            if let Some(best) = &best {
+                // Later starts can no longer win, earlier ones still can.
+                threads.retain(|&(_, start)| start <= best.start);
+            }
+            if threads.is_empty() || pos == dna.len() {
+                match best.take() {
+                    Some(range) => {
+                        // Resume right after the match, which may lie behind `pos`.
+                        pos = range.end;
+                        found.push(range);
+                        threads.clear();
+                        generation += 1;
+                        continue;
+                    }
+                    None if pos == dna.len() => break,
+                    None => {}
+                }
+            }
+            let nuc = dna.nuc_at(pos) as u8;
+            pos += 1;
+            generation += 1;
+            next_threads.clear();
+            for &(state, start) in &threads {
+                if let State::Nucs { nucs, next } = self.states[state] {
+                    if nucs >> nuc & 1 == 1 {
+                        self.add(
+                            &mut next_threads,
+                            next,
+                            (start, pos),
+                            &mut best,
+                            (&mut added, generation),
+                        );
+                    }
+                }
+            }
+            std::mem::swap(&mut threads, &mut next_threads);
+        }
+        found
+    }
+
+    /// Add `state` and the states reachable from it without consuming a nucleotide to
+    /// `threads`, for a match spanning `start..pos` so far, recording a match ending at `pos`
+    /// in `best` if it is leftmost, then longest
+    fn add(
+        &self,
+        threads: &mut Vec<(usize, usize)>,
+        state: usize,
+        (start, pos): (usize, usize),
+        best: &mut Option<Range<usize>>,
+        (added, generation): (&mut [usize], usize),
+    ) {
+        let mut pending = vec![state];
+        while let Some(state) = pending.pop() {
+            if added[state] == generation {
+                continue;
+            }
+            added[state] = generation;
+            match self.states[state] {
+                State::Nucs { .. } => threads.push((state, start)),
+                State::Split(first, second) => {
+                    pending.push(second);
+                    pending.push(first);
+                }
+                State::Match => {
+                    let better = best.as_ref().is_none_or(|best| {
+                        start < best.start || (start == best.start && pos > best.end)
+                    });
+                    if better {
+                        *best = Some(start..pos);
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// Compile `node` into `states`, continuing to the state `next`, and return its first state
+fn compile(node: &Node, next: usize, states: &mut Vec<State>) -> usize {
+    fn push(states: &mut Vec<State>, state: State) -> usize {
+        states.push(state);
+        states.len() - 1
+    }
+    match node {
+        Node::Nucs(nucs) => push(states, State::Nucs { nucs: *nucs, next }),
+        Node::Concat(nodes) => nodes
+            .iter()
+            .rev()
+            .fold(next, |next, node| compile(node, next, states)),
+        Node::Alternation(nodes) => {
+            let mut starts: Vec<usize> = nodes
+                .iter()
+                .map(|node| compile(node, next, states))
+                .collect();
+            let mut first = starts.pop().unwrap_or(next);
+            while let Some(start) = starts.pop() {
+                first = push(states, State::Split(start, first));
+            }
+            first
+        }
+        Node::Repeat { node, min, max } => {
+            let mut first = match max {
+                Some(max) => (*min..*max).fold(next, |next, _| {
+                    let start = compile(node, next, states);
+                    push(states, State::Split(start, next))
+                }),
+                None => {
+                    // A loop: the split is filled in once the body pointing back at it exists.
+                    let split = push(states, State::Split(next, next));
+                    let body = compile(node, split, states);
+                    states[split] = State::Split(body, next);
+                    split
+                }
+            };
+            for _ in 0..*min {
+                first = compile(node, first, states);
+            }
+            first
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::dna;
+
+    fn matches(pattern: &str, text: &str) -> Vec<(Range<usize>, char)> {
+        IupacRegex::new(pattern)
+            .unwrap()
+            .find_all(&dna(text))
+            .into_iter()
+            .map(|m| {
+                let strand = match m.strand {
+                    Strand::Forward => '+',
+                    Strand::Reverse => '-',
+                };
+                (m.range, strand)
+            })
+            .collect()
+    }
+
+    #[test]
+    fn repetitions_and_classes() {
+        let text = "TTGGACCCAAAAAAAAAAGGTTGGTCCCCTTTTTTTTTTGG";
+        assert_eq!(
+            matches("GGWCC{2,4}N{10}GG", text),
+            [(2..20, '+'), (22..41, '+')]
+        );
+        // The reverse complement of the pattern, CCN{10}G{2,4}GWCC, is found on the forward
+        // strand and reported on the reverse one.
+        assert_eq!(
+            matches("GGWCC{2,4}N{10}GG", "CCAAAAAAAAAAGGGGTCC"),
+            [(0..19, '-')]
+        );
+        assert_eq!(matches("[^AT]+", "AACGGTAGCA"), [(2..5, '+'), (7..9, '+')]);
+        assert_eq!(
+            matches("A(CG|T)+a", "ACGTCGATA"),
+            [(0..7, '+'), (3..8, '-')]
+        );
+        assert_eq!(matches("a(cg|t)+A", "ACGTCGATA").len(), 2);
+    }
+
+    #[test]
+    fn leftmost_longest_without_overlaps() {
+        let regex = IupacRegex::new("AC*").unwrap().forward_only();
+        let found: Vec<Range<usize>> = regex
+            .find_all(&dna("TACCCAACAT"))
+            .into_iter()
+            .map(|m| m.range)
+            .collect();
+        assert_eq!(found, [1..5, 5..6, 6..8, 8..9]);
+        // An earlier start wins over a longer match starting later.
+        assert_eq!(matches("AAT|ATTTTT", "AATTTTT"), [(0..3, '+'), (1..4, '-')]);
+        // Palindromes are only reported once.
+        assert_eq!(
+            matches("GAATTC", "GAATTCGAATTC"),
+            [(0..6, '+'), (6..12, '+')]
+        );
+    }
+
+    #[test]
+    fn invalid_patterns() {
+        let err = |pattern: &str| IupacRegex::new(pattern).unwrap_err().to_string();
+        assert_eq!(
+            err("AC(GT"),
+            "invalid pattern at position 5: unclosed group"
+        );
+        assert_eq!(
+            err("ACGT)"),
+            "invalid pattern at position 4: unmatched closing parenthesis"
+        );
+        assert_eq!(
+            err("AC[GT"),
+            "invalid pattern at position 5: unclosed class"
+        );
+        assert_eq!(
+            err("A*+"),
+            "invalid pattern at position 2: nothing to repeat"
+        );
+        assert_eq!(
+            err("AXG"),
+            "invalid pattern at position 1: not an IUPAC code"
+        );
+        assert_eq!(
+            err("A{3,2}"),
+            "invalid pattern at position 6: repetition maximum below its minimum"
+        );
+        assert_eq!(
+            err("A{5000}"),
+            "invalid pattern at position 2: repetition count too large"
+        );
+        assert_eq!(
+            err("[^N]"),
+            "invalid pattern at position 3: class matches no nucleotide"
+        );
+        assert_eq!(err("A*|C"), "pattern matches the empty sequence");
+        assert_eq!(err(""), "pattern matches the empty sequence");
+        assert_eq!(
+            IupacRegex::new("(N{1000}){1000}").unwrap_err(),
+            RegexError::TooLarge
+        );
+    }
+}
@@ -0,0 +1,209 @@
+//! Exact motif search on packed sequences.
+//!
+//! The haystack is scanned once with a rolling 2-bit window holding up to the first 32
+//! nucleotides of the needle, so no text is decoded. Longer needles verify their remaining
+//! nucleotides only where the window matches.
+
+use crate::{kmer::MAX_K, PackedDna};
+use std::ops::Range;
+
+impl PackedDna {
+    /// 0-based position of the first occurrence of `needle`, or `None` if it does not occur.
+    ///
+    /// An empty needle matches at position 0.
+    pub fn find(&self, needle: &PackedDna) -> Option<usize> {
+        self.find_all(needle).next()
+    }
+
+    /// 0-based positions of every occurrence of `needle`, in increasing order.
+    ///
+    /// Overlapping occurrences are all reported. An empty needle matches at every position from
+    /// 0 to the sequence length inclusive, like [`str::match_indices`].
+    pub fn find_all<'a>(&'a self, needle: &'a PackedDna) -> Matches<'a> {
+        self.find_all_in(0..self.len(), needle)
+    }
+
+    /// Occurrences of `needle` within the in-bounds `range`, as positions relative to its start
+    pub(crate) fn find_all_in<'a>(
+        &'a self,
+        range: Range<usize>,
+        needle: &'a PackedDna,
+    ) -> Matches<'a> {
+        let k = needle.len().min(MAX_K);
+        let prefix = (0..k).fold(0, |packed, i| (packed << 2) | needle.nuc_at(i) as u64);
+        Matches {
+            haystack: self,
+            needle,
+            k,
+            mask: if k == MAX_K {
+                u64::MAX
+            } else {
+                (1 << (2 * k)) - 1
+            },
+            prefix,
+            window: 0,
+            start: range.start,
+            end: range.end,
+            pos: range.start,
+        }
+    }
+}
+
+#[cfg(feature = "rayon")]
+impl PackedDna {
+    /// Positions of every occurrence of `needle` like [`find_all`](PackedDna::find_all),
+    /// searching chunks of the sequence in parallel
+    pub fn par_find_all(&self, needle: &PackedDna) -> Vec<usize> {
+        self.par_find_all_chunked(needle, crate::PAR_CHUNK_LEN)
+    }
+
+    fn par_find_all_chunked(&self, needle: &PackedDna, chunk_len: usize) -> Vec<usize> {
+        use rayon::prelude::*;
+        let len = self.len();
+        let needle_len = needle.len();
+        if needle_len == 0 {
+            return self.find_all(needle).collect();
+        }
+        let starts: Vec<usize> = (0..len).step_by(chunk_len).collect();
+        starts
+            .into_par_iter()
+            .flat_map_iter(|start| {
+                // Chunks overlap by the needle length - 1 so each match is found in the chunk
+                // it starts in.
+                let end = (start + chunk_len + needle_len - 1).min(len);
+                let positions: Vec<usize> = self
+                    .to_slice(start..end)
+                    .find_all(needle)
+                    .map(|pos| start + pos)
+                    .collect();
+                positions
+            })
+            .collect()
+    }
+}
+
+/// Iterator over the positions of a motif, see [`PackedDna::find_all`]
+#[derive(Debug, Clone)]
+pub struct Matches<'a> {
+    haystack: &'a PackedDna,
+    needle: &'a PackedDna,
+    /// Number of needle nucleotides held in `prefix`
+    k: usize,
+    mask: u64,
+    /// The first `k` nucleotides of the needle, packed
+    prefix: u64,
+    /// The last `k` haystack nucleotides before `pos`, packed
+    window: u64,
+    /// Start of the searched range of the haystack
+    start: usize,
+    /// End of the searched range of the haystack
+    end: usize,
+    /// Next haystack position to read
+    pos: usize,
+}
+
+impl Iterator for Matches<'_> {
+    type Item = usize;
+
+    fn next(&mut self) -> Option<usize> {
+        let len = self.end;
+        if self.k == 0 {
+            self.pos += 1;
+            return (self.pos <= len + 1).then(|| self.pos - 1 - self.start);
+        }
+        let needle_len = self.needle.len();
+        while self.pos < len {
+            self.window = ((self.window << 2) | self.haystack.nuc_at(self.pos) as u64) & self.mask;
+            self.pos += 1;
+            if self.pos - self.start < self.k || self.window != self.prefix {
+                continue;
+            }
+            let start = self.pos - self.k;
+            if start + needle_len <= len
+                && (self.k..needle_len)
+                    .all(|i| self.haystack.nuc_at(start + i) == self.needle.nuc_at(i))
+            {
+                return Some(start - self.start);
+            }
+        }
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::dna;
+
+    #[test]
+    fn find_first_occurrence() {
+        let haystack = dna("TTGATTACAGATTACA");
+        assert_eq!(haystack.find(&dna("GATTACA")), Some(2));
+        assert_eq!(haystack.find(&dna("TTT")), None);
+        assert_eq!(haystack.find(&dna("TTGATTACAGATTACAA")), None);
+    }
+
+    #[test]
+    fn find_all_overlapping() {
+        let haystack = dna("AAAACAAA");
+        assert_eq!(
+            haystack.find_all(&dna("AA")).collect::<Vec<_>>(),
+            vec![0, 1, 2, 5, 6]
+        );
+    }
+
+    #[test]
+    fn find_all_matches_naive_search() {
+        let text = "ACGTTGCACTGGATTACAACGTTGCA";
+        let haystack = dna(text);
+        for needle in ["A", "CA", "ACGTTGCA", "GCACTGGAT", "TGCA"] {
+            let expected: Vec<usize> = (0..=text.len() - needle.len())
+                .filter(|&i| &text[i..i + needle.len()] == needle)
+                .collect();
+            assert_eq!(
+                haystack.find_all(&dna(needle)).collect::<Vec<_>>(),
+                expected
+            );
+        }
+    }
+
+    #[test]
+    fn find_needle_longer_than_window() {
+        let motif = "ACGTTGCACTGGATTACAACGTTGCATTGACCAGT";
+        assert!(motif.len() > MAX_K);
+        let haystack = dna(&format!("GG{}C{}", motif, motif));
+        assert_eq!(
+            haystack.find_all(&dna(motif)).collect::<Vec<_>>(),
+            vec![2, 3 + motif.len()]
+        );
+        // Same first 32 nucleotides, different tail
+        let mut decoy = motif[..MAX_K].to_string();
+        decoy.push_str("TTT");
+        assert_eq!(haystack.find(&dna(&decoy)), None);
+    }
+
+    #[cfg(feature = "rayon")]
+    #[test]
+    fn par_find_all_matches_serial() {
+        let haystack = dna(&"TTGATTACAGATTACAAAA".repeat(5));
+        for needle in ["A", "AA", "GATTACA", "ACAAAATTGATTACAGATTACAAAATTGATTACA"] {
+            let needle = dna(needle);
+            let expected: Vec<usize> = haystack.find_all(&needle).collect();
+            for chunk_len in [1, 4, 7, 64, 1000] {
+                assert_eq!(haystack.par_find_all_chunked(&needle, chunk_len), expected);
+            }
+            assert_eq!(haystack.par_find_all(&needle), expected);
+        }
+    }
+
+    #[test]
+    fn find_empty_needle() {
+        let haystack = dna("ACG");
+        assert_eq!(haystack.find(&dna("")), Some(0));
+        assert_eq!(
+            haystack.find_all(&dna("")).collect::<Vec<_>>(),
+            vec![0, 1, 2, 3]
+        );
+        assert_eq!(dna("").find(&dna("A")), None);
+    }
+}
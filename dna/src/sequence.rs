@@ -0,0 +1,199 @@
+//! An object-safe view over the crate's sequence types.
+//!
+//! [`Sequence`] lets code hold sequences of different representations side by side, e.g. in a
+//! `Vec<Box<dyn Sequence>>`, and pick the representation at runtime. Each position is exposed
+//! as its text symbol, so ambiguity codes, soft-masking and RNA uracil survive the dispatch.
+
+use crate::{
+    iupac::{AmbiguityPolicy, Iupac, IupacDna},
+    softmask::SoftMaskedDna,
+    translate::PackedRna,
+    view::RevCompView,
+    PackedDna,
+};
+use std::fmt;
+
+/// Operations shared by every nucleotide sequence type, usable as `dyn Sequence`
+pub trait Sequence {
+    /// Number of positions in the sequence
+    fn len(&self) -> usize;
+
+    /// Whether the sequence has no positions
+    fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// The symbol at the 0-based position `idx`, or `None` if out of bounds
+    fn char_at(&self, idx: usize) -> Option<char>;
+
+    /// Iterate over the symbols of the sequence
+    fn chars(&self) -> Box<dyn Iterator<Item = char> + '_> {
+        Box::new((0..self.len()).filter_map(move |idx| self.char_at(idx)))
+    }
+
+    /// Fraction of positions that are G, C or the IUPAC code S, or 0 for an empty sequence
+    fn gc_content(&self) -> f64 {
+        if self.is_empty() {
+            return 0.0;
+        }
+        let gc = self
+            .chars()
+            .filter(|c| matches!(c.to_ascii_uppercase(), 'G' | 'C' | 'S'))
+            .count();
+        gc as f64 / self.len() as f64
+    }
+
+    /// The sequence as unambiguous DNA, or `None` if some position is ambiguous
+    fn to_packed(&self) -> Option<PackedDna>;
+}
+
+impl fmt::Display for dyn Sequence + '_ {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.chars().try_for_each(|c| write!(f, "{}", c))
+    }
+}
+
+impl Sequence for PackedDna {
+    fn len(&self) -> usize {
+        PackedDna::len(self)
+    }
+
+    fn char_at(&self, idx: usize) -> Option<char> {
+        self.get(idx).map(|nuc| nuc.to_char())
+    }
+
+    fn chars(&self) -> Box<dyn Iterator<Item = char> + '_> {
+        Box::new(self.iter().map(|nuc| nuc.to_char()))
+    }
+
+    fn gc_content(&self) -> f64 {
+        PackedDna::gc_content(self)
+    }
+
+    fn to_packed(&self) -> Option<PackedDna> {
+        Some(self.clone())
+    }
+}
+
+impl Sequence for IupacDna {
+    fn len(&self) -> usize {
+        IupacDna::len(self)
+    }
+
+    fn char_at(&self, idx: usize) -> Option<char> {
+        self.get(idx).map(Iupac::to_char)
+    }
+
+    fn chars(&self) -> Box<dyn Iterator<Item = char> + '_> {
+        Box::new(self.iter().map(Iupac::to_char))
+    }
+
+    fn to_packed(&self) -> Option<PackedDna> {
+        IupacDna::to_packed(self, AmbiguityPolicy::Error).ok()
+    }
+}
+
+impl Sequence for SoftMaskedDna {
+    fn len(&self) -> usize {
+        SoftMaskedDna::len(self)
+    }
+
+    fn char_at(&self, idx: usize) -> Option<char> {
+        let c = self.dna().get(idx)?.to_char();
+        Some(if self.is_masked(idx)? {
+            c.to_ascii_lowercase()
+        } else {
+            c
+        })
+    }
+
+    fn gc_content(&self) -> f64 {
+        self.dna().gc_content()
+    }
+
+    fn to_packed(&self) -> Option<PackedDna> {
+        Some(self.dna().clone())
+    }
+}
+
+impl Sequence for PackedRna {
+    fn len(&self) -> usize {
+        PackedRna::len(self)
+    }
+
+    fn char_at(&self, idx: usize) -> Option<char> {
+        self.dna.get(idx).map(|nuc| match nuc.to_char() {
+            'T' => 'U',
+            c => c,
+        })
+    }
+
+    fn gc_content(&self) -> f64 {
+        self.dna.gc_content()
+    }
+
+    fn to_packed(&self) -> Option<PackedDna> {
+        Some(self.back_transcribe())
+    }
+}
+
+impl Sequence for RevCompView<'_> {
+    fn len(&self) -> usize {
+        RevCompView::len(self)
+    }
+
+    fn char_at(&self, idx: usize) -> Option<char> {
+        self.get(idx).map(|nuc| nuc.to_char())
+    }
+
+    fn chars(&self) -> Box<dyn Iterator<Item = char> + '_> {
+        Box::new(self.iter().map(|nuc| nuc.to_char()))
+    }
+
+    fn gc_content(&self) -> f64 {
+        RevCompView::gc_content(self)
+    }
+
+    fn to_packed(&self) -> Option<PackedDna> {
+        Some(RevCompView::to_packed(self))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::str::FromStr;
+
+    #[test]
+    fn heterogeneous_collection() {
+        let mut masked = SoftMaskedDna::new(PackedDna::from_str("ACGTAC").unwrap());
+        masked.set_masked(0..2, true);
+        let forward = PackedDna::from_str("ACTTG").unwrap();
+        let sequences: Vec<Box<dyn Sequence>> = vec![
+            Box::new(PackedDna::from_str("ACGT").unwrap()),
+            Box::new(IupacDna::from_str("ACNGS").unwrap()),
+            Box::new(masked),
+            Box::new(PackedDna::from_str("GGTT").unwrap().transcribe()),
+            Box::new(forward.revcomp_view()),
+        ];
+        let text: Vec<String> = sequences.iter().map(|seq| seq.to_string()).collect();
+        assert_eq!(text, ["ACGT", "ACNGS", "acGTAC", "GGUU", "CAAGT"]);
+        let lens: Vec<usize> = sequences.iter().map(|seq| seq.len()).collect();
+        assert_eq!(lens, [4, 5, 6, 4, 5]);
+        assert_eq!(sequences[1].char_at(2), Some('N'));
+        assert_eq!(sequences[2].char_at(6), None);
+        assert!((sequences[1].gc_content() - 0.6).abs() < 1e-12);
+        assert!((sequences[3].gc_content() - 0.5).abs() < 1e-12);
+    }
+
+    #[test]
+    fn to_packed_unless_ambiguous() {
+        let iupac: Box<dyn Sequence> = Box::new(IupacDna::from_str("ACNT").unwrap());
+        assert_eq!(iupac.to_packed(), None);
+        let rna: Box<dyn Sequence> = Box::new(PackedDna::from_str("ACGT").unwrap().transcribe());
+        assert_eq!(rna.to_packed().unwrap().to_string(), "ACGT");
+        let empty: &dyn Sequence = &PackedDna::from_str("").unwrap();
+        assert!(empty.is_empty());
+        assert_eq!(empty.gc_content(), 0.0);
+    }
+}
@@ -0,0 +1,156 @@
+//! Strand-symmetric k-mer signatures for alignment-free genome comparison.
+//!
+//! A signature holds the frequency of every canonical k-mer, counting each k-mer together with
+//! its reverse complement, so it does not depend on which strand was sequenced. Tetranucleotide
+//! signatures (`k = 4`) are a standard taxonomic signal: genomes of related organisms have
+//! strongly correlated signatures.
+
+use crate::{kmer::KmerCounter, PackedDna};
+
+/// Frequencies of the canonical k-mers of a sequence
+#[derive(Debug, Clone, PartialEq)]
+pub struct Signature {
+    k: usize,
+    /// Canonical k-mers, ascending
+    kmers: Vec<u64>,
+    /// Frequency of each k-mer in `kmers`, summing to 1 unless no k-mer was counted
+    frequencies: Vec<f64>,
+}
+
+impl Signature {
+    /// The k-mer signature of `dna`.
+    ///
+    /// A sequence shorter than `k` has all frequencies zero.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `k` is zero or greater than 12.
+    pub fn new(dna: &PackedDna, k: usize) -> Self {
+        assert!(
+            (1..=12).contains(&k),
+            "signature k must be between 1 and 12, got {}",
+            k
+        );
+        let counts = KmerCounter::new(k).canonical(true).count(dna);
+        let total: usize = counts.iter().map(|(_, count)| count).sum();
+        let kmers: Vec<u64> = (0..1u64 << (2 * k))
+            .filter(|&kmer| kmer <= reverse_complement(kmer, k))
+            .collect();
+        let frequencies = kmers
+            .iter()
+            .map(|&kmer| {
+                if total == 0 {
+                    0.0
+                } else {
+                    counts.get(kmer) as f64 / total as f64
+                }
+            })
+            .collect();
+        Signature {
+            k,
+            kmers,
+            frequencies,
+        }
+    }
+
+    /// The tetranucleotide signature of `dna`, over its 136 canonical 4-mers
+    pub fn tetranucleotide(dna: &PackedDna) -> Self {
+        Signature::new(dna, 4)
+    }
+
+    /// The k-mer length
+    pub fn k(&self) -> usize {
+        self.k
+    }
+
+    /// The canonical k-mers, packed as in [`kmer`](crate::kmer), in ascending order
+    pub fn kmers(&self) -> &[u64] {
+        &self.kmers
+    }
+
+    /// The frequency of each k-mer of [`kmers`](Self::kmers)
+    pub fn frequencies(&self) -> &[f64] {
+        &self.frequencies
+    }
+
+    /// The frequency of the packed k-mer `kmer` together with its reverse complement
+    pub fn get(&self, kmer: u64) -> f64 {
+        let canonical = kmer.min(reverse_complement(kmer, self.k));
+        self.kmers
+            .binary_search(&canonical)
+            .map_or(0.0, |i| self.frequencies[i])
+    }
+
+    /// Pearson correlation between two signatures, from -1 to 1.
+    ///
+    /// Returns `None` if the signatures use different k-mer lengths or either has no variation,
+    /// e.g. because its sequence was shorter than `k`.
+    pub fn correlation(&self, other: &Signature) -> Option<f64> {
+        if self.k != other.k {
+            return None;
+        }
+        let n = self.frequencies.len() as f64;
+        let mean_a = self.frequencies.iter().sum::<f64>() / n;
+        let mean_b = other.frequencies.iter().sum::<f64>() / n;
+        let (mut cov, mut var_a, mut var_b) = (0.0, 0.0, 0.0);
+        for (a, b) in self.frequencies.iter().zip(&other.frequencies) {
+            cov += (a - mean_a) * (b - mean_b);
+            var_a += (a - mean_a) * (a - mean_a);
+            var_b += (b - mean_b) * (b - mean_b);
+        }
+        if var_a == 0.0 || var_b == 0.0 {
+            return None;
+        }
+        Some(cov / (var_a * var_b).sqrt())
+    }
+}
+
+/// Reverse complement of a packed k-mer of length `k`
+fn reverse_complement(kmer: u64, k: usize) -> u64 {
+    (0..k).fold(0, |rc, i| rc << 2 | (3 - (kmer >> (2 * i) & 0b11)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::kmer::encode;
+    use crate::test_util::dna;
+
+    #[test]
+    fn tetranucleotide_has_136_canonical_kmers() {
+        let signature = Signature::tetranucleotide(&dna("ACGTTGCAACGGT"));
+        assert_eq!(signature.kmers().len(), 136);
+        assert!((signature.frequencies().iter().sum::<f64>() - 1.0).abs() < 1e-12);
+    }
+
+    #[test]
+    fn strand_symmetric() {
+        let seq = dna("AACGTTTGCAGGATTACAGATTACCA");
+        let signature = Signature::new(&seq, 3);
+        assert_eq!(signature, Signature::new(&seq.reverse_complement(), 3));
+        assert_eq!(
+            signature.get(encode("AAC").unwrap()),
+            signature.get(encode("GTT").unwrap())
+        );
+    }
+
+    #[test]
+    fn correlation_ranks_similar_sequences() {
+        let at_rich = Signature::new(&dna(&"AATTAGATTACATTAT".repeat(4)), 2);
+        let at_rich_shifted = Signature::new(&dna(&"ATTAGATTACATTATA".repeat(4)), 2);
+        let gc_rich = Signature::new(&dna(&"GGCGCCGAGCGGCCGC".repeat(4)), 2);
+        assert!((at_rich.correlation(&at_rich).unwrap() - 1.0).abs() < 1e-12);
+        let similar = at_rich.correlation(&at_rich_shifted).unwrap();
+        let different = at_rich.correlation(&gc_rich).unwrap();
+        assert!(similar > 0.9);
+        assert!(different < similar);
+    }
+
+    #[test]
+    fn correlation_undefined() {
+        let short = Signature::new(&dna("AC"), 3);
+        let long = Signature::new(&dna("ACGTTGCA"), 3);
+        assert_eq!(short.correlation(&long), None);
+        assert_eq!(long.correlation(&Signature::new(&dna("ACGTTGCA"), 2)), None);
+    }
+}
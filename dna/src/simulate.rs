@@ -0,0 +1,307 @@
+//! Random sequences, shuffles and simulated reads, with the `rand` feature.
+//!
+//! Shuffles keep the composition of a sequence while destroying its order, which makes them null
+//! models for motif enrichment: [`PackedDna::shuffle`] keeps the nucleotide counts, and
+//! [`PackedDna::shuffle_dinucleotides`] also keeps the count of every dinucleotide, and with it
+//! effects such as CpG depletion.
+//!
+//! Every function takes the random number generator as an argument, so a seeded generator such
+//! as `rand::rngs::StdRng::seed_from_u64` makes property tests and benchmarks reproducible.
+
+use crate::{aligned::AlignedBuf, Nuc, PackedDna};
+use rand::{seq::SliceRandom, Rng};
+
+const NUCS: [Nuc; 4] = [Nuc::A, Nuc::C, Nuc::G, Nuc::T];
+
+impl PackedDna {
+    /// A sequence of `len` nucleotides drawn uniformly at random
+    pub fn random<R: Rng + ?Sized>(len: usize, rng: &mut R) -> PackedDna {
+        // Every byte of random bits is four random nucleotides.
+        let mut bytes = AlignedBuf::with_capacity(len.div_ceil(4));
+        for _ in 0..len / 4 {
+            bytes.push(rng.random());
+        }
+        let tail = len % 4;
+        if tail != 0 {
+            bytes.push(rng.random::<u8>() >> (2 * (4 - tail)));
+        }
+        PackedDna::from_packed(bytes, len)
+    }
+
+    /// A sequence of `len` nucleotides drawn independently with the probabilities `probs` of
+    /// A, C, G and T, which are normalised to sum to 1.
+    ///
+    /// # Panics
+    ///
+    /// Panics if a probability is negative or not finite, or if they are all 0.
+    pub fn random_with_composition<R: Rng + ?Sized>(
+        len: usize,
+        probs: [f64; 4],
+        rng: &mut R,
+    ) -> PackedDna {
+        assert!(
+            probs.iter().all(|p| p.is_finite() && *p >= 0.0) && probs.iter().sum::<f64>() > 0.0,
+            "invalid nucleotide probabilities {:?}",
+            probs
+        );
+        let total: f64 = probs.iter().sum();
+        let mut cumulative = [0.0; 4];
+        let mut sum = 0.0;
+        for (bound, p) in cumulative.iter_mut().zip(&probs) {
+            sum += p / total;
+            *bound = sum;
+        }
+        let mut dna = PackedDna::with_capacity(len);
+        for _ in 0..len {
+            let x: f64 = rng.random();
+            // The last nucleotide with a non-zero probability absorbs rounding at the top.
+            let idx = cumulative
+                .iter()
+                .position(|&bound| x < bound)
+                .unwrap_or_else(|| probs.iter().rposition(|&p| p > 0.0).unwrap());
+            dna.push(NUCS[idx]);
+        }
+        dna
+    }
+}
+
+impl PackedDna {
+    /// A random permutation of the sequence, with the same nucleotide counts
+    pub fn shuffle<R: Rng + ?Sized>(&self, rng: &mut R) -> PackedDna {
+        let mut nucs: Vec<Nuc> = self.iter().collect();
+        nucs.shuffle(rng);
+        nucs.into_iter().collect()
+    }
+
+    /// A random sequence with the same first nucleotide and the same count of every
+    /// dinucleotide, by the Altschul-Erickson algorithm.
+    ///
+    /// The sequence is a walk through the graph with an edge from each nucleotide to the next.
+    /// Every walk using each edge once is equally likely: the last edge out of each nucleotide is
+    /// drawn so that those edges form a random tree towards the last nucleotide, and the other
+    /// edges are shuffled before it.
+    pub fn shuffle_dinucleotides<R: Rng + ?Sized>(&self, rng: &mut R) -> PackedDna {
+        if self.len() < 3 {
+            return self.clone();
+        }
+        let mut edges: [Vec<Nuc>; 4] = Default::default();
+        for pos in 1..self.len() {
+            edges[self.nuc_at(pos - 1) as usize].push(self.nuc_at(pos));
+        }
+        // Wilson's algorithm: loop-erased random walks towards the last nucleotide.
+        let last = self.nuc_at(self.len() - 1) as usize;
+        let mut in_tree = [false; 4];
+        in_tree[last] = true;
+        let mut exit = [0; 4];
+        for start in 0..4 {
+            let mut nuc = start;
+            while !in_tree[nuc] && !edges[nuc].is_empty() {
+                exit[nuc] = rng.random_range(0..edges[nuc].len());
+                nuc = edges[nuc][exit[nuc]] as usize;
+            }
+            nuc = start;
+            while !in_tree[nuc] && !edges[nuc].is_empty() {
+                in_tree[nuc] = true;
+                nuc = edges[nuc][exit[nuc]] as usize;
+            }
+        }
+        for (nuc, out) in edges.iter_mut().enumerate() {
+            if out.is_empty() {
+                continue;
+            }
+            let len = out.len();
+            if nuc != last {
+                out.swap(exit[nuc], len - 1);
+                out[..len - 1].shuffle(rng);
+            } else {
+                out.shuffle(rng);
+            }
+        }
+        let mut next = [0; 4];
+        let mut nuc = self.nuc_at(0);
+        let mut dna = PackedDna::with_capacity(self.len());
+        dna.push(nuc);
+        for _ in 1..self.len() {
+            let idx = nuc as usize;
+            nuc = edges[idx][next[idx]];
+            next[idx] += 1;
+            dna.push(nuc);
+        }
+        dna
+    }
+}
+
+/// A read sampled by a [`ReadSimulator`]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SimulatedRead {
+    /// 0-based position of the fragment in the reference
+    pub position: usize,
+    /// The fragment, with substitution errors applied
+    pub sequence: PackedDna,
+    /// Number of substitution errors applied
+    pub errors: usize,
+}
+
+/// Samples fixed-length reads from random positions of a reference
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ReadSimulator {
+    read_len: usize,
+    error_rate: f64,
+}
+
+impl ReadSimulator {
+    /// Simulate error-free reads of `read_len` nucleotides
+    pub fn new(read_len: usize) -> Self {
+        ReadSimulator {
+            read_len,
+            error_rate: 0.0,
+        }
+    }
+
+    /// Replace each nucleotide of a read by one of the other three with probability
+    /// `error_rate`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `error_rate` is not between 0 and 1.
+    pub fn error_rate(mut self, error_rate: f64) -> Self {
+        assert!(
+            (0.0..=1.0).contains(&error_rate),
+            "error rate {} not between 0 and 1",
+            error_rate
+        );
+        self.error_rate = error_rate;
+        self
+    }
+
+    /// Sample one read from a uniformly random position of `reference`, or `None` if the
+    /// reference is shorter than a read
+    pub fn sample<R: Rng + ?Sized>(
+        &self,
+        reference: &PackedDna,
+        rng: &mut R,
+    ) -> Option<SimulatedRead> {
+        let last_start = reference.len().checked_sub(self.read_len)?;
+        let position = rng.random_range(0..=last_start);
+        let mut sequence = reference.to_slice(position..position + self.read_len);
+        let mut errors = 0;
+        for idx in 0..self.read_len {
+            if rng.random_bool(self.error_rate) {
+                let original = sequence.nuc_at(idx);
+                let others: Vec<Nuc> = NUCS.iter().copied().filter(|&n| n != original).collect();
+                // The index is in bounds, so setting cannot fail.
+                let _ = sequence.set(idx, others[rng.random_range(0..3)]);
+                errors += 1;
+            }
+        }
+        Some(SimulatedRead {
+            position,
+            sequence,
+            errors,
+        })
+    }
+
+    /// Sample `count` reads like [`sample`](Self::sample)
+    pub fn sample_n<R: Rng + ?Sized>(
+        &self,
+        reference: &PackedDna,
+        count: usize,
+        rng: &mut R,
+    ) -> Vec<SimulatedRead> {
+        (0..count)
+            .map_while(|_| self.sample(reference, rng))
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::{rngs::StdRng, SeedableRng};
+
+    #[test]
+    fn random_sequences() {
+        let mut rng = StdRng::seed_from_u64(1);
+        for len in [0, 1, 3, 4, 5, 1001] {
+            let dna = PackedDna::random(len, &mut rng);
+            assert_eq!(dna.len(), len);
+            assert_eq!(dna, dna.iter().collect::<PackedDna>());
+        }
+        let dna = PackedDna::random(40_000, &mut rng);
+        for (_, count) in dna.get_counts() {
+            assert!((9_000..11_000).contains(&count));
+        }
+        let same = PackedDna::random(100, &mut StdRng::seed_from_u64(7));
+        assert_eq!(same, PackedDna::random(100, &mut StdRng::seed_from_u64(7)));
+    }
+
+    #[test]
+    fn composition() {
+        let mut rng = StdRng::seed_from_u64(2);
+        let dna = PackedDna::random_with_composition(10_000, [0.0, 1.0, 3.0, 0.0], &mut rng);
+        let counts = dna.get_counts();
+        assert_eq!(counts[0].1 + counts[3].1, 0);
+        assert!((2_300..2_700).contains(&counts[1].1));
+        assert!((dna.gc_content() - 1.0).abs() < 1e-12);
+    }
+
+    #[test]
+    #[should_panic(expected = "invalid nucleotide probabilities")]
+    fn composition_rejects_zero() {
+        PackedDna::random_with_composition(5, [0.0; 4], &mut StdRng::seed_from_u64(0));
+    }
+
+    /// Counts of the 16 dinucleotides of `dna`
+    fn dinucleotides(dna: &PackedDna) -> [usize; 16] {
+        let mut counts = [0; 16];
+        for pos in 1..dna.len() {
+            counts[4 * dna.nuc_at(pos - 1) as usize + dna.nuc_at(pos) as usize] += 1;
+        }
+        counts
+    }
+
+    #[test]
+    fn shuffles_keep_composition() {
+        let mut rng = StdRng::seed_from_u64(4);
+        let dna: PackedDna = "ACGCGTTTAACGATCGGGCATTACGAAAAC".parse().unwrap();
+        let shuffled = dna.shuffle(&mut rng);
+        assert_ne!(shuffled, dna);
+        assert_eq!(shuffled.get_counts(), dna.get_counts());
+
+        let mut seen = std::collections::HashSet::new();
+        for _ in 0..50 {
+            let shuffled = dna.shuffle_dinucleotides(&mut rng);
+            assert_eq!(shuffled.len(), dna.len());
+            assert_eq!(shuffled.get(0), dna.get(0));
+            assert_eq!(dinucleotides(&shuffled), dinucleotides(&dna));
+            seen.insert(shuffled.to_string());
+        }
+        assert!(seen.len() > 10);
+
+        // Only one walk uses every edge of ACGT once.
+        let fixed: PackedDna = "ACGT".parse().unwrap();
+        assert_eq!(fixed.shuffle_dinucleotides(&mut rng), fixed);
+        let empty = PackedDna::random(0, &mut rng);
+        assert_eq!(empty.shuffle_dinucleotides(&mut rng), empty);
+    }
+
+    #[test]
+    fn simulated_reads() {
+        let mut rng = StdRng::seed_from_u64(3);
+        let reference = PackedDna::random(500, &mut rng);
+        let exact = ReadSimulator::new(50);
+        for read in exact.sample_n(&reference, 20, &mut rng) {
+            assert_eq!(read.errors, 0);
+            assert_eq!(
+                read.sequence,
+                reference.to_slice(read.position..read.position + 50)
+            );
+        }
+        let noisy = ReadSimulator::new(50).error_rate(1.0);
+        let read = noisy.sample(&reference, &mut rng).unwrap();
+        let original = reference.to_slice(read.position..read.position + 50);
+        assert_eq!(read.errors, 50);
+        assert_eq!(read.sequence.hamming_distance(&original), Some(50));
+        assert_eq!(ReadSimulator::new(501).sample(&reference, &mut rng), None);
+    }
+}
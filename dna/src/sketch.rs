@@ -0,0 +1,326 @@
+//! Minimizers and MinHash sketches for fast similarity estimation.
+//!
+//! Both work on hashed canonical k-mers, so they do not depend on which strand was sequenced.
+//! A [`Sketch`] keeps a small sample of a sequence's k-mer hashes; comparing two sketches
+//! estimates the Jaccard similarity of the full k-mer sets, from which [`Sketch::distance`]
+//! derives the Mash distance, an estimate of the per-nucleotide mutation rate.
+
+use crate::{
+    kmer::{Kmers, MAX_K},
+    PackedDna,
+};
+use std::collections::VecDeque;
+
+/// The k-mer minimizing the hash over a window of consecutive k-mers
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Minimizer {
+    /// 0-based start of the k-mer in the sequence
+    pub position: usize,
+    /// The packed canonical k-mer
+    pub kmer: u64,
+}
+
+/// The minimizers of every window of `w` consecutive k-mers of length `k`.
+///
+/// A minimizer shared by neighbouring windows is reported once, and ties go to the leftmost
+/// k-mer. A sequence with fewer than `w` k-mers has no minimizers.
+///
+/// # Panics
+///
+/// Panics if `w` is zero, or `k` is zero or greater than [`MAX_K`].
+pub fn minimizers(dna: &PackedDna, k: usize, w: usize) -> Vec<Minimizer> {
+    assert_k(k);
+    assert!(w > 0, "minimizer window must not be empty");
+    let mut minimizers: Vec<Minimizer> = Vec::new();
+    // Candidates of the current window with strictly increasing hashes
+    let mut window: VecDeque<(u64, Minimizer)> = VecDeque::new();
+    for (position, kmer) in Kmers::new(dna, k, true).enumerate() {
+        let hash = hash(kmer);
+        while window.back().is_some_and(|&(other, _)| other > hash) {
+            window.pop_back();
+        }
+        window.push_back((hash, Minimizer { position, kmer }));
+        while window
+            .front()
+            .is_some_and(|(_, m)| m.position + w <= position)
+        {
+            window.pop_front();
+        }
+        if position + 1 >= w {
+            let (_, minimizer) = window[0];
+            if minimizers.last() != Some(&minimizer) {
+                minimizers.push(minimizer);
+            }
+        }
+    }
+    minimizers
+}
+
+/// How a [`Sketch`] samples hashes
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Sampling {
+    /// The `size` smallest hashes
+    Bottom(usize),
+    /// Every hash below `u64::MAX / scaled`
+    Scaled(u64),
+}
+
+/// A sample of the hashed canonical k-mers of a sequence
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Sketch {
+    k: usize,
+    sampling: Sampling,
+    /// Distinct hashes, ascending
+    hashes: Vec<u64>,
+}
+
+impl Sketch {
+    /// A MinHash sketch keeping the `size` smallest k-mer hashes, like Mash.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `size` is zero, or `k` is zero or greater than [`MAX_K`].
+    pub fn minhash(dna: &PackedDna, k: usize, size: usize) -> Self {
+        assert_k(k);
+        assert!(size > 0, "sketch size must not be zero");
+        let mut hashes = Sketch::all_hashes(dna, k);
+        hashes.truncate(size);
+        Sketch {
+            k,
+            sampling: Sampling::Bottom(size),
+            hashes,
+        }
+    }
+
+    /// A FracMinHash sketch keeping about one k-mer hash in `scaled`.
+    ///
+    /// The sketch grows with the sequence, which makes [`containment`](Self::containment)
+    /// meaningful between sequences of very different lengths.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `scaled` is zero, or `k` is zero or greater than [`MAX_K`].
+    pub fn frac_minhash(dna: &PackedDna, k: usize, scaled: u64) -> Self {
+        assert_k(k);
+        assert!(scaled > 0, "sketch scale must not be zero");
+        let mut hashes = Sketch::all_hashes(dna, k);
+        let max_hash = u64::MAX / scaled;
+        hashes.truncate(hashes.partition_point(|&hash| hash <= max_hash));
+        Sketch {
+            k,
+            sampling: Sampling::Scaled(scaled),
+            hashes,
+        }
+    }
+
+    /// A MinHash sketch of `size` from distinct ascending `hashes`, such as the smallest ones of
+    /// several sequences, keeping only the `size` smallest
+    pub(crate) fn from_bottom(k: usize, size: usize, mut hashes: Vec<u64>) -> Self {
+        assert_k(k);
+        assert!(size > 0, "sketch size must not be zero");
+        debug_assert!(hashes.windows(2).all(|pair| pair[0] < pair[1]));
+        hashes.truncate(size);
+        Sketch {
+            k,
+            sampling: Sampling::Bottom(size),
+            hashes,
+        }
+    }
+
+    fn all_hashes(dna: &PackedDna, k: usize) -> Vec<u64> {
+        let mut hashes: Vec<u64> = Kmers::new(dna, k, true).map(hash).collect();
+        hashes.sort_unstable();
+        hashes.dedup();
+        hashes
+    }
+
+    /// The k-mer length
+    pub fn k(&self) -> usize {
+        self.k
+    }
+
+    /// The sampled hashes, in ascending order
+    pub fn hashes(&self) -> &[u64] {
+        &self.hashes
+    }
+
+    /// Number of sampled hashes
+    pub fn len(&self) -> usize {
+        self.hashes.len()
+    }
+
+    /// Whether no hash was sampled, e.g. because the sequence was shorter than `k`
+    pub fn is_empty(&self) -> bool {
+        self.hashes.is_empty()
+    }
+
+    /// Estimated Jaccard similarity of the k-mer sets of both sequences, from 0 to 1.
+    ///
+    /// Returns `None` if the sketches were built with different parameters or both are empty.
+    pub fn jaccard(&self, other: &Sketch) -> Option<f64> {
+        if self.k != other.k || self.sampling != other.sampling {
+            return None;
+        }
+        // For bottom sketches only the smallest hashes of the union are a valid sample.
+        let limit = match self.sampling {
+            Sampling::Bottom(size) => size,
+            Sampling::Scaled(_) => usize::MAX,
+        };
+        let (mut a, mut b) = (
+            self.hashes.iter().peekable(),
+            other.hashes.iter().peekable(),
+        );
+        let (mut union, mut shared) = (0usize, 0usize);
+        while union < limit {
+            match (a.peek(), b.peek()) {
+                (Some(x), Some(y)) if x == y => {
+                    shared += 1;
+                    a.next();
+                    b.next();
+                }
+                (Some(x), Some(y)) if x < y => {
+                    a.next();
+                }
+                (Some(_), Some(_)) | (None, Some(_)) => {
+                    b.next();
+                }
+                (Some(_), None) => {
+                    a.next();
+                }
+                (None, None) => break,
+            }
+            union += 1;
+        }
+        (union > 0).then(|| shared as f64 / union as f64)
+    }
+
+    /// The Mash distance between both sequences, from 0 for identical k-mer sets to 1 for
+    /// disjoint ones.
+    ///
+    /// Returns `None` if the sketches cannot be compared, see [`jaccard`](Self::jaccard).
+    pub fn distance(&self, other: &Sketch) -> Option<f64> {
+        let jaccard = self.jaccard(other)?;
+        if jaccard == 0.0 {
+            return Some(1.0);
+        }
+        let distance = ((1.0 + jaccard) / (2.0 * jaccard)).ln() / self.k as f64;
+        Some(distance.min(1.0))
+    }
+
+    /// Estimated fraction of the k-mers of this sequence that also occur in `other`.
+    ///
+    /// Only FracMinHash sketches give unbiased estimates when the sequences differ in length.
+    /// Returns `None` if the sketches were built with different parameters or this one is empty.
+    pub fn containment(&self, other: &Sketch) -> Option<f64> {
+        if self.k != other.k || self.sampling != other.sampling || self.is_empty() {
+            return None;
+        }
+        let shared = self
+            .hashes
+            .iter()
+            .filter(|hash| other.hashes.binary_search(hash).is_ok())
+            .count();
+        Some(shared as f64 / self.len() as f64)
+    }
+}
+
+fn assert_k(k: usize) {
+    assert!(
+        (1..=MAX_K).contains(&k),
+        "k-mer length must be between 1 and {}, got {}",
+        MAX_K,
+        k
+    );
+}
+
+/// Mix the bits of a packed k-mer so that small hashes are a uniform sample of k-mers
+pub(crate) fn hash(kmer: u64) -> u64 {
+    // The 64-bit finalizer of MurmurHash3, a bijection
+    let mut x = kmer;
+    x ^= x >> 33;
+    x = x.wrapping_mul(0xff51_afd7_ed55_8ccd);
+    x ^= x >> 33;
+    x = x.wrapping_mul(0xc4ce_b9fe_1a85_ec53);
+    x ^ (x >> 33)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::{dna, random};
+
+    /// A deterministic pseudo-random sequence of length `len`
+    fn random_dna(len: usize, seed: u64) -> String {
+        random(len, seed).to_string()
+    }
+
+    #[test]
+    fn minimizers_match_naive() {
+        let seq = dna(&random_dna(200, 1));
+        let (k, w) = (5, 4);
+        let kmers: Vec<u64> = Kmers::new(&seq, k, true).collect();
+        let mut expected: Vec<Minimizer> = Vec::new();
+        for start in 0..=kmers.len() - w {
+            let position = (start..start + w)
+                .min_by_key(|&i| (hash(kmers[i]), i))
+                .unwrap();
+            let minimizer = Minimizer {
+                position,
+                kmer: kmers[position],
+            };
+            if expected.last() != Some(&minimizer) {
+                expected.push(minimizer);
+            }
+        }
+        assert_eq!(minimizers(&seq, k, w), expected);
+        assert!(minimizers(&dna("ACGTAC"), 5, 3).is_empty());
+    }
+
+    #[test]
+    fn minhash_distance() {
+        let text = random_dna(5000, 2);
+        let mut mutated = text.clone().into_bytes();
+        for i in (0..mutated.len()).step_by(100) {
+            mutated[i] = if mutated[i] == b'A' { b'C' } else { b'A' };
+        }
+        let mutated = String::from_utf8(mutated).unwrap();
+
+        let original = Sketch::minhash(&dna(&text), 21, 500);
+        let reverse = Sketch::minhash(&dna(&text).reverse_complement(), 21, 500);
+        let close = Sketch::minhash(&dna(&mutated), 21, 500);
+        let unrelated = Sketch::minhash(&dna(&random_dna(5000, 3)), 21, 500);
+        assert_eq!(original.len(), 500);
+        assert_eq!(original.distance(&reverse), Some(0.0));
+        // One substitution every 100 nucleotides
+        let distance = original.distance(&close).unwrap();
+        assert!((0.005..0.02).contains(&distance), "{}", distance);
+        assert_eq!(original.distance(&unrelated), Some(1.0));
+    }
+
+    #[test]
+    fn frac_minhash_containment() {
+        let genome = random_dna(20_000, 4);
+        let whole = Sketch::frac_minhash(&dna(&genome), 15, 20);
+        let part = Sketch::frac_minhash(&dna(&genome[5000..10_000]), 15, 20);
+        assert!(whole.len() > 3 * part.len());
+        assert_eq!(part.containment(&whole), Some(1.0));
+        let reverse = whole.containment(&part).unwrap();
+        assert!((0.15..0.35).contains(&reverse), "{}", reverse);
+        let jaccard = whole.jaccard(&part).unwrap();
+        assert!((jaccard - reverse).abs() < 1e-12);
+    }
+
+    #[test]
+    fn incompatible_sketches() {
+        let seq = dna(&random_dna(100, 5));
+        let sketch = Sketch::minhash(&seq, 11, 10);
+        assert_eq!(sketch.distance(&Sketch::minhash(&seq, 12, 10)), None);
+        assert_eq!(sketch.distance(&Sketch::minhash(&seq, 11, 20)), None);
+        assert_eq!(sketch.jaccard(&Sketch::frac_minhash(&seq, 11, 10)), None);
+        let empty = Sketch::minhash(&dna("ACGT"), 11, 10);
+        assert!(empty.is_empty());
+        assert_eq!(empty.jaccard(&empty), None);
+        assert_eq!(empty.containment(&sketch), None);
+        assert_eq!(sketch.containment(&empty), Some(0.0));
+    }
+}
@@ -0,0 +1,185 @@
+//! Case-preserving sequences.
+//!
+//! Many references write repeats and low-complexity regions in lowercase ("soft-masking").
+//! [`PackedDna`] only stores nucleotides, so [`SoftMaskedDna`] pairs it with a bitmap holding one
+//! bit per position, set where the input was lowercase.
+
+use crate::{DnaError, Nuc, PackedDna};
+use std::{convert::TryFrom, fmt::Display, ops::Range, str::FromStr};
+
+/// A packed sequence that remembers which positions were lowercase
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct SoftMaskedDna {
+    dna: PackedDna,
+    /// Bit `i % 64` of word `i / 64` is set when position `i` is masked
+    mask: Vec<u64>,
+}
+
+impl SoftMaskedDna {
+    /// Wrap `dna` with no position masked
+    pub fn new(dna: PackedDna) -> Self {
+        let mask = vec![0; dna.len().div_ceil(64)];
+        SoftMaskedDna { dna, mask }
+    }
+
+    /// Number of nucleotides
+    pub fn len(&self) -> usize {
+        self.dna.len()
+    }
+
+    /// Whether the sequence holds no nucleotides
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// The nucleotides, without case information
+    pub fn dna(&self) -> &PackedDna {
+        &self.dna
+    }
+
+    /// Drop the case information and keep the nucleotides
+    pub fn into_dna(self) -> PackedDna {
+        self.dna
+    }
+
+    /// Whether the 0-based position `idx` is masked, or `None` if it is out of bounds
+    pub fn is_masked(&self, idx: usize) -> Option<bool> {
+        (idx < self.len()).then(|| self.mask[idx / 64] >> (idx % 64) & 1 == 1)
+    }
+
+    /// Mask or unmask every position in the 0-based `range`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the range is decreasing or extends past the end of the sequence.
+    pub fn set_masked(&mut self, range: Range<usize>, masked: bool) {
+        assert!(
+            range.start <= range.end && range.end <= self.len(),
+            "mask range {:?} out of bounds for DNA of length {}",
+            range,
+            self.len()
+        );
+        for idx in range {
+            if masked {
+                self.mask[idx / 64] |= 1 << (idx % 64);
+            } else {
+                self.mask[idx / 64] &= !(1 << (idx % 64));
+            }
+        }
+    }
+
+    /// Number of masked positions
+    pub fn masked_count(&self) -> usize {
+        self.mask
+            .iter()
+            .map(|word| word.count_ones() as usize)
+            .sum()
+    }
+
+    /// Maximal runs of masked positions, in order, e.g. for the mask blocks of a
+    /// [2bit record](crate::twobit::Record)
+    pub fn masked_ranges(&self) -> Vec<Range<usize>> {
+        let mut ranges: Vec<Range<usize>> = Vec::new();
+        for idx in (0..self.len()).filter(|&idx| self.is_masked(idx) == Some(true)) {
+            match ranges.last_mut() {
+                Some(last) if last.end == idx => last.end += 1,
+                _ => ranges.push(idx..idx + 1),
+            }
+        }
+        ranges
+    }
+}
+
+impl From<PackedDna> for SoftMaskedDna {
+    fn from(dna: PackedDna) -> Self {
+        SoftMaskedDna::new(dna)
+    }
+}
+
+/// Parses nucleotides in either case, remembering which were lowercase
+impl FromStr for SoftMaskedDna {
+    type Err = DnaError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut dna = PackedDna::empty();
+        let mut mask = Vec::with_capacity(s.len().div_ceil(64));
+        for (idx, c) in s.chars().enumerate() {
+            let nuc = Nuc::try_from(c).map_err(|_| DnaError::InvalidNuc {
+                character: c,
+                position: idx,
+            })?;
+            dna.push(nuc);
+            if idx % 64 == 0 {
+                mask.push(0);
+            }
+            if c.is_ascii_lowercase() {
+                mask[idx / 64] |= 1 << (idx % 64);
+            }
+        }
+        Ok(SoftMaskedDna { dna, mask })
+    }
+}
+
+/// Writes masked positions in lowercase, reproducing the parsed input
+impl Display for SoftMaskedDna {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        for (idx, nuc) in self.dna.iter().enumerate() {
+            let c = nuc.to_char();
+            if self.mask[idx / 64] >> (idx % 64) & 1 == 1 {
+                write!(f, "{}", c.to_ascii_lowercase())?;
+            } else {
+                write!(f, "{}", c)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn round_trip_preserves_case() {
+        let input = "ACgtTTgcacTGGATTACAACGTTGCATTGACCAGTacgtacgtacgtacgtacgtacgtacgtAC";
+        let masked = SoftMaskedDna::from_str(input).unwrap();
+        assert_eq!(masked.to_string(), input);
+        assert_eq!(masked.dna(), &PackedDna::from_str(input).unwrap());
+    }
+
+    #[test]
+    fn masked_positions() {
+        let masked = SoftMaskedDna::from_str("ACgtAa").unwrap();
+        assert_eq!(masked.is_masked(1), Some(false));
+        assert_eq!(masked.is_masked(2), Some(true));
+        assert_eq!(masked.is_masked(6), None);
+        assert_eq!(masked.masked_count(), 3);
+        assert_eq!(masked.masked_ranges(), vec![2..4, 5..6]);
+    }
+
+    #[test]
+    fn set_masked_across_words() {
+        let mut masked = SoftMaskedDna::new(PackedDna::from_str(&"A".repeat(130)).unwrap());
+        masked.set_masked(60..70, true);
+        masked.set_masked(62..64, false);
+        assert_eq!(masked.masked_ranges(), vec![60..62, 64..70]);
+        assert_eq!(masked.masked_count(), 8);
+    }
+
+    #[test]
+    fn parse_negative() {
+        let err = SoftMaskedDna::from_str("acgx").unwrap_err();
+        assert_eq!(
+            "failed to parse nucleotide from 'x' at position 3",
+            err.to_string()
+        );
+    }
+
+    #[test]
+    fn empty() {
+        let masked = SoftMaskedDna::from_str("").unwrap();
+        assert!(masked.is_empty());
+        assert_eq!(masked.to_string(), "");
+        assert!(masked.masked_ranges().is_empty());
+    }
+}
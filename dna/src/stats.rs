@@ -0,0 +1,441 @@
+//! GC content and base composition statistics, for single sequences and whole datasets.
+
+use crate::{Nuc, PackedDna};
+
+/// Composition summary of a sequence, see [`PackedDna::stats`]
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Stats {
+    /// Sequence length in nucleotides
+    pub len: usize,
+    /// Number of adenines
+    pub a_count: usize,
+    /// Number of cytosines
+    pub c_count: usize,
+    /// Number of guanines
+    pub g_count: usize,
+    /// Number of thymines
+    pub t_count: usize,
+    /// Fraction of G and C, between 0 and 1
+    pub gc_content: f64,
+    /// `(A - T) / (A + T)`, or 0 without any A or T
+    pub at_skew: f64,
+    /// `(G - C) / (G + C)`, or 0 without any G or C
+    pub gc_skew: f64,
+}
+
+impl Stats {
+    /// The statistics of a sequence with the counts of A, C, G and T
+    fn from_counts([a_count, c_count, g_count, t_count]: [usize; 4]) -> Self {
+        let len = a_count + c_count + g_count + t_count;
+        Stats {
+            len,
+            a_count,
+            c_count,
+            g_count,
+            t_count,
+            gc_content: ratio(g_count + c_count, len),
+            at_skew: skew(a_count, t_count),
+            gc_skew: skew(g_count, c_count),
+        }
+    }
+
+    /// Shannon entropy of the base composition in bits: 0 for a single repeated base or an
+    /// empty sequence, up to 2 for equal counts of all four bases.
+    ///
+    /// Low-complexity regions such as microsatellites and poly-A tails stand out with low
+    /// values.
+    pub fn entropy(&self) -> f64 {
+        [self.a_count, self.c_count, self.g_count, self.t_count]
+            .iter()
+            .filter(|&&count| count > 0)
+            .map(|&count| {
+                let p = ratio(count, self.len);
+                p * (1.0 / p).log2()
+            })
+            .sum()
+    }
+}
+
+impl PackedDna {
+    /// Fraction of the sequence that is G or C, or 0 for an empty sequence
+    pub fn gc_content(&self) -> f64 {
+        ratio(self.g_count + self.c_count, self.len())
+    }
+
+    /// Length, per-base counts, GC content and strand skews of the sequence
+    pub fn stats(&self) -> Stats {
+        Stats::from_counts([self.a_count, self.c_count, self.g_count, self.t_count])
+    }
+
+    /// GC content of successive windows, `window` long and `step` apart.
+    ///
+    /// Only full windows are reported, the `i`th one starting at `i * step`. Counts are carried
+    /// over between overlapping windows, so the cost does not grow with the window size.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `window` or `step` is zero.
+    pub fn gc_content_windows(&self, window: usize, step: usize) -> GcWindows<'_> {
+        assert!(window > 0 && step > 0, "window and step must be non-zero");
+        GcWindows {
+            dna: self,
+            window,
+            step,
+            start: 0,
+            end: 0,
+            gc: 0,
+        }
+    }
+
+    /// [`Stats`] of successive windows, `window` long and `step` apart, e.g. for GC or entropy
+    /// tracks.
+    ///
+    /// Windows are placed like those of [`gc_content_windows`](Self::gc_content_windows), and
+    /// counts are likewise carried over between overlapping windows.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `window` or `step` is zero.
+    pub fn stats_windows(&self, window: usize, step: usize) -> StatsWindows<'_> {
+        assert!(window > 0 && step > 0, "window and step must be non-zero");
+        StatsWindows {
+            dna: self,
+            window,
+            step,
+            start: 0,
+            end: 0,
+            counts: [0; 4],
+        }
+    }
+}
+
+/// Iterator over windowed GC content, see [`PackedDna::gc_content_windows`]
+#[derive(Debug, Clone)]
+pub struct GcWindows<'a> {
+    dna: &'a PackedDna,
+    window: usize,
+    step: usize,
+    /// Start of the next window
+    start: usize,
+    /// End of the span whose GC bases are tallied in `gc`
+    end: usize,
+    /// GC bases in `start..end`
+    gc: usize,
+}
+
+impl GcWindows<'_> {
+    fn is_gc(&self, idx: usize) -> bool {
+        matches!(self.dna.nuc_at(idx), Nuc::C | Nuc::G)
+    }
+}
+
+impl Iterator for GcWindows<'_> {
+    type Item = f64;
+
+    fn next(&mut self) -> Option<f64> {
+        let end = self.start + self.window;
+        if end > self.dna.len() {
+            return None;
+        }
+        if self.end < self.start {
+            self.end = self.start;
+            self.gc = 0;
+        }
+        while self.end < end {
+            self.gc += self.is_gc(self.end) as usize;
+            self.end += 1;
+        }
+        let value = ratio(self.gc, self.window);
+
+        let next_start = self.start + self.step;
+        for idx in self.start..next_start.min(self.end) {
+            self.gc -= self.is_gc(idx) as usize;
+        }
+        self.start = next_start;
+        Some(value)
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let len = self.dna.len();
+        let remaining = if self.start + self.window > len {
+            0
+        } else {
+            (len - self.start - self.window) / self.step + 1
+        };
+        (remaining, Some(remaining))
+    }
+}
+
+impl ExactSizeIterator for GcWindows<'_> {}
+
+/// Iterator over windowed statistics, see [`PackedDna::stats_windows`]
+#[derive(Debug, Clone)]
+pub struct StatsWindows<'a> {
+    dna: &'a PackedDna,
+    window: usize,
+    step: usize,
+    /// Start of the next window
+    start: usize,
+    /// End of the span whose bases are tallied in `counts`
+    end: usize,
+    /// Counts of A, C, G and T in `start..end`
+    counts: [usize; 4],
+}
+
+impl Iterator for StatsWindows<'_> {
+    type Item = Stats;
+
+    fn next(&mut self) -> Option<Stats> {
+        let end = self.start + self.window;
+        if end > self.dna.len() {
+            return None;
+        }
+        if self.end < self.start {
+            self.end = self.start;
+            self.counts = [0; 4];
+        }
+        while self.end < end {
+            self.counts[self.dna.nuc_at(self.end) as usize] += 1;
+            self.end += 1;
+        }
+        let stats = Stats::from_counts(self.counts);
+
+        let next_start = self.start + self.step;
+        for idx in self.start..next_start.min(self.end) {
+            self.counts[self.dna.nuc_at(idx) as usize] -= 1;
+        }
+        self.start = next_start;
+        Some(stats)
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let len = self.dna.len();
+        let remaining = if self.start + self.window > len {
+            0
+        } else {
+            (len - self.start - self.window) / self.step + 1
+        };
+        (remaining, Some(remaining))
+    }
+}
+
+impl ExactSizeIterator for StatsWindows<'_> {}
+
+/// Per-record statistics of a dataset with summaries across records, e.g. for a read QC report
+#[derive(Debug, Clone, PartialEq)]
+pub struct BatchStats {
+    records: Vec<Stats>,
+    /// Record lengths, ascending
+    lengths: Vec<usize>,
+    /// Record GC contents, ascending
+    gc_contents: Vec<f64>,
+}
+
+impl BatchStats {
+    /// Compute the statistics of every sequence, in parallel with the `rayon` feature
+    pub fn new(seqs: &[PackedDna]) -> Self {
+        #[cfg(feature = "rayon")]
+        let records = {
+            use rayon::prelude::*;
+            seqs.par_iter().map(PackedDna::stats).collect()
+        };
+        #[cfg(not(feature = "rayon"))]
+        let records = seqs.iter().map(PackedDna::stats).collect();
+        BatchStats::from_stats(records)
+    }
+
+    /// Summarise already computed per-record statistics, e.g. gathered while streaming a file
+    pub fn from_stats(records: Vec<Stats>) -> Self {
+        let mut lengths: Vec<usize> = records.iter().map(|stats| stats.len).collect();
+        lengths.sort_unstable();
+        let mut gc_contents: Vec<f64> = records.iter().map(|stats| stats.gc_content).collect();
+        gc_contents.sort_unstable_by(f64::total_cmp);
+        BatchStats {
+            records,
+            lengths,
+            gc_contents,
+        }
+    }
+
+    /// Statistics of each record, in input order
+    pub fn records(&self) -> &[Stats] {
+        &self.records
+    }
+
+    /// Number of records
+    pub fn len(&self) -> usize {
+        self.records.len()
+    }
+
+    /// Whether there are no records
+    pub fn is_empty(&self) -> bool {
+        self.records.is_empty()
+    }
+
+    /// Total number of nucleotides across records
+    pub fn total_len(&self) -> usize {
+        self.lengths.iter().sum()
+    }
+
+    /// Mean of the per-record GC contents, or `None` without records
+    pub fn mean_gc(&self) -> Option<f64> {
+        if self.is_empty() {
+            return None;
+        }
+        Some(self.gc_contents.iter().sum::<f64>() / self.len() as f64)
+    }
+
+    /// Median of the per-record GC contents, or `None` without records
+    pub fn median_gc(&self) -> Option<f64> {
+        let n = self.len();
+        match n {
+            0 => None,
+            _ if n % 2 == 1 => Some(self.gc_contents[n / 2]),
+            _ => Some((self.gc_contents[n / 2 - 1] + self.gc_contents[n / 2]) / 2.0),
+        }
+    }
+
+    /// Nearest-rank `percentile` of the record lengths, or `None` without records.
+    ///
+    /// Percentile 0 is the shortest record and 100 the longest.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `percentile` is not between 0 and 100.
+    pub fn length_percentile(&self, percentile: f64) -> Option<usize> {
+        assert!(
+            (0.0..=100.0).contains(&percentile),
+            "percentile must be between 0 and 100, got {}",
+            percentile
+        );
+        let rank = (percentile / 100.0 * self.len() as f64).ceil() as usize;
+        self.lengths.get(rank.saturating_sub(1)).copied()
+    }
+}
+
+fn ratio(part: usize, whole: usize) -> f64 {
+    if whole == 0 {
+        0.0
+    } else {
+        part as f64 / whole as f64
+    }
+}
+
+fn skew(a: usize, b: usize) -> f64 {
+    if a + b == 0 {
+        0.0
+    } else {
+        (a as f64 - b as f64) / (a + b) as f64
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::str::FromStr;
+
+    #[test]
+    fn gc_content() {
+        assert_eq!(
+            PackedDna::from_str("ACGTGG").unwrap().gc_content(),
+            4.0 / 6.0
+        );
+        assert_eq!(PackedDna::from_str("").unwrap().gc_content(), 0.0);
+    }
+
+    #[test]
+    fn stats() {
+        let stats = PackedDna::from_str("AAATGGGC").unwrap().stats();
+        assert_eq!(stats.len, 8);
+        assert_eq!(
+            [stats.a_count, stats.c_count, stats.g_count, stats.t_count],
+            [3, 1, 3, 1]
+        );
+        assert_eq!(stats.gc_content, 0.5);
+        assert_eq!(stats.at_skew, 0.5);
+        assert_eq!(stats.gc_skew, 0.5);
+    }
+
+    #[test]
+    fn stats_skew_without_bases() {
+        let stats = PackedDna::from_str("GGGG").unwrap().stats();
+        assert_eq!(stats.at_skew, 0.0);
+        assert_eq!(stats.gc_skew, 1.0);
+    }
+
+    #[test]
+    fn gc_content_windows_matches_slices() {
+        let dna = PackedDna::from_str("ATGCGCATTAGGCCATAGC").unwrap();
+        for (window, step) in [(4, 1), (4, 3), (5, 5), (3, 7), (19, 1), (20, 1)] {
+            let windows = dna.gc_content_windows(window, step);
+            let expected: Vec<f64> = (0..)
+                .map(|i| i * step)
+                .take_while(|start| start + window <= 19)
+                .map(|start| dna.slice(start..start + window).gc_content())
+                .collect();
+            assert_eq!(windows.len(), expected.len());
+            assert_eq!(windows.collect::<Vec<_>>(), expected);
+        }
+    }
+
+    #[test]
+    fn stats_windows_match_slices() {
+        let dna = PackedDna::from_str("ATGCGCATTAGGCCATAGCAAAAAAAA").unwrap();
+        for (window, step) in [(4, 1), (4, 3), (5, 5), (3, 7), (27, 1), (28, 1)] {
+            let windows = dna.stats_windows(window, step);
+            let expected: Vec<Stats> = (0..)
+                .map(|i| i * step)
+                .take_while(|start| start + window <= 27)
+                .map(|start| dna.to_slice(start..start + window).stats())
+                .collect();
+            assert_eq!(windows.len(), expected.len());
+            assert_eq!(windows.collect::<Vec<_>>(), expected);
+        }
+    }
+
+    #[test]
+    fn entropy() {
+        let entropy = |s: &str| PackedDna::from_str(s).unwrap().stats().entropy();
+        assert_eq!(entropy(""), 0.0);
+        assert_eq!(entropy("AAAAAAAA"), 0.0);
+        assert!(entropy("AAAAAAAA").is_sign_positive());
+        assert_eq!(entropy("ACGTACGT"), 2.0);
+        assert_eq!(entropy("ATATATAT"), 1.0);
+        assert!((entropy("AAAC") - 0.811_278_124_459_132_8).abs() < 1e-12);
+    }
+
+    #[test]
+    fn batch_stats_summaries() {
+        let seqs: Vec<PackedDna> = ["ACGT", "GGGGCC", "AT", "ATGCATGCAA", "CCA"]
+            .iter()
+            .map(|s| PackedDna::from_str(s).unwrap())
+            .collect();
+        let batch = BatchStats::new(&seqs);
+        assert_eq!(batch.len(), 5);
+        assert_eq!(batch.records()[1], seqs[1].stats());
+        assert_eq!(batch.total_len(), 25);
+        assert_eq!(batch.median_gc(), Some(0.5));
+        assert!(
+            (batch.mean_gc().unwrap() - (0.5 + 1.0 + 0.0 + 0.4 + 2.0 / 3.0) / 5.0).abs() < 1e-12
+        );
+        assert_eq!(batch.length_percentile(0.0), Some(2));
+        assert_eq!(batch.length_percentile(50.0), Some(4));
+        assert_eq!(batch.length_percentile(90.0), Some(10));
+        assert_eq!(batch.length_percentile(100.0), Some(10));
+    }
+
+    #[test]
+    fn batch_stats_even_median_and_empty() {
+        let seqs = [
+            PackedDna::from_str("AT").unwrap(),
+            PackedDna::from_str("GC").unwrap(),
+        ];
+        assert_eq!(BatchStats::new(&seqs).median_gc(), Some(0.5));
+
+        let empty = BatchStats::new(&[]);
+        assert!(empty.is_empty());
+        assert_eq!(empty.mean_gc(), None);
+        assert_eq!(empty.median_gc(), None);
+        assert_eq!(empty.length_percentile(50.0), None);
+    }
+}
@@ -0,0 +1,229 @@
+//! A compact binary format for persisting packed sequences.
+//!
+//! The format is a small header followed by the packed payload, all integers little-endian:
+//!
+//! | bytes | content                                      |
+//! |-------|----------------------------------------------|
+//! | 4     | magic `PDNA`                                 |
+//! | 1     | format version, currently [`FORMAT_VERSION`] |
+//! | 8     | sequence length in nucleotides               |
+//! | 4 × 8 | counts of A, C, G and T                      |
+//! | n     | packed nucleotides, `ceil(length / 4)` bytes |
+//!
+//! With the `serde` feature [`PackedDna`] serializes to the same bytes.
+
+use crate::{aligned::AlignedBuf, PackedDna};
+use std::convert::{TryFrom, TryInto};
+
+/// Magic bytes opening every encoded sequence
+const MAGIC: &[u8; 4] = b"PDNA";
+/// Current version of the binary format
+pub const FORMAT_VERSION: u8 = 1;
+const HEADER_LEN: usize = 4 + 1 + 8 + 4 * 8;
+
+/// An error that can occur when decoding a sequence from bytes.
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
+pub enum DecodeError {
+    /// The input does not start with the format's magic bytes
+    #[error("input is not a packed DNA encoding")]
+    BadMagic,
+    /// The input was written by an unknown format version
+    #[error("unsupported packed DNA format version {0}")]
+    UnsupportedVersion(u8),
+    /// The input is shorter or longer than its header announces
+    #[error("expected {expected} bytes of packed DNA, found {found}")]
+    WrongLength {
+        /// Length announced by the header
+        expected: usize,
+        /// Length of the input
+        found: usize,
+    },
+    /// The partially filled last byte has bits set past the last nucleotide
+    #[error("packed payload has bits set past the last nucleotide")]
+    InvalidPadding,
+    /// The stored counts disagree with the payload
+    #[error("stored nucleotide counts do not match the packed payload")]
+    CountMismatch,
+}
+
+impl PackedDna {
+    /// Encode the sequence in the binary format described in the [module docs](self)
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let payload = self.used_bytes();
+        let mut bytes = Vec::with_capacity(HEADER_LEN + payload.len());
+        bytes.extend_from_slice(MAGIC);
+        bytes.push(FORMAT_VERSION);
+        for value in [
+            self.len(),
+            self.a_count,
+            self.c_count,
+            self.g_count,
+            self.t_count,
+        ] {
+            bytes.extend_from_slice(&(value as u64).to_le_bytes());
+        }
+        bytes.extend_from_slice(payload);
+        bytes
+    }
+
+    /// Decode a sequence written by [`to_bytes`](PackedDna::to_bytes)
+    pub fn from_bytes(bytes: &[u8]) -> Result<PackedDna, DecodeError> {
+        if bytes.len() < 5 || &bytes[..4] != MAGIC {
+            return Err(DecodeError::BadMagic);
+        }
+        if bytes[4] != FORMAT_VERSION {
+            return Err(DecodeError::UnsupportedVersion(bytes[4]));
+        }
+        if bytes.len() < HEADER_LEN {
+            return Err(DecodeError::WrongLength {
+                expected: HEADER_LEN,
+                found: bytes.len(),
+            });
+        }
+        let field = |i: usize| {
+            let start = 5 + 8 * i;
+            let value = u64::from_le_bytes(bytes[start..start + 8].try_into().unwrap_or_default());
+            // A length past the address space cannot match the input, and fails below.
+            usize::try_from(value).unwrap_or(usize::MAX)
+        };
+        let len = field(0);
+        let expected = HEADER_LEN + len.div_ceil(4);
+        if bytes.len() != expected {
+            return Err(DecodeError::WrongLength {
+                expected,
+                found: bytes.len(),
+            });
+        }
+        let payload = &bytes[HEADER_LEN..];
+        if len % 4 != 0 && payload[payload.len() - 1] >> (2 * (len % 4)) != 0 {
+            return Err(DecodeError::InvalidPadding);
+        }
+        let dna = PackedDna::from_packed(AlignedBuf::from(payload), len);
+        if [dna.a_count, dna.c_count, dna.g_count, dna.t_count]
+            != [field(1), field(2), field(3), field(4)]
+        {
+            return Err(DecodeError::CountMismatch);
+        }
+        Ok(dna)
+    }
+}
+
+#[cfg(feature = "serde")]
+impl serde::Serialize for PackedDna {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_bytes(&self.to_bytes())
+    }
+}
+
+#[cfg(feature = "serde")]
+impl<'de> serde::Deserialize<'de> for PackedDna {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        struct BytesVisitor;
+
+        impl<'de> serde::de::Visitor<'de> for BytesVisitor {
+            type Value = PackedDna;
+
+            fn expecting(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+                f.write_str("packed DNA bytes")
+            }
+
+            fn visit_bytes<E: serde::de::Error>(self, v: &[u8]) -> Result<PackedDna, E> {
+                PackedDna::from_bytes(v).map_err(E::custom)
+            }
+
+            fn visit_seq<A: serde::de::SeqAccess<'de>>(
+                self,
+                mut seq: A,
+            ) -> Result<PackedDna, A::Error> {
+                let mut bytes = Vec::with_capacity(seq.size_hint().unwrap_or(0));
+                while let Some(byte) = seq.next_element()? {
+                    bytes.push(byte);
+                }
+                self.visit_bytes(&bytes)
+            }
+        }
+
+        deserializer.deserialize_bytes(BytesVisitor)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::str::FromStr;
+
+    #[test]
+    fn to_bytes_layout() {
+        let bytes = PackedDna::from_str("ACGTTGCACT").unwrap().to_bytes();
+        assert_eq!(&bytes[..5], b"PDNA\x01");
+        assert_eq!(bytes[5], 10);
+        assert_eq!(&bytes[HEADER_LEN..], [27, 228, 7]);
+    }
+
+    #[test]
+    fn bytes_round_trip() {
+        for input in ["", "A", "ACGT", "ACGTTGCACT"] {
+            let dna = PackedDna::from_str(input).unwrap();
+            let decoded = PackedDna::from_bytes(&dna.to_bytes()).unwrap();
+            assert_eq!(decoded, dna);
+            assert_eq!(decoded.get_counts(), dna.get_counts());
+            assert_eq!(decoded.last_nuc_set_count, dna.last_nuc_set_count);
+        }
+    }
+
+    #[test]
+    fn from_bytes_bad_magic() {
+        assert_eq!(PackedDna::from_bytes(b"ACGT"), Err(DecodeError::BadMagic));
+    }
+
+    #[test]
+    fn from_bytes_unsupported_version() {
+        let mut bytes = PackedDna::from_str("ACGT").unwrap().to_bytes();
+        bytes[4] = 9;
+        assert_eq!(
+            PackedDna::from_bytes(&bytes),
+            Err(DecodeError::UnsupportedVersion(9))
+        );
+    }
+
+    #[test]
+    fn from_bytes_truncated() {
+        let bytes = PackedDna::from_str("ACGTTGCACT").unwrap().to_bytes();
+        assert_eq!(
+            PackedDna::from_bytes(&bytes[..bytes.len() - 1]),
+            Err(DecodeError::WrongLength {
+                expected: HEADER_LEN + 3,
+                found: HEADER_LEN + 2
+            })
+        );
+    }
+
+    #[test]
+    fn from_bytes_count_mismatch() {
+        let mut bytes = PackedDna::from_str("ACGT").unwrap().to_bytes();
+        bytes[HEADER_LEN] = 0;
+        assert_eq!(
+            PackedDna::from_bytes(&bytes),
+            Err(DecodeError::CountMismatch)
+        );
+    }
+
+    #[test]
+    fn from_bytes_invalid_padding() {
+        let mut bytes = PackedDna::from_str("ACGTT").unwrap().to_bytes();
+        bytes[HEADER_LEN + 1] |= 0b1100;
+        assert_eq!(
+            PackedDna::from_bytes(&bytes),
+            Err(DecodeError::InvalidPadding)
+        );
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn serde_json_round_trip() {
+        let dna = PackedDna::from_str("ACGTTGCACT").unwrap();
+        let json = serde_json::to_string(&dna).unwrap();
+        let decoded: PackedDna = serde_json::from_str(&json).unwrap();
+        assert_eq!(decoded, dna);
+    }
+}
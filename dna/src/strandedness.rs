@@ -0,0 +1,248 @@
+//! Inference of RNA-seq library strandedness from reads and a gene annotation.
+//!
+//! Each read is placed on the genome by exact k-mer seeds looked up in a [`KmerIndex`] of every
+//! contig, on both strands, and compared with the strand of the genes it overlaps. A library
+//! whose reads mostly match the sense strand of their genes was sequenced from the transcript,
+//! one whose reads mostly match the antisense strand from its complement (as with dUTP
+//! protocols), and an unstranded library gives about half of each.
+//!
+//! Paired-end libraries are judged by their first reads.
+
+use crate::{
+    fasta, gff::FeatureIndex, index::KmerIndex, kmer::Kmers, translate::Strand, PackedDna,
+};
+use std::collections::HashMap;
+
+/// How a read relates to the genes it overlaps
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ReadOrientation {
+    /// The read lies on the same strand as the genes it overlaps
+    Sense,
+    /// The read lies on the opposite strand
+    Antisense,
+    /// The read overlaps genes on both strands
+    Ambiguous,
+    /// The read could not be placed, or overlaps no stranded gene
+    Unassigned,
+}
+
+/// The kind of library the reads come from, see [`Strandedness::library`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Library {
+    /// At least 80% of the informative reads are sense
+    Forward,
+    /// At most 20% of the informative reads are sense
+    Reverse,
+    /// Between 40% and 60% of the informative reads are sense
+    Unstranded,
+    /// Too few informative reads, or a fraction between the other cases
+    Undetermined,
+}
+
+/// Counts of reads by orientation
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Strandedness {
+    /// Reads on the strand of their genes
+    pub sense: usize,
+    /// Reads on the opposite strand of their genes
+    pub antisense: usize,
+    /// Reads overlapping genes on both strands
+    pub ambiguous: usize,
+    /// Reads that could not be placed, or outside stranded genes
+    pub unassigned: usize,
+}
+
+impl Strandedness {
+    /// Fraction of the sense and antisense reads that are sense, or `None` if there are none
+    pub fn sense_fraction(&self) -> Option<f64> {
+        let informative = self.sense + self.antisense;
+        (informative > 0).then(|| self.sense as f64 / informative as f64)
+    }
+
+    /// The library type suggested by the sense fraction
+    pub fn library(&self) -> Library {
+        match self.sense_fraction() {
+            Some(fraction) if fraction >= 0.8 => Library::Forward,
+            Some(fraction) if fraction <= 0.2 => Library::Reverse,
+            Some(fraction) if (0.4..=0.6).contains(&fraction) => Library::Unstranded,
+            _ => Library::Undetermined,
+        }
+    }
+
+    fn add(&mut self, orientation: ReadOrientation) {
+        match orientation {
+            ReadOrientation::Sense => self.sense += 1,
+            ReadOrientation::Antisense => self.antisense += 1,
+            ReadOrientation::Ambiguous => self.ambiguous += 1,
+            ReadOrientation::Unassigned => self.unassigned += 1,
+        }
+    }
+}
+
+/// Places reads on an annotated genome to infer library strandedness
+#[derive(Debug)]
+pub struct StrandednessInference<'a> {
+    contigs: Vec<(&'a str, KmerIndex)>,
+    genes: &'a FeatureIndex,
+    kind: String,
+    k: usize,
+}
+
+impl<'a> StrandednessInference<'a> {
+    /// Index the k-mers of length `k` of each contig, whose identifiers must match the sequence
+    /// names of `genes`. Features of type `gene` are compared with the reads.
+    ///
+    /// Shorter k-mers place more reads but are more often repeated across the genome; reads with
+    /// seeds pointing equally at several places are left unassigned.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `k` is zero or greater than [`MAX_K`](crate::kmer::MAX_K).
+    pub fn new(contigs: &'a [fasta::Record], genes: &'a FeatureIndex, k: usize) -> Self {
+        let contigs = contigs
+            .iter()
+            .map(|record| (record.id(), KmerIndex::new(record.sequence(), k)))
+            .collect();
+        StrandednessInference {
+            contigs,
+            genes,
+            kind: "gene".to_string(),
+            k,
+        }
+    }
+
+    /// Compare reads with features of type `kind`, such as `exon`, instead of `gene`
+    pub fn feature_kind(mut self, kind: impl Into<String>) -> Self {
+        self.kind = kind.into();
+        self
+    }
+
+    /// Count the orientation of each read
+    pub fn infer<'r>(&self, reads: impl IntoIterator<Item = &'r PackedDna>) -> Strandedness {
+        let mut counts = Strandedness::default();
+        for read in reads {
+            counts.add(self.orientation(read));
+        }
+        counts
+    }
+
+    /// How `read` relates to the genes it overlaps
+    pub fn orientation(&self, read: &PackedDna) -> ReadOrientation {
+        let (contig, strand, range) = match self.place(read) {
+            Some(placement) => placement,
+            None => return ReadOrientation::Unassigned,
+        };
+        let mut gene_strands = self
+            .genes
+            .overlapping(self.contigs[contig].0, range)
+            .filter(|feature| feature.kind == self.kind)
+            .filter_map(|feature| feature.strand);
+        let gene_strand = match gene_strands.next() {
+            Some(gene_strand) => gene_strand,
+            None => return ReadOrientation::Unassigned,
+        };
+        if gene_strands.any(|other| other != gene_strand) {
+            ReadOrientation::Ambiguous
+        } else if gene_strand == strand {
+            ReadOrientation::Sense
+        } else {
+            ReadOrientation::Antisense
+        }
+    }
+
+    /// The contig, strand and 0-based range most seeds of `read` point at, if that place is
+    /// unique
+    fn place(&self, read: &PackedDna) -> Option<(usize, Strand, std::ops::Range<usize>)> {
+        let mut votes: HashMap<(usize, Strand, usize), usize> = HashMap::new();
+        let reverse = read.reverse_complement();
+        for (strand, seq) in [(Strand::Forward, read), (Strand::Reverse, &reverse)] {
+            // Non-overlapping seeds, so one mismatch costs at most one vote.
+            for (offset, kmer) in Kmers::new(seq, self.k, false).enumerate().step_by(self.k) {
+                for (contig, (_, index)) in self.contigs.iter().enumerate() {
+                    for &position in index.positions(kmer) {
+                        if let Some(start) = position.checked_sub(offset) {
+                            *votes.entry((contig, strand, start)).or_default() += 1;
+                        }
+                    }
+                }
+            }
+        }
+        let best = votes.values().copied().max()?;
+        let mut places = votes.into_iter().filter(|&(_, count)| count == best);
+        let ((contig, strand, start), _) = places.next()?;
+        if places.next().is_some() {
+            return None;
+        }
+        Some((contig, strand, start..start + read.len()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::random;
+    use crate::{gff::Feature, Nuc};
+
+    fn gene(range: std::ops::Range<usize>, strand: Strand) -> Feature {
+        Feature {
+            seqid: "chr1".to_string(),
+            source: "test".to_string(),
+            kind: "gene".to_string(),
+            range,
+            strand: Some(strand),
+            attributes: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn infers_library_type() {
+        let chr1 = random(3000, 1);
+        let contigs = vec![fasta::Record::new("chr1 test", chr1.clone())];
+        let genes = FeatureIndex::new(vec![
+            gene(0..1000, Strand::Forward),
+            gene(1500..2500, Strand::Reverse),
+            gene(2000..3000, Strand::Forward),
+        ]);
+        let inference = StrandednessInference::new(&contigs, &genes, 15);
+
+        // Reads copied from the transcripts: the forward strand of the first gene and the
+        // reverse complement of the second.
+        let mut sense: Vec<PackedDna> = (0..8)
+            .map(|i| chr1.to_slice(i * 100..i * 100 + 60))
+            .collect();
+        sense.extend((0..4).map(|i| {
+            chr1.to_slice(1500 + i * 100..1560 + i * 100)
+                .reverse_complement()
+        }));
+        let mut mutated = chr1.to_slice(200..260);
+        mutated.set(30, Nuc::A).unwrap();
+        mutated.set(31, Nuc::C).unwrap();
+        sense.push(mutated);
+        let counts = inference.infer(&sense);
+        assert_eq!(counts.sense, 13);
+        assert_eq!(counts.library(), Library::Forward);
+
+        let antisense: Vec<PackedDna> = sense.iter().map(PackedDna::reverse_complement).collect();
+        assert_eq!(inference.infer(&antisense).library(), Library::Reverse);
+
+        let mixed: Vec<PackedDna> = sense
+            .iter()
+            .take(6)
+            .chain(antisense.iter().take(6))
+            .cloned()
+            .collect();
+        let counts = inference.infer(&mixed);
+        assert_eq!((counts.sense, counts.antisense), (6, 6));
+        assert_eq!(counts.library(), Library::Unstranded);
+
+        // Where the genes overlap, between the gene-free gap and an unrelated read.
+        let others = [
+            chr1.to_slice(2100..2160),
+            chr1.to_slice(1100..1160),
+            random(60, 2),
+        ];
+        let counts = inference.infer(&others);
+        assert_eq!((counts.ambiguous, counts.unassigned), (1, 2));
+        assert_eq!(counts.library(), Library::Undetermined);
+    }
+}
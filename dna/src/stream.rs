@@ -0,0 +1,209 @@
+//! Packing of sequences that arrive in chunks of bytes.
+//!
+//! A [`StreamingPacker`] packs each chunk as it is fed, e.g. from a socket, a decompressor or an
+//! async reader, so the sequence is never held as text. Chunks may split a UTF-8 character,
+//! which only matters for reporting it: no character outside ASCII is a nucleotide.
+
+use crate::{DnaError, Nuc, PackedDna};
+use std::{convert::TryFrom, io};
+
+/// Incremental packer of sequence text fed in byte chunks
+#[derive(Debug, Clone)]
+pub struct StreamingPacker {
+    dna: PackedDna,
+    skip_whitespace: bool,
+    /// Number of characters fed so far, including skipped whitespace
+    position: usize,
+    /// Leading bytes of a character split across chunks
+    partial: [u8; 4],
+    partial_len: usize,
+}
+
+impl StreamingPacker {
+    /// Create a packer accepting only A, C, G and T in either case, as parsing a [`PackedDna`]
+    /// from a string does
+    pub fn new() -> Self {
+        StreamingPacker {
+            dna: PackedDna::empty(),
+            skip_whitespace: false,
+            position: 0,
+            partial: [0; 4],
+            partial_len: 0,
+        }
+    }
+
+    /// Skip whitespace, such as the line breaks of wrapped sequence text
+    pub fn skip_whitespace(mut self, skip: bool) -> Self {
+        self.skip_whitespace = skip;
+        self
+    }
+
+    /// The nucleotides packed so far, with their counts
+    pub fn packed(&self) -> &PackedDna {
+        &self.dna
+    }
+
+    /// Number of characters fed so far, including skipped whitespace
+    pub fn position(&self) -> usize {
+        self.position
+    }
+
+    /// Pack the next chunk of the stream.
+    ///
+    /// If the chunk holds a character that is not a nucleotide, or bytes that are not UTF-8,
+    /// nothing of it is packed, and the error gives the position of the character within the
+    /// whole stream. A character left incomplete at the end of the chunk is completed by the
+    /// next one.
+    pub fn feed(&mut self, chunk: &[u8]) -> Result<(), DnaError> {
+        let (len, position, partial, partial_len) = (
+            self.dna.len(),
+            self.position,
+            self.partial,
+            self.partial_len,
+        );
+        self.dna.reserve(chunk.len());
+        let fed = self.feed_bytes(chunk);
+        if fed.is_err() {
+            while self.dna.len() > len {
+                self.dna.pop();
+            }
+            self.position = position;
+            self.partial = partial;
+            self.partial_len = partial_len;
+        }
+        fed
+    }
+
+    fn feed_bytes(&mut self, chunk: &[u8]) -> Result<(), DnaError> {
+        for &byte in chunk {
+            if self.partial_len == 0 && byte.is_ascii() {
+                self.push_char(char::from(byte))?;
+                continue;
+            }
+            self.partial[self.partial_len] = byte;
+            self.partial_len += 1;
+            match std::str::from_utf8(&self.partial[..self.partial_len]) {
+                Ok(s) => {
+                    let character = s.chars().next().expect("a complete character");
+                    self.partial_len = 0;
+                    self.push_char(character)?;
+                }
+                // The character continues in the next byte.
+                Err(e) if e.error_len().is_none() => {}
+                Err(_) => {
+                    self.partial_len = 0;
+                    return Err(self.invalid(char::REPLACEMENT_CHARACTER));
+                }
+            }
+        }
+        Ok(())
+    }
+
+    fn push_char(&mut self, character: char) -> Result<(), DnaError> {
+        if !(self.skip_whitespace && character.is_whitespace()) {
+            let nuc = Nuc::try_from(character).map_err(|_| self.invalid(character))?;
+            self.dna.push(nuc);
+        }
+        self.position += 1;
+        Ok(())
+    }
+
+    fn invalid(&self, character: char) -> DnaError {
+        DnaError::InvalidNuc {
+            character,
+            position: self.position,
+        }
+    }
+
+    /// End the stream and return the packed sequence, failing if it ends within a character
+    pub fn finish(self) -> Result<PackedDna, DnaError> {
+        if self.partial_len > 0 {
+            return Err(self.invalid(char::REPLACEMENT_CHARACTER));
+        }
+        Ok(self.dna)
+    }
+}
+
+impl Default for StreamingPacker {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Feeds every write, so a reader can be packed with [`std::io::copy`]. Errors are
+/// [`io::ErrorKind::InvalidData`] wrapping the [`DnaError`].
+impl io::Write for StreamingPacker {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.feed(buf)
+            .map(|()| buf.len())
+            .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn chunks_pack_like_whole_text() {
+        let text = "ACGTTGCAACGTA";
+        let expected: PackedDna = text.parse().unwrap();
+        for size in 1..=text.len() {
+            let mut packer = StreamingPacker::new();
+            for chunk in text.as_bytes().chunks(size) {
+                packer.feed(chunk).unwrap();
+            }
+            assert_eq!(packer.packed().get_counts(), expected.get_counts());
+            assert_eq!(packer.finish().unwrap(), expected);
+        }
+    }
+
+    #[test]
+    fn split_character_is_reported_whole() {
+        let bytes = "ACé".as_bytes();
+        let mut packer = StreamingPacker::new();
+        packer.feed(&bytes[..3]).unwrap();
+        let err = packer.feed(&bytes[3..]).unwrap_err();
+        assert_eq!(
+            "failed to parse nucleotide from 'é' at position 2",
+            err.to_string()
+        );
+        // The failed chunk is undone, so the stream can be finished before it.
+        assert_eq!(packer.position(), 2);
+        let err = packer.finish().unwrap_err();
+        assert!(matches!(
+            err,
+            DnaError::InvalidNuc {
+                character: char::REPLACEMENT_CHARACTER,
+                position: 2
+            }
+        ));
+    }
+
+    #[test]
+    fn failed_chunk_packs_nothing() {
+        let mut packer = StreamingPacker::new();
+        packer.feed(b"ACG").unwrap();
+        assert!(packer.feed(b"TTX").is_err());
+        assert!(packer.feed(b"\xff").is_err());
+        assert_eq!(packer.packed().to_string(), "ACG");
+        packer.feed(b"T").unwrap();
+        assert_eq!(packer.finish().unwrap().to_string(), "ACGT");
+    }
+
+    #[test]
+    fn whitespace_and_readers() {
+        let text = "ACGT\r\nacgt\n\u{a0}TT";
+        let mut packer = StreamingPacker::new().skip_whitespace(true);
+        io::copy(&mut text.as_bytes(), &mut packer).unwrap();
+        assert_eq!(packer.position(), text.chars().count());
+        assert_eq!(packer.finish().unwrap().to_string(), "ACGTACGTTT");
+        let mut strict = StreamingPacker::default();
+        let err = io::copy(&mut "AC GT".as_bytes(), &mut strict).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+    }
+}
@@ -0,0 +1,419 @@
+//! Striped Smith–Waterman (Farrar 2007) for [`Aligner::local_score`](crate::align::Aligner).
+//!
+//! The query is split into `LANES` segments that are processed side by side in the lanes of a
+//! vector register, so one instruction scores `LANES` query positions against the same target
+//! nucleotide. Vertical gaps crossing segment boundaries are fixed up afterwards by the "lazy F"
+//! loop, which rarely runs more than once per column.
+//!
+//! Scores are saturating 16-bit integers. x86_64 uses SSE2 and aarch64 uses NEON, both part of
+//! the baseline of their architectures; other targets use a portable implementation of the same
+//! lane operations. Alignments scoring too high for 16 bits are rescored with 32-bit scalar code.
+
+use crate::{
+    align::{LocalScore, Scoring},
+    Nuc,
+};
+use std::convert::TryFrom;
+
+/// Number of 16-bit lanes in a vector
+const LANES: usize = 8;
+
+/// The lane operations the kernel needs
+trait Lanes: Copy {
+    fn splat(value: i16) -> Self;
+    fn from_array(values: [i16; LANES]) -> Self;
+    fn to_array(self) -> [i16; LANES];
+    fn adds(self, other: Self) -> Self;
+    fn subs(self, other: Self) -> Self;
+    fn max(self, other: Self) -> Self;
+    /// Move every lane up by one, filling lane 0 with `first`
+    fn shift_in(self, first: i16) -> Self;
+    /// Whether any lane of `self` is greater than the same lane of `other`
+    fn any_gt(self, other: Self) -> bool;
+}
+
+// SAFETY, for both native implementations: the intrinsics only need SSE2 on x86_64 and NEON on
+// aarch64, which every CPU of those architectures has, and the loads and stores touch exactly
+// the 16 bytes of a `[i16; LANES]`.
+
+#[cfg(target_arch = "x86_64")]
+mod sse2 {
+    use super::{Lanes, LANES};
+    use std::arch::x86_64::*;
+
+    #[derive(Clone, Copy)]
+    pub(super) struct Vector(__m128i);
+
+    impl Lanes for Vector {
+        fn splat(value: i16) -> Self {
+            Vector(unsafe { _mm_set1_epi16(value) })
+        }
+
+        fn from_array(values: [i16; LANES]) -> Self {
+            Vector(unsafe { _mm_loadu_si128(values.as_ptr().cast()) })
+        }
+
+        fn to_array(self) -> [i16; LANES] {
+            let mut values = [0; LANES];
+            unsafe { _mm_storeu_si128(values.as_mut_ptr().cast(), self.0) };
+            values
+        }
+
+        fn adds(self, other: Self) -> Self {
+            Vector(unsafe { _mm_adds_epi16(self.0, other.0) })
+        }
+
+        fn subs(self, other: Self) -> Self {
+            Vector(unsafe { _mm_subs_epi16(self.0, other.0) })
+        }
+
+        fn max(self, other: Self) -> Self {
+            Vector(unsafe { _mm_max_epi16(self.0, other.0) })
+        }
+
+        fn shift_in(self, first: i16) -> Self {
+            Vector(unsafe { _mm_insert_epi16::<0>(_mm_slli_si128::<2>(self.0), i32::from(first)) })
+        }
+
+        fn any_gt(self, other: Self) -> bool {
+            unsafe { _mm_movemask_epi8(_mm_cmpgt_epi16(self.0, other.0)) != 0 }
+        }
+    }
+}
+
+#[cfg(target_arch = "aarch64")]
+mod neon {
+    use super::{Lanes, LANES};
+    use std::arch::aarch64::*;
+
+    #[derive(Clone, Copy)]
+    pub(super) struct Vector(int16x8_t);
+
+    impl Lanes for Vector {
+        fn splat(value: i16) -> Self {
+            Vector(unsafe { vdupq_n_s16(value) })
+        }
+
+        fn from_array(values: [i16; LANES]) -> Self {
+            Vector(unsafe { vld1q_s16(values.as_ptr()) })
+        }
+
+        fn to_array(self) -> [i16; LANES] {
+            let mut values = [0; LANES];
+            unsafe { vst1q_s16(values.as_mut_ptr(), self.0) };
+            values
+        }
+
+        fn adds(self, other: Self) -> Self {
+            Vector(unsafe { vqaddq_s16(self.0, other.0) })
+        }
+
+        fn subs(self, other: Self) -> Self {
+            Vector(unsafe { vqsubq_s16(self.0, other.0) })
+        }
+
+        fn max(self, other: Self) -> Self {
+            Vector(unsafe { vmaxq_s16(self.0, other.0) })
+        }
+
+        fn shift_in(self, first: i16) -> Self {
+            Vector(unsafe { vextq_s16::<7>(vdupq_n_s16(first), self.0) })
+        }
+
+        fn any_gt(self, other: Self) -> bool {
+            unsafe { vmaxvq_u16(vcgtq_s16(self.0, other.0)) != 0 }
+        }
+    }
+}
+
+/// Lane operations on plain arrays, for targets without a native implementation
+#[cfg_attr(any(target_arch = "x86_64", target_arch = "aarch64"), allow(dead_code))]
+mod portable {
+    use super::{Lanes, LANES};
+
+    #[derive(Clone, Copy)]
+    pub(super) struct Vector([i16; LANES]);
+
+    impl Vector {
+        fn zip(self, other: Self, f: impl Fn(i16, i16) -> i16) -> Self {
+            let mut values = self.0;
+            for (value, &other) in values.iter_mut().zip(&other.0) {
+                *value = f(*value, other);
+            }
+            Vector(values)
+        }
+    }
+
+    impl Lanes for Vector {
+        fn splat(value: i16) -> Self {
+            Vector([value; LANES])
+        }
+
+        fn from_array(values: [i16; LANES]) -> Self {
+            Vector(values)
+        }
+
+        fn to_array(self) -> [i16; LANES] {
+            self.0
+        }
+
+        fn adds(self, other: Self) -> Self {
+            self.zip(other, i16::saturating_add)
+        }
+
+        fn subs(self, other: Self) -> Self {
+            self.zip(other, i16::saturating_sub)
+        }
+
+        fn max(self, other: Self) -> Self {
+            self.zip(other, Ord::max)
+        }
+
+        fn shift_in(self, first: i16) -> Self {
+            let mut values = [first; LANES];
+            values[1..].copy_from_slice(&self.0[..LANES - 1]);
+            Vector(values)
+        }
+
+        fn any_gt(self, other: Self) -> bool {
+            self.0.iter().zip(&other.0).any(|(a, b)| a > b)
+        }
+    }
+}
+
+#[cfg(target_arch = "x86_64")]
+use sse2::Vector;
+
+#[cfg(target_arch = "aarch64")]
+use neon::Vector;
+
+#[cfg(not(any(target_arch = "x86_64", target_arch = "aarch64")))]
+use portable::Vector;
+
+/// Score of every pair of nucleotides, indexed by query then target nucleotide
+pub(crate) type Substitutions = [[i32; 4]; 4];
+
+/// The best local alignment score of `query` against `target` and where it ends, with gaps
+/// scored by `scoring`
+pub(crate) fn local_score(
+    query: &[Nuc],
+    target: &[Nuc],
+    substitutions: &Substitutions,
+    scoring: &Scoring,
+) -> LocalScore {
+    striped::<Vector>(query, target, substitutions, scoring)
+        .unwrap_or_else(|| scalar(query, target, substitutions, scoring))
+}
+
+/// The striped kernel, or `None` if the scores do not fit in 16 bits
+fn striped<V: Lanes>(
+    query: &[Nuc],
+    target: &[Nuc],
+    substitutions: &Substitutions,
+    scoring: &Scoring,
+) -> Option<LocalScore> {
+    let narrow = |score: i32| i16::try_from(score).ok();
+    let mut narrow_substitutions = [[0; 4]; 4];
+    for (narrow_row, row) in narrow_substitutions.iter_mut().zip(substitutions) {
+        for (narrow_score, &score) in narrow_row.iter_mut().zip(row) {
+            *narrow_score = narrow(score)?;
+        }
+    }
+    let gap_open = narrow(-(scoring.gap_open + scoring.gap_extend)).filter(|&p| p >= 0)?;
+    let gap_extend = narrow(-scoring.gap_extend).filter(|&p| p >= 0)?;
+
+    // Lane `l` of segment `s` holds query position `l * seg_len + s`.
+    let seg_len = query.len().div_ceil(LANES).max(1);
+    let profile: Vec<Vec<V>> = [Nuc::A, Nuc::C, Nuc::G, Nuc::T]
+        .iter()
+        .map(|&nuc| {
+            (0..seg_len)
+                .map(|s| {
+                    let mut scores = [i16::MIN / 2; LANES];
+                    for (lane, score) in scores.iter_mut().enumerate() {
+                        if let Some(&other) = query.get(lane * seg_len + s) {
+                            *score = narrow_substitutions[other as usize][nuc as usize];
+                        }
+                    }
+                    V::from_array(scores)
+                })
+                .collect()
+        })
+        .collect();
+
+    let (zero, neg_inf) = (V::splat(0), V::splat(i16::MIN));
+    let (v_gap_open, v_gap_extend) = (V::splat(gap_open), V::splat(gap_extend));
+    let mut h_store = vec![zero; seg_len];
+    let mut h_load = vec![zero; seg_len];
+    let mut e = vec![neg_inf; seg_len];
+    let mut best = LocalScore {
+        score: 0,
+        query_end: 0,
+        target_end: 0,
+    };
+    let mut best_column = Vec::new();
+    for (j, &nuc) in target.iter().enumerate() {
+        let profile = &profile[nuc as usize];
+        let mut f = neg_inf;
+        let mut h = h_store[seg_len - 1].shift_in(0);
+        std::mem::swap(&mut h_load, &mut h_store);
+        let mut column_max = zero;
+        for s in 0..seg_len {
+            h = h.adds(profile[s]).max(e[s]).max(f).max(zero);
+            column_max = column_max.max(h);
+            h_store[s] = h;
+            let opened = h.subs(v_gap_open);
+            e[s] = e[s].subs(v_gap_extend).max(opened);
+            f = f.subs(v_gap_extend).max(opened);
+            h = h_load[s];
+        }
+        // Lazy F: carry vertical gaps across segment boundaries until they stop mattering, which
+        // is once they are no better than a gap opened from a cell the first pass already saw.
+        'lazy: for _ in 0..LANES {
+            f = f.shift_in(i16::MIN);
+            for s in 0..seg_len {
+                if !f.any_gt(h_store[s].subs(v_gap_open)) {
+                    break 'lazy;
+                }
+                let h = h_store[s].max(f);
+                h_store[s] = h;
+                column_max = column_max.max(h);
+                e[s] = e[s].max(h.subs(v_gap_open));
+                f = f.subs(v_gap_extend);
+            }
+        }
+
+        let column_max = column_max.to_array().iter().copied().max().unwrap_or(0);
+        if column_max == i16::MAX {
+            return None;
+        }
+        if i32::from(column_max) > best.score {
+            best.score = i32::from(column_max);
+            best.target_end = j + 1;
+            best_column.clone_from(&h_store);
+        }
+    }
+
+    if best.score > 0 {
+        let column: Vec<[i16; LANES]> = best_column.iter().map(|h| h.to_array()).collect();
+        best.query_end = (0..query.len())
+            .find(|&i| i32::from(column[i % seg_len][i / seg_len]) == best.score)
+            .map_or(0, |i| i + 1);
+    }
+    Some(best)
+}
+
+/// The same computation with 32-bit scores, one cell at a time
+fn scalar(
+    query: &[Nuc],
+    target: &[Nuc],
+    substitutions: &Substitutions,
+    scoring: &Scoring,
+) -> LocalScore {
+    let open = scoring.gap_open + scoring.gap_extend;
+    let mut h = vec![0i32; query.len() + 1];
+    let mut e = vec![i32::MIN / 2; query.len() + 1];
+    let mut best = LocalScore {
+        score: 0,
+        query_end: 0,
+        target_end: 0,
+    };
+    for (j, &nuc) in target.iter().enumerate() {
+        let mut diagonal = 0;
+        let mut f = i32::MIN / 2;
+        for i in 1..=query.len() {
+            e[i] = (e[i] + scoring.gap_extend).max(h[i] + open);
+            f = (f + scoring.gap_extend).max(h[i - 1] + open);
+            let substitution = substitutions[query[i - 1] as usize][nuc as usize];
+            let score = (diagonal + substitution).max(e[i]).max(f).max(0);
+            diagonal = h[i];
+            h[i] = score;
+            if score > best.score {
+                best = LocalScore {
+                    score,
+                    query_end: i,
+                    target_end: j + 1,
+                };
+            }
+        }
+    }
+    best
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::Lcg;
+
+    fn uniform(scoring: &Scoring) -> Substitutions {
+        let mut substitutions = [[scoring.mismatch; 4]; 4];
+        for (idx, row) in substitutions.iter_mut().enumerate() {
+            row[idx] = scoring.match_score;
+        }
+        substitutions
+    }
+
+    fn random_nucs(rng: &mut Lcg, len: usize) -> Vec<Nuc> {
+        (0..len).map(|_| rng.nuc()).collect()
+    }
+
+    #[test]
+    fn striped_matches_scalar() {
+        let scorings = [
+            Scoring::default(),
+            Scoring {
+                match_score: 2,
+                mismatch: -3,
+                gap_open: -5,
+                gap_extend: -2,
+            },
+            Scoring {
+                match_score: 1,
+                mismatch: -1,
+                gap_open: 0,
+                gap_extend: -1,
+            },
+        ];
+        let mut rng = Lcg::new(7);
+        for case in 0..300 {
+            let query_len = (case * 7) % 70;
+            let query = random_nucs(&mut rng, query_len);
+            let mut target = random_nucs(&mut rng, case % 40);
+            // Plant a mutated copy of part of the query so there is something to find.
+            target.extend(query.iter().skip(case % 5).step_by(1 + case % 2));
+            target.extend(random_nucs(&mut rng, case % 13));
+            let scoring = &scorings[case % scorings.len()];
+            let substitutions = &uniform(scoring);
+            let expected = scalar(&query, &target, substitutions, scoring);
+            assert_eq!(
+                striped::<Vector>(&query, &target, substitutions, scoring),
+                Some(expected),
+                "case {}",
+                case
+            );
+            assert_eq!(
+                striped::<portable::Vector>(&query, &target, substitutions, scoring),
+                Some(expected),
+                "case {}",
+                case
+            );
+        }
+    }
+
+    #[test]
+    fn overflow_falls_back_to_scalar() {
+        let query = vec![Nuc::G; 3000];
+        let scoring = Scoring {
+            match_score: 20,
+            ..Scoring::default()
+        };
+        let substitutions = &uniform(&scoring);
+        assert_eq!(
+            striped::<Vector>(&query, &query, substitutions, &scoring),
+            None
+        );
+        let score = local_score(&query, &query, substitutions, &scoring);
+        assert_eq!(score.score, 60_000);
+        assert_eq!((score.query_end, score.target_end), (3000, 3000));
+    }
+}
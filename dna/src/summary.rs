@@ -0,0 +1,401 @@
+//! Small summaries of FASTA files, kept in a sidecar file next to them.
+//!
+//! A [`Summary`] records the length, digest and GC content of every sequence of a FASTA file and
+//! a MinHash [`Sketch`] of all of them, in a few lines of text. Saved next to the file as
+//! `<file>.summary`, it lets later runs skip parsing the sequences: [`Summary::load`] returns it
+//! only while it is fresh, that is while the FASTA file still has the length and digest it was
+//! computed from. Checking reads the bytes of the file but builds no sequences.
+//!
+//! Digests are 64-bit FNV-1a hashes, which catch accidental changes but not deliberate
+//! collisions.
+
+use crate::{
+    fasta::{self, FastaError},
+    kmer::MAX_K,
+    sketch::Sketch,
+    PackedDna,
+};
+use std::{
+    fs::File,
+    io::{self, BufRead, BufReader, BufWriter, Read, Write},
+    path::{Path, PathBuf},
+};
+
+/// First line of every summary, naming the format version
+const MAGIC: &str = "#dna-summary 1";
+
+/// An error that can occur when computing, reading or writing a summary.
+#[derive(Debug, thiserror::Error)]
+pub enum SummaryError {
+    /// The underlying reader or writer failed
+    #[error("failed to read or write summary")]
+    Io(#[from] io::Error),
+    /// The summarized FASTA input could not be read
+    #[error(transparent)]
+    Fasta(#[from] FastaError),
+    /// A line of a summary could not be parsed
+    #[error("malformed summary line {0}")]
+    Malformed(usize),
+}
+
+/// Length, digest and GC content of one sequence of a FASTA file
+#[derive(Debug, Clone, PartialEq)]
+pub struct SequenceSummary {
+    /// The record identifier, the header up to the first whitespace
+    pub id: String,
+    /// Number of nucleotides
+    pub len: usize,
+    /// FNV-1a digest of the nucleotides as upper case letters
+    pub digest: u64,
+    /// Fraction of the sequence that is G or C
+    pub gc_content: f64,
+}
+
+/// A summary of a FASTA file, see the [module documentation](self)
+#[derive(Debug, Clone, PartialEq)]
+pub struct Summary {
+    file_len: u64,
+    file_digest: u64,
+    sketch_size: usize,
+    sketch: Sketch,
+    sequences: Vec<SequenceSummary>,
+}
+
+impl Summary {
+    /// Summarize the FASTA input of `reader`, sketching it with the `size` smallest hashes of its
+    /// canonical k-mers of length `k`. Records are read one at a time.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `size` is zero, or `k` is zero or greater than [`MAX_K`].
+    pub fn from_reader(reader: impl Read, k: usize, size: usize) -> Result<Self, SummaryError> {
+        let mut input = Digester::new(reader);
+        let mut sequences = Vec::new();
+        let mut hashes = Vec::new();
+        for record in fasta::Reader::new(&mut input) {
+            let record = record?;
+            let dna = record.sequence();
+            sequences.push(SequenceSummary {
+                id: record.id().to_string(),
+                len: dna.len(),
+                digest: sequence_digest(dna),
+                gc_content: dna.gc_content(),
+            });
+            hashes.extend_from_slice(Sketch::minhash(dna, k, size).hashes());
+            hashes.sort_unstable();
+            hashes.dedup();
+            hashes.truncate(size);
+        }
+        Ok(Summary {
+            file_len: input.len,
+            file_digest: input.hash,
+            sketch_size: size,
+            sketch: Sketch::from_bottom(k, size, hashes),
+            sequences,
+        })
+    }
+
+    /// Summarize the FASTA file at `path`, see [`from_reader`](Self::from_reader)
+    pub fn from_path(path: impl AsRef<Path>, k: usize, size: usize) -> Result<Self, SummaryError> {
+        Summary::from_reader(File::open(path)?, k, size)
+    }
+
+    /// The summary saved next to the FASTA file at `path`, or `None` if there is none or the
+    /// file has changed since
+    pub fn load(path: impl AsRef<Path>) -> Result<Option<Self>, SummaryError> {
+        let path = path.as_ref();
+        let sidecar = match File::open(sidecar_path(path)) {
+            Ok(sidecar) => sidecar,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(None),
+            Err(e) => return Err(e.into()),
+        };
+        let summary = Summary::read(sidecar)?;
+        Ok(if summary.is_fresh(path)? {
+            Some(summary)
+        } else {
+            None
+        })
+    }
+
+    /// The fresh summary saved next to the FASTA file at `path` if it was sketched with `k` and
+    /// `size`, or else a new summary, which is saved in its place
+    pub fn load_or_compute(
+        path: impl AsRef<Path>,
+        k: usize,
+        size: usize,
+    ) -> Result<Self, SummaryError> {
+        let path = path.as_ref();
+        if let Some(summary) = Summary::load(path)? {
+            if summary.sketch.k() == k && summary.sketch_size == size {
+                return Ok(summary);
+            }
+        }
+        let summary = Summary::from_path(path, k, size)?;
+        summary.save(path)?;
+        Ok(summary)
+    }
+
+    /// Save the summary next to the FASTA file at `path`, at [`sidecar_path`]
+    pub fn save(&self, path: impl AsRef<Path>) -> io::Result<()> {
+        let mut sidecar = BufWriter::new(File::create(sidecar_path(path.as_ref()))?);
+        self.write(&mut sidecar)?;
+        sidecar.flush()
+    }
+
+    /// Whether the FASTA file at `path` is still the one summarized
+    pub fn is_fresh(&self, path: impl AsRef<Path>) -> io::Result<bool> {
+        let file = File::open(path)?;
+        if file.metadata()?.len() != self.file_len {
+            return Ok(false);
+        }
+        let mut input = Digester::new(file);
+        io::copy(&mut input, &mut io::sink())?;
+        Ok(input.len == self.file_len && input.hash == self.file_digest)
+    }
+
+    /// Number of bytes of the summarized file
+    pub fn file_len(&self) -> u64 {
+        self.file_len
+    }
+
+    /// FNV-1a digest of the bytes of the summarized file
+    pub fn file_digest(&self) -> u64 {
+        self.file_digest
+    }
+
+    /// The sequences of the file, in file order
+    pub fn sequences(&self) -> &[SequenceSummary] {
+        &self.sequences
+    }
+
+    /// The MinHash sketch of the k-mers of all sequences together
+    pub fn sketch(&self) -> &Sketch {
+        &self.sketch
+    }
+
+    /// Write the summary as text, one tab-separated line per item
+    pub fn write(&self, mut writer: impl Write) -> io::Result<()> {
+        writeln!(writer, "{}", MAGIC)?;
+        writeln!(writer, "file\t{}\t{:016x}", self.file_len, self.file_digest)?;
+        let hashes: Vec<String> = self
+            .sketch
+            .hashes()
+            .iter()
+            .map(|hash| format!("{:016x}", hash))
+            .collect();
+        writeln!(
+            writer,
+            "sketch\t{}\t{}\t{}",
+            self.sketch.k(),
+            self.sketch_size,
+            hashes.join(",")
+        )?;
+        for sequence in &self.sequences {
+            writeln!(
+                writer,
+                "sequence\t{}\t{}\t{:016x}\t{}",
+                sequence.id, sequence.len, sequence.digest, sequence.gc_content
+            )?;
+        }
+        Ok(())
+    }
+
+    /// Read a summary written by [`write`](Self::write)
+    pub fn read(reader: impl Read) -> Result<Self, SummaryError> {
+        let lines = BufReader::new(reader)
+            .lines()
+            .collect::<io::Result<Vec<String>>>()?;
+        let line = |idx: usize| lines.get(idx).map(String::as_str).unwrap_or_default();
+        if line(0) != MAGIC {
+            return Err(SummaryError::Malformed(1));
+        }
+        let (file_len, file_digest) = match fields(line(1), "file")[..] {
+            [len, digest] => len.parse().ok().zip(u64::from_str_radix(digest, 16).ok()),
+            _ => None,
+        }
+        .ok_or(SummaryError::Malformed(2))?;
+        let (sketch_size, sketch) = parse_sketch(line(2)).ok_or(SummaryError::Malformed(3))?;
+        let sequences = lines
+            .iter()
+            .enumerate()
+            .skip(3)
+            .map(|(idx, line)| parse_sequence(line).ok_or(SummaryError::Malformed(idx + 1)))
+            .collect::<Result<_, _>>()?;
+        Ok(Summary {
+            file_len,
+            file_digest,
+            sketch_size,
+            sketch,
+            sequences,
+        })
+    }
+}
+
+/// Where the summary of the FASTA file at `path` is saved: the same path with `.summary`
+/// appended, e.g. `genome.fa.summary`
+pub fn sidecar_path(path: impl AsRef<Path>) -> PathBuf {
+    let mut sidecar = path.as_ref().as_os_str().to_owned();
+    sidecar.push(".summary");
+    PathBuf::from(sidecar)
+}
+
+/// The tab-separated fields after `tag`, or none if the line is not tagged with it
+fn fields<'a>(line: &'a str, tag: &str) -> Vec<&'a str> {
+    let mut fields = line.split('\t');
+    if fields.next() == Some(tag) {
+        fields.collect()
+    } else {
+        Vec::new()
+    }
+}
+
+/// The sketch size and sketch of a `sketch` line
+fn parse_sketch(line: &str) -> Option<(usize, Sketch)> {
+    let (k, size, hashes) = match fields(line, "sketch")[..] {
+        [k, size, hashes] => (k.parse().ok()?, size.parse().ok()?, hashes),
+        _ => return None,
+    };
+    let hashes = hashes
+        .split(',')
+        .filter(|hash| !hash.is_empty())
+        .map(|hash| u64::from_str_radix(hash, 16).ok())
+        .collect::<Option<Vec<u64>>>()?;
+    let valid = (1..=MAX_K).contains(&k)
+        && size > 0
+        && hashes.len() <= size
+        && hashes.windows(2).all(|pair| pair[0] < pair[1]);
+    valid.then(|| (size, Sketch::from_bottom(k, size, hashes)))
+}
+
+/// The summary of a `sequence` line
+fn parse_sequence(line: &str) -> Option<SequenceSummary> {
+    match fields(line, "sequence")[..] {
+        [id, len, digest, gc_content] => Some(SequenceSummary {
+            id: id.to_string(),
+            len: len.parse().ok()?,
+            digest: u64::from_str_radix(digest, 16).ok()?,
+            gc_content: gc_content.parse().ok()?,
+        }),
+        _ => None,
+    }
+}
+
+/// FNV-1a digest of the nucleotides of `dna` as upper case letters
+fn sequence_digest(dna: &PackedDna) -> u64 {
+    symbols_digest(dna.iter().map(|nuc| nuc.to_char()))
+}
+
+/// FNV-1a digest of the ASCII `symbols` of a sequence, as [`SequenceSummary::digest`]
+pub(crate) fn symbols_digest(symbols: impl IntoIterator<Item = char>) -> u64 {
+    let mut digester = Digester::new(io::empty());
+    for symbol in symbols {
+        digester.update(&[symbol as u8]);
+    }
+    digester.hash
+}
+
+/// A reader passing the bytes of another on, keeping their FNV-1a digest
+struct Digester<R> {
+    inner: R,
+    hash: u64,
+    len: u64,
+}
+
+impl<R> Digester<R> {
+    fn new(inner: R) -> Self {
+        Digester {
+            inner,
+            hash: 0xcbf2_9ce4_8422_2325,
+            len: 0,
+        }
+    }
+
+    fn update(&mut self, bytes: &[u8]) {
+        for &byte in bytes {
+            self.hash = (self.hash ^ u64::from(byte)).wrapping_mul(0x0100_0000_01b3);
+        }
+        self.len += bytes.len() as u64;
+    }
+}
+
+impl<R: Read> Read for Digester<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let read = self.inner.read(buf)?;
+        self.update(&buf[..read]);
+        Ok(read)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const FASTA: &str = ">chr1 first\nACGTTGCAACGGTACCAGTA\n>chr2\nGGGCCCAT\n>empty\n";
+
+    #[test]
+    fn write_and_read_back() {
+        let summary = Summary::from_reader(FASTA.as_bytes(), 5, 8).unwrap();
+        assert_eq!(summary.file_len(), FASTA.len() as u64);
+        let ids: Vec<(&str, usize)> = summary
+            .sequences()
+            .iter()
+            .map(|sequence| (sequence.id.as_str(), sequence.len))
+            .collect();
+        assert_eq!(ids, [("chr1", 20), ("chr2", 8), ("empty", 0)]);
+        assert_eq!(summary.sequences()[1].gc_content, 0.75);
+        assert_ne!(summary.sequences()[0].digest, summary.sequences()[1].digest);
+        assert_eq!(summary.sketch().len(), 8);
+
+        // The smallest hashes of the k-mers of all sequences together
+        let mut hashes: Vec<u64> = fasta::Reader::new(FASTA.as_bytes())
+            .flat_map(|record| {
+                Sketch::minhash(record.unwrap().sequence(), 5, 100)
+                    .hashes()
+                    .to_vec()
+            })
+            .collect();
+        hashes.sort_unstable();
+        hashes.dedup();
+        assert_eq!(summary.sketch().hashes(), &hashes[..8]);
+
+        let mut text = Vec::new();
+        summary.write(&mut text).unwrap();
+        assert_eq!(Summary::read(&text[..]).unwrap(), summary);
+
+        let text = String::from_utf8(text).unwrap();
+        let broken = text.replacen("\tchr2\t8\t", "\tchr2\teight\t", 1);
+        assert!(matches!(
+            Summary::read(broken.as_bytes()),
+            Err(SummaryError::Malformed(5))
+        ));
+        assert!(matches!(
+            Summary::read("#dna-summary 2\n".as_bytes()),
+            Err(SummaryError::Malformed(1))
+        ));
+    }
+
+    #[test]
+    fn sidecar_goes_stale_with_the_file() {
+        let path = std::env::temp_dir().join(format!("summary-{}.fa", std::process::id()));
+        std::fs::write(&path, FASTA).unwrap();
+        assert!(Summary::load(&path).unwrap().is_none());
+
+        let summary = Summary::load_or_compute(&path, 5, 8).unwrap();
+        assert_eq!(Summary::load(&path).unwrap(), Some(summary.clone()));
+        assert_eq!(Summary::load_or_compute(&path, 5, 8).unwrap(), summary);
+        assert_eq!(
+            Summary::load_or_compute(&path, 4, 8).unwrap().sketch().k(),
+            4
+        );
+
+        // Same length, different content
+        std::fs::write(&path, FASTA.replace("GGGCCCAT", "GGGACCAT")).unwrap();
+        assert!(Summary::load(&path).unwrap().is_none());
+        let updated = Summary::load_or_compute(&path, 5, 8).unwrap();
+        assert_eq!(updated.sequences()[1].gc_content, 0.625);
+        assert!(Summary::load(&path).unwrap().is_some());
+
+        std::fs::remove_file(sidecar_path(&path)).unwrap();
+        std::fs::remove_file(path).unwrap();
+    }
+}
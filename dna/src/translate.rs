@@ -0,0 +1,463 @@
+//! Transcription, translation and open reading frames.
+//!
+//! Codons are translated with one of the NCBI genetic codes, see [`GeneticCode`]. Reading
+//! frames are numbered 0 to 2 from the start of the strand being read.
+
+use crate::{
+    nuc_from_code,
+    protein::{AminoAcid, PackedProtein},
+    DnaError, Nuc, PackedDna,
+};
+use std::{convert::TryFrom, fmt::Display, ops::Range, str::FromStr};
+
+/// An RNA sequence, packed like [`PackedDna`] with uracil in place of thymine
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct PackedRna {
+    pub(crate) dna: PackedDna,
+}
+
+impl PackedRna {
+    /// Number of bases
+    pub fn len(&self) -> usize {
+        self.dna.len()
+    }
+
+    /// Whether the sequence holds no bases
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// The DNA coding strand this RNA was transcribed from
+    pub fn back_transcribe(&self) -> PackedDna {
+        self.dna.clone()
+    }
+
+    /// Translate `frame` with the standard genetic code, see [`PackedDna::translate`]
+    pub fn translate(&self, frame: usize) -> PackedProtein {
+        self.dna.translate(frame)
+    }
+
+    /// Translate `frame` with the genetic `code`, see [`PackedDna::translate_with`]
+    pub fn translate_with(&self, frame: usize, code: GeneticCode) -> PackedProtein {
+        self.dna.translate_with(frame, code)
+    }
+}
+
+impl Display for PackedRna {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        for nuc in &self.dna {
+            let base = match nuc {
+                Nuc::A => 'A',
+                Nuc::C => 'C',
+                Nuc::G => 'G',
+                Nuc::T => 'U',
+            };
+            write!(f, "{}", base)?;
+        }
+        Ok(())
+    }
+}
+
+/// Parses A, C, G and U in either case; `T` is rejected like any other non-RNA character
+impl FromStr for PackedRna {
+    type Err = DnaError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut dna = PackedDna::with_capacity(s.len());
+        for (position, character) in s.chars().enumerate() {
+            let nuc = match character.to_ascii_uppercase() {
+                'U' => Some(Nuc::T),
+                'T' => None,
+                _ => Nuc::try_from(character).ok(),
+            };
+            dna.push(nuc.ok_or(DnaError::InvalidNuc {
+                character,
+                position,
+            })?);
+        }
+        Ok(PackedRna { dna })
+    }
+}
+
+/// Transcription, see [`PackedDna::transcribe`]
+impl From<PackedDna> for PackedRna {
+    fn from(dna: PackedDna) -> Self {
+        PackedRna { dna }
+    }
+}
+
+/// Reverse transcription, see [`PackedRna::back_transcribe`]
+impl From<PackedRna> for PackedDna {
+    fn from(rna: PackedRna) -> Self {
+        rna.dna
+    }
+}
+
+/// An NCBI genetic code, mapping codons to amino acids
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub enum GeneticCode {
+    /// The standard code (NCBI table 1)
+    #[default]
+    Standard,
+    /// Vertebrate mitochondrial code (NCBI table 2)
+    VertebrateMitochondrial,
+    /// Yeast mitochondrial code (NCBI table 3)
+    YeastMitochondrial,
+    /// Mold, protozoan and coelenterate mitochondrial code (NCBI table 4)
+    MoldMitochondrial,
+    /// Invertebrate mitochondrial code (NCBI table 5)
+    InvertebrateMitochondrial,
+    /// Ciliate, dasycladacean and hexamita nuclear code (NCBI table 6)
+    Ciliate,
+    /// Bacterial, archaeal and plant plastid code (NCBI table 11)
+    Bacterial,
+}
+
+impl GeneticCode {
+    /// The code with NCBI translation table number `id`, if supported
+    pub fn from_ncbi_id(id: u8) -> Option<Self> {
+        match id {
+            1 => Some(Self::Standard),
+            2 => Some(Self::VertebrateMitochondrial),
+            3 => Some(Self::YeastMitochondrial),
+            4 => Some(Self::MoldMitochondrial),
+            5 => Some(Self::InvertebrateMitochondrial),
+            6 => Some(Self::Ciliate),
+            11 => Some(Self::Bacterial),
+            _ => None,
+        }
+    }
+
+    /// The NCBI translation table number
+    pub fn ncbi_id(self) -> u8 {
+        match self {
+            Self::Standard => 1,
+            Self::VertebrateMitochondrial => 2,
+            Self::YeastMitochondrial => 3,
+            Self::MoldMitochondrial => 4,
+            Self::InvertebrateMitochondrial => 5,
+            Self::Ciliate => 6,
+            Self::Bacterial => 11,
+        }
+    }
+
+    /// One-letter amino acids of all 64 codons, in the NCBI `TCAG` order
+    fn table(self) -> &'static [u8; 64] {
+        match self {
+            Self::Standard | Self::Bacterial => {
+                b"FFLLSSSSYY**CC*WLLLLPPPPHHQQRRRRIIIMTTTTNNKKSSRRVVVVAAAADDEEGGGG"
+            }
+            Self::VertebrateMitochondrial => {
+                b"FFLLSSSSYY**CCWWLLLLPPPPHHQQRRRRIIMMTTTTNNKKSS**VVVVAAAADDEEGGGG"
+            }
+            Self::YeastMitochondrial => {
+                b"FFLLSSSSYY**CCWWTTTTPPPPHHQQRRRRIIMMTTTTNNKKSSRRVVVVAAAADDEEGGGG"
+            }
+            Self::MoldMitochondrial => {
+                b"FFLLSSSSYY**CCWWLLLLPPPPHHQQRRRRIIIMTTTTNNKKSSRRVVVVAAAADDEEGGGG"
+            }
+            Self::InvertebrateMitochondrial => {
+                b"FFLLSSSSYY**CCWWLLLLPPPPHHQQRRRRIIMMTTTTNNKKSSSSVVVVAAAADDEEGGGG"
+            }
+            Self::Ciliate => b"FFLLSSSSYYQQCC*WLLLLPPPPHHQQRRRRIIIMTTTTNNKKSSRRVVVVAAAADDEEGGGG",
+        }
+    }
+
+    /// The amino acid encoded by a codon
+    pub fn translate_codon(self, codon: [Nuc; 3]) -> AminoAcid {
+        let index = codon.iter().fold(0, |index, nuc| {
+            let rank = match nuc {
+                Nuc::T => 0,
+                Nuc::C => 1,
+                Nuc::A => 2,
+                Nuc::G => 3,
+            };
+            index * 4 + rank
+        });
+        AminoAcid::try_from(char::from(self.table()[index])).unwrap_or(AminoAcid::Xaa)
+    }
+}
+
+/// The strand a feature such as an open reading frame or a motif was found on
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Strand {
+    /// The sequence as given
+    Forward,
+    /// The reverse complement of the sequence
+    Reverse,
+}
+
+/// An open reading frame, see [`PackedDna::find_orfs`]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Orf {
+    /// Strand the frame was read from
+    pub strand: Strand,
+    /// Reading frame on that strand, 0 to 2
+    pub frame: usize,
+    /// 0-based nucleotides of the ORF on the forward strand, start and stop codons included
+    pub range: Range<usize>,
+    /// The encoded protein, without the stop
+    pub protein: PackedProtein,
+}
+
+impl PackedDna {
+    /// The RNA transcribed from this sequence taken as the coding strand
+    pub fn transcribe(&self) -> PackedRna {
+        PackedRna { dna: self.clone() }
+    }
+
+    /// Translate the codons of `frame` with the standard genetic code.
+    ///
+    /// Stop codons are kept as [`AminoAcid::Stop`] and a trailing partial codon is dropped.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `frame` is greater than 2.
+    pub fn translate(&self, frame: usize) -> PackedProtein {
+        self.translate_with(frame, GeneticCode::Standard)
+    }
+
+    /// Translate the codons of `frame` with the genetic `code`, see [`translate`](Self::translate)
+    ///
+    /// # Panics
+    ///
+    /// Panics if `frame` is greater than 2.
+    pub fn translate_with(&self, frame: usize, code: GeneticCode) -> PackedProtein {
+        assert!(frame < 3, "reading frame must be 0, 1 or 2, got {}", frame);
+        self.codons(frame)
+            .map(|codon| code.translate_codon(codon))
+            .collect()
+    }
+
+    /// Translations of all six reading frames: frames 0 to 2 of the sequence, then frames 0 to 2
+    /// of its reverse complement
+    pub fn six_frame_translation(&self, code: GeneticCode) -> [PackedProtein; 6] {
+        let frame = |strand, frame| self.translate_frame(strand, frame, code).collect();
+        [
+            frame(Strand::Forward, 0),
+            frame(Strand::Forward, 1),
+            frame(Strand::Forward, 2),
+            frame(Strand::Reverse, 0),
+            frame(Strand::Reverse, 1),
+            frame(Strand::Reverse, 2),
+        ]
+    }
+
+    /// The amino acids of `frame` of `strand`, translated with the genetic `code` as they are
+    /// iterated.
+    ///
+    /// Frames of [`Strand::Reverse`] are those of the reverse complement, but it is never built,
+    /// so a chromosome can be translated to a writer with no more memory than its packed
+    /// sequence. Stop codons are kept and a trailing partial codon is dropped, as with
+    /// [`translate`](Self::translate).
+    ///
+    /// # Panics
+    ///
+    /// Panics if `frame` is greater than 2.
+    pub fn translate_frame(
+        &self,
+        strand: Strand,
+        frame: usize,
+        code: GeneticCode,
+    ) -> impl Iterator<Item = AminoAcid> + '_ {
+        assert!(frame < 3, "reading frame must be 0, 1 or 2, got {}", frame);
+        let len = self.len();
+        let count = len.saturating_sub(frame) / 3;
+        (0..count).map(move |i| {
+            let start = frame + 3 * i;
+            let codon = match strand {
+                Strand::Forward => [
+                    self.nuc_at(start),
+                    self.nuc_at(start + 1),
+                    self.nuc_at(start + 2),
+                ],
+                Strand::Reverse => {
+                    // Complementing a 2-bit code swaps A with T and C with G.
+                    let complement = |idx| nuc_from_code(3 - self.nuc_at(idx) as u8);
+                    let end = len - start;
+                    [
+                        complement(end - 1),
+                        complement(end - 2),
+                        complement(end - 3),
+                    ]
+                }
+            };
+            code.translate_codon(codon)
+        })
+    }
+
+    /// Open reading frames on both strands encoding at least `min_len` amino acids.
+    ///
+    /// An ORF runs from an `ATG` start codon to the first in-frame stop codon; starts inside an
+    /// ORF are not reported separately, and a start without a downstream stop is not an ORF.
+    /// ORFs are ordered by strand, then frame, then position along the strand read.
+    pub fn find_orfs(&self, min_len: usize, code: GeneticCode) -> Vec<Orf> {
+        let len = self.len();
+        let revcomp = self.reverse_complement();
+        let mut orfs = Vec::new();
+        for (strand, dna) in [(Strand::Forward, self), (Strand::Reverse, &revcomp)] {
+            for frame in 0..3 {
+                let mut open: Option<(usize, PackedProtein)> = None;
+                for (i, codon) in dna.codons(frame).enumerate() {
+                    let aa = code.translate_codon(codon);
+                    let pos = frame + 3 * i;
+                    match open.as_mut() {
+                        None if codon == [Nuc::A, Nuc::T, Nuc::G] => {
+                            open = Some((pos, [aa].iter().copied().collect()))
+                        }
+                        None => {}
+                        Some(_) if aa == AminoAcid::Stop => {
+                            let (start, protein) = open.take().unwrap_or_default();
+                            if protein.len() < min_len {
+                                continue;
+                            }
+                            let end = pos + 3;
+                            let range = match strand {
+                                Strand::Forward => start..end,
+                                Strand::Reverse => len - end..len - start,
+                            };
+                            orfs.push(Orf {
+                                strand,
+                                frame,
+                                range,
+                                protein,
+                            });
+                        }
+                        Some((_, protein)) => protein.push(aa),
+                    }
+                }
+            }
+        }
+        orfs
+    }
+
+    /// The complete codons of `frame`
+    fn codons(&self, frame: usize) -> impl Iterator<Item = [Nuc; 3]> + '_ {
+        let count = self.len().saturating_sub(frame) / 3;
+        (0..count).map(move |i| {
+            let start = frame + 3 * i;
+            [
+                self.nuc_at(start),
+                self.nuc_at(start + 1),
+                self.nuc_at(start + 2),
+            ]
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::dna;
+    use std::str::FromStr;
+
+    #[test]
+    fn transcribe_writes_uracil() {
+        let rna = dna("ATGCTT").transcribe();
+        assert_eq!(rna.to_string(), "AUGCUU");
+        assert_eq!(rna.len(), 6);
+        assert_eq!(rna.back_transcribe(), dna("ATGCTT"));
+    }
+
+    #[test]
+    fn parse_rna() {
+        let rna = PackedRna::from_str("AUGcuu").unwrap();
+        assert_eq!(rna.to_string(), "AUGCUU");
+        assert_eq!(PackedDna::from(rna.clone()), dna("ATGCTT"));
+        assert_eq!(PackedRna::from(dna("ATGCTT")), rna);
+        assert_eq!(
+            PackedRna::from_str("AUGT").unwrap_err().to_string(),
+            "failed to parse nucleotide from 'T' at position 3"
+        );
+    }
+
+    #[test]
+    fn translate_standard_frames() {
+        let seq = dna("ATGGCCTGGTAAC");
+        assert_eq!(seq.translate(0).to_string(), "MAW*");
+        assert_eq!(seq.translate(1).to_string(), "WPGN");
+        assert_eq!(seq.translate(2).to_string(), "GLV");
+        assert_eq!(seq.transcribe().translate(0), seq.translate(0));
+    }
+
+    #[test]
+    fn translate_with_alternative_code() {
+        // TGA is a stop in the standard code but tryptophan in vertebrate mitochondria.
+        let seq = dna("ATGTGAAGA");
+        assert_eq!(seq.translate(0).to_string(), "M*R");
+        assert_eq!(
+            seq.translate_with(0, GeneticCode::VertebrateMitochondrial)
+                .to_string(),
+            "MW*"
+        );
+    }
+
+    #[test]
+    fn ncbi_ids_round_trip() {
+        for id in 0..=30 {
+            if let Some(code) = GeneticCode::from_ncbi_id(id) {
+                assert_eq!(code.ncbi_id(), id);
+            }
+        }
+        assert_eq!(GeneticCode::from_ncbi_id(11), Some(GeneticCode::Bacterial));
+        assert_eq!(GeneticCode::from_ncbi_id(7), None);
+    }
+
+    #[test]
+    fn six_frames() {
+        let frames = dna("ATGAAACCC").six_frame_translation(GeneticCode::Standard);
+        let frames: Vec<String> = frames.iter().map(|p| p.to_string()).collect();
+        assert_eq!(frames, vec!["MKP", "*N", "ET", "GFH", "GF", "VS"]);
+    }
+
+    #[test]
+    fn translate_frame_matches_reverse_complement() {
+        let seq = dna("CCATGAAATAGGGCTATTTCATGGT");
+        let revcomp = seq.reverse_complement();
+        for len in 0..seq.len() {
+            let (seq, revcomp) = (
+                seq.to_slice(0..len),
+                revcomp.to_slice(seq.len() - len..seq.len()),
+            );
+            for frame in 0..3 {
+                let code = GeneticCode::VertebrateMitochondrial;
+                let forward: PackedProtein =
+                    seq.translate_frame(Strand::Forward, frame, code).collect();
+                assert_eq!(forward, seq.translate_with(frame, code));
+                let reverse: PackedProtein =
+                    seq.translate_frame(Strand::Reverse, frame, code).collect();
+                assert_eq!(reverse, revcomp.translate_with(frame, code));
+            }
+        }
+    }
+
+    #[test]
+    fn find_orfs_both_strands() {
+        // Forward: ATG AAA TAG at 2..11. Reverse: the complement of CTA TTT CAT.
+        let seq = dna("CCATGAAATAGGGCTATTTCATGG");
+        let orfs = seq.find_orfs(1, GeneticCode::Standard);
+        assert_eq!(orfs.len(), 2);
+        assert_eq!(orfs[0].strand, Strand::Forward);
+        assert_eq!(orfs[0].frame, 2);
+        assert_eq!(orfs[0].range, 2..11);
+        assert_eq!(orfs[0].protein.to_string(), "MK");
+        assert_eq!(orfs[1].strand, Strand::Reverse);
+        assert_eq!(orfs[1].range, 13..22);
+        assert_eq!(
+            seq.to_slice(orfs[1].range.clone())
+                .reverse_complement()
+                .translate(0)
+                .to_string(),
+            "MK*"
+        );
+    }
+
+    #[test]
+    fn find_orfs_min_len_and_unterminated() {
+        let seq = dna("ATGTAAATGCCCGGGTAGATGAAA");
+        let orfs = seq.find_orfs(3, GeneticCode::Standard);
+        assert_eq!(orfs.len(), 1);
+        assert_eq!(orfs[0].range, 6..18);
+        assert_eq!(orfs[0].protein.to_string(), "MPG");
+    }
+}
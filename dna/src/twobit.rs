@@ -0,0 +1,531 @@
+//! Reading and writing of UCSC `.2bit` genome files.
+//!
+//! The `.2bit` format stores each sequence at 2 bits per base (T = `00`, C = `01`, A = `10`,
+//! G = `11`) together with blocks of `N` and blocks of soft-masked (lowercase) positions. `N`
+//! blocks map onto the ambiguous runs of an [`IupacDna`]; mask blocks are kept as ranges on the
+//! [`Record`]. Both version 0 (32-bit offsets) and version 1 (64-bit offsets) files can be read;
+//! files are written as version 0 unless they are larger than 4 GiB. In either version a sequence
+//! holds fewer than 2^32 nucleotides.
+
+use crate::{
+    aligned::AlignedBuf,
+    iupac::{AmbiguousRun, Iupac, IupacDna},
+    Nuc, PackedDna,
+};
+use std::{
+    collections::HashMap,
+    convert::TryFrom,
+    io::{self, Read, Seek, SeekFrom, Write},
+    ops::Range,
+};
+
+const SIGNATURE: u32 = 0x1A41_2743;
+
+/// Re-encode each 2-bit field of a byte with `map`, which maps one code to another
+const fn translate_table(map: [u8; 4]) -> [u8; 256] {
+    let mut table = [0u8; 256];
+    let mut byte = 0;
+    while byte < 256 {
+        let mut translated = 0;
+        let mut field = 0;
+        while field < 4 {
+            let code = (byte >> (2 * field)) & 0b11;
+            translated |= (map[code] as usize) << (2 * field);
+            field += 1;
+        }
+        table[byte] = translated as u8;
+        byte += 1;
+    }
+    table
+}
+
+/// Crate encoding (A, C, G, T = 0..3) to `.2bit` encoding (T, C, A, G = 0..3)
+const TO_TWOBIT: [u8; 256] = translate_table([2, 1, 3, 0]);
+/// `.2bit` encoding back to the crate encoding
+const FROM_TWOBIT: [u8; 256] = translate_table([3, 1, 0, 2]);
+
+/// An error that can occur when reading or writing `.2bit` data.
+#[derive(Debug, thiserror::Error)]
+pub enum TwoBitError {
+    /// The underlying reader or writer failed
+    #[error("failed to read or write 2bit data")]
+    Io(#[from] io::Error),
+    /// The file does not start with the `.2bit` signature
+    #[error("input is not a 2bit file")]
+    BadSignature,
+    /// The file uses an unknown format version
+    #[error("unsupported 2bit version {0}")]
+    UnsupportedVersion(u32),
+    /// No sequence with the requested name exists
+    #[error("no sequence named {0} in 2bit file")]
+    UnknownSequence(String),
+    /// A record is internally inconsistent
+    #[error("malformed 2bit record {0}")]
+    Malformed(String),
+    /// A fetched range does not lie within its sequence
+    #[error("range {range:?} out of bounds for sequence {name} of length {len}")]
+    OutOfRange {
+        /// Name of the sequence
+        name: String,
+        /// The requested 0-based range
+        range: Range<usize>,
+        /// Length of the sequence
+        len: usize,
+    },
+    /// A sequence name does not fit the 255-byte limit
+    #[error("sequence name {0} is longer than 255 bytes")]
+    NameTooLong(String),
+    /// A sequence, or one of its blocks, has 2^32 nucleotides or more
+    #[error("sequence is too long for a 2bit record")]
+    TooLarge,
+    /// A sequence contains an ambiguity code other than `N`
+    #[error("sequence {name} contains ambiguity code {code}, which 2bit cannot store")]
+    UnsupportedCode {
+        /// Name of the offending sequence
+        name: String,
+        /// The ambiguity code
+        code: Iupac,
+    },
+}
+
+impl TwoBitError {
+    /// A stable code for the kind of error, such as `twobit.unknown-sequence`, see
+    /// [`DnaError::code`](crate::DnaError::code)
+    pub fn code(&self) -> &'static str {
+        match self {
+            TwoBitError::Io(_) => "twobit.io",
+            TwoBitError::BadSignature => "twobit.bad-signature",
+            TwoBitError::UnsupportedVersion(_) => "twobit.unsupported-version",
+            TwoBitError::UnknownSequence(_) => "twobit.unknown-sequence",
+            TwoBitError::Malformed(_) => "twobit.malformed",
+            TwoBitError::OutOfRange { .. } => "twobit.out-of-range",
+            TwoBitError::NameTooLong(_) => "twobit.name-too-long",
+            TwoBitError::TooLarge => "twobit.too-large",
+            TwoBitError::UnsupportedCode { .. } => "twobit.unsupported-code",
+        }
+    }
+}
+
+/// A named sequence stored in a `.2bit` file
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Record {
+    /// Sequence name
+    pub name: String,
+    /// The sequence, with `N` blocks as ambiguous runs
+    pub sequence: IupacDna,
+    /// Soft-masked (lowercase) regions, as 0-based half-open ranges
+    pub mask_blocks: Vec<Range<usize>>,
+}
+
+/// The header of a record: everything but its packed payload
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct RecordHeader {
+    /// Sequence length in nucleotides
+    pub(crate) len: usize,
+    /// Blocks of `N`, sorted and non-overlapping
+    pub(crate) n_blocks: Vec<Range<usize>>,
+    /// Soft-masked blocks
+    pub(crate) mask_blocks: Vec<Range<usize>>,
+    /// Offset of the packed payload from the start of the file
+    pub(crate) payload_offset: u64,
+}
+
+/// Random-access reader over a `.2bit` file
+#[derive(Debug)]
+pub struct Reader<R> {
+    inner: R,
+    swapped: bool,
+    names: Vec<String>,
+    offsets: HashMap<String, u64>,
+}
+
+impl<R: Read + Seek> Reader<R> {
+    /// Read the file header and sequence index
+    pub fn new(mut inner: R) -> Result<Self, TwoBitError> {
+        let mut word = [0u8; 4];
+        inner.read_exact(&mut word)?;
+        let swapped = match u32::from_le_bytes(word) {
+            SIGNATURE => false,
+            s if s.swap_bytes() == SIGNATURE => true,
+            _ => return Err(TwoBitError::BadSignature),
+        };
+        let mut reader = Reader {
+            inner,
+            swapped,
+            names: Vec::new(),
+            offsets: HashMap::new(),
+        };
+        let version = reader.read_u32()?;
+        if version > 1 {
+            return Err(TwoBitError::UnsupportedVersion(version));
+        }
+        let count = reader.read_u32()?;
+        reader.read_u32()?;
+        for _ in 0..count {
+            let mut name_len = [0u8; 1];
+            reader.inner.read_exact(&mut name_len)?;
+            let mut name = vec![0u8; usize::from(name_len[0])];
+            reader.inner.read_exact(&mut name)?;
+            let name = String::from_utf8_lossy(&name).into_owned();
+            let offset = if version == 0 {
+                u64::from(reader.read_u32()?)
+            } else {
+                reader.read_u64()?
+            };
+            reader.offsets.insert(name.clone(), offset);
+            reader.names.push(name);
+        }
+        Ok(reader)
+    }
+
+    /// Names of the sequences in file order
+    pub fn names(&self) -> &[String] {
+        &self.names
+    }
+
+    /// Read the sequence called `name`
+    pub fn read(&mut self, name: &str) -> Result<Record, TwoBitError> {
+        let header = self.read_header(name)?;
+        // The length comes from the file, so the payload buffer only grows with what is read.
+        let payload_len = header.len.div_ceil(4);
+        let mut payload = Vec::new();
+        (&mut self.inner)
+            .take(payload_len as u64)
+            .read_to_end(&mut payload)?;
+        if payload.len() < payload_len {
+            return Err(io::Error::from(io::ErrorKind::UnexpectedEof).into());
+        }
+        let mut dna = decode_payload(&payload, header.len);
+
+        let mut runs: Vec<AmbiguousRun> = Vec::with_capacity(header.n_blocks.len());
+        for block in header.n_blocks {
+            // Ambiguous positions use the crate's placeholder `A`.
+            dna.fill(block.clone(), Nuc::A)
+                .map_err(|_| TwoBitError::Malformed(name.to_string()))?;
+            runs.push(AmbiguousRun {
+                start: block.start,
+                len: block.len(),
+                code: Iupac::N,
+            });
+        }
+        Ok(Record {
+            name: name.to_string(),
+            sequence: IupacDna::from_parts(dna, runs),
+            mask_blocks: header.mask_blocks,
+        })
+    }
+
+    /// Read and validate the header of the sequence called `name`, leaving the reader at the
+    /// start of its packed payload
+    pub(crate) fn read_header(&mut self, name: &str) -> Result<RecordHeader, TwoBitError> {
+        let offset = *self
+            .offsets
+            .get(name)
+            .ok_or_else(|| TwoBitError::UnknownSequence(name.to_string()))?;
+        self.inner.seek(SeekFrom::Start(offset))?;
+        let malformed = || TwoBitError::Malformed(name.to_string());
+
+        let len = self.read_u32()? as usize;
+        let n_blocks = self.read_blocks()?;
+        let mask_blocks = self.read_blocks()?;
+        self.read_u32()?;
+        let sorted = n_blocks.windows(2).all(|pair| pair[0].end <= pair[1].start);
+        if !sorted || n_blocks.iter().chain(&mask_blocks).any(|b| b.end > len) {
+            return Err(malformed());
+        }
+        Ok(RecordHeader {
+            len,
+            n_blocks,
+            mask_blocks,
+            payload_offset: self.inner.stream_position()?,
+        })
+    }
+
+    /// Read every sequence in file order
+    pub fn read_all(&mut self) -> Result<Vec<Record>, TwoBitError> {
+        let names = self.names.clone();
+        names.iter().map(|name| self.read(name)).collect()
+    }
+
+    fn read_u32(&mut self) -> io::Result<u32> {
+        let mut word = [0u8; 4];
+        self.inner.read_exact(&mut word)?;
+        let value = u32::from_le_bytes(word);
+        Ok(if self.swapped {
+            value.swap_bytes()
+        } else {
+            value
+        })
+    }
+
+    fn read_u64(&mut self) -> io::Result<u64> {
+        let mut word = [0u8; 8];
+        self.inner.read_exact(&mut word)?;
+        let value = u64::from_le_bytes(word);
+        Ok(if self.swapped {
+            value.swap_bytes()
+        } else {
+            value
+        })
+    }
+
+    /// Read a block count followed by the block starts and sizes
+    fn read_blocks(&mut self) -> io::Result<Vec<Range<usize>>> {
+        let count = self.read_u32()? as usize;
+        let starts = (0..count)
+            .map(|_| self.read_u32())
+            .collect::<io::Result<Vec<_>>>()?;
+        let sizes = (0..count)
+            .map(|_| self.read_u32())
+            .collect::<io::Result<Vec<_>>>()?;
+        Ok(starts
+            .into_iter()
+            .zip(sizes)
+            .map(|(start, size)| start as usize..(start as usize + size as usize))
+            .collect())
+    }
+}
+
+/// Convert `len` nucleotides of `.2bit` payload into a sequence, with `N` blocks left as stored
+pub(crate) fn decode_payload(payload: &[u8], len: usize) -> PackedDna {
+    let mut packed = AlignedBuf::with_capacity(len.div_ceil(4));
+    for &byte in &payload[..len.div_ceil(4)] {
+        packed.push(FROM_TWOBIT[usize::from(byte)]);
+    }
+    if !len.is_multiple_of(4) {
+        let last = packed.len() - 1;
+        packed[last] >>= 2 * (4 - len % 4);
+    }
+    PackedDna::from_packed(packed, len)
+}
+
+/// Write `records` as a `.2bit` file: version 0, or version 1 with 64-bit offsets if the file
+/// would be larger than 4 GiB
+pub fn write<W: Write>(out: W, records: &[Record]) -> Result<(), TwoBitError> {
+    let file_len = records.iter().fold(index_len(records, 4), |len, record| {
+        len + record_len(record) as u64
+    });
+    let version = if u32::try_from(file_len).is_ok() {
+        0
+    } else {
+        1
+    };
+    write_version(out, records, version)
+}
+
+/// Write `records` as a `.2bit` file of `version`, 0 or 1
+fn write_version<W: Write>(
+    mut out: W,
+    records: &[Record],
+    version: u32,
+) -> Result<(), TwoBitError> {
+    let to_u32 = |value: usize| u32::try_from(value).map_err(|_| TwoBitError::TooLarge);
+
+    for record in records {
+        if record.name.len() > 255 {
+            return Err(TwoBitError::NameTooLong(record.name.clone()));
+        }
+        if let Some(run) = record
+            .sequence
+            .ambiguous_runs()
+            .iter()
+            .find(|run| run.code != Iupac::N)
+        {
+            return Err(TwoBitError::UnsupportedCode {
+                name: record.name.clone(),
+                code: run.code,
+            });
+        }
+    }
+
+    out.write_all(&SIGNATURE.to_le_bytes())?;
+    out.write_all(&version.to_le_bytes())?;
+    out.write_all(&to_u32(records.len())?.to_le_bytes())?;
+    out.write_all(&0u32.to_le_bytes())?;
+    let mut offset = index_len(records, if version == 0 { 4 } else { 8 });
+    for record in records {
+        out.write_all(&[record.name.len() as u8])?;
+        out.write_all(record.name.as_bytes())?;
+        if version == 0 {
+            let offset = u32::try_from(offset).map_err(|_| TwoBitError::TooLarge)?;
+            out.write_all(&offset.to_le_bytes())?;
+        } else {
+            out.write_all(&offset.to_le_bytes())?;
+        }
+        offset += record_len(record) as u64;
+    }
+
+    for record in records {
+        let dna = record.sequence.packed();
+        let len = dna.len();
+        out.write_all(&to_u32(len)?.to_le_bytes())?;
+        let runs = record.sequence.ambiguous_runs();
+        let n_blocks: Vec<Range<usize>> = runs.iter().map(|r| r.start..r.start + r.len).collect();
+        write_blocks(&mut out, &n_blocks)?;
+        write_blocks(&mut out, &record.mask_blocks)?;
+        out.write_all(&0u32.to_le_bytes())?;
+        let payload: Vec<u8> = (0..len.div_ceil(4))
+            .map(|idx| TO_TWOBIT[usize::from(dna.stream_byte(idx))])
+            .collect();
+        out.write_all(&payload)?;
+    }
+    Ok(())
+}
+
+/// Size in bytes of the file header and the index, whose offsets take `offset_len` bytes
+fn index_len(records: &[Record], offset_len: u64) -> u64 {
+    records.iter().fold(16, |len, record| {
+        len + 1 + record.name.len() as u64 + offset_len
+    })
+}
+
+/// Size in bytes of a record's data section
+fn record_len(record: &Record) -> usize {
+    let blocks = record.sequence.ambiguous_runs().len() + record.mask_blocks.len();
+    4 + 4 + 4 + 8 * blocks + 4 + record.sequence.len().div_ceil(4)
+}
+
+fn write_blocks<W: Write>(out: &mut W, blocks: &[Range<usize>]) -> Result<(), TwoBitError> {
+    let to_u32 = |value: usize| u32::try_from(value).map_err(|_| TwoBitError::TooLarge);
+    out.write_all(&to_u32(blocks.len())?.to_le_bytes())?;
+    for block in blocks {
+        out.write_all(&to_u32(block.start)?.to_le_bytes())?;
+    }
+    for block in blocks {
+        out.write_all(&to_u32(block.len())?.to_le_bytes())?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::{io::Cursor, str::FromStr};
+
+    fn record(name: &str, seq: &str, mask_blocks: Vec<Range<usize>>) -> Record {
+        Record {
+            name: name.to_string(),
+            sequence: IupacDna::from_str(seq).unwrap(),
+            mask_blocks,
+        }
+    }
+
+    fn round_trip(records: &[Record]) -> Vec<Record> {
+        let mut bytes = Vec::new();
+        write(&mut bytes, records).unwrap();
+        Reader::new(Cursor::new(bytes)).unwrap().read_all().unwrap()
+    }
+
+    #[test]
+    fn translate_tables_invert() {
+        for byte in 0..=255u8 {
+            assert_eq!(FROM_TWOBIT[usize::from(TO_TWOBIT[usize::from(byte)])], byte);
+        }
+    }
+
+    #[test]
+    fn write_packs_like_ucsc() {
+        let mut bytes = Vec::new();
+        write(&mut bytes, &[record("s", "TCAG", vec![])]).unwrap();
+        // Header, one index entry (1 + 1 + 4 bytes), then length, two empty block lists,
+        // the reserved word and one packed byte.
+        assert_eq!(bytes.len(), 16 + 6 + 16 + 1);
+        assert_eq!(bytes[bytes.len() - 1], 0b00_01_10_11);
+    }
+
+    #[test]
+    fn round_trip_with_n_and_mask_blocks() {
+        let records = vec![
+            record("chr1", "ACGTNNNNACGTA", vec![0..2, 9..13]),
+            record("chr2", "", vec![]),
+            record("chrM", "NNGATTACA", vec![]),
+        ];
+        assert_eq!(round_trip(&records), records);
+    }
+
+    #[test]
+    fn round_trip_version_1() {
+        let records = vec![
+            record("chr1", "ACGTNNNNACGTA", vec![0..2, 9..13]),
+            record("chr2", "GATTACA", vec![]),
+        ];
+        let mut bytes = Vec::new();
+        write_version(&mut bytes, &records, 1).unwrap();
+        // The two index entries take four more bytes each than in version 0.
+        let mut short = Vec::new();
+        write(&mut short, &records).unwrap();
+        assert_eq!(bytes.len(), short.len() + 8);
+        assert_eq!(bytes[4], 1);
+        let read = Reader::new(Cursor::new(bytes)).unwrap().read_all().unwrap();
+        assert_eq!(read, records);
+    }
+
+    #[cfg(target_pointer_width = "64")]
+    #[test]
+    fn write_rejects_blocks_past_32_bits() {
+        let huge = 1usize << 32;
+        assert!(matches!(
+            write(
+                Vec::new(),
+                &[record("r", "ACGT", vec![0..1, huge..huge + 1])]
+            ),
+            Err(TwoBitError::TooLarge)
+        ));
+    }
+
+    #[test]
+    fn reader_names_and_random_access() {
+        let records = vec![record("a", "ACGT", vec![]), record("b", "GGC", vec![])];
+        let mut bytes = Vec::new();
+        write(&mut bytes, &records).unwrap();
+        let mut reader = Reader::new(Cursor::new(bytes)).unwrap();
+        assert_eq!(reader.names(), ["a", "b"]);
+        assert_eq!(reader.read("b").unwrap(), records[1]);
+        assert!(matches!(
+            reader.read("c"),
+            Err(TwoBitError::UnknownSequence(name)) if name == "c"
+        ));
+    }
+
+    #[test]
+    fn reader_big_endian() {
+        let mut bytes = Vec::new();
+        bytes.extend_from_slice(&SIGNATURE.to_be_bytes());
+        bytes.extend_from_slice(&[0, 0, 0, 0, 0, 0, 0, 1, 0, 0, 0, 0]);
+        bytes.extend_from_slice(&[1, b'x', 0, 0, 0, 22]);
+        bytes.extend_from_slice(&[0, 0, 0, 5, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0]);
+        bytes.extend_from_slice(&[0b00_01_10_11, 0b1000_0000]);
+        let record = Reader::new(Cursor::new(bytes)).unwrap().read("x").unwrap();
+        let expected: PackedDna = "TCAGA".parse().unwrap();
+        assert_eq!(record.sequence.packed(), &expected);
+    }
+
+    #[test]
+    fn reader_truncated_payload() {
+        let mut bytes = Vec::new();
+        write(&mut bytes, &[record("x", "ACGTACGT", vec![])]).unwrap();
+        // Claim the most nucleotides a record can hold, with two bytes of payload.
+        let len_offset = bytes.len() - 2 - 16;
+        bytes[len_offset..len_offset + 4].copy_from_slice(&u32::MAX.to_le_bytes());
+        let err = Reader::new(Cursor::new(bytes))
+            .unwrap()
+            .read("x")
+            .unwrap_err();
+        assert!(matches!(err, TwoBitError::Io(e) if e.kind() == io::ErrorKind::UnexpectedEof));
+    }
+
+    #[test]
+    fn reader_bad_signature() {
+        let err = Reader::new(Cursor::new(vec![0u8; 16])).unwrap_err();
+        assert_eq!("input is not a 2bit file", err.to_string());
+    }
+
+    #[test]
+    fn write_rejects_other_ambiguity_codes() {
+        let err = write(Vec::new(), &[record("r", "ACRT", vec![])]).unwrap_err();
+        assert_eq!(
+            "sequence r contains ambiguity code R, which 2bit cannot store",
+            err.to_string()
+        );
+    }
+}
@@ -0,0 +1,555 @@
+//! Borrowed views into a sequence.
+//!
+//! A [`PackedDnaSlice`] refers to a range of a [`PackedDna`] without copying it, much as `&str`
+//! refers into a `String`: [`PackedDna::slice`] borrows one, and [`PackedDna::to_slice`] copies
+//! the range out instead. [`PackedDna::windows`] and [`PackedDna::chunks`] yield such views, so
+//! scanning a large sequence window by window allocates nothing, and
+//! [`PackedDna::iter_with_context`] pairs each nucleotide with views of its flanks. A
+//! [`RevCompView`] reads a sequence or view along the other strand, still without copying.
+
+use crate::{nuc_from_code, search::Matches, Iter, Nuc, PackedDna};
+use std::{fmt, ops::Range};
+
+/// A borrowed range of a [`PackedDna`]
+#[derive(Debug, Clone, Copy)]
+pub struct PackedDnaSlice<'a> {
+    dna: &'a PackedDna,
+    start: usize,
+    end: usize,
+}
+
+impl<'a> PackedDnaSlice<'a> {
+    /// Number of nucleotides in the view
+    pub fn len(&self) -> usize {
+        self.end - self.start
+    }
+
+    /// Whether the view holds no nucleotides
+    pub fn is_empty(&self) -> bool {
+        self.start == self.end
+    }
+
+    /// The 0-based range of the underlying sequence covered by the view
+    pub fn range(&self) -> Range<usize> {
+        self.start..self.end
+    }
+
+    /// Get the nucleotide at the 0-based position `idx` of the view, or `None` if it is out of
+    /// bounds
+    pub fn get(&self, idx: usize) -> Option<Nuc> {
+        (idx < self.len()).then(|| self.dna.nuc_at(self.start + idx))
+    }
+
+    /// Iterate over the nucleotides of the view
+    pub fn iter(&self) -> Iter<'a> {
+        Iter {
+            dna: self.dna,
+            front: self.start,
+            back: self.end,
+        }
+    }
+
+    /// Number of occurrences of `nuc` in the view
+    pub fn count(&self, nuc: Nuc) -> usize {
+        self.iter().filter(|&other| other == nuc).count()
+    }
+
+    /// Get the counts of individual nucleotides, like [`PackedDna::get_counts`]
+    pub fn get_counts(&self) -> Vec<(char, usize)> {
+        let mut counts = [0; 4];
+        for nuc in self.iter() {
+            counts[nuc as usize] += 1;
+        }
+        vec![
+            ('A', counts[0]),
+            ('C', counts[1]),
+            ('G', counts[2]),
+            ('T', counts[3]),
+        ]
+    }
+
+    /// Fraction of the view that is G or C, or 0 for an empty view
+    pub fn gc_content(&self) -> f64 {
+        if self.is_empty() {
+            return 0.0;
+        }
+        let gc = self
+            .iter()
+            .filter(|&nuc| nuc == Nuc::C || nuc == Nuc::G)
+            .count();
+        gc as f64 / self.len() as f64
+    }
+
+    /// 0-based position in the view of the first occurrence of `needle`, or `None` if it does
+    /// not occur within the view
+    pub fn find(&self, needle: &PackedDna) -> Option<usize> {
+        self.find_all(needle).next()
+    }
+
+    /// 0-based positions in the view of every occurrence of `needle` lying entirely within it,
+    /// like [`PackedDna::find_all`]
+    pub fn find_all(&self, needle: &'a PackedDna) -> Matches<'a> {
+        self.dna.find_all_in(self.range(), needle)
+    }
+
+    /// Copy the view into a new sequence
+    pub fn to_packed(&self) -> PackedDna {
+        self.dna.to_slice(self.range())
+    }
+
+    /// Read the view along the other strand, without copying it
+    pub fn revcomp(self) -> RevCompView<'a> {
+        RevCompView { forward: self }
+    }
+}
+
+impl<'a> From<&'a PackedDna> for PackedDnaSlice<'a> {
+    fn from(dna: &'a PackedDna) -> Self {
+        dna.slice(0..dna.len())
+    }
+}
+
+impl From<PackedDnaSlice<'_>> for PackedDna {
+    fn from(view: PackedDnaSlice<'_>) -> Self {
+        view.to_packed()
+    }
+}
+
+impl PartialEq for PackedDnaSlice<'_> {
+    fn eq(&self, other: &Self) -> bool {
+        self.len() == other.len() && self.iter().eq(other.iter())
+    }
+}
+
+impl Eq for PackedDnaSlice<'_> {}
+
+impl fmt::Display for PackedDnaSlice<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.iter()
+            .try_for_each(|nuc| write!(f, "{}", nuc.to_char()))
+    }
+}
+
+impl<'a> IntoIterator for PackedDnaSlice<'a> {
+    type Item = Nuc;
+    type IntoIter = Iter<'a>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
+
+/// The reverse complement of a borrowed sequence, computed as it is read
+#[derive(Debug, Clone, Copy)]
+pub struct RevCompView<'a> {
+    forward: PackedDnaSlice<'a>,
+}
+
+impl<'a> RevCompView<'a> {
+    /// Number of nucleotides in the view
+    pub fn len(&self) -> usize {
+        self.forward.len()
+    }
+
+    /// Whether the view holds no nucleotides
+    pub fn is_empty(&self) -> bool {
+        self.forward.is_empty()
+    }
+
+    /// Get the nucleotide at the 0-based position `idx` of the reverse complement, or `None` if
+    /// it is out of bounds
+    pub fn get(&self, idx: usize) -> Option<Nuc> {
+        let forward = &self.forward;
+        (idx < self.len()).then(|| complement(forward.dna.nuc_at(forward.end - 1 - idx)))
+    }
+
+    /// Iterate over the nucleotides of the reverse complement
+    pub fn iter(&self) -> RevCompIter<'a> {
+        RevCompIter(self.forward.iter())
+    }
+
+    /// Fraction of the view that is G or C, or 0 for an empty view
+    pub fn gc_content(&self) -> f64 {
+        // Complementing swaps G with C, which leaves their total alone.
+        self.forward.gc_content()
+    }
+
+    /// The forward strand the view reads backwards
+    pub fn revcomp(self) -> PackedDnaSlice<'a> {
+        self.forward
+    }
+
+    /// Copy the reverse complement into a new sequence
+    pub fn to_packed(&self) -> PackedDna {
+        let mut dna = self.forward.to_packed();
+        dna.reverse_complement_in_place();
+        dna
+    }
+}
+
+impl From<RevCompView<'_>> for PackedDna {
+    fn from(view: RevCompView<'_>) -> Self {
+        view.to_packed()
+    }
+}
+
+impl PartialEq for RevCompView<'_> {
+    fn eq(&self, other: &Self) -> bool {
+        self.len() == other.len() && self.iter().eq(other.iter())
+    }
+}
+
+impl Eq for RevCompView<'_> {}
+
+impl fmt::Display for RevCompView<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.iter()
+            .try_for_each(|nuc| write!(f, "{}", nuc.to_char()))
+    }
+}
+
+impl<'a> IntoIterator for RevCompView<'a> {
+    type Item = Nuc;
+    type IntoIter = RevCompIter<'a>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
+
+/// Iterator over the nucleotides of a [`RevCompView`]
+#[derive(Debug, Clone)]
+pub struct RevCompIter<'a>(Iter<'a>);
+
+impl Iterator for RevCompIter<'_> {
+    type Item = Nuc;
+
+    fn next(&mut self) -> Option<Nuc> {
+        self.0.next_back().map(complement)
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.0.size_hint()
+    }
+}
+
+impl DoubleEndedIterator for RevCompIter<'_> {
+    fn next_back(&mut self) -> Option<Nuc> {
+        self.0.next().map(complement)
+    }
+}
+
+impl ExactSizeIterator for RevCompIter<'_> {}
+
+fn complement(nuc: Nuc) -> Nuc {
+    nuc_from_code(3 - nuc as u8)
+}
+
+impl PackedDna {
+    /// Borrow the nucleotides in the 0-based `range` without copying them.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the range is decreasing or extends past the end of the sequence.
+    pub fn slice(&self, range: Range<usize>) -> PackedDnaSlice<'_> {
+        let len = self.len();
+        assert!(
+            range.start <= range.end && range.end <= len,
+            "slice range {:?} out of bounds for DNA of length {}",
+            range,
+            len
+        );
+        PackedDnaSlice {
+            dna: self,
+            start: range.start,
+            end: range.end,
+        }
+    }
+
+    /// Read the reverse complement of the whole sequence without copying it; see
+    /// [`PackedDnaSlice::revcomp`] for part of it
+    pub fn revcomp_view(&self) -> RevCompView<'_> {
+        self.slice(0..self.len()).revcomp()
+    }
+
+    /// Iterate over every overlapping window of `size` nucleotides, stepping by one.
+    ///
+    /// A sequence shorter than `size` has no windows.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `size` is 0.
+    pub fn windows(&self, size: usize) -> Windows<'_> {
+        assert!(size != 0, "window size must be non-zero");
+        Windows {
+            dna: self,
+            size,
+            start: 0,
+        }
+    }
+
+    /// Iterate over consecutive, non-overlapping chunks of `size` nucleotides.
+    ///
+    /// The last chunk is shorter if `size` does not divide the length.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `size` is 0.
+    pub fn chunks(&self, size: usize) -> Chunks<'_> {
+        assert!(size != 0, "chunk size must be non-zero");
+        Chunks {
+            dna: self,
+            size,
+            start: 0,
+        }
+    }
+
+    /// Iterate over each nucleotide with its 0-based position and up to `flank` nucleotides on
+    /// either side, as `(position, nucleotide, left, right)`.
+    ///
+    /// The flanks are views, so stepping along the sequence copies nothing. Near the ends they
+    /// are cut short; models needing the full context can skip positions where
+    /// `left.len() < flank` or `right.len() < flank`.
+    pub fn iter_with_context(&self, flank: usize) -> WithContext<'_> {
+        WithContext {
+            dna: self,
+            flank,
+            front: 0,
+            back: self.len(),
+        }
+    }
+}
+
+/// Iterator over overlapping windows of a [`PackedDna`], created by [`PackedDna::windows`]
+#[derive(Debug, Clone)]
+pub struct Windows<'a> {
+    dna: &'a PackedDna,
+    size: usize,
+    start: usize,
+}
+
+impl<'a> Iterator for Windows<'a> {
+    type Item = PackedDnaSlice<'a>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let end = self.start + self.size;
+        if end > self.dna.len() {
+            return None;
+        }
+        let window = self.dna.slice(self.start..end);
+        self.start += 1;
+        Some(window)
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let remaining = (self.dna.len() + 1).saturating_sub(self.start + self.size);
+        (remaining, Some(remaining))
+    }
+}
+
+impl ExactSizeIterator for Windows<'_> {}
+
+/// Iterator over non-overlapping chunks of a [`PackedDna`], created by [`PackedDna::chunks`]
+#[derive(Debug, Clone)]
+pub struct Chunks<'a> {
+    dna: &'a PackedDna,
+    size: usize,
+    start: usize,
+}
+
+impl<'a> Iterator for Chunks<'a> {
+    type Item = PackedDnaSlice<'a>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let len = self.dna.len();
+        if self.start == len {
+            return None;
+        }
+        let end = (self.start + self.size).min(len);
+        let chunk = self.dna.slice(self.start..end);
+        self.start = end;
+        Some(chunk)
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let remaining = (self.dna.len() - self.start).div_ceil(self.size);
+        (remaining, Some(remaining))
+    }
+}
+
+impl ExactSizeIterator for Chunks<'_> {}
+
+/// Iterator over the nucleotides of a [`PackedDna`] with their flanking sequence, created by
+/// [`PackedDna::iter_with_context`]
+#[derive(Debug, Clone)]
+pub struct WithContext<'a> {
+    dna: &'a PackedDna,
+    flank: usize,
+    front: usize,
+    back: usize,
+}
+
+impl<'a> WithContext<'a> {
+    /// The position `idx` with its flanks
+    fn context(&self, idx: usize) -> (usize, Nuc, PackedDnaSlice<'a>, PackedDnaSlice<'a>) {
+        let len = self.dna.len();
+        let left = self.dna.slice(idx.saturating_sub(self.flank)..idx);
+        let right = self
+            .dna
+            .slice(idx + 1..(idx + 1).saturating_add(self.flank).min(len));
+        (idx, self.dna.nuc_at(idx), left, right)
+    }
+}
+
+impl<'a> Iterator for WithContext<'a> {
+    type Item = (usize, Nuc, PackedDnaSlice<'a>, PackedDnaSlice<'a>);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.front == self.back {
+            return None;
+        }
+        let context = self.context(self.front);
+        self.front += 1;
+        Some(context)
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let remaining = self.back - self.front;
+        (remaining, Some(remaining))
+    }
+}
+
+impl DoubleEndedIterator for WithContext<'_> {
+    fn next_back(&mut self) -> Option<Self::Item> {
+        if self.front == self.back {
+            return None;
+        }
+        self.back -= 1;
+        Some(self.context(self.back))
+    }
+}
+
+impl ExactSizeIterator for WithContext<'_> {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::str::FromStr;
+
+    #[test]
+    fn revcomp_view_reads_the_other_strand() {
+        let dna = PackedDna::from_str("AACGTTTGCA").unwrap();
+        let revcomp = dna.revcomp_view();
+        assert_eq!(revcomp.to_string(), dna.reverse_complement().to_string());
+        assert_eq!(revcomp.to_packed(), dna.reverse_complement());
+        assert_eq!(revcomp.get(0), Some(Nuc::T));
+        assert_eq!(revcomp.get(9), Some(Nuc::T));
+        assert_eq!(revcomp.get(10), None);
+        assert_eq!(
+            revcomp.iter().rev().collect::<PackedDna>().to_string(),
+            "TTGCAAACGT"
+        );
+        assert_eq!(revcomp.gc_content(), dna.gc_content());
+
+        let part = dna.slice(2..7).revcomp();
+        assert_eq!(part.to_string(), "AAACG");
+        assert_eq!(part.len(), 5);
+        assert_eq!(part.iter().len(), 5);
+        assert_eq!(part.revcomp(), dna.slice(2..7));
+        assert!(dna.slice(3..3).revcomp().is_empty());
+    }
+
+    #[test]
+    fn slice_matches_to_slice() {
+        let dna = PackedDna::from_str("ACGTTGCAAC").unwrap();
+        for start in 0..=dna.len() {
+            for end in start..=dna.len() {
+                let view = dna.slice(start..end);
+                assert_eq!(view.len(), end - start);
+                assert_eq!(view.to_packed(), dna.to_slice(start..end));
+                assert_eq!(view.to_string(), dna.to_string_range(start..end));
+                assert_eq!(view.iter().rev().count(), end - start);
+            }
+        }
+        let view = dna.slice(3..6);
+        assert_eq!(view.get(0), Some(Nuc::T));
+        assert_eq!(view.get(2), Some(Nuc::G));
+        assert_eq!(view.get(3), None);
+        assert_eq!(view, dna.slice(3..6));
+    }
+
+    #[test]
+    fn view_counts_and_search() {
+        let dna = PackedDna::from_str("GATTACAGATTACA").unwrap();
+        let view = dna.slice(1..12);
+        assert_eq!(view.to_string(), "ATTACAGATTA");
+        assert_eq!(view.count(Nuc::A), 5);
+        assert_eq!(
+            view.get_counts(),
+            vec![('A', 5), ('C', 1), ('G', 1), ('T', 4)]
+        );
+        assert!((view.gc_content() - 2.0 / 11.0).abs() < 1e-12);
+        let needle = PackedDna::from_str("GATTACA").unwrap();
+        assert_eq!(view.find(&needle), None);
+        assert_eq!(dna.slice(7..14).find(&needle), Some(0));
+        let needle = PackedDna::from_str("TTA").unwrap();
+        assert_eq!(view.find_all(&needle).collect::<Vec<_>>(), [1, 8]);
+        let empty = PackedDna::from_str("").unwrap();
+        assert_eq!(
+            dna.slice(3..5).find_all(&empty).collect::<Vec<_>>(),
+            [0, 1, 2]
+        );
+        assert_eq!(PackedDna::from(view), dna.to_slice(1..12));
+        assert_eq!(PackedDnaSlice::from(&dna).len(), 14);
+    }
+
+    #[test]
+    fn iter_with_context() {
+        let dna = PackedDna::from_str("ACGTTGC").unwrap();
+        let contexts: Vec<(usize, Nuc, String, String)> = dna
+            .iter_with_context(2)
+            .map(|(idx, nuc, left, right)| (idx, nuc, left.to_string(), right.to_string()))
+            .collect();
+        assert_eq!(contexts.len(), 7);
+        assert_eq!(contexts[0], (0, Nuc::A, String::new(), "CG".to_string()));
+        assert_eq!(contexts[3], (3, Nuc::T, "CG".to_string(), "TG".to_string()));
+        assert_eq!(contexts[6], (6, Nuc::C, "TG".to_string(), String::new()));
+
+        let mut iter = dna.iter_with_context(usize::MAX);
+        let (idx, nuc, left, right) = iter.next_back().unwrap();
+        assert_eq!((idx, nuc, left.len(), right.len()), (6, Nuc::C, 6, 0));
+        assert_eq!(iter.len(), 6);
+        assert!(dna
+            .iter_with_context(0)
+            .all(|(_, _, l, r)| l.is_empty() && r.is_empty()));
+        assert_eq!(
+            PackedDna::from_str("")
+                .unwrap()
+                .iter_with_context(3)
+                .count(),
+            0
+        );
+    }
+
+    #[test]
+    fn windows_and_chunks() {
+        let dna = PackedDna::from_str("ACGTTGC").unwrap();
+        let windows = dna.windows(3);
+        assert_eq!(windows.len(), 5);
+        let windows: Vec<String> = windows.map(|window| window.to_string()).collect();
+        assert_eq!(windows, ["ACG", "CGT", "GTT", "TTG", "TGC"]);
+        assert_eq!(dna.windows(8).count(), 0);
+
+        let chunks = dna.chunks(3);
+        assert_eq!(chunks.len(), 3);
+        let chunks: Vec<Range<usize>> = chunks.map(|chunk| chunk.range()).collect();
+        assert_eq!(chunks, [0..3, 3..6, 6..7]);
+        assert_eq!(PackedDna::from_str("").unwrap().chunks(2).count(), 0);
+    }
+
+    #[test]
+    #[should_panic(expected = "window size must be non-zero")]
+    fn zero_window() {
+        PackedDna::from_str("ACGT").unwrap().windows(0);
+    }
+}
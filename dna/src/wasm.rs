@@ -0,0 +1,93 @@
+//! JavaScript bindings for `wasm32-unknown-unknown`, with the `wasm` feature.
+//!
+//! Built as a `cdylib` and passed through `wasm-bindgen`, the crate exports a `PackedDna` class
+//! for in-browser sequence viewers: parsing, nucleotide counts, GC content, reverse complement
+//! and slicing. Errors are thrown as JavaScript `Error`s carrying the crate's error message.
+//!
+//! The manifest leaves the crate type to the build, so other users of the crate do not link a
+//! `cdylib` too:
+//!
+//! ```text
+//! cargo rustc --lib --release --target wasm32-unknown-unknown --features wasm --crate-type cdylib
+//! wasm-bindgen --target web --out-dir pkg target/wasm32-unknown-unknown/release/dna.wasm
+//! ```
+
+use crate::PackedDna;
+use std::str::FromStr;
+use wasm_bindgen::prelude::*;
+
+/// A DNA sequence packed at 2 bits per nucleotide
+#[wasm_bindgen(js_name = PackedDna)]
+pub struct WasmPackedDna(PackedDna);
+
+#[wasm_bindgen(js_class = PackedDna)]
+impl WasmPackedDna {
+    /// Parse a sequence of `A`, `C`, `G` and `T`, in either case
+    #[wasm_bindgen(constructor)]
+    pub fn new(sequence: &str) -> Result<WasmPackedDna, JsError> {
+        Ok(WasmPackedDna(PackedDna::from_str(sequence)?))
+    }
+
+    /// Number of nucleotides
+    #[wasm_bindgen(getter)]
+    pub fn length(&self) -> usize {
+        self.0.len()
+    }
+
+    /// The sequence as a string of `A`, `C`, `G` and `T`
+    #[wasm_bindgen(js_name = toString)]
+    pub fn to_js_string(&self) -> String {
+        self.0.to_string()
+    }
+
+    /// Number of A, C, G and T, in that order
+    pub fn counts(&self) -> Vec<usize> {
+        self.0
+            .get_counts()
+            .into_iter()
+            .map(|(_, count)| count)
+            .collect()
+    }
+
+    /// Fraction of positions that are G or C, or 0 for an empty sequence
+    #[wasm_bindgen(js_name = gcContent)]
+    pub fn gc_content(&self) -> f64 {
+        self.0.gc_content()
+    }
+
+    /// The reverse complement of the sequence
+    #[wasm_bindgen(js_name = reverseComplement)]
+    pub fn reverse_complement(&self) -> WasmPackedDna {
+        WasmPackedDna(self.0.reverse_complement())
+    }
+
+    /// The nucleotides from `start` up to but excluding `end`
+    pub fn slice(&self, start: usize, end: usize) -> Result<WasmPackedDna, JsError> {
+        if start > end || end > self.0.len() {
+            return Err(JsError::new(&format!(
+                "slice range {:?} out of bounds for DNA of length {}",
+                start..end,
+                self.0.len()
+            )));
+        }
+        Ok(WasmPackedDna(self.0.to_slice(start..end)))
+    }
+}
+
+#[cfg(all(test, target_arch = "wasm32"))]
+mod tests {
+    use super::*;
+    use wasm_bindgen_test::wasm_bindgen_test;
+
+    #[wasm_bindgen_test]
+    fn parse_and_query() {
+        let dna = WasmPackedDna::new("GATTACA").unwrap();
+        assert_eq!(dna.length(), 7);
+        assert_eq!(dna.counts(), vec![3, 1, 1, 2]);
+        assert!((dna.gc_content() - 2.0 / 7.0).abs() < 1e-12);
+        assert_eq!(dna.reverse_complement().to_js_string(), "TGTAATC");
+        assert_eq!(dna.slice(1, 4).unwrap().to_js_string(), "ATT");
+        assert!(dna.slice(4, 8).is_err());
+        assert!(WasmPackedDna::new("GAXT").is_err());
+    }
+}
@@ -0,0 +1,239 @@
+//! Gap-affine wavefront alignment (WFA, Marco-Sola et al. 2021).
+//!
+//! WFA minimises a penalty under which matches are free. For every penalty `s` in turn it keeps,
+//! on each diagonal, the furthest target position an alignment of penalty `s` reaches, then
+//! slides along runs of matches for free. It stops as soon as the end of both sequences is
+//! reached, so the work grows with the penalty of the alignment rather than the product of the
+//! lengths: near-identical sequences are aligned in close to linear time.
+//!
+//! Scores with a match bonus are handled by doubling them: for a global alignment of lengths
+//! `n` and `m`, `2 * score = match_score * (n + m) - penalty`, where a mismatch is penalised by
+//! `2 * (match_score - mismatch)`, a gap opening by `-2 * gap_open` and a gap position by
+//! `match_score - 2 * gap_extend`.
+
+use crate::{
+    align::{AlignOp, Alignment, Scoring},
+    Nuc,
+};
+use std::convert::TryFrom;
+
+/// Offset of a diagonal that no alignment of the penalty reaches
+const NONE: i32 = i32::MIN / 2;
+
+/// Non-negative penalties equivalent to a [`Scoring`] for global alignment
+#[derive(Debug, Clone, Copy)]
+struct Penalties {
+    mismatch: usize,
+    open: usize,
+    extend: usize,
+}
+
+impl Penalties {
+    /// The penalties for `scoring`, or `None` if a mismatch or a gap would not be penalised,
+    /// which the wavefronts cannot represent
+    fn new(scoring: &Scoring) -> Option<Self> {
+        let mismatch = 2 * (scoring.match_score - scoring.mismatch);
+        let open = -2 * scoring.gap_open;
+        let extend = scoring.match_score - 2 * scoring.gap_extend;
+        (mismatch > 0 && open >= 0 && extend > 0).then_some(Penalties {
+            mismatch: mismatch as usize,
+            open: open as usize,
+            extend: extend as usize,
+        })
+    }
+}
+
+/// Furthest target offsets of the alignments of one penalty, on diagonals `lo..=hi`.
+///
+/// Diagonal `k` holds the positions where the target offset minus the query offset is `k`.
+/// `best` ends in any column, `ins` in an insertion and `del` in a deletion.
+#[derive(Debug)]
+struct Wavefront {
+    lo: i32,
+    hi: i32,
+    best: Vec<i32>,
+    ins: Vec<i32>,
+    del: Vec<i32>,
+}
+
+impl Wavefront {
+    fn get(&self, offsets: &[i32], k: i32) -> i32 {
+        if (self.lo..=self.hi).contains(&k) {
+            offsets[(k - self.lo) as usize]
+        } else {
+            NONE
+        }
+    }
+}
+
+/// The wavefronts of every penalty up to the current one
+struct Wavefronts(Vec<Option<Wavefront>>);
+
+impl Wavefronts {
+    fn at(&self, s: usize, back: usize) -> Option<&Wavefront> {
+        s.checked_sub(back).and_then(|s| self.0[s].as_ref())
+    }
+
+    fn best(&self, s: usize, back: usize, k: i32) -> i32 {
+        self.at(s, back)
+            .map_or(NONE, |front| front.get(&front.best, k))
+    }
+
+    fn ins(&self, s: usize, back: usize, k: i32) -> i32 {
+        self.at(s, back)
+            .map_or(NONE, |front| front.get(&front.ins, k))
+    }
+
+    fn del(&self, s: usize, back: usize, k: i32) -> i32 {
+        self.at(s, back)
+            .map_or(NONE, |front| front.get(&front.del, k))
+    }
+}
+
+/// The traceback state: which kind of column ends the alignment being traced
+enum State {
+    Best,
+    Insertion,
+    Deletion,
+}
+
+/// Globally align `query` against `target`, or `None` if `scoring` has no equivalent penalties
+/// or either sequence has 2^30 nucleotides or more
+pub(crate) fn align(query: &[Nuc], target: &[Nuc], scoring: &Scoring) -> Option<Alignment> {
+    let penalties = Penalties::new(scoring)?;
+    // Offsets and diagonals are i32 and must not overflow.
+    let fits = |len: usize| i32::try_from(len).ok().filter(|&len| len <= i32::MAX / 2);
+    let (n, m) = (fits(query.len())?, fits(target.len())?);
+    // Offsets must stay within both sequences.
+    let valid = |k: i32, h: i32| {
+        if h >= 0 && h <= m && h - k <= n {
+            h
+        } else {
+            NONE
+        }
+    };
+    let step = |h: i32| if h == NONE { NONE } else { h + 1 };
+    let extend = |k: i32, h: &mut i32| {
+        while *h != NONE && *h < m && *h - k < n && query[(*h - k) as usize] == target[*h as usize]
+        {
+            *h += 1;
+        }
+    };
+
+    let k_end = m - n;
+    let mut start = Wavefront {
+        lo: 0,
+        hi: 0,
+        best: vec![0],
+        ins: vec![NONE],
+        del: vec![NONE],
+    };
+    extend(0, &mut start.best[0]);
+    let mut fronts = Wavefronts(vec![Some(start)]);
+    let gap_open = penalties.open + penalties.extend;
+    let mut s = 0;
+    while fronts.best(s, 0, k_end) < m {
+        s += 1;
+        let sources = [
+            fronts.at(s, penalties.mismatch).map(|f| (f.lo, f.hi)),
+            fronts.at(s, gap_open).map(|f| (f.lo - 1, f.hi + 1)),
+            fronts.at(s, penalties.extend).map(|f| (f.lo - 1, f.hi + 1)),
+        ];
+        let Some((lo, hi)) = sources
+            .iter()
+            .flatten()
+            .copied()
+            .reduce(|(lo, hi), (l, h)| (lo.min(l), hi.max(h)))
+        else {
+            fronts.0.push(None);
+            continue;
+        };
+        let (lo, hi) = (lo.max(-n), hi.min(m));
+        let width = (hi - lo + 1) as usize;
+        let mut front = Wavefront {
+            lo,
+            hi,
+            best: Vec::with_capacity(width),
+            ins: Vec::with_capacity(width),
+            del: Vec::with_capacity(width),
+        };
+        for k in lo..=hi {
+            let del = fronts
+                .best(s, gap_open, k - 1)
+                .max(fronts.del(s, penalties.extend, k - 1));
+            let del = valid(k, step(del));
+            let ins = fronts
+                .best(s, gap_open, k + 1)
+                .max(fronts.ins(s, penalties.extend, k + 1));
+            let ins = valid(k, ins);
+            let substitution = valid(k, step(fronts.best(s, penalties.mismatch, k)));
+            let mut best = substitution.max(del).max(ins);
+            extend(k, &mut best);
+            front.best.push(best);
+            front.ins.push(ins);
+            front.del.push(del);
+        }
+        fronts.0.push(Some(front));
+    }
+
+    // Trace back from the end, collecting the columns last to first.
+    let penalty = s;
+    let (mut k, mut h) = (k_end, m);
+    let mut state = State::Best;
+    let mut ops = Vec::new();
+    loop {
+        match state {
+            State::Best => {
+                let substitution = valid(k, step(fronts.best(s, penalties.mismatch, k)));
+                let (ins, del) = (fronts.ins(s, 0, k), fronts.del(s, 0, k));
+                let base = if s == 0 {
+                    0
+                } else {
+                    substitution.max(ins).max(del)
+                };
+                ops.extend(std::iter::repeat_n(AlignOp::Match, (h - base) as usize));
+                h = base;
+                if s == 0 {
+                    break;
+                } else if base == substitution {
+                    ops.push(AlignOp::Mismatch);
+                    s -= penalties.mismatch;
+                    h -= 1;
+                } else if base == del {
+                    state = State::Deletion;
+                } else {
+                    state = State::Insertion;
+                }
+            }
+            State::Deletion => {
+                ops.push(AlignOp::Deletion);
+                h -= 1;
+                k -= 1;
+                if fronts.best(s, gap_open, k) == h {
+                    s -= gap_open;
+                    state = State::Best;
+                } else {
+                    s -= penalties.extend;
+                }
+            }
+            State::Insertion => {
+                ops.push(AlignOp::Insertion);
+                k += 1;
+                if fronts.best(s, gap_open, k) == h {
+                    s -= gap_open;
+                    state = State::Best;
+                } else {
+                    s -= penalties.extend;
+                }
+            }
+        }
+    }
+    ops.reverse();
+    Some(Alignment {
+        score: (scoring.match_score * (n + m) - penalty as i32) / 2,
+        query_range: 0..query.len(),
+        target_range: 0..target.len(),
+        ops,
+        query_len: query.len(),
+    })
+}
@@ -0,0 +1,392 @@
+//! The results of each subcommand, computed from its input and arguments.
+//!
+//! These are what [`run`](crate::run) writes out, as values, so they can be checked or used
+//! directly instead of parsing the text.
+
+use crate::{
+    input::Source,
+    matrix::{DistanceMatrix, Metric},
+    report, CliError, Input, Sequence,
+};
+use dna::{
+    concordance::{self, Concordance},
+    external::{ExternalKmerCounter, ExternalKmerCounts},
+    fasta::BaseCounts,
+    fastq, kmer,
+    motif::Pfm,
+    redundancy::{RedundancyFinder, RedundantContig},
+    regex::IupacRegex,
+    sketch::Sketch,
+    stats::{BatchStats, Stats},
+    translate::Strand,
+    DnaError, PackedDna,
+};
+use rand::{rngs::StdRng, SeedableRng};
+use std::{ops::Range, path::PathBuf, str::FromStr};
+
+/// The nucleotide counts of each sequence, see [`Input::count`]
+pub fn count(input: &Input) -> Result<Vec<Sequence<BaseCounts>>, CliError> {
+    input.count()
+}
+
+/// The length, GC content and strand skews of each sequence
+pub fn stats(input: &Input) -> Result<Vec<Sequence<Stats>>, CliError> {
+    Ok(input
+        .read()?
+        .into_iter()
+        .map(|seq| Sequence {
+            dna: seq.dna.stats(),
+            header: seq.header,
+        })
+        .collect())
+}
+
+/// The reverse complement of each sequence
+pub fn revcomp(input: &Input) -> Result<Vec<Sequence>, CliError> {
+    Ok(input
+        .read()?
+        .into_iter()
+        .map(|seq| Sequence {
+            dna: seq.dna.reverse_complement(),
+            header: seq.header,
+        })
+        .collect())
+}
+
+/// Check that `k` is a k-mer length the packed k-mers can hold
+fn check_k(k: usize) -> Result<(), CliError> {
+    if k == 0 || k > kmer::MAX_K {
+        return Err(CliError::Argument(format!(
+            "k must be between 1 and {}, got {}",
+            kmer::MAX_K,
+            k
+        )));
+    }
+    Ok(())
+}
+
+/// The k-mers of a sequence, spelled out, with their counts
+pub type KmerCounts = Vec<(String, usize)>;
+
+/// The k-mers of each sequence with their counts, in lexicographic order, merged with their
+/// reverse complements if `canonical`
+pub fn kmers(
+    input: &Input,
+    k: usize,
+    canonical: bool,
+) -> Result<Vec<Sequence<KmerCounts>>, CliError> {
+    check_k(k)?;
+    let counter = kmer::KmerCounter::new(k).canonical(canonical);
+    Ok(input
+        .read()?
+        .into_iter()
+        .map(|seq| {
+            let mut counts: Vec<(u64, usize)> = counter.count(&seq.dna).iter().collect();
+            counts.sort_unstable();
+            Sequence {
+                dna: counts
+                    .into_iter()
+                    .map(|(kmer, count)| (kmer::decode(kmer, k), count))
+                    .collect(),
+                header: seq.header,
+            }
+        })
+        .collect())
+}
+
+/// A memory limit in bytes, parsed from a number with an optional `K`, `M` or `G` suffix for
+/// binary kilo-, mega- and gigabytes
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct MemoryLimit(pub usize);
+
+impl FromStr for MemoryLimit {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (digits, shift) = match s.char_indices().last() {
+            Some((idx, 'k' | 'K')) => (&s[..idx], 10),
+            Some((idx, 'm' | 'M')) => (&s[..idx], 20),
+            Some((idx, 'g' | 'G')) => (&s[..idx], 30),
+            _ => (s, 0),
+        };
+        digits
+            .parse::<usize>()
+            .ok()
+            .and_then(|n| n.checked_mul(1 << shift))
+            .map(MemoryLimit)
+            .ok_or_else(|| format!("invalid memory size {}", s))
+    }
+}
+
+/// The k-mers of all sequences of the input together, merged with their reverse complements
+/// if `canonical`, counted in about `max_mem` of memory. Records are read one at a time, and
+/// k-mers spanning an ambiguity code of a FASTQ read are left out.
+pub fn kmer_count(
+    input: &Input,
+    k: usize,
+    canonical: bool,
+    max_mem: MemoryLimit,
+    temp_dir: Option<PathBuf>,
+) -> Result<ExternalKmerCounts, CliError> {
+    check_k(k)?;
+    if max_mem.0 < 8 {
+        return Err(CliError::Argument(format!(
+            "--max-mem must be at least 8 bytes, got {}",
+            max_mem.0
+        )));
+    }
+    let mut counter = ExternalKmerCounter::new(k)
+        .canonical(canonical)
+        .max_memory(max_mem.0);
+    if let Some(dir) = temp_dir {
+        counter = counter.temp_dir(dir);
+    }
+    if input.dna.is_some() {
+        for seq in input.read()? {
+            counter.add(&seq.dna)?;
+        }
+        return Ok(counter.finish());
+    }
+    match input.open()? {
+        Source::Fasta(reader) => {
+            let mut records = input.fasta_records(reader);
+            for record in records.by_ref() {
+                counter.add(record?.sequence())?;
+            }
+            input.warn_skipped(records.skipped());
+        }
+        Source::Fastq(reader) => {
+            for read in fastq::Reader::new(reader).iupac() {
+                let read = read.map_err(DnaError::from)?;
+                for part in read.sequence().unambiguous_slices() {
+                    counter.add(&part.to_packed())?;
+                }
+            }
+        }
+        Source::TwoBit(records) => {
+            for record in records {
+                let (_, dna) = record?;
+                for part in dna.unambiguous_slices() {
+                    counter.add(&part.to_packed())?;
+                }
+            }
+        }
+        Source::Text(reader) => counter.add(&input.read_text::<PackedDna>(reader)?.dna)?,
+    }
+    Ok(counter.finish())
+}
+
+/// The nucleotide counts at each position of the sequences, which must have the same length
+pub fn pfm(input: &Input) -> Result<Pfm, CliError> {
+    let sequences = input.read()?;
+    let pfm = Pfm::from_sequences(sequences.iter().map(|seq| &seq.dna)).map_err(DnaError::from)?;
+    Ok(pfm)
+}
+
+/// The first word of the FASTA header of each sequence, or `seq1`, `seq2` and so on by position
+/// for those without one
+fn names(sequences: &[Sequence]) -> Vec<String> {
+    sequences
+        .iter()
+        .enumerate()
+        .map(|(idx, seq)| name(seq.header.as_deref(), idx))
+        .collect()
+}
+
+/// The first word of `header`, or `seq` and the 1-based position `idx + 1` without one
+pub(crate) fn name(header: Option<&str>, idx: usize) -> String {
+    header
+        .and_then(|header| header.split_whitespace().next())
+        .map_or_else(|| format!("seq{}", idx + 1), str::to_string)
+}
+
+/// The redundant contigs of an assembly, see [`redundant`]
+#[derive(Debug, Clone)]
+pub struct Redundancy {
+    /// Name of each contig, in input order
+    pub names: Vec<String>,
+    /// The contigs contained in others, in input order
+    pub redundant: Vec<RedundantContig>,
+}
+
+/// The contigs contained in another one at `min_identity` or more, see [`RedundancyFinder`]
+pub fn redundant(input: &Input, min_identity: f64) -> Result<Redundancy, CliError> {
+    if !(0.0..=1.0).contains(&min_identity) {
+        return Err(CliError::Argument(format!(
+            "identity must be between 0 and 1, got {}",
+            min_identity
+        )));
+    }
+    let sequences = input.read()?;
+    let contigs: Vec<PackedDna> = sequences.iter().map(|seq| seq.dna.clone()).collect();
+    Ok(Redundancy {
+        names: names(&sequences),
+        redundant: RedundancyFinder::new()
+            .min_identity(min_identity)
+            .find(&contigs),
+    })
+}
+
+/// A match of a pattern, see [`grep`]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct GrepMatch {
+    /// Name of the sequence the match is in
+    pub name: String,
+    /// 0-based range of the match on the forward strand
+    pub range: Range<usize>,
+    /// The strand the pattern reads on
+    pub strand: Strand,
+    /// The matched nucleotides, reverse complemented on the reverse strand
+    pub matched: PackedDna,
+}
+
+/// The matches of the regular expression over IUPAC codes `pattern` in each sequence, on both
+/// strands unless `forward_only`, see [`IupacRegex::find_all`]
+pub fn grep(input: &Input, pattern: &str, forward_only: bool) -> Result<Vec<GrepMatch>, CliError> {
+    let mut regex =
+        IupacRegex::new(pattern).map_err(|e| CliError::Argument(format!("{}: {}", pattern, e)))?;
+    if forward_only {
+        regex = regex.forward_only();
+    }
+    let sequences = input.read()?;
+    let names = names(&sequences);
+    let mut found = Vec::new();
+    for (seq, name) in sequences.iter().zip(names) {
+        for m in regex.find_all(&seq.dna) {
+            let mut matched = seq.dna.to_slice(m.range.clone());
+            if m.strand == Strand::Reverse {
+                matched.reverse_complement_in_place();
+            }
+            found.push(GrepMatch {
+                name: name.clone(),
+                range: m.range,
+                strand: m.strand,
+                matched,
+            });
+        }
+    }
+    Ok(found)
+}
+
+/// How each sequence is sketched before comparing them
+#[derive(Debug, Clone, Copy)]
+pub enum Sampling {
+    /// A MinHash sketch of this many k-mers, see [`Sketch::minhash`]
+    Size(usize),
+    /// A FracMinHash sketch of one k-mer in this many, see [`Sketch::frac_minhash`]
+    Scaled(u64),
+}
+
+/// The distances between every pair of sequences, from sketches of their canonical k-mers.
+///
+/// Sequences without a FASTA header are named `seq1`, `seq2` and so on by their position.
+pub fn kmer_distances(
+    input: &Input,
+    k: usize,
+    sampling: Sampling,
+    metric: Metric,
+) -> Result<DistanceMatrix, CliError> {
+    check_k(k)?;
+    if let Sampling::Size(0) | Sampling::Scaled(0) = sampling {
+        return Err(CliError::Argument(
+            "sketch size and scale must be non-zero".to_string(),
+        ));
+    }
+    let sequences = input.read()?;
+    let names = names(&sequences);
+    let sketches: Vec<Sketch> = sequences
+        .iter()
+        .map(|seq| match sampling {
+            Sampling::Size(size) => Sketch::minhash(&seq.dna, k, size),
+            Sampling::Scaled(scaled) => Sketch::frac_minhash(&seq.dna, k, scaled),
+        })
+        .collect();
+    Ok(DistanceMatrix::new(names, &sketches, metric))
+}
+
+/// What a shuffle keeps of each sequence
+#[derive(Debug, Clone, Copy)]
+pub enum Preserve {
+    /// The count of each nucleotide, see [`PackedDna::shuffle`]
+    Nucleotides,
+    /// The count of each dinucleotide, see [`PackedDna::shuffle_dinucleotides`]
+    Dinucleotides,
+}
+
+impl FromStr for Preserve {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "nucleotides" => Ok(Preserve::Nucleotides),
+            "dinucleotides" => Ok(Preserve::Dinucleotides),
+            _ => Err(format!("unknown composition {}", s)),
+        }
+    }
+}
+
+/// A shuffle of each sequence keeping its composition, the same for the same `seed`
+pub fn shuffle(input: &Input, preserve: Preserve, seed: u64) -> Result<Vec<Sequence>, CliError> {
+    let mut rng = StdRng::seed_from_u64(seed);
+    Ok(input
+        .read()?
+        .into_iter()
+        .map(|seq| Sequence {
+            dna: match preserve {
+                Preserve::Nucleotides => seq.dna.shuffle(&mut rng),
+                Preserve::Dinucleotides => seq.dna.shuffle_dinucleotides(&mut rng),
+            },
+            header: seq.header,
+        })
+        .collect())
+}
+
+/// The range `start..end` of each sequence, up to its end if `end` is `None`
+pub fn extract(input: &Input, start: usize, end: Option<usize>) -> Result<Vec<Sequence>, CliError> {
+    input
+        .read()?
+        .into_iter()
+        .map(|seq| {
+            let len = seq.dna.len();
+            let end = end.unwrap_or(len);
+            if start > end || end > len {
+                return Err(CliError::Argument(format!(
+                    "range {}..{} out of bounds for DNA of length {}",
+                    start, end, len
+                )));
+            }
+            Ok(Sequence {
+                dna: seq.dna.to_slice(start..end),
+                header: seq.header,
+            })
+        })
+        .collect()
+}
+
+/// A standalone HTML page with `bins` bars in each histogram
+pub fn report(input: &Input, bins: usize) -> Result<String, CliError> {
+    if bins == 0 {
+        return Err(CliError::Argument("bins must be non-zero".to_string()));
+    }
+    let dnas: Vec<PackedDna> = input.read()?.into_iter().map(|seq| seq.dna).collect();
+    Ok(report::html(&BatchStats::new(&dnas), bins))
+}
+
+/// The contig by contig comparison of the FASTA reference read from the input with the one at
+/// `other`, see [`concordance::compare_references`]
+pub fn compare_refs(input: &Input, other: PathBuf) -> Result<Concordance, CliError> {
+    if input.dna.is_some() {
+        return Err(CliError::Argument(
+            "compare-refs needs a FASTA file, not --dna".to_string(),
+        ));
+    }
+    let (first, second) = match (input.open()?, Input::from_file(other).open()?) {
+        (Source::Fasta(first), Source::Fasta(second)) => (first, second),
+        _ => {
+            return Err(CliError::Argument(
+                "compare-refs needs FASTA references".to_string(),
+            ))
+        }
+    };
+    Ok(concordance::compare_references(first, second).map_err(DnaError::from)?)
+}
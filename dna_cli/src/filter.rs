@@ -0,0 +1,85 @@
+//! Streaming filters dropping the sequences that fail a test.
+//!
+//! Records are read, tested and written one at a time, so input far larger than memory can be
+//! filtered in a pipeline. FASTA input is written back as FASTA and FASTQ input as FASTQ, with
+//! the qualities of the reads kept, while the records of 2bit input are written as FASTA. The
+//! ambiguity codes of FASTQ and 2bit records are written back too, but left out of their
+//! composition.
+
+use crate::{input::Source, write_header, write_sequence, CliError, Input, Sequence};
+use dna::{fastq, iupac::AmbiguityPolicy, DnaError, PackedDna};
+use std::io::{BufRead, Write};
+
+/// Copy the sequences of `input` whose base composition has an entropy of at least
+/// `min_entropy` bits to `out`, dropping low-complexity ones such as poly-A runs
+pub fn run(input: &Input, min_entropy: f64, out: &mut dyn Write) -> Result<(), CliError> {
+    let keep = |dna: &PackedDna| dna.stats().entropy() >= min_entropy;
+    if input.dna.is_some() {
+        for seq in input.read()? {
+            write_if(out, &seq, keep(&seq.dna))?;
+        }
+        return Ok(());
+    }
+    match input.open()? {
+        Source::Fasta(reader) => filter_fasta(input, reader, keep, out),
+        Source::Fastq(reader) => {
+            let mut writer = fastq::Writer::new(out);
+            for read in fastq::Reader::new(reader).iupac() {
+                let read = read.map_err(DnaError::from)?;
+                let called = read
+                    .sequence()
+                    .to_packed(AmbiguityPolicy::Skip)
+                    .map_err(DnaError::from)?;
+                if keep(&called) {
+                    writer.write(&read)?;
+                }
+            }
+            Ok(())
+        }
+        Source::TwoBit(records) => {
+            for record in records {
+                let (name, dna) = record?;
+                let called = dna
+                    .to_packed(AmbiguityPolicy::Skip)
+                    .map_err(DnaError::from)?;
+                if keep(&called) {
+                    writeln!(out, ">{}\n{}", name, dna)?;
+                }
+            }
+            Ok(())
+        }
+        Source::Text(reader) => {
+            let seq = input.read_text(reader)?;
+            write_if(out, &seq, keep(&seq.dna))
+        }
+    }
+}
+
+/// Filter FASTA records, warning about skipped characters once the input is read
+fn filter_fasta(
+    input: &Input,
+    reader: impl BufRead,
+    keep: impl Fn(&PackedDna) -> bool,
+    out: &mut dyn Write,
+) -> Result<(), CliError> {
+    let mut records = input.fasta_records(reader);
+    for record in records.by_ref() {
+        let (header, dna) = record?.into_parts();
+        let keep = keep(&dna);
+        let seq = Sequence {
+            header: Some(header),
+            dna,
+        };
+        write_if(out, &seq, keep)?;
+    }
+    input.warn_skipped(records.skipped());
+    Ok(())
+}
+
+fn write_if(out: &mut dyn Write, seq: &Sequence, keep: bool) -> Result<(), CliError> {
+    if keep {
+        write_header(out, seq)?;
+        write_sequence(out, &seq.dna)?;
+    }
+    Ok(())
+}
@@ -0,0 +1,416 @@
+//! Resolution of the input of a subcommand into sequences, and of where its results go.
+
+use crate::CliError;
+use dna::{
+    detect::{self, DetectError, FileFormat},
+    fasta::{self, BaseCounts, FastaError, Skipped},
+    fastq,
+    iupac::{AmbiguityPolicy, Iupac, IupacDna},
+    normalize::{normalize, Normalization, SequenceText},
+    DnaError, ErrorContext, Nuc, PackedDna,
+};
+use std::{
+    cell::RefCell,
+    convert::TryFrom,
+    fs::File,
+    io::{self, BufRead, BufReader, BufWriter, Read, Write},
+    path::PathBuf,
+    rc::Rc,
+};
+use structopt::StructOpt;
+
+// Where a subcommand reads its sequences from and writes its results to. A plain comment, as a
+// doc comment would override the about text of subcommands flattening it.
+#[allow(missing_docs)]
+#[derive(Debug, Default, StructOpt)]
+pub struct Input {
+    /// The DNA sequence to operate on.
+    ///
+    /// It is case insensitive and may be pasted with a leading FASTA header and line breaks,
+    /// see `--strict`.
+    #[structopt(short, long, required_unless = "file", conflicts_with = "file")]
+    pub dna: Option<String>,
+
+    /// Read the DNA from a file instead, or from stdin if the path is `-`.
+    ///
+    /// The file may be FASTA, FASTQ or 2bit, in which case every record is processed, or plain
+    /// text holding a single sequence (whitespace is ignored). Its format is detected from its
+    /// start, and gzip-compressed files are decompressed.
+    #[structopt(short, long, parse(from_os_str))]
+    pub file: Option<PathBuf>,
+
+    /// Reject input that is not exactly a sequence.
+    ///
+    /// Without it, a leading FASTA header, whitespace and invisible characters such as byte
+    /// order marks are stripped from `--dna` and plain text input, and `count` counts ambiguity
+    /// codes. With it, only line breaks are allowed and ambiguity codes are errors.
+    #[structopt(long)]
+    pub strict: bool,
+
+    /// Skip characters that are not nucleotides instead of failing on the first one.
+    ///
+    /// Each skipped character is reported on stderr with its position: the line and column in
+    /// FASTA input, or the 0-based position in the sequence otherwise. FASTQ input cannot skip
+    /// characters, as each one has a quality score.
+    #[structopt(long, conflicts_with = "strict")]
+    pub lenient: bool,
+
+    /// Write the results to this file instead of stdout
+    #[structopt(short, long, parse(from_os_str))]
+    pub output: Option<PathBuf>,
+
+    /// Warnings about the input, shared with [`run`](crate::run) to write them out
+    #[structopt(skip)]
+    warnings: Rc<RefCell<Vec<String>>>,
+}
+
+/// A sequence read from the input, or a result computed from one, with its FASTA header if it
+/// had one
+#[derive(Debug, Clone, PartialEq)]
+pub struct Sequence<T = PackedDna> {
+    /// The FASTA header, without the leading `>`
+    pub header: Option<String>,
+    /// The sequence or result
+    pub dna: T,
+}
+
+/// A sequence type the input can be read as
+pub(crate) trait Parse: Sized {
+    /// Whether `c` is a nucleotide of the sequence type
+    fn accepts(c: char) -> bool;
+
+    /// Parse the characters of a sequence, failing on the first invalid one
+    fn parse(chars: impl Iterator<Item = char>) -> Result<Self, DnaError>;
+
+    /// Convert a sequence read over the IUPAC alphabet, failing if it holds codes the sequence
+    /// type does not
+    fn from_iupac(dna: IupacDna) -> Result<Self, DnaError>;
+
+    /// Read every record of FASTA input, with the characters skipped if `lenient`
+    fn read_fasta(input: impl Read, lenient: bool) -> Result<FastaRecords<Self>, FastaError>;
+}
+
+/// The records of FASTA input and the characters skipped reading them
+type FastaRecords<T> = (Vec<(String, T)>, Vec<Skipped>);
+
+/// A FASTA reader, lenient if asked to be
+fn fasta_reader<R: Read>(input: R, lenient: bool) -> fasta::Reader<R> {
+    let reader = fasta::Reader::new(input);
+    if lenient {
+        reader.lenient()
+    } else {
+        reader
+    }
+}
+
+impl Parse for PackedDna {
+    fn accepts(c: char) -> bool {
+        Nuc::try_from(c).is_ok()
+    }
+
+    fn parse(chars: impl Iterator<Item = char>) -> Result<Self, DnaError> {
+        PackedDna::try_collect_chars(chars)
+    }
+
+    fn from_iupac(dna: IupacDna) -> Result<Self, DnaError> {
+        Ok(dna.to_packed(AmbiguityPolicy::Error)?)
+    }
+
+    fn read_fasta(input: impl Read, lenient: bool) -> Result<FastaRecords<Self>, FastaError> {
+        let mut reader = fasta_reader(input, lenient);
+        let records = reader
+            .by_ref()
+            .map(|record| record.map(fasta::Record::into_parts))
+            .collect::<Result<_, _>>()?;
+        Ok((records, reader.skipped().to_vec()))
+    }
+}
+
+impl Parse for IupacDna {
+    fn accepts(c: char) -> bool {
+        Iupac::try_from(c).is_ok()
+    }
+
+    fn parse(chars: impl Iterator<Item = char>) -> Result<Self, DnaError> {
+        chars.collect::<String>().parse()
+    }
+
+    fn from_iupac(dna: IupacDna) -> Result<Self, DnaError> {
+        Ok(dna)
+    }
+
+    fn read_fasta(input: impl Read, lenient: bool) -> Result<FastaRecords<Self>, FastaError> {
+        let mut records = fasta_reader(input, lenient).iupac();
+        let parsed = records.by_ref().collect::<Result<_, _>>()?;
+        Ok((parsed, records.skipped().to_vec()))
+    }
+}
+
+impl Input {
+    /// Input of the single sequence `dna`, as given with `--dna`
+    pub fn from_dna(dna: impl Into<String>) -> Self {
+        Input {
+            dna: Some(dna.into()),
+            ..Input::default()
+        }
+    }
+
+    /// Input read from the file at `path`, or from stdin if it is `-`, with the default options
+    pub fn from_file(path: impl Into<PathBuf>) -> Self {
+        Input {
+            file: Some(path.into()),
+            ..Input::default()
+        }
+    }
+
+    /// Take the warnings about the input so far, such as the characters skipped with
+    /// `--lenient`
+    pub fn take_warnings(&self) -> Vec<String> {
+        self.warnings.take()
+    }
+
+    /// The warnings about the input, to take once it has been read
+    pub(crate) fn warnings(&self) -> Rc<RefCell<Vec<String>>> {
+        Rc::clone(&self.warnings)
+    }
+
+    /// Warn about the characters skipped reading lenient FASTA input
+    pub(crate) fn warn_skipped(&self, skipped: &[Skipped]) {
+        self.warnings
+            .borrow_mut()
+            .extend(skipped.iter().map(|skipped| {
+                format!(
+                    "skipped {:?} on line {}, column {}",
+                    skipped.nuc, skipped.line, skipped.column
+                )
+            }));
+    }
+
+    /// Read every sequence of the input
+    pub fn read(&self) -> Result<Vec<Sequence>, CliError> {
+        self.read_as()
+    }
+
+    /// Read every sequence of the input as `T`
+    fn read_as<T: Parse>(&self) -> Result<Vec<Sequence<T>>, CliError> {
+        if let Some(dna) = &self.dna {
+            return Ok(vec![self.parse_text(dna)?]);
+        }
+        self.read_from(self.open()?)
+    }
+
+    /// Read every sequence of the opened input as `T`
+    fn read_from<T: Parse>(&self, source: Source) -> Result<Vec<Sequence<T>>, CliError> {
+        let records: Vec<(String, T)> = match source {
+            Source::Fasta(input) => {
+                let (records, skipped) = T::read_fasta(input, self.lenient)?;
+                self.warn_skipped(&skipped);
+                records
+            }
+            Source::Fastq(input) => fastq::Reader::new(input)
+                .iupac()
+                .map(|read| {
+                    let (header, dna, _) = read.map_err(DnaError::from)?.into_parts();
+                    from_iupac(header, dna)
+                })
+                .collect::<Result<_, _>>()?,
+            Source::TwoBit(records) => records
+                .map(|record| {
+                    let (name, dna) = record?;
+                    from_iupac(name, dna)
+                })
+                .collect::<Result<_, _>>()?,
+            Source::Text(input) => return Ok(vec![self.read_text(input)?]),
+        };
+        Ok(records
+            .into_iter()
+            .map(|(header, dna)| Sequence {
+                header: Some(header),
+                dna,
+            })
+            .collect())
+    }
+
+    /// The records of opened FASTA input one at a time, skipping invalid characters if
+    /// `--lenient` is given
+    pub(crate) fn fasta_records<R: Read>(&self, input: R) -> fasta::Reader<R> {
+        fasta_reader(input, self.lenient)
+    }
+
+    /// Read the single sequence of opened plain text input
+    pub(crate) fn read_text<T: Parse>(
+        &self,
+        mut input: impl Read,
+    ) -> Result<Sequence<T>, CliError> {
+        let mut text = String::new();
+        input.read_to_string(&mut text)?;
+        let text = text.strip_prefix('\u{feff}').unwrap_or(&text);
+        self.parse_text(text)
+    }
+
+    /// Count the nucleotides of every sequence of the input, counting ambiguity codes unless
+    /// `--strict` is given.
+    ///
+    /// FASTA records are counted without packing them, unless characters have to be skipped.
+    pub fn count(&self) -> Result<Vec<Sequence<BaseCounts>>, CliError> {
+        if self.strict {
+            return Ok(self
+                .read()?
+                .into_iter()
+                .map(|seq| Sequence {
+                    dna: strict_counts(&seq.dna),
+                    header: seq.header,
+                })
+                .collect());
+        }
+        let sequences: Vec<Sequence<IupacDna>> = match &self.dna {
+            Some(dna) => vec![self.parse_text(dna)?],
+            None => match self.open()? {
+                Source::Fasta(input) if !self.lenient => {
+                    return Ok(fasta::count_bases_in_reader(input)?
+                        .into_iter()
+                        .map(|(header, dna)| Sequence {
+                            header: Some(header),
+                            dna,
+                        })
+                        .collect())
+                }
+                source => self.read_from(source)?,
+            },
+        };
+        Ok(sequences
+            .into_iter()
+            .map(|seq| Sequence {
+                dna: base_counts(&seq.dna),
+                header: seq.header,
+            })
+            .collect())
+    }
+
+    /// Open the file given with `--file`, or stdin if the path is `-`, by the format detected
+    /// from its start, decompressing it if it is gzip-compressed
+    pub(crate) fn open(&self) -> Result<Source, CliError> {
+        let path = self
+            .file
+            .as_ref()
+            .expect("structopt requires --dna or --file");
+        let input: Box<dyn Read> = if path.as_os_str() == "-" {
+            Box::new(io::stdin())
+        } else {
+            let file = File::open(path).map_err(|source| CliError::File {
+                path: path.clone(),
+                source,
+            })?;
+            Box::new(file)
+        };
+        let mut input = BufReader::new(input);
+        let detected = detect::detect(&mut input)?;
+        let decompress = |input| detect::decompress(input, detected.compression);
+        let source = match detected.format {
+            Some(FileFormat::Fasta) => Source::Fasta(decompress(input).map_err(DnaError::from)?),
+            Some(FileFormat::Fastq) => Source::Fastq(decompress(input).map_err(DnaError::from)?),
+            Some(FileFormat::TwoBit) => {
+                Source::TwoBit(detect::read_detected(input, detected).map_err(DnaError::from)?)
+            }
+            Some(format @ FileFormat::GenBank) => {
+                return Err(DnaError::from(DetectError::Unsupported(format)).into())
+            }
+            None => Source::Text(decompress(input).map_err(DnaError::from)?),
+        };
+        if let (true, Some(format @ (FileFormat::Fastq | FileFormat::TwoBit))) =
+            (self.lenient, detected.format)
+        {
+            return Err(CliError::Argument(format!(
+                "--lenient only applies to FASTA and plain text input, not {}",
+                format
+            )));
+        }
+        Ok(source)
+    }
+
+    /// Parse a single sequence typed or pasted as text, normalised unless `--strict` is given
+    fn parse_text<T: Parse>(&self, text: &str) -> Result<Sequence<T>, CliError> {
+        let mode = if self.strict {
+            Normalization::Strict
+        } else {
+            Normalization::Lenient
+        };
+        let SequenceText { header, sequence } = normalize(text, mode);
+        let dna = if self.lenient {
+            T::parse(sequence.chars().enumerate().filter_map(|(position, c)| {
+                if T::accepts(c) {
+                    return Some(c);
+                }
+                self.warnings
+                    .borrow_mut()
+                    .push(format!("skipped {:?} at position {}", c, position));
+                None
+            }))?
+        } else {
+            T::parse(sequence.chars())?
+        };
+        Ok(Sequence { header, dna })
+    }
+
+    /// Create the output file, or write to `stdout` if none was given
+    pub fn writer<'a>(&self, stdout: &'a mut dyn Write) -> Result<Box<dyn Write + 'a>, CliError> {
+        Ok(match &self.output {
+            Some(path) => {
+                let file = File::create(path).map_err(|source| CliError::File {
+                    path: path.clone(),
+                    source,
+                })?;
+                Box::new(BufWriter::new(file))
+            }
+            None => Box::new(BufWriter::new(stdout)),
+        })
+    }
+}
+
+/// Opened input, by the format detected from its start, see [`Input::open`]
+pub(crate) enum Source {
+    /// FASTA records
+    Fasta(Box<dyn BufRead>),
+    /// FASTQ reads
+    Fastq(Box<dyn BufRead>),
+    /// The records of a 2bit file
+    TwoBit(detect::Records),
+    /// A single sequence as plain text
+    Text(Box<dyn BufRead>),
+}
+
+/// Convert the sequence of the record `header` read over the IUPAC alphabet to `T`
+fn from_iupac<T: Parse>(header: String, dna: IupacDna) -> Result<(String, T), CliError> {
+    let dna = T::from_iupac(dna).with_context(|| format!("reading {}", header))?;
+    Ok((header, dna))
+}
+
+/// The counts of a sequence without ambiguity codes
+fn strict_counts(dna: &PackedDna) -> BaseCounts {
+    let stats = dna.stats();
+    BaseCounts {
+        a: stats.a_count,
+        c: stats.c_count,
+        g: stats.g_count,
+        t: stats.t_count,
+        ..BaseCounts::default()
+    }
+}
+
+/// The counts of a sequence read as text
+fn base_counts(dna: &IupacDna) -> BaseCounts {
+    let ambiguous = dna
+        .ambiguous_runs()
+        .iter()
+        .filter(|run| run.code != Iupac::N)
+        .map(|run| run.len)
+        .sum();
+    BaseCounts {
+        a: dna.count(Iupac::A),
+        c: dna.count(Iupac::C),
+        g: dna.count(Iupac::G),
+        t: dna.count(Iupac::T),
+        n: dna.count(Iupac::N),
+        ambiguous,
+    }
+}
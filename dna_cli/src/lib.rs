@@ -0,0 +1,764 @@
+//! The `nuccount` command line tool as a library.
+//!
+//! [`Opts`] is the parsed command line and [`run`] carries it out, writing the results where
+//! the [`Input`] asks. The [`commands`] compute the results of each subcommand as values, so the
+//! tool can be tested or embedded without going through text.
+
+#![warn(missing_docs)]
+
+pub mod commands;
+mod filter;
+mod input;
+pub mod matrix;
+pub mod output;
+mod plugin;
+mod report;
+pub mod track;
+mod translate;
+
+pub use input::{Input, Sequence};
+
+use dna::{
+    concordance::{ContigInfo, ContigStatus},
+    fasta::FastaError,
+    kmer,
+    translate::{GeneticCode, Strand},
+    DnaError, PackedDna,
+};
+use output::Value;
+use std::{
+    io::{self, Write},
+    path::PathBuf,
+};
+use structopt::StructOpt;
+
+/// Small tools for exercising packed DNA from the shell.
+#[derive(Debug, StructOpt)]
+#[structopt(name = "nuccount")]
+pub enum Opts {
+    /// Count the number of occurrences of each nucleotide.
+    ///
+    /// `N` and the other IUPAC ambiguity codes are counted separately after A, C, G and T,
+    /// unless `--strict` makes them errors. The JSON and TSV formats also give the length and
+    /// the GC content among A, C, G and T.
+    Count {
+        #[allow(missing_docs)]
+        #[structopt(flatten)]
+        input: Input,
+        #[allow(missing_docs)]
+        #[structopt(flatten)]
+        format: OutputFormat,
+    },
+    /// Report the GC content as a fraction of the sequence length
+    Gc {
+        #[allow(missing_docs)]
+        #[structopt(flatten)]
+        input: Input,
+        #[allow(missing_docs)]
+        #[structopt(flatten)]
+        format: OutputFormat,
+    },
+    /// Write the reverse complement of each sequence
+    Revcomp(Input),
+    /// Count the k-mers of each sequence
+    Kmer {
+        #[allow(missing_docs)]
+        #[structopt(flatten)]
+        input: Input,
+        /// Length of the counted k-mers, at most 32
+        #[structopt(short, long, default_value = "4")]
+        k: usize,
+        /// Merge each k-mer with its reverse complement
+        #[structopt(short, long)]
+        canonical: bool,
+    },
+    /// Count the k-mers of all sequences together in bounded memory, spilling to disk.
+    ///
+    /// Suits read sets far larger than memory: k-mers are buffered up to `--max-mem`, then
+    /// written out in partitions and merged at the end. Counts are listed partition by
+    /// partition, sorted within each.
+    KmerCount {
+        #[allow(missing_docs)]
+        #[structopt(flatten)]
+        input: Input,
+        /// Length of the counted k-mers, at most 32
+        #[structopt(short, long, default_value = "31")]
+        k: usize,
+        /// Merge each k-mer with its reverse complement
+        #[structopt(short, long)]
+        canonical: bool,
+        /// Memory for buffered k-mers, in bytes or with a K, M or G suffix
+        #[structopt(long, default_value = "1G")]
+        max_mem: commands::MemoryLimit,
+        /// Directory for the spilled k-mers, the system's temporary directory by default
+        #[structopt(long, parse(from_os_str))]
+        temp_dir: Option<PathBuf>,
+    },
+    /// Drop low-complexity sequences, such as poly-A reads and adapter dimers, while streaming.
+    ///
+    /// A sequence is kept if the Shannon entropy of its base composition is at least
+    /// `--min-entropy`, from 0 bits for a single repeated nucleotide to 2 for equal counts of
+    /// all four. FASTA and FASTQ input are written back in the same format.
+    Filter {
+        #[allow(missing_docs)]
+        #[structopt(flatten)]
+        input: Input,
+        /// Lowest entropy of a kept sequence, in bits
+        #[structopt(long)]
+        min_entropy: f64,
+    },
+    /// Translate each sequence in all six reading frames to protein FASTA, while streaming.
+    ///
+    /// Each sequence gives six records named after its identifier and the frame, from `_+1` to
+    /// `_+3` on the forward strand then `_-1` to `_-3` on the reverse complement. Stops are
+    /// written as `*`, codons with an ambiguity code as `X`, and a trailing partial codon is
+    /// dropped. Only one sequence is held in
+    /// memory at a time, packed.
+    Translate {
+        #[allow(missing_docs)]
+        #[structopt(flatten)]
+        input: Input,
+        /// The NCBI translation table: 1 to 6 or 11
+        #[structopt(long, default_value = "1")]
+        table: u8,
+    },
+    /// Search each sequence for a regular expression over IUPAC codes, on both strands.
+    ///
+    /// Patterns such as `GGWCC{2,4}N{10}GG` take IUPAC codes, `.` for any nucleotide, classes
+    /// like `[AG]` or `[^C]`, groups, alternatives with `|` and the repetitions `*`, `+`, `?`
+    /// and `{n,m}`. Each match is written with the sequence name, its 0-based, end-exclusive
+    /// range on the forward strand, the strand and the matched nucleotides read along it.
+    Grep {
+        /// The pattern to search for
+        pattern: String,
+        #[allow(missing_docs)]
+        #[structopt(flatten)]
+        input: Input,
+        /// Only search the forward strand
+        #[structopt(long)]
+        forward_only: bool,
+    },
+    /// Count the nucleotides at each position of equal-length sequences, such as aligned sites,
+    /// reads or barcodes.
+    ///
+    /// The consensus comes first, with an IUPAC code where nucleotides are tied, then a line
+    /// per 1-based position with the counts of A, C, G and T and the entropy in bits.
+    Pfm(Input),
+    /// Compare every pair of sequences by their k-mers, writing a distance matrix.
+    ///
+    /// Each sequence is reduced to a MinHash sketch of its canonical k-mers, or a FracMinHash
+    /// sketch with `--scaled`, which suits sequences of very different lengths and containment.
+    /// Sequences shorter than k are at distance 1 from all others.
+    KmerDist {
+        #[allow(missing_docs)]
+        #[structopt(flatten)]
+        input: Input,
+        /// Length of the compared k-mers, at most 32
+        #[structopt(short, long, default_value = "21")]
+        k: usize,
+        /// Number of k-mer hashes kept per sequence
+        #[structopt(short, long, default_value = "1000")]
+        size: usize,
+        /// Keep about one k-mer hash in this many instead of a fixed number
+        #[structopt(long, conflicts_with = "size")]
+        scaled: Option<u64>,
+        /// How sequences are compared
+        #[structopt(
+            long,
+            default_value = "jaccard",
+            possible_values = &["jaccard", "containment", "mash"]
+        )]
+        metric: matrix::Metric,
+        /// The file format of the matrix
+        #[structopt(long, default_value = "phylip", possible_values = &["phylip", "tsv"])]
+        format: matrix::Format,
+    },
+    /// Report the contigs of an assembly contained in, or nearly identical to, a longer one.
+    ///
+    /// Candidates sharing most of their sampled 21-mers with a longer contig are confirmed by
+    /// aligning them on both strands. Each redundant contig is written with the longest contig
+    /// containing it, the strand it aligns to and a lower bound on its identity. Of identical
+    /// contigs the first is kept.
+    Redundant {
+        #[allow(missing_docs)]
+        #[structopt(flatten)]
+        input: Input,
+        /// Lowest fraction of a contig's nucleotides matching its container
+        #[structopt(long, default_value = "0.95")]
+        min_identity: f64,
+    },
+    /// Shuffle each sequence keeping its composition, as a null model for motif enrichment.
+    ///
+    /// By default the count of every dinucleotide is kept, by the Altschul-Erickson algorithm.
+    /// Runs with the same `--seed` and input give the same shuffles.
+    Shuffle {
+        #[allow(missing_docs)]
+        #[structopt(flatten)]
+        input: Input,
+        /// What to keep of each sequence
+        #[structopt(
+            long,
+            default_value = "dinucleotides",
+            possible_values = &["nucleotides", "dinucleotides"]
+        )]
+        preserve: commands::Preserve,
+        /// Seed of the random shuffles, drawn at random if not given
+        #[structopt(long)]
+        seed: Option<u64>,
+    },
+    /// Extract the 0-based, end-exclusive range `start..end` of each sequence
+    Extract {
+        #[allow(missing_docs)]
+        #[structopt(flatten)]
+        input: Input,
+        /// First position of the range
+        #[structopt(short, long)]
+        start: usize,
+        /// Position just past the end of the range, defaults to the sequence length
+        #[structopt(short, long)]
+        end: Option<usize>,
+    },
+    /// Report the length, GC content, strand skews and entropy of each sequence.
+    ///
+    /// With `--per-window`, one statistic of consecutive windows is written instead as a
+    /// BedGraph or Wiggle track for genome browsers such as IGV. Windows running past the end
+    /// of a sequence are left out.
+    Stats {
+        #[allow(missing_docs)]
+        #[structopt(flatten)]
+        input: Input,
+        /// Write a track with one value per window of this many nucleotides
+        #[structopt(short = "w", long)]
+        per_window: Option<usize>,
+        /// The statistic of the track
+        #[structopt(long, default_value = "gc", possible_values = &["gc", "entropy"])]
+        track: track::Track,
+        /// The file format of the track
+        #[structopt(long, default_value = "bedgraph", possible_values = &["bedgraph", "wig"])]
+        format: track::Format,
+    },
+    /// Write a standalone HTML report of length and GC content distributions
+    Report {
+        #[allow(missing_docs)]
+        #[structopt(flatten)]
+        input: Input,
+        /// Number of bars in each histogram
+        #[structopt(short, long, default_value = "20")]
+        bins: usize,
+    },
+    /// Check that two FASTA references agree, contig by contig.
+    ///
+    /// Contigs are matched by name and compared by length and digest. Those of the same length
+    /// whose sequences differ are compared position by position, counting substitutions apart
+    /// from positions where just one reference has an `N` and other ambiguity code mismatches.
+    CompareRefs {
+        #[allow(missing_docs)]
+        #[structopt(flatten)]
+        input: Input,
+        /// The FASTA reference to compare the input against
+        #[structopt(parse(from_os_str))]
+        other: PathBuf,
+    },
+    /// Run the external plugin `nuccount-<name>` from the PATH over the sequences.
+    ///
+    /// The plugin receives every sequence as single-line FASTA on stdin, with an empty header
+    /// for input that had none, and its stdout becomes the output. Arguments after `--` are
+    /// passed on to the plugin.
+    Plugin {
+        /// Name of the plugin
+        name: String,
+        #[allow(missing_docs)]
+        #[structopt(flatten)]
+        input: Input,
+        /// Arguments for the plugin
+        #[structopt(last = true)]
+        args: Vec<String>,
+    },
+}
+
+// How results are written, flattened into the subcommands supporting it.
+#[allow(missing_docs)]
+#[derive(Debug, Default, StructOpt)]
+pub struct OutputFormat {
+    /// Write the results as plain text, JSON or TSV
+    #[structopt(long, default_value = "plain", possible_values = &["plain", "json", "tsv"])]
+    pub format: output::Format,
+}
+
+/// An error that stops a subcommand
+#[derive(Debug, thiserror::Error)]
+pub enum CliError {
+    /// An argument is invalid, or out of range for the input
+    #[error("{0}")]
+    Argument(String),
+    /// A file could not be opened or created
+    #[error("failed to open {}", .path.display())]
+    File {
+        /// The path of the file
+        path: PathBuf,
+        /// Why it could not be opened
+        source: io::Error,
+    },
+    /// The input is not valid sequence data
+    #[error(transparent)]
+    Dna(#[from] DnaError),
+    /// A plugin could not be run, or failed
+    #[error("{0}")]
+    Plugin(String),
+    /// Reading the input or writing the results failed
+    #[error(transparent)]
+    Io(#[from] io::Error),
+}
+
+impl From<FastaError> for CliError {
+    fn from(e: FastaError) -> Self {
+        CliError::Dna(e.into())
+    }
+}
+
+impl Opts {
+    /// The input of the subcommand
+    pub fn input(&self) -> &Input {
+        match self {
+            Opts::Revcomp(input) | Opts::Pfm(input) => input,
+            Opts::Count { input, .. }
+            | Opts::Gc { input, .. }
+            | Opts::Kmer { input, .. }
+            | Opts::KmerCount { input, .. }
+            | Opts::Filter { input, .. }
+            | Opts::Translate { input, .. }
+            | Opts::Grep { input, .. }
+            | Opts::KmerDist { input, .. }
+            | Opts::Redundant { input, .. }
+            | Opts::Shuffle { input, .. }
+            | Opts::Extract { input, .. }
+            | Opts::Stats { input, .. }
+            | Opts::Report { input, .. }
+            | Opts::CompareRefs { input, .. }
+            | Opts::Plugin { input, .. } => input,
+        }
+    }
+}
+
+/// Carry out `opts`, writing the results to `stdout` unless the input names an output file, and
+/// warnings about the input, such as the characters skipped with `--lenient`, to `stderr`.
+///
+/// Warnings are written once the subcommand is done, whether or not it succeeded.
+pub fn run(opts: Opts, stdout: &mut dyn Write, stderr: &mut dyn Write) -> Result<(), CliError> {
+    let warnings = opts.input().warnings();
+    let result = execute(opts, stdout);
+    for warning in warnings.take() {
+        writeln!(stderr, "Warning: {}", warning)?;
+    }
+    result
+}
+
+fn execute(opts: Opts, stdout: &mut dyn Write) -> Result<(), CliError> {
+    match opts {
+        Opts::Count { input, format } => {
+            let sequences = commands::count(&input)?;
+            let mut out = input.writer(stdout)?;
+            match format.format {
+                output::Format::Plain => {
+                    for seq in &sequences {
+                        let counts = seq.dna;
+                        write_header(&mut out, seq)?;
+                        writeln!(out, "A {}", counts.a)?;
+                        writeln!(out, "C {}", counts.c)?;
+                        writeln!(out, "G {}", counts.g)?;
+                        writeln!(out, "T {}", counts.t)?;
+                        if !input.strict {
+                            writeln!(out, "N {}", counts.n)?;
+                            writeln!(out, "ambiguous {}", counts.ambiguous)?;
+                        }
+                    }
+                }
+                format => {
+                    let rows = sequences
+                        .iter()
+                        .map(|seq| {
+                            let counts = seq.dna;
+                            let values = [
+                                counts.len(),
+                                counts.a,
+                                counts.c,
+                                counts.g,
+                                counts.t,
+                                counts.n,
+                                counts.ambiguous,
+                            ];
+                            let mut values: Vec<Value> =
+                                values.iter().map(|&count| Value::Count(count)).collect();
+                            values.push(Value::Fraction(counts.gc_content()));
+                            output::Row::new(seq.header.as_deref(), values)
+                        })
+                        .collect();
+                    let columns = &["length", "A", "C", "G", "T", "N", "ambiguous", "gc"];
+                    write_table(&mut out, format, &output::Table { columns, rows })?;
+                }
+            }
+            out.flush()?;
+        }
+        Opts::Gc { input, format } => {
+            let sequences = commands::stats(&input)?;
+            let mut out = input.writer(stdout)?;
+            match format.format {
+                output::Format::Plain => {
+                    for seq in &sequences {
+                        write_header(&mut out, seq)?;
+                        writeln!(out, "{:.4}", seq.dna.gc_content)?;
+                    }
+                }
+                format => {
+                    let rows = sequences
+                        .iter()
+                        .map(|seq| {
+                            let values = vec![
+                                Value::Count(seq.dna.len),
+                                Value::Fraction(seq.dna.gc_content),
+                            ];
+                            output::Row::new(seq.header.as_deref(), values)
+                        })
+                        .collect();
+                    let columns = &["length", "gc"];
+                    write_table(&mut out, format, &output::Table { columns, rows })?;
+                }
+            }
+            out.flush()?;
+        }
+        Opts::Revcomp(input) => {
+            let sequences = commands::revcomp(&input)?;
+            let mut out = input.writer(stdout)?;
+            for seq in &sequences {
+                write_header(&mut out, seq)?;
+                write_sequence(&mut out, &seq.dna)?;
+            }
+            out.flush()?;
+        }
+        Opts::Kmer {
+            input,
+            k,
+            canonical,
+        } => {
+            let sequences = commands::kmers(&input, k, canonical)?;
+            let mut out = input.writer(stdout)?;
+            for seq in &sequences {
+                write_header(&mut out, seq)?;
+                for (kmer, count) in &seq.dna {
+                    writeln!(out, "{} {}", kmer, count)?;
+                }
+            }
+            out.flush()?;
+        }
+        Opts::KmerCount {
+            input,
+            k,
+            canonical,
+            max_mem,
+            temp_dir,
+        } => {
+            let counts = commands::kmer_count(&input, k, canonical, max_mem, temp_dir)?;
+            let mut out = input.writer(stdout)?;
+            for entry in counts {
+                let (kmer, count) = entry?;
+                writeln!(out, "{} {}", kmer::decode(kmer, k), count)?;
+            }
+            out.flush()?;
+        }
+        Opts::Filter { input, min_entropy } => {
+            let mut out = input.writer(stdout)?;
+            filter::run(&input, min_entropy, &mut out)?;
+            out.flush()?;
+        }
+        Opts::Translate { input, table } => {
+            let code = GeneticCode::from_ncbi_id(table).ok_or_else(|| {
+                CliError::Argument(format!("unsupported translation table {}", table))
+            })?;
+            let mut out = input.writer(stdout)?;
+            translate::run(&input, code, &mut out)?;
+            out.flush()?;
+        }
+        Opts::Grep {
+            pattern,
+            input,
+            forward_only,
+        } => {
+            let found = commands::grep(&input, &pattern, forward_only)?;
+            let mut out = input.writer(stdout)?;
+            writeln!(out, "sequence start end strand match")?;
+            for m in &found {
+                let strand = match m.strand {
+                    Strand::Forward => '+',
+                    Strand::Reverse => '-',
+                };
+                writeln!(
+                    out,
+                    "{} {} {} {} {}",
+                    m.name, m.range.start, m.range.end, strand, m.matched
+                )?;
+            }
+            out.flush()?;
+        }
+        Opts::Pfm(input) => {
+            let pfm = commands::pfm(&input)?;
+            let mut out = input.writer(stdout)?;
+            let consensus: String = pfm
+                .consensus()
+                .codes()
+                .iter()
+                .map(|code| code.to_char())
+                .collect();
+            writeln!(out, "consensus {}", consensus)?;
+            writeln!(out, "position A C G T entropy")?;
+            for (idx, (counts, entropy)) in pfm.counts().iter().zip(pfm.entropy()).enumerate() {
+                let [a, c, g, t] = counts;
+                writeln!(out, "{} {} {} {} {} {:.4}", idx + 1, a, c, g, t, entropy)?;
+            }
+            out.flush()?;
+        }
+        Opts::KmerDist {
+            input,
+            k,
+            size,
+            scaled,
+            metric,
+            format,
+        } => {
+            let sampling = match scaled {
+                Some(scaled) => commands::Sampling::Scaled(scaled),
+                None => commands::Sampling::Size(size),
+            };
+            let matrix = commands::kmer_distances(&input, k, sampling, metric)?;
+            let mut out = input.writer(stdout)?;
+            matrix.write(&mut out, format)?;
+            out.flush()?;
+        }
+        Opts::Redundant {
+            input,
+            min_identity,
+        } => {
+            let found = commands::redundant(&input, min_identity)?;
+            let mut out = input.writer(stdout)?;
+            writeln!(out, "contig container strand identity")?;
+            for redundant in &found.redundant {
+                let strand = match redundant.strand {
+                    Strand::Forward => '+',
+                    Strand::Reverse => '-',
+                };
+                writeln!(
+                    out,
+                    "{} {} {} {:.4}",
+                    found.names[redundant.contig],
+                    found.names[redundant.container],
+                    strand,
+                    redundant.identity
+                )?;
+            }
+            out.flush()?;
+        }
+        Opts::Shuffle {
+            input,
+            preserve,
+            seed,
+        } => {
+            let seed = seed.unwrap_or_else(rand::random);
+            let sequences = commands::shuffle(&input, preserve, seed)?;
+            let mut out = input.writer(stdout)?;
+            for seq in &sequences {
+                write_header(&mut out, seq)?;
+                write_sequence(&mut out, &seq.dna)?;
+            }
+            out.flush()?;
+        }
+        Opts::Extract { input, start, end } => {
+            let sequences = commands::extract(&input, start, end)?;
+            let mut out = input.writer(stdout)?;
+            for seq in &sequences {
+                write_header(&mut out, seq)?;
+                write_sequence(&mut out, &seq.dna)?;
+            }
+            out.flush()?;
+        }
+        Opts::Stats {
+            input,
+            per_window,
+            track,
+            format,
+        } => match per_window {
+            Some(0) => {
+                return Err(CliError::Argument(
+                    "window size must be non-zero".to_string(),
+                ))
+            }
+            Some(window) => {
+                let sequences = input.read()?;
+                let mut out = input.writer(stdout)?;
+                track::write(&mut out, &sequences, window, track, format)?;
+                out.flush()?;
+            }
+            None => {
+                let sequences = commands::stats(&input)?;
+                let mut out = input.writer(stdout)?;
+                for seq in &sequences {
+                    let stats = &seq.dna;
+                    write_header(&mut out, seq)?;
+                    writeln!(out, "length {}", stats.len)?;
+                    writeln!(out, "gc {:.4}", stats.gc_content)?;
+                    writeln!(out, "at_skew {:.4}", stats.at_skew)?;
+                    writeln!(out, "gc_skew {:.4}", stats.gc_skew)?;
+                    writeln!(out, "entropy {:.4}", stats.entropy())?;
+                }
+                out.flush()?;
+            }
+        },
+        Opts::Report { input, bins } => {
+            let page = commands::report(&input, bins)?;
+            let mut out = input.writer(stdout)?;
+            out.write_all(page.as_bytes())?;
+            out.flush()?;
+        }
+        Opts::CompareRefs { input, other } => {
+            let concordance = commands::compare_refs(&input, other)?;
+            let mut out = input.writer(stdout)?;
+            writeln!(
+                out,
+                "contig length_1 length_2 status substitutions n_discrepancies iupac_discrepancies"
+            )?;
+            let len =
+                |info: Option<ContigInfo>| info.map_or("-".to_string(), |i| i.len.to_string());
+            for contig in &concordance.contigs {
+                let found = contig.discrepancies;
+                writeln!(
+                    out,
+                    "{} {} {} {} {} {} {}",
+                    contig.name,
+                    len(contig.first),
+                    len(contig.second),
+                    match contig.status() {
+                        ContigStatus::Identical => "identical",
+                        ContigStatus::OnlyInFirst => "only-in-1",
+                        ContigStatus::OnlyInSecond => "only-in-2",
+                        ContigStatus::LengthDiffers => "length-differs",
+                        ContigStatus::SequenceDiffers => "sequence-differs",
+                    },
+                    found.substitutions,
+                    found.n,
+                    found.ambiguous
+                )?;
+            }
+            if concordance.is_concordant() {
+                writeln!(out, "references are concordant")?;
+            } else {
+                let differing = concordance.differences().count();
+                writeln!(out, "references differ in {} contig(s)", differing)?;
+            }
+            if !concordance.same_order {
+                writeln!(out, "contigs are in a different order")?;
+            }
+            out.flush()?;
+        }
+        Opts::Plugin { name, input, args } => {
+            let sequences = input.read()?;
+            let mut out = input.writer(stdout)?;
+            plugin::run(&name, &args, sequences, &mut out)?;
+            out.flush()?;
+        }
+    }
+    Ok(())
+}
+
+/// Write `table` in a machine-readable `format`
+fn write_table(
+    out: &mut dyn Write,
+    format: output::Format,
+    table: &output::Table,
+) -> io::Result<()> {
+    match format {
+        output::Format::Json => table.write_json(out),
+        output::Format::Tsv => table.write_tsv(out),
+        output::Format::Plain => unreachable!("plain output is written by each subcommand"),
+    }
+}
+
+fn write_header<T>(out: &mut dyn Write, seq: &Sequence<T>) -> io::Result<()> {
+    match &seq.header {
+        Some(header) => writeln!(out, ">{}", header),
+        None => Ok(()),
+    }
+}
+
+fn write_sequence(out: &mut dyn Write, dna: &PackedDna) -> io::Result<()> {
+    writeln!(out, "{}", dna)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn run_to_string(opts: Opts) -> Result<String, CliError> {
+        let mut out = Vec::new();
+        run(opts, &mut out, &mut io::sink())?;
+        Ok(String::from_utf8(out).unwrap())
+    }
+
+    #[test]
+    fn count_writes_each_format() {
+        let args = ["nuccount", "count", "--dna", ">seq one\nACGTN\nRA"];
+        let plain = run_to_string(Opts::from_iter(args)).unwrap();
+        assert_eq!(plain, ">seq one\nA 2\nC 1\nG 1\nT 1\nN 1\nambiguous 1\n");
+        let tsv = run_to_string(Opts::from_iter(args.iter().chain(&["--format", "tsv"]))).unwrap();
+        assert_eq!(
+            tsv,
+            "id\tlength\tA\tC\tG\tT\tN\tambiguous\tgc\nseq\t7\t2\t1\t1\t1\t1\t1\t0.4000\n"
+        );
+    }
+
+    #[test]
+    fn warnings_go_to_stderr() {
+        let opts = Opts::from_iter(["nuccount", "revcomp", "--lenient", "--dna", "AXCG"]);
+        let (mut out, mut err) = (Vec::new(), Vec::new());
+        run(opts, &mut out, &mut err).unwrap();
+        assert_eq!(out, b"CGT\n");
+        assert_eq!(err, b"Warning: skipped 'X' at position 1\n");
+
+        let mut input = Input::from_dna("AC-G");
+        input.lenient = true;
+        commands::count(&input).unwrap();
+        assert_eq!(input.take_warnings(), ["skipped '-' at position 2"]);
+        assert!(input.take_warnings().is_empty());
+    }
+
+    #[test]
+    fn commands_return_values() {
+        let input = Input::from_dna("AACGT");
+        let counts = commands::count(&input).unwrap();
+        assert_eq!(counts[0].dna.a, 2);
+        assert_eq!(counts[0].header, None);
+        let kmers = commands::kmers(&input, 4, false).unwrap();
+        assert_eq!(
+            kmers[0].dna,
+            vec![("AACG".to_string(), 1), ("ACGT".to_string(), 1)]
+        );
+        let extracted = commands::extract(&input, 1, Some(3)).unwrap();
+        assert_eq!(extracted[0].dna.to_string(), "AC");
+    }
+
+    #[test]
+    fn errors_are_typed() {
+        let input = Input::from_dna("ACGT");
+        assert!(matches!(
+            commands::extract(&input, 2, Some(9)),
+            Err(CliError::Argument(_))
+        ));
+        assert!(matches!(
+            commands::kmers(&input, 0, false),
+            Err(CliError::Argument(_))
+        ));
+        let err = commands::revcomp(&Input::from_dna("ACXT")).unwrap_err();
+        assert!(matches!(err, CliError::Dna(DnaError::InvalidNuc { .. })));
+        let missing = Input::from_file("/nonexistent/seq.fa");
+        let err = commands::count(&missing).unwrap_err();
+        assert_eq!(err.to_string(), "failed to open /nonexistent/seq.fa");
+        assert!(std::error::Error::source(&err).is_some());
+    }
+}
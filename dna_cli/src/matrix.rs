@@ -0,0 +1,172 @@
+//! Pairwise distances between sequences, for tree building and clustering tools.
+//!
+//! A [`DistanceMatrix`] is written as a square PHYLIP matrix, which `neighbor` from PHYLIP,
+//! RapidNJ and most other tree builders read, or as TSV with a header row. Each sequence is named
+//! after the first word of its FASTA header.
+
+use dna::sketch::Sketch;
+use std::{io::Write, str::FromStr};
+
+/// How two sequences are compared
+#[derive(Debug, Clone, Copy)]
+pub enum Metric {
+    /// One minus the Jaccard similarity of their k-mer sets
+    Jaccard,
+    /// One minus the fraction of the k-mers of the row sequence found in the column one, which
+    /// is not symmetric
+    Containment,
+    /// The Mash distance, an estimate of the per-nucleotide mutation rate
+    Mash,
+}
+
+impl Metric {
+    /// The distance between the sequences sketched as `a` and `b`, 1 if they cannot be compared
+    /// because one is shorter than k
+    fn distance(self, a: &Sketch, b: &Sketch) -> f64 {
+        let distance = match self {
+            Metric::Jaccard => a.jaccard(b).map(|jaccard| 1.0 - jaccard),
+            Metric::Containment => a.containment(b).map(|containment| 1.0 - containment),
+            Metric::Mash => a.distance(b),
+        };
+        distance.unwrap_or(1.0)
+    }
+}
+
+impl FromStr for Metric {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "jaccard" => Ok(Metric::Jaccard),
+            "containment" => Ok(Metric::Containment),
+            "mash" => Ok(Metric::Mash),
+            _ => Err(format!("unknown metric {}", s)),
+        }
+    }
+}
+
+/// The file format of a matrix
+#[derive(Debug, Clone, Copy)]
+pub enum Format {
+    /// The number of sequences, then one line per sequence with its name and distances
+    Phylip,
+    /// Tab-separated values with a header row of names
+    Tsv,
+}
+
+impl FromStr for Format {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "phylip" => Ok(Format::Phylip),
+            "tsv" => Ok(Format::Tsv),
+            _ => Err(format!("unknown matrix format {}", s)),
+        }
+    }
+}
+
+/// The distance between every pair of sequences
+#[derive(Debug, Clone, PartialEq)]
+pub struct DistanceMatrix {
+    /// Name of each sequence, in input order
+    pub names: Vec<String>,
+    /// `distances[i][j]` is the distance from sequence `i` to sequence `j`, 0 when `i == j`
+    pub distances: Vec<Vec<f64>>,
+}
+
+impl DistanceMatrix {
+    /// Compare every pair of `sketches` by `metric`
+    pub fn new(names: Vec<String>, sketches: &[Sketch], metric: Metric) -> Self {
+        let distances = sketches
+            .iter()
+            .enumerate()
+            .map(|(i, a)| {
+                sketches
+                    .iter()
+                    .enumerate()
+                    .map(|(j, b)| if i == j { 0.0 } else { metric.distance(a, b) })
+                    .collect()
+            })
+            .collect();
+        DistanceMatrix { names, distances }
+    }
+
+    /// Write the matrix in `format`
+    pub fn write(&self, out: &mut dyn Write, format: Format) -> std::io::Result<()> {
+        match format {
+            Format::Phylip => {
+                writeln!(out, "{}", self.names.len())?;
+                // Names are padded to the 10 columns of strict PHYLIP, but not truncated.
+                let width = self.names.iter().map(|name| name.len()).max().unwrap_or(0);
+                let width = width.max(10);
+                for (name, row) in self.names.iter().zip(&self.distances) {
+                    write!(out, "{:<width$}", name, width = width)?;
+                    for distance in row {
+                        write!(out, " {:.6}", distance)?;
+                    }
+                    writeln!(out)?;
+                }
+            }
+            Format::Tsv => {
+                write!(out, "id")?;
+                for name in &self.names {
+                    write!(out, "\t{}", name)?;
+                }
+                writeln!(out)?;
+                for (name, row) in self.names.iter().zip(&self.distances) {
+                    write!(out, "{}", name)?;
+                    for distance in row {
+                        write!(out, "\t{:.6}", distance)?;
+                    }
+                    writeln!(out)?;
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use dna::PackedDna;
+    use std::convert::TryFrom;
+
+    fn sketch(dna: &str) -> Sketch {
+        Sketch::frac_minhash(&PackedDna::try_from(dna).unwrap(), 3, 1)
+    }
+
+    #[test]
+    fn metrics_and_formats() {
+        let sketches = [sketch("ACGTTGCA"), sketch("ACGTTG"), sketch("AC")];
+        let names = vec!["long".to_string(), "short".to_string(), "tiny".to_string()];
+        let matrix = DistanceMatrix::new(names.clone(), &sketches, Metric::Containment);
+        // Every k-mer of `short` is in `long`, but not the other way around.
+        assert_eq!(matrix.distances[1][0], 0.0);
+        assert!(matrix.distances[0][1] > 0.0);
+        assert_eq!(matrix.distances[2], vec![1.0, 1.0, 0.0]);
+
+        let matrix = DistanceMatrix::new(names, &sketches[..2], Metric::Jaccard);
+        let jaccard = sketches[0].jaccard(&sketches[1]).unwrap();
+        assert_eq!(matrix.distances[0][1], 1.0 - jaccard);
+        assert_eq!(matrix.distances[0][1], matrix.distances[1][0]);
+
+        let matrix = DistanceMatrix {
+            names: vec!["a".to_string(), "b".to_string()],
+            distances: vec![vec![0.0, 0.25], vec![0.25, 0.0]],
+        };
+        let mut phylip = Vec::new();
+        matrix.write(&mut phylip, Format::Phylip).unwrap();
+        assert_eq!(
+            String::from_utf8(phylip).unwrap(),
+            "2\na          0.000000 0.250000\nb          0.250000 0.000000\n"
+        );
+        let mut tsv = Vec::new();
+        matrix.write(&mut tsv, Format::Tsv).unwrap();
+        assert_eq!(
+            String::from_utf8(tsv).unwrap(),
+            "id\ta\tb\na\t0.000000\t0.250000\nb\t0.250000\t0.000000\n"
+        );
+    }
+}
@@ -0,0 +1,130 @@
+//! Machine-readable results for downstream scripts.
+//!
+//! A [`Table`] holds one row per sequence, identified by the first word of its FASTA header.
+//! It is written as TSV with a header row, or as a JSON array of objects keyed by column name.
+
+use std::{fmt, io::Write, str::FromStr};
+
+/// How results are written
+#[derive(Debug, Clone, Copy, Default)]
+pub enum Format {
+    /// The plain text of each subcommand, for reading
+    #[default]
+    Plain,
+    /// A JSON array with an object per sequence
+    Json,
+    /// Tab-separated values with a header row
+    Tsv,
+}
+
+impl FromStr for Format {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "plain" => Ok(Format::Plain),
+            "json" => Ok(Format::Json),
+            "tsv" => Ok(Format::Tsv),
+            _ => Err(format!("unknown output format {}", s)),
+        }
+    }
+}
+
+/// A single result
+#[derive(Debug, Clone, Copy)]
+pub enum Value {
+    /// A number of nucleotides
+    Count(usize),
+    /// A fraction such as the GC content, written to 4 decimal places
+    Fraction(f64),
+}
+
+impl fmt::Display for Value {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Value::Count(count) => write!(f, "{}", count),
+            Value::Fraction(fraction) => write!(f, "{:.4}", fraction),
+        }
+    }
+}
+
+/// The results of a sequence
+#[derive(Debug)]
+pub struct Row {
+    /// First word of the FASTA header, if the sequence had one
+    pub id: Option<String>,
+    /// One value per column of the table
+    pub values: Vec<Value>,
+}
+
+impl Row {
+    /// A row for the sequence with FASTA header `header`
+    pub fn new(header: Option<&str>, values: Vec<Value>) -> Self {
+        Row {
+            id: header.map(|header| header.split_whitespace().next().unwrap_or("").to_string()),
+            values,
+        }
+    }
+}
+
+/// Results of every sequence, in input order
+#[derive(Debug)]
+pub struct Table {
+    /// Names of the columns after the `id` one
+    pub columns: &'static [&'static str],
+    /// One row per sequence
+    pub rows: Vec<Row>,
+}
+
+impl Table {
+    /// Write the table as TSV, with an empty id for sequences without a header
+    pub fn write_tsv(&self, out: &mut dyn Write) -> std::io::Result<()> {
+        writeln!(out, "id\t{}", self.columns.join("\t"))?;
+        for row in &self.rows {
+            write!(out, "{}", row.id.as_deref().unwrap_or(""))?;
+            for value in &row.values {
+                write!(out, "\t{}", value)?;
+            }
+            writeln!(out)?;
+        }
+        Ok(())
+    }
+
+    /// Write the table as a JSON array, with a null id for sequences without a header
+    pub fn write_json(&self, out: &mut dyn Write) -> std::io::Result<()> {
+        write!(out, "[")?;
+        for (idx, row) in self.rows.iter().enumerate() {
+            let separator = if idx == 0 { "" } else { "," };
+            let id = match &row.id {
+                Some(id) => json_string(id),
+                None => "null".to_string(),
+            };
+            write!(out, "{}\n  {{\"id\": {}", separator, id)?;
+            for (column, value) in self.columns.iter().zip(&row.values) {
+                write!(out, ", {}: {}", json_string(column), value)?;
+            }
+            write!(out, "}}")?;
+        }
+        let end = if self.rows.is_empty() { "" } else { "\n" };
+        writeln!(out, "{}]", end)
+    }
+}
+
+/// `s` as a quoted JSON string
+fn json_string(s: &str) -> String {
+    let mut quoted = String::with_capacity(s.len() + 2);
+    quoted.push('"');
+    for c in s.chars() {
+        match c {
+            '"' => quoted.push_str("\\\""),
+            '\\' => quoted.push_str("\\\\"),
+            '\n' => quoted.push_str("\\n"),
+            '\r' => quoted.push_str("\\r"),
+            '\t' => quoted.push_str("\\t"),
+            c if (c as u32) < 0x20 => quoted.push_str(&format!("\\u{:04x}", c as u32)),
+            c => quoted.push(c),
+        }
+    }
+    quoted.push('"');
+    quoted
+}
@@ -0,0 +1,59 @@
+//! External plugins: executables named `nuccount-<name>` on the PATH.
+//!
+//! Like git's external subcommands, a plugin extends the tool without rebuilding it. It is
+//! started once and streamed every sequence as FASTA, so it can be written in any language and
+//! only has to read simple records from stdin.
+
+use crate::{write_header, write_sequence, CliError, Sequence};
+use std::{
+    io::{self, BufWriter, Write},
+    process::{Command, Stdio},
+    thread,
+};
+
+/// Prefix of the executable name of every plugin
+const PREFIX: &str = "nuccount-";
+
+/// Run the plugin `name` with `args` over `sequences`, copying its output to `out`
+pub fn run(
+    name: &str,
+    args: &[String],
+    sequences: Vec<Sequence>,
+    out: &mut dyn Write,
+) -> Result<(), CliError> {
+    let program = format!("{}{}", PREFIX, name);
+    let mut child = Command::new(&program)
+        .args(args)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .spawn()
+        .map_err(|e| CliError::Plugin(format!("failed to run plugin {}: {}", program, e)))?;
+
+    // Feed the plugin from another thread so neither side blocks on a full pipe.
+    let stdin = child.stdin.take().expect("stdin is piped");
+    let feeder = thread::spawn(move || -> io::Result<()> {
+        let mut stdin = BufWriter::new(stdin);
+        for seq in &sequences {
+            if seq.header.is_none() {
+                writeln!(stdin, ">")?;
+            }
+            write_header(&mut stdin, seq)?;
+            write_sequence(&mut stdin, &seq.dna)?;
+        }
+        stdin.flush()
+    });
+    io::copy(&mut child.stdout.take().expect("stdout is piped"), out)?;
+    let fed = feeder.join().expect("plugin input thread panicked");
+    let status = child.wait()?;
+    if !status.success() {
+        return Err(CliError::Plugin(format!(
+            "plugin {} failed: {}",
+            program, status
+        )));
+    }
+    // A plugin may stop reading early; that is only an error if it also failed.
+    match fed {
+        Err(e) if e.kind() != io::ErrorKind::BrokenPipe => Err(e.into()),
+        _ => Ok(()),
+    }
+}
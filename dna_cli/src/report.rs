@@ -0,0 +1,118 @@
+//! A standalone HTML quality-control report built from [`BatchStats`].
+//!
+//! Charts are inline SVG so the page needs no scripts or network access to display.
+
+use dna::stats::BatchStats;
+use std::fmt::Write;
+
+const CHART_WIDTH: usize = 640;
+const CHART_HEIGHT: usize = 200;
+
+/// Render the report, with `bins` bars in each histogram
+pub fn html(stats: &BatchStats, bins: usize) -> String {
+    let mut page = String::from(
+        "<!DOCTYPE html>\n<html>\n<head>\n<meta charset=\"utf-8\">\n\
+         <title>Sequence QC report</title>\n<style>\n\
+         body { font-family: sans-serif; margin: 2em; }\n\
+         table { border-collapse: collapse; }\n\
+         td, th { border: 1px solid #ccc; padding: 0.3em 0.8em; text-align: right; }\n\
+         rect { fill: #4a7ab5; }\n\
+         </style>\n</head>\n<body>\n<h1>Sequence QC report</h1>\n",
+    );
+
+    page.push_str("<h2>Summary</h2>\n<table>\n");
+    let mut row = |name: &str, value: String| {
+        let _ = writeln!(page, "<tr><th>{}</th><td>{}</td></tr>", name, value);
+    };
+    row("Records", stats.len().to_string());
+    row("Total length", stats.total_len().to_string());
+    row("Mean GC", percent(stats.mean_gc()));
+    row("Median GC", percent(stats.median_gc()));
+    for (name, p) in [
+        ("Shortest", 0.0),
+        ("Length 25th percentile", 25.0),
+        ("Median length", 50.0),
+        ("Length 75th percentile", 75.0),
+        ("Longest", 100.0),
+    ] {
+        row(name, length(stats.length_percentile(p)));
+    }
+    page.push_str("</table>\n");
+
+    let lengths: Vec<f64> = stats.records().iter().map(|r| r.len as f64).collect();
+    let min = stats.length_percentile(0.0).unwrap_or(0) as f64;
+    let max = stats.length_percentile(100.0).unwrap_or(0) as f64;
+    page.push_str("<h2>Length distribution</h2>\n");
+    page.push_str(&bar_chart(&histogram(&lengths, min, max, bins), min, max));
+
+    let gc: Vec<f64> = stats.records().iter().map(|r| r.gc_content).collect();
+    page.push_str("<h2>GC content distribution</h2>\n");
+    page.push_str(&bar_chart(&histogram(&gc, 0.0, 1.0, bins), 0.0, 1.0));
+
+    page.push_str("</body>\n</html>\n");
+    page
+}
+
+/// Counts of `values` in `bins` equal-width bins spanning `min..=max`
+fn histogram(values: &[f64], min: f64, max: f64, bins: usize) -> Vec<usize> {
+    let mut counts = vec![0; bins];
+    let width = (max - min) / bins as f64;
+    for &value in values {
+        let bin = if width > 0.0 {
+            ((value - min) / width) as usize
+        } else {
+            0
+        };
+        counts[bin.min(bins - 1)] += 1;
+    }
+    counts
+}
+
+/// An SVG bar chart of `counts`, with the axis labelled from `min` to `max`
+fn bar_chart(counts: &[usize], min: f64, max: f64) -> String {
+    let tallest = counts.iter().copied().max().unwrap_or(0).max(1);
+    let bar_width = CHART_WIDTH as f64 / counts.len() as f64;
+    let mut svg = format!(
+        "<svg width=\"{w}\" height=\"{h}\" viewBox=\"0 0 {w} {h}\">\n",
+        w = CHART_WIDTH,
+        h = CHART_HEIGHT + 20
+    );
+    for (i, &count) in counts.iter().enumerate() {
+        let height = count as f64 / tallest as f64 * CHART_HEIGHT as f64;
+        let _ = writeln!(
+            svg,
+            "<rect x=\"{:.1}\" y=\"{:.1}\" width=\"{:.1}\" height=\"{:.1}\"><title>{}</title></rect>",
+            i as f64 * bar_width,
+            CHART_HEIGHT as f64 - height,
+            (bar_width - 1.0).max(1.0),
+            height,
+            count
+        );
+    }
+    let _ = writeln!(
+        svg,
+        "<text x=\"0\" y=\"{y}\">{}</text>\n<text x=\"{w}\" y=\"{y}\" text-anchor=\"end\">{}</text>",
+        label(min),
+        label(max),
+        y = CHART_HEIGHT + 15,
+        w = CHART_WIDTH
+    );
+    svg.push_str("</svg>\n");
+    svg
+}
+
+fn label(value: f64) -> String {
+    if value.fract() == 0.0 {
+        format!("{}", value)
+    } else {
+        format!("{:.2}", value)
+    }
+}
+
+fn percent(fraction: Option<f64>) -> String {
+    fraction.map_or_else(|| "-".to_string(), |f| format!("{:.2}%", f * 100.0))
+}
+
+fn length(length: Option<usize>) -> String {
+    length.map_or_else(|| "-".to_string(), |l| l.to_string())
+}
@@ -0,0 +1,120 @@
+//! Per-window statistics as genome browser tracks.
+//!
+//! BedGraph and fixed-step Wiggle are both plain text that IGV and the UCSC browser load
+//! directly. Each sequence becomes a chromosome named after the first word of its FASTA header.
+
+use crate::Sequence;
+use dna::stats::Stats;
+use std::{io::Write, str::FromStr};
+
+/// Chromosome name of a sequence without a FASTA header
+const UNNAMED: &str = "sequence";
+
+/// The statistic plotted by a track
+#[derive(Debug, Clone, Copy)]
+pub enum Track {
+    /// Fraction of G and C
+    Gc,
+    /// Shannon entropy of the base composition, in bits
+    Entropy,
+}
+
+impl Track {
+    fn name(self) -> &'static str {
+        match self {
+            Track::Gc => "gc",
+            Track::Entropy => "entropy",
+        }
+    }
+
+    fn value(self, stats: &Stats) -> f64 {
+        match self {
+            Track::Gc => stats.gc_content,
+            Track::Entropy => stats.entropy(),
+        }
+    }
+}
+
+impl FromStr for Track {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "gc" => Ok(Track::Gc),
+            "entropy" => Ok(Track::Entropy),
+            _ => Err(format!("unknown track {}", s)),
+        }
+    }
+}
+
+/// The file format of a track
+#[derive(Debug, Clone, Copy)]
+pub enum Format {
+    /// One `chrom start end value` line per window, 0-based and end-exclusive
+    BedGraph,
+    /// A `fixedStep` block per sequence, with one value line per window
+    Wiggle,
+}
+
+impl FromStr for Format {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "bedgraph" => Ok(Format::BedGraph),
+            "wig" => Ok(Format::Wiggle),
+            _ => Err(format!("unknown track format {}", s)),
+        }
+    }
+}
+
+/// Write `track` over consecutive windows of `window` nucleotides of every sequence
+pub fn write(
+    out: &mut dyn Write,
+    sequences: &[Sequence],
+    window: usize,
+    track: Track,
+    format: Format,
+) -> std::io::Result<()> {
+    let kind = match format {
+        Format::BedGraph => "bedGraph",
+        Format::Wiggle => "wiggle_0",
+    };
+    writeln!(out, "track type={} name=\"{}\"", kind, track.name())?;
+    for seq in sequences {
+        let chrom = seq
+            .header
+            .as_deref()
+            .and_then(|header| header.split_whitespace().next())
+            .unwrap_or(UNNAMED);
+        let windows = seq.dna.stats_windows(window, window);
+        if let Format::Wiggle = format {
+            if windows.len() == 0 {
+                continue;
+            }
+            writeln!(
+                out,
+                "fixedStep chrom={} start=1 step={} span={}",
+                chrom, window, window
+            )?;
+        }
+        for (idx, stats) in windows.enumerate() {
+            let value = track.value(&stats);
+            match format {
+                Format::BedGraph => {
+                    let start = idx * window;
+                    writeln!(
+                        out,
+                        "{}\t{}\t{}\t{:.4}",
+                        chrom,
+                        start,
+                        start + window,
+                        value
+                    )?;
+                }
+                Format::Wiggle => writeln!(out, "{:.4}", value)?,
+            }
+        }
+    }
+    Ok(())
+}
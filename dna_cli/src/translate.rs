@@ -0,0 +1,107 @@
+//! Streaming six-frame translation to protein FASTA.
+//!
+//! Contigs are read and translated one at a time, and each frame is written as it is translated,
+//! so a whole genome takes no more memory than its longest contig packed, a quarter of a byte per
+//! nucleotide. Every contig gives six records, named after its identifier and the frame: `_+1` to
+//! `_+3` on the forward strand, then `_-1` to `_-3` on the reverse complement. Codons with an
+//! ambiguity code, which FASTQ reads and 2bit records may have, are translated as `X`.
+
+use crate::{commands, input::Source, CliError, Input};
+use dna::{
+    fastq,
+    iupac::AmbiguousRun,
+    protein::AminoAcid,
+    translate::{GeneticCode, Strand},
+    DnaError, PackedDna,
+};
+use std::{io::Write, ops::Range};
+
+/// Amino acids buffered before each write
+const CHUNK_LEN: usize = 1 << 16;
+
+/// Write the six-frame translation of each sequence of `input` with the genetic `code` to `out`
+pub fn run(input: &Input, code: GeneticCode, out: &mut dyn Write) -> Result<(), CliError> {
+    if input.dna.is_some() {
+        for (idx, seq) in input.read()?.iter().enumerate() {
+            let name = commands::name(seq.header.as_deref(), idx);
+            write_frames(out, &name, &seq.dna, &[], code)?;
+        }
+        return Ok(());
+    }
+    match input.open()? {
+        Source::Fasta(reader) => {
+            let mut records = input.fasta_records(reader);
+            for (idx, record) in records.by_ref().enumerate() {
+                let record = record?;
+                let name = commands::name(Some(record.header()), idx);
+                write_frames(out, &name, record.sequence(), &[], code)?;
+            }
+            input.warn_skipped(records.skipped());
+        }
+        Source::Fastq(reader) => {
+            for (idx, read) in fastq::Reader::new(reader).iupac().enumerate() {
+                let read = read.map_err(DnaError::from)?;
+                let name = commands::name(Some(read.header()), idx);
+                let dna = read.sequence();
+                write_frames(out, &name, dna.packed(), dna.ambiguous_runs(), code)?;
+            }
+        }
+        Source::TwoBit(records) => {
+            for (idx, record) in records.enumerate() {
+                let (name, dna) = record?;
+                let name = commands::name(Some(&name), idx);
+                write_frames(out, &name, dna.packed(), dna.ambiguous_runs(), code)?;
+            }
+        }
+        Source::Text(reader) => {
+            let seq = input.read_text::<PackedDna>(reader)?;
+            write_frames(out, &commands::name(None, 0), &seq.dna, &[], code)?;
+        }
+    }
+    Ok(())
+}
+
+/// Write the six frames of `dna` as protein FASTA records named after `name`, with `X` for the
+/// codons overlapping the `ambiguous` runs
+fn write_frames(
+    out: &mut dyn Write,
+    name: &str,
+    dna: &PackedDna,
+    ambiguous: &[AmbiguousRun],
+    code: GeneticCode,
+) -> Result<(), CliError> {
+    let len = dna.len();
+    let mut chunk = Vec::with_capacity(CHUNK_LEN);
+    for (strand, sign) in [(Strand::Forward, '+'), (Strand::Reverse, '-')] {
+        for frame in 0..3 {
+            writeln!(out, ">{}_{}{}", name, sign, frame + 1)?;
+            for (idx, aa) in dna.translate_frame(strand, frame, code).enumerate() {
+                let start = frame + 3 * idx;
+                let codon = match strand {
+                    Strand::Forward => start..start + 3,
+                    Strand::Reverse => len - start - 3..len - start,
+                };
+                let aa = if overlaps(ambiguous, codon) {
+                    AminoAcid::Xaa
+                } else {
+                    aa
+                };
+                chunk.push(aa.to_char() as u8);
+                if chunk.len() == CHUNK_LEN {
+                    out.write_all(&chunk)?;
+                    chunk.clear();
+                }
+            }
+            chunk.push(b'\n');
+            out.write_all(&chunk)?;
+            chunk.clear();
+        }
+    }
+    Ok(())
+}
+
+/// Whether any of the sorted `runs` overlaps `range`
+fn overlaps(runs: &[AmbiguousRun], range: Range<usize>) -> bool {
+    let idx = runs.partition_point(|run| run.start + run.len <= range.start);
+    runs.get(idx).is_some_and(|run| run.start < range.end)
+}
@@ -0,0 +1,317 @@
+//! Golden-file tests of every subcommand.
+//!
+//! Each case runs the binary over the files in `tests/fixtures` and compares what it wrote with
+//! `tests/golden/<case>.txt`: stdout, then stderr and the exit code if there were any. After an
+//! intended change of output, rerun with `UPDATE_GOLDEN=1` to rewrite the files and review their
+//! diff.
+
+use assert_cmd::Command;
+use std::{env, fs, path::Path};
+
+const CLEAN: &str = "tests/fixtures/clean.fa";
+const AMBIGUOUS: &str = "tests/fixtures/ambiguous.fa";
+const MESSY: &str = "tests/fixtures/messy.fa";
+const ASSEMBLY: &str = "tests/fixtures/assembly.fa";
+const READS: &str = "tests/fixtures/reads.fq";
+const PLAIN: &str = "tests/fixtures/plain.txt";
+const PATCHED: &str = "tests/fixtures/clean_patched.fa";
+const CLEAN_GZ: &str = "tests/fixtures/clean.fa.gz";
+const GENBANK: &str = "tests/fixtures/phix.gb";
+const TWOBIT: &str = "tests/fixtures/genome.2bit";
+
+/// Run the binary with `args` and `stdin`, and describe what it wrote
+fn transcript(args: &[&str], stdin: &str) -> String {
+    let output = Command::cargo_bin("nuccount")
+        .unwrap()
+        .args(args)
+        .env("PATH", plugin_path())
+        .write_stdin(stdin)
+        .output()
+        .unwrap();
+    let mut transcript = String::from_utf8(output.stdout).unwrap();
+    if !output.stderr.is_empty() {
+        transcript.push_str("--- stderr\n");
+        transcript.push_str(&String::from_utf8(output.stderr).unwrap());
+    }
+    if !output.status.success() {
+        transcript.push_str(&format!("--- exit code {:?}\n", output.status.code()));
+    }
+    transcript
+}
+
+/// The `PATH` with the fixture plugins in front
+fn plugin_path() -> String {
+    let plugins = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures/plugins");
+    match env::var("PATH") {
+        Ok(path) => format!("{}:{}", plugins.display(), path),
+        Err(_) => plugins.display().to_string(),
+    }
+}
+
+/// Compare the transcript of `args` run with `stdin` with the golden file of `case`
+fn check_stdin(case: &str, args: &[&str], stdin: &str) {
+    let actual = transcript(args, stdin);
+    let golden = Path::new(env!("CARGO_MANIFEST_DIR"))
+        .join("tests/golden")
+        .join(case)
+        .with_extension("txt");
+    if env::var_os("UPDATE_GOLDEN").is_some() {
+        fs::write(&golden, &actual).unwrap();
+        return;
+    }
+    let expected = fs::read_to_string(&golden)
+        .unwrap_or_else(|e| panic!("{}: {}, run with UPDATE_GOLDEN=1", golden.display(), e));
+    assert!(
+        actual == expected,
+        "output of `nuccount {}` differs from {}\n--- expected\n{}--- actual\n{}",
+        args.join(" "),
+        golden.display(),
+        expected,
+        actual
+    );
+}
+
+/// Compare the transcript of `args` with the golden file of `case`
+fn check(case: &str, args: &[&str]) {
+    check_stdin(case, args, "");
+}
+
+#[test]
+fn count() {
+    check("count", &["count", "-f", CLEAN]);
+    check("count_ambiguous", &["count", "-f", AMBIGUOUS]);
+    check(
+        "count_json",
+        &["count", "-f", AMBIGUOUS, "--format", "json"],
+    );
+    check("count_tsv", &["count", "-f", CLEAN, "--format", "tsv"]);
+    check("count_strict", &["count", "--strict", "-f", CLEAN]);
+    check(
+        "count_strict_error",
+        &["count", "--strict", "-f", AMBIGUOUS],
+    );
+    check("count_lenient", &["count", "--lenient", "-f", MESSY]);
+    check("count_invalid", &["count", "-f", MESSY]);
+    check("count_plain", &["count", "-f", PLAIN]);
+    check("count_fastq", &["count", "-f", READS]);
+    check("count_twobit", &["count", "-f", TWOBIT]);
+    check(
+        "count_missing_file",
+        &["count", "-f", "tests/fixtures/missing.fa"],
+    );
+    check("count_dna", &["count", "--dna", ">pasted\nAC GT\nNN"]);
+    check_stdin("count_stdin", &["count", "-f", "-"], ">in\nACGTTN\n");
+}
+
+#[test]
+fn gc() {
+    check("gc", &["gc", "-f", CLEAN]);
+    check("gc_json", &["gc", "-f", CLEAN, "--format", "json"]);
+    check("gc_ambiguous", &["gc", "-f", AMBIGUOUS]);
+}
+
+#[test]
+fn revcomp() {
+    check("revcomp", &["revcomp", "-f", CLEAN]);
+    check("revcomp_plain", &["revcomp", "-f", PLAIN]);
+}
+
+#[test]
+fn kmer() {
+    check("kmer", &["kmer", "-f", CLEAN, "-k", "2"]);
+    check(
+        "kmer_canonical",
+        &["kmer", "-f", CLEAN, "-k", "3", "--canonical"],
+    );
+    check("kmer_invalid_k", &["kmer", "-f", CLEAN, "-k", "33"]);
+}
+
+#[test]
+fn kmer_count() {
+    check(
+        "kmer_count",
+        &[
+            "kmer-count",
+            "-f",
+            READS,
+            "-k",
+            "4",
+            "--canonical",
+            "--max-mem",
+            "64",
+        ],
+    );
+    check(
+        "kmer_count_invalid_mem",
+        &["kmer-count", "-f", CLEAN, "--max-mem", "2T"],
+    );
+}
+
+#[test]
+fn compare_refs() {
+    check("compare_refs", &["compare-refs", "-f", CLEAN, PATCHED]);
+    check(
+        "compare_refs_identical",
+        &["compare-refs", "-f", CLEAN, CLEAN],
+    );
+}
+
+#[test]
+fn detected_formats() {
+    check("count_gzip", &["count", "-f", CLEAN_GZ]);
+    check("count_genbank", &["count", "-f", GENBANK]);
+}
+
+#[test]
+fn kmer_dist() {
+    check("kmer_dist", &["kmer-dist", "-f", CLEAN, "-k", "3"]);
+    check(
+        "kmer_dist_containment_tsv",
+        &[
+            "kmer-dist",
+            "-f",
+            CLEAN,
+            "-k",
+            "2",
+            "--scaled",
+            "1",
+            "--metric",
+            "containment",
+            "--format",
+            "tsv",
+        ],
+    );
+    check_stdin(
+        "kmer_dist_mash",
+        &["kmer-dist", "-f", "-", "-k", "4", "--metric", "mash"],
+        ">a\nACGTTGCAAC\n>b\nACGTTGCATC\n",
+    );
+}
+
+#[test]
+fn filter() {
+    check(
+        "filter_fastq",
+        &["filter", "-f", READS, "--min-entropy", "1.5"],
+    );
+    check(
+        "filter_fastq_repeats",
+        &["filter", "-f", READS, "--min-entropy", "0.5"],
+    );
+    check(
+        "filter_fastq_lenient",
+        &["filter", "--lenient", "-f", READS, "--min-entropy", "1.5"],
+    );
+    check(
+        "filter_fasta",
+        &["filter", "-f", CLEAN, "--min-entropy", "1.995"],
+    );
+    check(
+        "filter_dna",
+        &["filter", "--dna", "AAAAAAAA", "--min-entropy", "0.1"],
+    );
+}
+
+#[test]
+fn pfm() {
+    check_stdin(
+        "pfm",
+        &["pfm", "-f", "-"],
+        ">site1\nTATAAT\n>site2\nTATGAT\n>site3\nTACAAT\n>site4\nGATACT\n",
+    );
+    check("pfm_lengths_differ", &["pfm", "-f", CLEAN]);
+}
+
+#[test]
+fn redundant() {
+    check("redundant", &["redundant", "-f", ASSEMBLY]);
+    check(
+        "redundant_exact",
+        &["redundant", "-f", ASSEMBLY, "--min-identity", "1"],
+    );
+    check(
+        "redundant_invalid",
+        &["redundant", "-f", ASSEMBLY, "--min-identity", "2"],
+    );
+}
+
+#[test]
+fn shuffle() {
+    check("shuffle", &["shuffle", "-f", CLEAN, "--seed", "7"]);
+    check(
+        "shuffle_nucleotides",
+        &[
+            "shuffle",
+            "-f",
+            CLEAN,
+            "--seed",
+            "7",
+            "--preserve",
+            "nucleotides",
+        ],
+    );
+}
+
+#[test]
+fn translate() {
+    check("translate", &["translate", "-f", CLEAN]);
+    check("translate_fastq", &["translate", "-f", READS]);
+    check_stdin(
+        "translate_mitochondrial",
+        &["translate", "-f", "-", "--table", "2"],
+        ">mt\nATGAGATGATAA\n",
+    );
+    check(
+        "translate_invalid_table",
+        &["translate", "--dna", "ATG", "--table", "7"],
+    );
+}
+
+#[test]
+fn grep() {
+    check("grep", &["grep", "GAATTC|GGCCN{0,2}AA", "-f", CLEAN]);
+    check_stdin(
+        "grep_forward_only",
+        &["grep", "A[CG]+T", "-f", "-", "--forward-only"],
+        ">s1\nTTACGGTAAGCTA\n>s2\nACCCT\n",
+    );
+    check("grep_invalid", &["grep", "GG(WCC", "-d", "ACGT"]);
+}
+
+#[test]
+fn extract() {
+    check(
+        "extract",
+        &["extract", "-d", "ACGTACGTTT", "-s", "2", "-e", "6"],
+    );
+    check("extract_to_end", &["extract", "-f", PLAIN, "-s", "8"]);
+    check(
+        "extract_out_of_bounds",
+        &["extract", "-f", CLEAN, "-s", "4"],
+    );
+}
+
+#[test]
+fn stats() {
+    check("stats", &["stats", "-f", CLEAN]);
+    check("stats_bedgraph", &["stats", "-f", CLEAN, "-w", "8"]);
+    check(
+        "stats_wig_entropy",
+        &[
+            "stats", "-f", CLEAN, "-w", "6", "--track", "entropy", "--format", "wig",
+        ],
+    );
+    check("stats_zero_window", &["stats", "-f", CLEAN, "-w", "0"]);
+}
+
+#[test]
+fn report() {
+    check("report", &["report", "-f", CLEAN, "--bins", "4"]);
+}
+
+#[cfg(unix)]
+#[test]
+fn plugin() {
+    check("plugin", &["plugin", "echo", "-f", CLEAN]);
+    check("plugin_dna", &["plugin", "echo", "--dna", "ACGT"]);
+}